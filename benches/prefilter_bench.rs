@@ -0,0 +1,127 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use rgrc::colorizer::{ColorizeOptions, colorize_regex, colorize_regex_with_options};
+use rgrc::grc::{GrcatConfigEntry, GrcatConfigReader};
+use rgrc::prefilter::LiteralPrefilter;
+use std::hint::black_box;
+use std::io::BufRead;
+
+/// Load the rules of a bundled config from `share/`.
+fn load_rules(name: &str) -> Vec<GrcatConfigEntry> {
+    let file = std::fs::File::open(format!("share/conf.{}", name)).unwrap();
+    GrcatConfigReader::new(std::io::BufReader::new(file).lines()).collect()
+}
+
+/// Representative `ping` output: mostly reply lines, a few summary lines.
+fn ping_output() -> String {
+    let mut text = String::from("PING example.com (93.184.216.34): 56 data bytes\n");
+    for seq in 0..500 {
+        text.push_str(&format!(
+            "64 bytes from 93.184.216.34: icmp_seq={} ttl=56 time=11.{} ms\n",
+            seq,
+            seq % 10
+        ));
+    }
+    text.push_str("--- example.com ping statistics ---\n");
+    text.push_str("500 packets transmitted, 500 packets received, 0.0% packet loss\n");
+    text
+}
+
+fn benchmark_literal_scan_vs_regex(c: &mut Criterion) {
+    let rules = load_rules("ping");
+    let text = ping_output();
+    let prefilter = LiteralPrefilter::new(rules.iter().map(|rule| rule.regex.as_str())).unwrap();
+
+    // Current approach: ask every rule's regex whether it matches the line
+    c.bench_function("ping_rules_regex_screen", |b| {
+        b.iter(|| {
+            for line in black_box(&text).lines() {
+                for rule in &rules {
+                    black_box(rule.regex.is_match(line));
+                }
+            }
+        });
+    });
+
+    // Prefilter stage: one Aho-Corasick pass per line
+    c.bench_function("ping_rules_literal_screen", |b| {
+        b.iter(|| {
+            for line in black_box(&text).lines() {
+                black_box(prefilter.candidates(line));
+            }
+        });
+    });
+}
+
+fn benchmark_colorize_ping(c: &mut Criterion) {
+    let rules = load_rules("ping");
+    let text = ping_output();
+
+    c.bench_function("colorize_ping_500_lines", |b| {
+        b.iter(|| {
+            let mut output = Vec::with_capacity(text.len() * 2);
+            colorize_regex(&mut black_box(text.as_bytes()), &mut output, &rules).unwrap();
+            output
+        });
+    });
+}
+
+/// A noisy service log, long enough for idle rules to be demoted: routine
+/// lines with the odd warning and error.
+fn noisy_log() -> String {
+    let mut text = String::new();
+    for n in 0..5_000 {
+        let message = match n % 500 {
+            0 => "kernel: EXT4-fs error (device sda1): bad block bitmap".to_string(),
+            250 => "systemd[1]: nginx.service: Failed with result 'exit-code'.".to_string(),
+            _ => format!("app[4242]: GET /api/items/{} 200 {}ms", n, n % 97),
+        };
+        text.push_str(&format!(
+            "Oct 16 13:{:02}:{:02} web-1 {}\n",
+            n / 60 % 60,
+            n % 60,
+            message
+        ));
+    }
+    text
+}
+
+fn benchmark_prefilter_on_large_config(c: &mut Criterion) {
+    // Several bundled configs together make a large rule list
+    let rules: Vec<GrcatConfigEntry> = ["dmesg", "journalctl", "kubectl", "dockerps", "apt"]
+        .iter()
+        .flat_map(|name| load_rules(name))
+        .collect();
+    let text = noisy_log();
+    let colorize = |disable_prefilter: bool| {
+        let mut output = Vec::with_capacity(text.len() * 2);
+        let mut options = ColorizeOptions {
+            disable_prefilter,
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut black_box(text.as_bytes()),
+            &mut output,
+            &rules,
+            &mut options,
+        )
+        .unwrap();
+        output
+    };
+
+    // The prefilter only skips regexes that cannot match
+    assert!(colorize(false) == colorize(true));
+
+    let mut group = c.benchmark_group("large_config_noisy_log");
+    group.sample_size(10);
+    group.bench_function("without_prefilter", |b| b.iter(|| colorize(true)));
+    group.bench_function("with_prefilter", |b| b.iter(|| colorize(false)));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_literal_scan_vs_regex,
+    benchmark_colorize_ping,
+    benchmark_prefilter_on_large_config
+);
+criterion_main!(benches);
@@ -3,7 +3,13 @@ use std::fs;
 use std::path::Path;
 
 fn main() {
-    // Only run compression when embed-configs feature is enabled
+    // The release archive `rgrc self-update` downloads is named after the target
+    println!(
+        "cargo:rustc-env=RGRC_TARGET={}",
+        env::var("TARGET").unwrap()
+    );
+
+    // Only generate the embedded config table when embed-configs feature is enabled
     if env::var("CARGO_FEATURE_EMBED_CONFIGS").is_ok() {
         compress_configs();
     }
@@ -12,7 +18,8 @@ fn main() {
 fn compress_configs() {
     // Simpler build-time generation: scan `share/` and generate a small Rust
     // source file (`embedded_configs.rs`) with a `pub const EMBEDDED_CONFIGS`.
-    // This avoids hard-coding and doesn't require external crates or compression.
+    // This avoids hard-coding; with `compress-configs` each file is embedded as a
+    // zstd frame written to OUT_DIR instead of as text.
     println!("cargo:rerun-if-changed=share/");
 
     let out_dir = env::var("OUT_DIR").unwrap();
@@ -40,15 +47,33 @@ fn compress_configs() {
     embedded_output.push_str("/// Embedded configuration files compiled into the binary when the `embed-configs` feature is enabled.\n");
     embedded_output.push_str("/// Each entry is a tuple of `(filename, contents)` corresponding to files under `share/conf.*`.\n");
     embedded_output.push_str("/// This file is generated by build.rs — do not edit.\n");
-    embedded_output.push_str("pub const EMBEDDED_CONFIGS: &[(&str, &str)] = &[\n");
+    #[cfg(not(feature = "compress-configs"))]
+    {
+        embedded_output.push_str("pub const EMBEDDED_CONFIGS: &[(&str, &str)] = &[\n");
+        for f in &config_files {
+            // Use include_str! anchored to the manifest dir so the files are included
+            // from the workspace path at compile-time.
+            embedded_output.push_str(&format!(
+                "    (\"{}\", include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/share/{}\"))),\n",
+                f, f
+            ));
+        }
+    }
 
-    for f in &config_files {
-        // Use include_str! anchored to the manifest dir so the files are included
-        // from the workspace path at compile-time.
-        embedded_output.push_str(&format!(
-            "    (\"{}\", include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/share/{}\"))),\n",
-            f, f
-        ));
+    #[cfg(feature = "compress-configs")]
+    {
+        embedded_output
+            .push_str("/// Contents are zstd frames; use `embedded_config()` to get the text.\n");
+        embedded_output.push_str("pub const EMBEDDED_CONFIGS: &[(&str, &[u8])] = &[\n");
+        for f in &config_files {
+            let text = fs::read(Path::new("share").join(f)).unwrap();
+            let compressed = zstd::encode_all(text.as_slice(), 19).unwrap();
+            fs::write(Path::new(&out_dir).join(format!("{}.zst", f)), compressed).unwrap();
+            embedded_output.push_str(&format!(
+                "    (\"{}\", include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}.zst\"))),\n",
+                f, f
+            ));
+        }
     }
 
     embedded_output.push_str("];\n\n");
@@ -6,12 +6,12 @@
 #
 # See also the plugin at https://github.com/oh-my-fish/plugin-grc
 
-set -U grc_plugin_execs cat cvs df diff dig gcc g++ ls ifconfig \
-       make mount mtr netstat ping ps head tail traceroute \
-       wdiff blkid du dnf docker docker-compose docker-machine env id ip iostat journalctl kubectl \
+set -U grc_plugin_execs cat cvs df diff dig dmesg apt apt-get dpkg gcc g++ ls ifconfig \
+       make mount mtr netstat pacman paru yay ping ps head tail traceroute \
+       wdiff blkid brew du dnf docker docker-compose docker-machine env id ip iostat journalctl kubectl \
        last lsattr lsblk lspci lsmod lsof lsusb getfacl getsebool ulimit uptime nmap \
        fdisk findmnt free semanage sar ss sysctl systemctl stat showmount \
-       tcpdump tune2fs vmstat w who sockstat
+       tcpdump terraform tune2fs vmstat w who sockstat
 
 for executable in $grc_plugin_execs
     if type -q $executable
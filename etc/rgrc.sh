@@ -11,10 +11,15 @@
 GRC="$(which rgrc)"
 if tty -s && [ -n "$TERM" ] && [ "$TERM" != dumb ] && [ -n "$GRC" ]; then
     alias colourify="$GRC -es"
+    alias apt='colourify apt'
+    alias apt-get='colourify apt-get'
     alias blkid='colourify blkid'
+    alias brew='colourify brew'
     alias configure='colourify ./configure'
     alias df='colourify df'
     alias diff='colourify diff'
+    alias dmesg='colourify dmesg'
+    alias dpkg='colourify dpkg'
     alias docker='colourify docker'
     alias docker-compose='colourify docker-compose'
     alias docker-machine='colourify docker-machine'
@@ -40,12 +45,15 @@ if tty -s && [ -n "$TERM" ] && [ "$TERM" != dumb ] && [ -n "$GRC" ]; then
     alias lspci='colourify lspci'
     alias lsusb='colourify lsusb'
     alias netstat='colourify netstat'
+    alias pacman='colourify pacman'
+    alias paru='colourify paru'
     alias ping='colourify ping'
     alias ss='colourify ss'
     alias traceroute='colourify traceroute'
     alias traceroute6='colourify traceroute6'
     alias head='colourify head'
     alias tail='colourify tail'
+    alias terraform='colourify terraform'
     alias dig='colourify dig'
     alias mount='colourify mount'
     alias ps='colourify ps'
@@ -54,5 +62,6 @@ if tty -s && [ -n "$TERM" ] && [ "$TERM" != dumb ] && [ -n "$GRC" ]; then
     alias getsebool='colourify getsebool'
     alias ifconfig='colourify ifconfig'
     alias sockstat='colourify sockstat'
+    alias yay='colourify yay'
 fi
 
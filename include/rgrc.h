@@ -0,0 +1,36 @@
+/*
+ * rgrc.h - C interface to the rgrc colorization core
+ *
+ * Build the library with the `ffi` feature (`make ffi`), which produces
+ * target/release/librgrc.{so,dylib,a}. See src/ffi.rs for details.
+ */
+#ifndef RGRC_H
+#define RGRC_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The rules for one command and the state of the stream they colorize. */
+typedef struct RgrcRules rgrc_rules;
+
+/* Load the rules `rgrc COMMAND` would use for `command` (e.g. "ping -c 1 host").
+ * NULL when `command` is NULL or not UTF-8. Release with rgrc_free_rules(). */
+rgrc_rules *rgrc_load_rules(const char *command);
+
+/* Colorize the next line (without its line ending) of the stream. Returns a
+ * string to release with rgrc_free(), or NULL when an argument is NULL, the
+ * line is not UTF-8 or a skip= rule hides it. One thread per handle. */
+char *rgrc_colorize_line(rgrc_rules *rules, const char *line);
+
+/* Release a string returned by rgrc_colorize_line(). NULL is ignored. */
+void rgrc_free(char *text);
+
+/* Release a handle returned by rgrc_load_rules(). NULL is ignored. */
+void rgrc_free_rules(rgrc_rules *rules);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RGRC_H */
@@ -0,0 +1,395 @@
+//! # aliases.rs - Shell aliases for the supported commands
+//!
+//! `rgrc --aliases` prints one alias per command so that typing `ping` runs
+//! `rgrc ping`. Unix shells get `alias` lines to `eval`; PowerShell gets wrapper
+//! functions and cmd.exe doskey macros, which `--install` writes to the user's
+//! PowerShell profile or a doskey macro file.
+//!
+//! `rgrc plugin bash|zsh|fish` prints a plugin to source or drop into a plugin
+//! manager instead: it sets the aliases up (cached until rgrc changes) and rgrc's
+//! completions in interactive shells where rgrc is installed, and leaves the
+//! aliases out when `RGRC_NO_ALIASES` is set.
+
+use crate::RgrcError;
+use std::path::{Path, PathBuf};
+
+/// Shell the aliases are written for (`--shell`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AliasShell {
+    /// `alias` lines, understood by sh, bash, zsh, fish and ash
+    #[default]
+    Sh,
+    /// Wrapper functions for PowerShell
+    PowerShell,
+    /// doskey macros for cmd.exe
+    Cmd,
+}
+
+impl std::str::FromStr for AliasShell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sh" | "bash" | "zsh" | "fish" | "ash" => Ok(AliasShell::Sh),
+            "powershell" | "pwsh" => Ok(AliasShell::PowerShell),
+            "cmd" => Ok(AliasShell::Cmd),
+            _ => Err(format!(
+                "unknown shell '{}' (expected sh, bash, zsh, fish, ash, powershell or cmd)",
+                s
+            )),
+        }
+    }
+}
+
+/// Shell a plugin is written for (`rgrc plugin SHELL`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginShell {
+    Bash,
+    /// Also an oh-my-zsh plugin (`rgrc.plugin.zsh`)
+    Zsh,
+    /// Also a fisher plugin (`conf.d/rgrc.fish`)
+    Fish,
+}
+
+impl std::str::FromStr for PluginShell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(PluginShell::Bash),
+            "zsh" => Ok(PluginShell::Zsh),
+            "fish" => Ok(PluginShell::Fish),
+            _ => Err(format!(
+                "unknown plugin shell '{}' (expected bash, zsh or fish)",
+                s
+            )),
+        }
+    }
+}
+
+/// Variable that keeps a plugin from setting up the aliases.
+pub const NO_ALIASES_ENV: &str = "RGRC_NO_ALIASES";
+
+const BASH_PLUGIN: &str = r#"# rgrc plugin for bash, written by `rgrc plugin bash`
+#
+# Source it from ~/.bashrc. In interactive shells it aliases the commands rgrc
+# colours to run through rgrc, and completes rgrc's options. Set
+# RGRC_NO_ALIASES=1 before it to keep the completions without the aliases.
+# The aliases are cached until rgrc changes; remove ~/.cache/rgrc/aliases.bash
+# to pick up newly installed commands.
+
+if [[ $- == *i* ]] && command -v @RGRC@ >/dev/null 2>&1; then
+    _rgrc_cache="${XDG_CACHE_HOME:-$HOME/.cache}/rgrc"
+    if [[ -z ${RGRC_NO_ALIASES-} ]]; then
+        if [[ ! -s $_rgrc_cache/aliases.bash || $(command -v @RGRC@) -nt $_rgrc_cache/aliases.bash ]]; then
+            mkdir -p "$_rgrc_cache" && @RGRC@ --aliases >"$_rgrc_cache/aliases.bash"
+        fi
+        source "$_rgrc_cache/aliases.bash"
+    fi
+    source <(@RGRC@ --completions bash)
+    unset _rgrc_cache
+fi
+"#;
+
+const ZSH_PLUGIN: &str = r#"# rgrc plugin for zsh, written by `rgrc plugin zsh`
+#
+# Save it as $ZSH_CUSTOM/plugins/rgrc/rgrc.plugin.zsh and add rgrc to the
+# plugins of oh-my-zsh, or source it from ~/.zshrc. In interactive shells it
+# aliases the commands rgrc colours to run through rgrc, and completes rgrc's
+# options. Set RGRC_NO_ALIASES=1 before it to keep the completions without the
+# aliases. The aliases are cached until rgrc changes; remove
+# ~/.cache/rgrc/aliases.zsh to pick up newly installed commands.
+
+if [[ -o interactive ]] && (( $+commands[@RGRC@] )); then
+    () {
+        local cache=${XDG_CACHE_HOME:-$HOME/.cache}/rgrc
+        mkdir -p $cache/zsh || return
+        if [[ -z ${RGRC_NO_ALIASES-} ]]; then
+            if [[ ! -s $cache/aliases.zsh || $commands[@RGRC@] -nt $cache/aliases.zsh ]]; then
+                @RGRC@ --aliases >| $cache/aliases.zsh
+            fi
+            source $cache/aliases.zsh
+        fi
+        if [[ ! -s $cache/zsh/_rgrc || $commands[@RGRC@] -nt $cache/zsh/_rgrc ]]; then
+            @RGRC@ --completions zsh >| $cache/zsh/_rgrc
+        fi
+        # After compinit the completion is registered now, before it compinit finds it
+        if (( $+functions[compdef] )); then
+            source $cache/zsh/_rgrc
+        else
+            fpath=($cache/zsh $fpath)
+        fi
+    }
+fi
+"#;
+
+const FISH_PLUGIN: &str = r#"# rgrc plugin for fish, written by `rgrc plugin fish`
+#
+# Save it as ~/.config/fish/conf.d/rgrc.fish, or as conf.d/rgrc.fish of a
+# plugin installed with fisher. In interactive shells it aliases the commands
+# rgrc colours to run through rgrc, and completes rgrc's options. Run
+# `set -Ux RGRC_NO_ALIASES 1` to keep the completions without the aliases. The
+# aliases are cached until rgrc changes (fish 3.5 or later); remove
+# ~/.cache/rgrc/aliases.fish to pick up newly installed commands.
+
+if status is-interactive; and command -q @RGRC@
+    set -l cache (set -q XDG_CACHE_HOME; and echo $XDG_CACHE_HOME; or echo $HOME/.cache)/rgrc
+    if not set -q RGRC_NO_ALIASES
+        set -l rgrc (command -s @RGRC@)
+        if not test -s $cache/aliases.fish; or test (path mtime $rgrc) -gt (path mtime $cache/aliases.fish)
+            mkdir -p $cache; and @RGRC@ --aliases >$cache/aliases.fish
+        end
+        source $cache/aliases.fish
+    end
+    @RGRC@ --completions fish | source
+end
+"#;
+
+/// The plugin for `shell`, calling rgrc as `rgrc` (the name it was run as).
+pub fn plugin_script(shell: PluginShell, rgrc: &str) -> String {
+    let template = match shell {
+        PluginShell::Bash => BASH_PLUGIN,
+        PluginShell::Zsh => ZSH_PLUGIN,
+        PluginShell::Fish => FISH_PLUGIN,
+    };
+    template.replace("@RGRC@", rgrc)
+}
+
+/// First line of the alias block `--install` keeps in a PowerShell profile.
+const BLOCK_START: &str = "# >>> rgrc aliases >>>";
+/// Last line of that block.
+const BLOCK_END: &str = "# <<< rgrc aliases <<<";
+
+/// The alias that makes `cmd` run through `rgrc` (the name rgrc was run as).
+pub fn alias_line(shell: AliasShell, rgrc: &str, cmd: &str) -> String {
+    match shell {
+        // rgrc controls paging and colouring, so the pager must accept colours
+        AliasShell::Sh if cmd == "journalctl" => {
+            format!("alias {}='{} journalctl --no-pager | less -R'", cmd, rgrc)
+        }
+        AliasShell::Sh => format!("alias {}='{} {}'", cmd, rgrc, cmd),
+        // Built-in aliases (ls, ps, diff, curl) take precedence over functions
+        AliasShell::PowerShell => format!(
+            "Remove-Item Alias:{cmd} -Force -ErrorAction Ignore\nfunction {cmd} {{ {rgrc} {cmd} @args }}"
+        ),
+        AliasShell::Cmd => format!("{}={} {} $*", cmd, rgrc, cmd),
+    }
+}
+
+/// The aliases for `commands`, one per line.
+pub fn alias_script(shell: AliasShell, rgrc: &str, commands: &[&str]) -> String {
+    commands
+        .iter()
+        .map(|cmd| alias_line(shell, rgrc, cmd) + "\n")
+        .collect()
+}
+
+/// `profile` with its rgrc alias block replaced by `script`, or with the block
+/// appended when it has none, so installing again updates the aliases.
+pub fn with_alias_block(profile: &str, script: &str) -> String {
+    let block = format!("{}\n{}{}\n", BLOCK_START, script, BLOCK_END);
+    if let Some(start) = profile.find(BLOCK_START)
+        && let Some(end) = profile[start..].find(BLOCK_END)
+    {
+        let mut end = start + end + BLOCK_END.len();
+        if profile[end..].starts_with('\n') {
+            end += 1;
+        }
+        return format!("{}{}{}", &profile[..start], block, &profile[end..]);
+    }
+
+    let separator = match profile {
+        "" => "",
+        text if text.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    format!("{}{}{}", profile, separator, block)
+}
+
+/// Write the aliases `script` where `shell` loads them from, and return that file.
+///
+/// PowerShell aliases go into the current user's profile (`$PROFILE`) as a
+/// marked block, replacing the one of an earlier install; cmd.exe macros are
+/// written to their own doskey macro file. `Sh` aliases cannot be installed:
+/// they are meant for `eval "$(rgrc --aliases)"` in the shell's startup file.
+pub fn install(shell: AliasShell, script: &str) -> Result<PathBuf, RgrcError> {
+    let env = |name: &str| std::env::var(name).ok();
+    let (path, text) = match shell {
+        AliasShell::PowerShell => {
+            let path = powershell_profile(&env)
+                .ok_or_else(|| not_found("cannot locate the PowerShell profile"))?;
+            let profile = match std::fs::read_to_string(&path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                profile => profile?,
+            };
+            let text = with_alias_block(&profile, script);
+            (path, text)
+        }
+        AliasShell::Cmd => {
+            let path = doskey_file(&env)
+                .ok_or_else(|| not_found("cannot locate a directory for the doskey macros"))?;
+            (path, script.to_string())
+        }
+        AliasShell::Sh => {
+            return Err(RgrcError::Args(
+                "--install needs --shell powershell or cmd; for other shells add \
+                 eval \"$(rgrc --aliases)\" to their startup file"
+                    .to_string(),
+            ));
+        }
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+fn not_found(msg: &str) -> RgrcError {
+    RgrcError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, msg))
+}
+
+/// How to start using the aliases installed in `path`.
+pub fn install_hint(shell: AliasShell, path: &Path) -> String {
+    match shell {
+        AliasShell::PowerShell => format!(
+            "Restart PowerShell, or load them now with: . \"{}\"",
+            path.display()
+        ),
+        AliasShell::Cmd => format!(
+            "Load them with: doskey /macrofile=\"{}\"\n(add that command to the AutoRun value of \
+             HKCU\\Software\\Microsoft\\Command Processor to load them in every cmd.exe)",
+            path.display()
+        ),
+        AliasShell::Sh => String::new(),
+    }
+}
+
+/// The current user's PowerShell profile for the console host (`$PROFILE`).
+///
+/// Asked from `pwsh`, then Windows PowerShell, as the Documents folder may be
+/// redirected; when neither runs, PowerShell 7's default location.
+fn powershell_profile(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    for shell in ["pwsh", "powershell"] {
+        let output = std::process::Command::new(shell)
+            .args([
+                "-NoLogo",
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "$PROFILE",
+            ])
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output();
+        if let Ok(output) = output
+            && output.status.success()
+        {
+            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if !path.is_empty() {
+                return Some(path.into());
+            }
+        }
+    }
+
+    const PROFILE: &str = "Microsoft.PowerShell_profile.ps1";
+    if cfg!(windows) {
+        let home = env("USERPROFILE").filter(|home| !home.is_empty())?;
+        Some(Path::new(&home).join("Documents\\PowerShell").join(PROFILE))
+    } else {
+        let config_home = crate::XdgDirs::new(env).config_home?;
+        Some(config_home.join("powershell").join(PROFILE))
+    }
+}
+
+/// The doskey macro file for cmd.exe: `%APPDATA%\rgrc\aliases.doskey`, or in
+/// rgrc's user config directory without `APPDATA`.
+fn doskey_file(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let dir = match env("APPDATA").filter(|dir| !dir.is_empty()) {
+        Some(appdata) => Path::new(&appdata).join("rgrc"),
+        None => crate::XdgDirs::new(env).config_home?.join("rgrc"),
+    };
+    Some(dir.join("aliases.doskey"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_lines() {
+        assert_eq!(
+            alias_line(AliasShell::Sh, "rgrc", "ping"),
+            "alias ping='rgrc ping'"
+        );
+        assert_eq!(
+            alias_line(AliasShell::Sh, "rgrc", "journalctl"),
+            "alias journalctl='rgrc journalctl --no-pager | less -R'"
+        );
+        assert_eq!(
+            alias_script(AliasShell::PowerShell, "rgrc.exe", &["ls"]),
+            "Remove-Item Alias:ls -Force -ErrorAction Ignore\nfunction ls { rgrc.exe ls @args }\n"
+        );
+        assert_eq!(
+            alias_script(AliasShell::Cmd, "rgrc.exe", &["ping", "netstat"]),
+            "ping=rgrc.exe ping $*\nnetstat=rgrc.exe netstat $*\n"
+        );
+        assert_eq!("pwsh".parse(), Ok(AliasShell::PowerShell));
+        assert_eq!("zsh".parse(), Ok(AliasShell::Sh));
+        assert!("tcsh".parse::<AliasShell>().is_err());
+    }
+
+    #[test]
+    fn test_plugins() {
+        for shell in ["bash", "zsh", "fish"] {
+            let script = plugin_script(shell.parse().unwrap(), "rgrc");
+            assert!(script.starts_with(&format!("# rgrc plugin for {}", shell)));
+            assert!(script.contains(NO_ALIASES_ENV));
+            assert!(script.contains(&format!("rgrc --completions {}", shell)));
+            assert!(!script.contains("@RGRC@"));
+        }
+        assert!(plugin_script(PluginShell::Bash, "grc").contains("grc --aliases >"));
+        assert!("tcsh".parse::<PluginShell>().is_err());
+    }
+
+    #[test]
+    fn test_alias_block_is_appended_then_replaced() {
+        let first = with_alias_block("Set-PSReadLineOption -EditMode Emacs", "a\n");
+        assert_eq!(
+            first,
+            "Set-PSReadLineOption -EditMode Emacs\n\n# >>> rgrc aliases >>>\na\n# <<< rgrc aliases <<<\n"
+        );
+
+        // Installing again swaps the block and keeps what surrounds it
+        let edited = format!("{}Import-Module posh-git\n", first);
+        assert_eq!(
+            with_alias_block(&edited, "b\n"),
+            "Set-PSReadLineOption -EditMode Emacs\n\n# >>> rgrc aliases >>>\nb\n# <<< rgrc aliases <<<\nImport-Module posh-git\n"
+        );
+        assert_eq!(
+            with_alias_block("", "a\n"),
+            "# >>> rgrc aliases >>>\na\n# <<< rgrc aliases <<<\n"
+        );
+    }
+
+    #[test]
+    fn test_install_locations() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            doskey_file(&env(&[("APPDATA", "/appdata"), ("HOME", "/home/u")])),
+            Some(PathBuf::from("/appdata/rgrc/aliases.doskey"))
+        );
+        assert_eq!(
+            doskey_file(&env(&[("HOME", "/home/u")])),
+            Some(PathBuf::from("/home/u/.config/rgrc/aliases.doskey"))
+        );
+        assert_eq!(doskey_file(&env(&[])), None);
+    }
+}
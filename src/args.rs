@@ -3,7 +3,13 @@
 //! This module handles parsing command-line arguments and provides structured
 //! access to the parsed options.
 
-use crate::ColorMode;
+use crate::aliases::{AliasShell, PluginShell};
+use crate::colorizer::RuleSelector;
+use crate::fetch::ConfigCommand;
+use crate::grc::{Compat, RegexEngine};
+use crate::timestamps::TimestampFormat;
+use crate::{ColorMode, InputFormat, OutputFormat, RgrcError};
+use std::time::Duration;
 
 /// Debug level for rule debugging output.
 ///
@@ -48,6 +54,32 @@ impl std::str::FromStr for DebugLevel {
     }
 }
 
+/// Format of the rule debugging output (`--verbose-format`).
+///
+/// - `Text`: human-readable report on stderr, detailed according to the debug level
+/// - `Json`: one JSON match event per input line on stderr, for tooling
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum VerboseFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for VerboseFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(VerboseFormat::Text),
+            "json" => Ok(VerboseFormat::Json),
+            _ => Err(format!(
+                "Invalid verbose format: {}. Must be text or json.",
+                s
+            )),
+        }
+    }
+}
+
 /// Parsed command-line arguments for the `rgrc` binary.
 ///
 /// This structure contains the semantic options extracted from the raw
@@ -57,13 +89,57 @@ impl std::str::FromStr for DebugLevel {
 /// # Fields
 ///
 /// - `color`: Color mode requested by the user (`On`, `Off`, `Auto`).
+/// - `color_stdout`: Optional color mode for stdout, in place of `color`.
+/// - `color_stderr`: Optional color mode for the command's stderr, in place of `color`.
 /// - `command`: The command and its arguments to run (first element is the
 ///   executable name).
+/// - `shell_command`: Optional command line run through the user's shell instead of `command`.
 /// - `show_aliases`: Whether to print shell aliases for available commands.
 /// - `show_all_aliases`: Whether to print aliases for all known commands.
 /// - `except_aliases`: Comma-separated list of commands to exclude when
 ///   generating aliases.
+/// - `alias_shell`: Shell the aliases are written for.
+/// - `install_aliases`: Whether to install the aliases for `alias_shell` instead of printing them.
+/// - `show_paths`: Whether to print the config search order and exit.
+/// - `show_config`: Whether to print the effective rules for `command` (or `config`) as a conf file.
+/// - `explain`: Whether to explain how `command` resolves to configs and rules instead of running it.
+/// - `strip`: Whether escape sequences are removed from the output instead of added.
+/// - `repl`: Whether to start the interactive rule tester (`rgrc repl [-c NAME]`).
+/// - `fetch_configs`: Whether to install the configs of a config index (`rgrc fetch-configs [URL]`).
+/// - `config_index`: URL of the config index given to `fetch-configs`.
+/// - `config_command`: The `rgrc config` subcommand managing installed configs, if given.
+/// - `self_update`: Whether to replace rgrc with its latest release (`rgrc self-update`).
+/// - `new_config`: Whether to write a starter config for `command` (`rgrc new-config CMD`).
+/// - `plugin_shell`: Shell to print a plugin for (`rgrc plugin bash|zsh|fish`), if given.
+/// - `suggest`: Whether to print rules suggested for sample output (`rgrc suggest < SAMPLE`).
+/// - `sample`: File with sample output for `new-config` or `suggest`, instead of the command or stdin.
 /// - `flush_cache`: Whether to flush and rebuild the cache directory (embed-configs only).
+/// - `cache_dir`: Optional cache directory overriding `RGRC_CACHE_DIR` and the XDG default.
+/// - `conf`: Optional grc.conf (command→config mapping) searched before all others.
+/// - `log_file`: Optional path receiving a plain-text copy of the output.
+/// - `format`: Output format (`ansi` text, `jsonl` match records or `markup` style tags).
+/// - `input_format`: Input format (`text`, `csv`/`tsv` to colour columns or `json` to colour tokens).
+/// - `shade_rows`: Whether every second line of CSV/TSV input gets a dark background.
+/// - `pretty`: Whether JSON input is re-indented.
+/// - `stats`: Whether to print per-rule hit counts to stderr at exit.
+/// - `profile`: Whether to print per-rule regex timing to stderr at exit.
+/// - `allow_hooks`: Whether rules may run their `command=` hooks.
+/// - `stderr`: Whether the command's stderr is colorized too.
+/// - `timeout`: Optional time after which the command is stopped.
+/// - `exit_on_match`: Optional rules whose first match stops the command, with no output.
+/// - `count`: Optional rules whose matching lines are counted and reported at exit.
+/// - `fail_above`: Optional count above which rgrc exits with a failure status.
+/// - `only_matching_lines`: Whether the lines no rule matched are left out.
+/// - `drop_matching`: Rules whose matching lines are left out.
+/// - `timestamps`: Optional time prefix for each line of the output.
+/// - `bell_on`: Optional regex whose matching lines ring the terminal bell.
+/// - `notify_on`: Optional regex whose matching lines send a desktop notification.
+/// - `tag_stderr`: Whether the command's stderr lines are tagged (implies `stderr`).
+/// - `line_timeout`: Optional time a line may spend in regex matching.
+/// - `disable_slow_rules`: Whether a rule that runs out the line timeout is disabled.
+/// - `max_line_length`: Optional length in bytes above which lines are not colorized.
+/// - `engine`: Regex engine for the rules that do not choose one with `engine=`.
+/// - `compat`: Implementation whose quirks config files are read with.
 ///
 /// # Example
 ///
@@ -75,24 +151,121 @@ impl std::str::FromStr for DebugLevel {
 pub struct Args {
     /// Requested color mode (on/off/auto)
     pub color: ColorMode,
+    /// Color mode of stdout, overriding `color` (`--color-stdout`)
+    pub color_stdout: Option<ColorMode>,
+    /// Color mode of the command's stderr with `--stderr`, overriding `color`;
+    /// `Auto` looks at whether stderr is a terminal (`--color-stderr`)
+    pub color_stderr: Option<ColorMode>,
     /// Command to execute and its arguments
     pub command: Vec<String>,
+    /// Command line to run with `$SHELL -c` (`--shell` without `--aliases`)
+    pub shell_command: Option<String>,
     /// Print aliases for detected commands in PATH
     pub show_aliases: bool,
     /// Print aliases for all supported commands
     pub show_all_aliases: bool,
     /// Commands to exclude from alias generation
     pub except_aliases: Vec<String>,
+    /// Shell the aliases are written for
+    pub alias_shell: AliasShell,
+    /// Install the aliases into the shell's profile (PowerShell) or a doskey file (cmd)
+    pub install_aliases: bool,
+    /// Print every config file and directory rgrc would consult, then exit
+    pub show_paths: bool,
+    /// Print the rules that would be applied, in grcat config syntax, instead of running the command
+    pub show_config: bool,
+    /// Explain how the command resolves to configs and rules instead of running it
+    pub explain: bool,
+    /// Remove the escape sequences of the command's output, or of stdin without
+    /// a command, instead of colouring it (`--strip`)
+    pub strip: bool,
+    /// Start the interactive rule tester (`rgrc repl`)
+    pub repl: bool,
+    /// Install the configs of a config index (`rgrc fetch-configs`)
+    pub fetch_configs: bool,
+    /// URL of the index for `fetch-configs`; `$RGRC_CONFIG_INDEX` when not given
+    pub config_index: Option<String>,
+    /// Manage the installed configs (`rgrc config list|install|remove|update`)
+    pub config_command: Option<ConfigCommand>,
+    /// Replace rgrc with its latest release (`rgrc self-update`)
+    pub self_update: bool,
+    /// Write a starter config for the command (`rgrc new-config CMD`)
+    pub new_config: bool,
+    /// Print a shell plugin for this shell (`rgrc plugin bash|zsh|fish`)
+    pub plugin_shell: Option<PluginShell>,
+    /// Print rules suggested for sample output on stdin (`rgrc suggest`)
+    pub suggest: bool,
+    /// Sample output for `new-config` or `suggest` to read instead of running
+    /// the command or reading stdin
+    pub sample: Option<String>,
     /// Flush and rebuild cache directory (embed-configs only)
     pub flush_cache: bool,
+    /// Cache directory to use instead of the default location (embed-configs only)
+    pub cache_dir: Option<String>,
     /// Print the CLI version and exit
     pub show_version: bool,
     /// Print shell completions for specified shell (bash|zsh|fish|ash)
     pub show_completions: Option<String>,
     /// Debug level for rule matching (0=off, 1=basic, 2=verbose)
     pub debug_level: DebugLevel,
+    /// Debug output format (text|json); json emits per-line match events
+    pub verbose_format: VerboseFormat,
     /// Explicitly specify config file name (e.g., "df" to load conf.df)
     pub config: Option<String>,
+    /// Alternate grc.conf mapping commands to config files (overrides RGRC_CONF)
+    pub conf: Option<String>,
+    /// Write an ANSI-stripped copy of the output to this file (tee mode)
+    pub log_file: Option<String>,
+    /// Output format (ansi|jsonl|markup)
+    pub format: OutputFormat,
+    /// Input format (text|csv|tsv|json); delimited input has its columns coloured,
+    /// JSON its tokens
+    pub input_format: InputFormat,
+    /// Shade every second row of CSV/TSV input
+    pub shade_rows: bool,
+    /// Pretty-print JSON input
+    pub pretty: bool,
+    /// Print a per-rule hit count summary to stderr at exit
+    pub stats: bool,
+    /// Print the rules with the highest cumulative regex time to stderr at exit
+    pub profile: bool,
+    /// Run the `command=` hooks of rules that match (off by default)
+    pub allow_hooks: bool,
+    /// Colorize the command's stderr as well as its stdout
+    pub stderr: bool,
+    /// Start each line of the command's stderr with `[stderr]`
+    pub tag_stderr: bool,
+    /// Stop the command (SIGTERM, then SIGKILL) once it has run this long
+    pub timeout: Option<Duration>,
+    /// Print nothing, and stop the command at the first line these rules match
+    /// (`-q`, `--exit-on-match[=RULE]`)
+    pub exit_on_match: Option<RuleSelector>,
+    /// Print how many lines these rules matched (to stderr) at exit (`--count[=RULE]`)
+    pub count: Option<RuleSelector>,
+    /// Exit with status 1 when the `count` is higher than this (`--fail-above`)
+    pub fail_above: Option<usize>,
+    /// Print only the lines a rule matched (`--only-matching-lines`)
+    pub only_matching_lines: bool,
+    /// Leave out the lines these rules matched (`--drop-matching RULES|all`)
+    pub drop_matching: Vec<RuleSelector>,
+    /// Start each output line with the time, of day or since the command started
+    /// (`--timestamps[=absolute|relative]`)
+    pub timestamps: Option<TimestampFormat>,
+    /// Ring the terminal bell on lines matching this regex (`--bell-on`)
+    pub bell_on: Option<String>,
+    /// Send a desktop notification for lines matching this regex (`--notify-on`)
+    pub notify_on: Option<String>,
+    /// Write lines that take longer than this in regex matching uncoloured
+    pub line_timeout: Option<Duration>,
+    /// Disable a rule for the rest of the input once it ran out the line timeout
+    pub disable_slow_rules: bool,
+    /// Pass lines longer than this many bytes through without colorizing them
+    /// (also `RGRC_MAX_LINE_LENGTH`)
+    pub max_line_length: Option<usize>,
+    /// Regex engine for the rules without `engine=`
+    pub engine: RegexEngine,
+    /// Read configs with Python grc's quirks (`--compat=grc`)
+    pub compat: Compat,
 }
 
 /// Parse command-line arguments
@@ -101,8 +274,8 @@ pub struct Args {
 /// Parse command-line arguments and return an `Args` structure.
 ///
 /// This function reads `std::env::args()` (skipping the program name) and
-/// supports flags documented in the CLI help. On invalid usage it returns an
-/// `Err(String)` describing the problem.
+/// supports flags documented in the CLI help. On invalid usage it returns
+/// `RgrcError::Args` describing the problem.
 ///
 /// # Examples
 ///
@@ -110,9 +283,31 @@ pub struct Args {
 /// // Simulated invocation: rgrc --color=on ping -c 1 google.com
 /// let args = rgrc::args::parse_args().expect("valid args");
 /// ```
-pub fn parse_args() -> Result<Args, String> {
+pub fn parse_args() -> Result<Args, RgrcError> {
     let args: Vec<String> = std::env::args().skip(1).collect();
-    parse_args_impl(args)
+    let mut parsed = parse_args_impl(args).map_err(RgrcError::Args)?;
+
+    // The environment supplies the line length limit when the flag does not
+    if parsed.max_line_length.is_none()
+        && let Some(value) = std::env::var("RGRC_MAX_LINE_LENGTH")
+            .ok()
+            .filter(|value| !value.is_empty())
+    {
+        parsed.max_line_length = Some(
+            parse_line_length(&value)
+                .map_err(|e| RgrcError::Args(format!("RGRC_MAX_LINE_LENGTH: {}", e)))?,
+        );
+    }
+    Ok(parsed)
+}
+
+/// Parse a line length limit: a positive number of bytes.
+fn parse_line_length(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|bytes| *bytes > 0)
+        .ok_or_else(|| format!("Invalid line length: {} (expected bytes)", value))
 }
 
 /// Internal implementation of argument parsing
@@ -153,23 +348,83 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
     }
 
     let mut color = ColorMode::Auto;
+    let mut color_stdout: Option<ColorMode> = None;
+    let mut color_stderr: Option<ColorMode> = None;
     let mut command = Vec::new();
     let mut show_aliases = false;
     let mut show_all_aliases = false;
     let mut except_aliases = Vec::new();
+    let mut alias_shell = AliasShell::Sh;
+    let mut shell_value: Option<String> = None;
+    let mut install_aliases = false;
+    let mut show_paths = false;
+    let mut explain = false;
+    let mut show_config = false;
+    let mut repl = false;
+    let mut fetch_configs = false;
+    let mut config_subcommand = false;
+    let mut self_update = false;
+    let mut new_config = false;
+    let mut suggest = false;
+    let mut plugin = false;
+    // Whether one of the subcommands above was given; after it their names are command words
+    let mut subcommand = false;
+    let mut sample: Option<String> = None;
+    let mut strip = false;
     let mut flush_cache = false;
+    let mut cache_dir: Option<String> = None;
     let mut show_version = false;
     let mut show_completions: Option<String> = None;
     let mut config: Option<String> = None;
+    let mut conf: Option<String> = None;
+    let mut log_file: Option<String> = None;
+    let mut format = OutputFormat::Ansi;
+    let mut input_format = InputFormat::Text;
+    let mut shade_rows = false;
+    let mut pretty = false;
+    let mut line_timeout: Option<Duration> = None;
+    let mut disable_slow_rules = false;
+    let mut max_line_length: Option<usize> = None;
+    let mut engine = RegexEngine::Auto;
+    let mut compat = Compat::Rgrc;
+    let mut stats = false;
+    let mut profile = false;
+    let mut allow_hooks = false;
+    let mut stderr = false;
+    let mut tag_stderr = false;
+    let mut timeout: Option<Duration> = None;
+    let mut exit_on_match: Option<RuleSelector> = None;
+    let mut count: Option<RuleSelector> = None;
+    let mut fail_above: Option<usize> = None;
+    let mut only_matching_lines = false;
+    let mut drop_matching: Vec<RuleSelector> = Vec::new();
+    let mut timestamps: Option<TimestampFormat> = None;
+    let mut bell_on: Option<String> = None;
+    let mut notify_on: Option<String> = None;
     #[cfg(feature = "debug")]
     let mut debug_level = DebugLevel::Off;
     #[cfg(not(feature = "debug"))]
     let debug_level = DebugLevel::Off;
+    let mut verbose_format = VerboseFormat::Text;
 
     let mut i = 0;
     while i < args.len() {
         let arg = args[i].as_str();
         match arg {
+            arg if arg.starts_with("--color-std") || arg.starts_with("--colour-std") => {
+                // --color-stdout, --color-stderr, and their --colour spellings
+                let name = arg[2..].split('=').next().unwrap_or_default();
+                let (value, next_i) = parse_arg_value(&args, i, name)?;
+                let mode = value
+                    .parse()
+                    .map_err(|_| format!("Invalid color mode: {}", value))?;
+                match name {
+                    "color-stdout" | "colour-stdout" => color_stdout = Some(mode),
+                    "color-stderr" | "colour-stderr" => color_stderr = Some(mode),
+                    _ => return Err(format!("Unknown option: {}", arg)),
+                }
+                i = next_i;
+            }
             arg if arg.starts_with("--color") || arg.starts_with("--colour") => {
                 // Determine which spelling variant was used
                 let arg_name = if arg.starts_with("--colour") {
@@ -197,6 +452,11 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
                 show_completions = Some(value.to_string());
                 i = next_i;
             }
+            arg if arg == "--conf" || arg.starts_with("--conf=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "conf")?;
+                conf = Some(value.to_string());
+                i = next_i;
+            }
             arg if arg.starts_with("--config") || arg == "-c" => {
                 // Handle both -c value and --config=value formats
                 let arg_name = "config";
@@ -213,6 +473,74 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
                 config = Some(value.to_string());
                 i = next_i;
             }
+            arg if arg == "--cache-dir" || arg.starts_with("--cache-dir=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "cache-dir")?;
+                cache_dir = Some(value.to_string());
+                i = next_i;
+            }
+            arg if arg == "--log" || arg.starts_with("--log=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "log")?;
+                log_file = Some(value.to_string());
+                i = next_i;
+            }
+            arg if arg == "--format" || arg.starts_with("--format=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "format")?;
+                format = value.parse()?;
+                i = next_i;
+            }
+            arg if arg == "--format-in" || arg.starts_with("--format-in=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "format-in")?;
+                input_format = value.parse()?;
+                i = next_i;
+            }
+            "--shade-rows" => {
+                shade_rows = true;
+                i += 1;
+            }
+            "--pretty" => {
+                pretty = true;
+                i += 1;
+            }
+            arg if arg == "--line-timeout" || arg.starts_with("--line-timeout=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "line-timeout")?;
+                line_timeout = match value.parse::<u64>() {
+                    Ok(millis) if millis > 0 => Some(Duration::from_millis(millis)),
+                    _ => {
+                        return Err(format!(
+                            "Invalid line timeout: {} (expected milliseconds)",
+                            value
+                        ));
+                    }
+                };
+                i = next_i;
+            }
+            arg if arg == "--timeout" || arg.starts_with("--timeout=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "timeout")?;
+                timeout = match value.parse::<f64>().map(Duration::try_from_secs_f64) {
+                    Ok(Ok(secs)) if !secs.is_zero() => Some(secs),
+                    _ => return Err(format!("Invalid timeout: {} (expected seconds)", value)),
+                };
+                i = next_i;
+            }
+            "--disable-slow-rules" => {
+                disable_slow_rules = true;
+                i += 1;
+            }
+            arg if arg == "--max-line-length" || arg.starts_with("--max-line-length=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "max-line-length")?;
+                max_line_length = Some(parse_line_length(value)?);
+                i = next_i;
+            }
+            arg if arg == "--engine" || arg.starts_with("--engine=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "engine")?;
+                engine = value.parse()?;
+                i = next_i;
+            }
+            arg if arg == "--compat" || arg.starts_with("--compat=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "compat")?;
+                compat = value.parse()?;
+                i = next_i;
+            }
             "--aliases" => {
                 show_aliases = true;
                 i += 1;
@@ -221,6 +549,21 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
                 show_all_aliases = true;
                 i += 1;
             }
+            arg if arg == "--shell" || arg.starts_with("--shell=") => {
+                // A shell name with --aliases, else a command line (resolved below)
+                let (value, next_i) = parse_arg_value(&args, i, "shell")?;
+                shell_value = Some(value.to_string());
+                i = next_i;
+            }
+            "--install" => {
+                install_aliases = true;
+                i += 1;
+            }
+            arg if arg == "--verbose-format" || arg.starts_with("--verbose-format=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "verbose-format")?;
+                verbose_format = value.parse()?;
+                i = next_i;
+            }
             arg if arg.starts_with("--verbose") || arg == "-v" || arg == "-vv" => {
                 #[cfg(feature = "debug")]
                 {
@@ -241,6 +584,113 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
                 }
                 i += 1;
             }
+            "--stats" => {
+                stats = true;
+                i += 1;
+            }
+            "--profile" => {
+                profile = true;
+                i += 1;
+            }
+            "--allow-hooks" => {
+                allow_hooks = true;
+                i += 1;
+            }
+            "--stderr" => {
+                stderr = true;
+                i += 1;
+            }
+            "--tag-stderr" => {
+                stderr = true;
+                tag_stderr = true;
+                i += 1;
+            }
+            "-q" | "--exit-on-match" => {
+                exit_on_match = Some(RuleSelector::Any);
+                i += 1;
+            }
+            arg if arg.starts_with("--exit-on-match=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "exit-on-match")?;
+                exit_on_match = Some(value.parse()?);
+                i = next_i;
+            }
+            "--count" => {
+                count = Some(RuleSelector::Any);
+                i += 1;
+            }
+            arg if arg.starts_with("--count=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "count")?;
+                count = Some(value.parse()?);
+                i = next_i;
+            }
+            arg if arg == "--fail-above" || arg.starts_with("--fail-above=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "fail-above")?;
+                fail_above = Some(value.parse().map_err(|_| {
+                    format!(
+                        "Invalid count for --fail-above: {} (expected a number)",
+                        value
+                    )
+                })?);
+                i = next_i;
+            }
+            "--strip" => {
+                strip = true;
+                i += 1;
+            }
+            "--only-matching-lines" => {
+                only_matching_lines = true;
+                i += 1;
+            }
+            arg if arg == "--drop-matching" || arg.starts_with("--drop-matching=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "drop-matching")?;
+                drop_matching = match value {
+                    "all" => vec![RuleSelector::Any],
+                    _ => value.split(',').map(str::parse).collect::<Result<_, _>>()?,
+                };
+                i = next_i;
+            }
+            "--timestamps" => {
+                timestamps = Some(TimestampFormat::Absolute);
+                i += 1;
+            }
+            arg if arg.starts_with("--timestamps=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "timestamps")?;
+                timestamps = Some(value.parse()?);
+                i = next_i;
+            }
+            arg if arg == "--bell-on" || arg.starts_with("--bell-on=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "bell-on")?;
+                if let Err(e) = crate::grc::CompiledRegex::new(value) {
+                    return Err(format!("Invalid regex for --bell-on: {}", e));
+                }
+                bell_on = Some(value.to_string());
+                i = next_i;
+            }
+            arg if arg == "--notify-on" || arg.starts_with("--notify-on=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "notify-on")?;
+                if let Err(e) = crate::grc::CompiledRegex::new(value) {
+                    return Err(format!("Invalid regex for --notify-on: {}", e));
+                }
+                notify_on = Some(value.to_string());
+                i = next_i;
+            }
+            arg if arg == "--sample" || arg.starts_with("--sample=") => {
+                let (value, next_i) = parse_arg_value(&args, i, "sample")?;
+                sample = Some(value.to_string());
+                i = next_i;
+            }
+            "--paths" => {
+                show_paths = true;
+                i += 1;
+            }
+            "--show-config" => {
+                show_config = true;
+                i += 1;
+            }
+            "--explain" => {
+                explain = true;
+                i += 1;
+            }
             "--flush-cache" => {
                 flush_cache = true;
                 i += 1;
@@ -253,6 +703,47 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
                 print_help();
                 std::process::exit(0);
             }
+            "repl" if command.is_empty() && !subcommand => {
+                // `rgrc repl [-c NAME]`: the options that follow still apply
+                repl = true;
+                subcommand = true;
+                i += 1;
+            }
+            "config" if command.is_empty() && !subcommand => {
+                // `rgrc config COMMAND [NAME...]`
+                config_subcommand = true;
+                subcommand = true;
+                i += 1;
+            }
+            "new-config" if command.is_empty() && !subcommand => {
+                // `rgrc new-config [--sample FILE] CMD [ARGS...]`
+                new_config = true;
+                subcommand = true;
+                i += 1;
+            }
+            "plugin" if command.is_empty() && !subcommand => {
+                // `rgrc plugin SHELL`
+                plugin = true;
+                subcommand = true;
+                i += 1;
+            }
+            "suggest" if command.is_empty() && !subcommand => {
+                // `rgrc suggest [--sample FILE]`, reading stdin by default
+                suggest = true;
+                subcommand = true;
+                i += 1;
+            }
+            "self-update" if command.is_empty() && !subcommand => {
+                self_update = true;
+                subcommand = true;
+                i += 1;
+            }
+            "fetch-configs" if command.is_empty() && !subcommand => {
+                // `rgrc fetch-configs [URL]`
+                fetch_configs = true;
+                subcommand = true;
+                i += 1;
+            }
             _ => {
                 // Everything else is treated as command arguments
                 command.extend_from_slice(&args[i..]);
@@ -261,13 +752,118 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
         }
     }
 
-    if command.is_empty()
+    let mut shell_command = None;
+    if let Some(value) = shell_value {
+        if show_aliases || show_all_aliases {
+            alias_shell = value.parse()?;
+        } else if !command.is_empty() {
+            return Err(format!(
+                "--shell takes the whole command line as one argument, e.g. --shell '{} ...'",
+                value
+            ));
+        } else {
+            shell_command = Some(value);
+        }
+    }
+
+    if repl && !command.is_empty() {
+        return Err(format!("Unexpected argument for repl: {}", command[0]));
+    }
+    let mut config_index = None;
+    if fetch_configs {
+        if command.len() > 1 {
+            return Err(format!(
+                "Unexpected argument for fetch-configs: {}",
+                command[1]
+            ));
+        }
+        config_index = command.pop();
+    }
+    if self_update && !command.is_empty() {
+        return Err(format!(
+            "Unexpected argument for self-update: {}",
+            command[0]
+        ));
+    }
+    if new_config && command.is_empty() {
+        return Err("new-config needs a command".to_string());
+    }
+    if sample.is_some() && !new_config && !suggest {
+        return Err("--sample is for new-config and suggest".to_string());
+    }
+    let mut plugin_shell = None;
+    if plugin {
+        match command.as_slice() {
+            [shell] => plugin_shell = Some(shell.parse()?),
+            [] => return Err("plugin needs a shell: bash, zsh or fish".to_string()),
+            [_, extra, ..] => return Err(format!("Unexpected argument for plugin: {}", extra)),
+        }
+        command.clear();
+    }
+    if suggest && !command.is_empty() {
+        return Err(format!("Unexpected argument for suggest: {}", command[0]));
+    }
+    if sample.is_some() && command.len() > 1 {
+        return Err(format!(
+            "new-config --sample takes the command name only, not {}",
+            command[1]
+        ));
+    }
+    let mut config_command = None;
+    if config_subcommand {
+        config_command = Some(ConfigCommand::parse(&command)?);
+        command.clear();
+    }
+    if explain && command.is_empty() {
+        return Err("Missing command for --explain".to_string());
+    }
+    if show_config && command.is_empty() && config.is_none() {
+        return Err("Missing command or --config for --show-config".to_string());
+    }
+    if install_aliases && !show_aliases && !show_all_aliases {
+        return Err("--install needs --aliases or --all-aliases".to_string());
+    }
+    let has_command = !command.is_empty() || shell_command.is_some();
+    if color_stderr.is_some() && !stderr {
+        return Err("--color-stderr needs --stderr or --tag-stderr".to_string());
+    }
+    if stderr && !has_command {
+        return Err("--stderr and --tag-stderr need a command".to_string());
+    }
+    if timeout.is_some() && !has_command {
+        return Err("--timeout needs a command".to_string());
+    }
+    if exit_on_match.is_some() && !has_command {
+        return Err("-q and --exit-on-match need a command".to_string());
+    }
+    if fail_above.is_some() && count.is_none() {
+        return Err("--fail-above needs --count".to_string());
+    }
+    if timestamps.is_some() && format == OutputFormat::Jsonl {
+        return Err("--timestamps cannot be used with --format=jsonl".to_string());
+    }
+    if strip && config.is_some() {
+        return Err("--strip reads stdin itself, without --config".to_string());
+    }
+    if install_aliases && alias_shell == AliasShell::Sh {
+        return Err("--install needs --shell powershell or --shell cmd".to_string());
+    }
+
+    if !has_command
         && !show_aliases
         && !show_all_aliases
+        && !show_paths
+        && !repl
+        && !fetch_configs
+        && config_command.is_none()
+        && !self_update
+        && !suggest
+        && plugin_shell.is_none()
         && !flush_cache
         && !show_version
         && show_completions.is_none()
         && config.is_none()
+        && !strip
     {
         return Err("No command specified".to_string());
     }
@@ -279,17 +875,67 @@ fn parse_args_impl(args: Vec<String>) -> Result<Args, String> {
         color = ColorMode::On;
     }
 
+    // `-c json` highlights JSON with the tokenizer, under the rules of conf.json
+    if config.as_deref() == Some("json") && input_format == InputFormat::Text {
+        input_format = InputFormat::Json;
+    }
+
     Ok(Args {
         color,
+        color_stdout,
+        color_stderr,
         command,
+        shell_command,
         show_aliases,
         show_all_aliases,
         except_aliases,
+        alias_shell,
+        install_aliases,
+        show_paths,
+        explain,
+        show_config,
+        repl,
+        fetch_configs,
+        config_index,
+        config_command,
+        self_update,
+        new_config,
+        plugin_shell,
+        suggest,
+        sample,
+        strip,
         flush_cache,
+        cache_dir,
         show_version,
         show_completions,
         debug_level,
+        verbose_format,
         config,
+        conf,
+        log_file,
+        format,
+        input_format,
+        shade_rows,
+        pretty,
+        stats,
+        profile,
+        allow_hooks,
+        stderr,
+        tag_stderr,
+        timeout,
+        exit_on_match,
+        count,
+        fail_above,
+        only_matching_lines,
+        drop_matching,
+        timestamps,
+        bell_on,
+        notify_on,
+        line_timeout,
+        disable_slow_rules,
+        max_line_length,
+        engine,
+        compat,
     })
 }
 
@@ -309,7 +955,7 @@ pub fn get_completion_script(shell: &str) -> Option<&'static str> {
     fi
 
     if [[ ${cur} == --* ]]; then
-        COMPREPLY=( $(compgen -W "--color --aliases --all-aliases --except --flush-cache --help -h --version -v --completions" -- "$cur") )
+        COMPREPLY=( $(compgen -W "--color --color-stdout --color-stderr --aliases --all-aliases --except --shell --install --paths --explain --show-config --flush-cache --cache-dir --config --conf --log --format --format-in --shade-rows --pretty --line-timeout --disable-slow-rules --max-line-length --strip --engine --compat --stats --profile --allow-hooks --stderr --tag-stderr --timeout --exit-on-match -q --count --fail-above --only-matching-lines --drop-matching --timestamps --bell-on --notify-on --sample --help -h --version -v --completions" -- "$cur") )
         return 0
     fi
 
@@ -325,10 +971,46 @@ complete -F _rgrc_completions rgrc
 _rgrc() {
   _arguments \
     '--color=[Override color output]:mode:(on off auto)' \
+    '--color-stdout=[Override color output on stdout]:mode:(on off auto)' \
+    '--color-stderr=[Override color output on stderr]:mode:(on off auto)' \
     '--aliases[Output shell aliases for available binaries]' \
     '--all-aliases[Output all shell aliases]' \
     '--except=[Exclude commands from alias generation]:commands:' \
+    '--shell=[Shell to write aliases for, or command line to run with $SHELL -c]:shell:(sh bash zsh fish ash powershell cmd)' \
+    '--install[Install the aliases (powershell, cmd)]' \
+    '--paths[Show the config search order]' \
+    '--explain[Explain how a command resolves to configs and rules]' \
+    '--show-config[Print the effective rules as a config file]' \
     '--flush-cache[Flush and rebuild cache dir]' \
+    '--cache-dir=[Use DIR as the cache directory]:directory:_files -/' \
+    '--conf=[Map commands to configs with FILE]:file:_files' \
+    '--log=[Write a plain-text copy of the output to FILE]:file:_files' \
+    '--format=[Output format]:format:(ansi jsonl markup)' \
+    '--format-in=[Input format]:format:(text csv tsv json)' \
+    '--shade-rows[Shade every second row of CSV/TSV input]' \
+    '--pretty[Pretty-print JSON input]' \
+    '--line-timeout=[Leave lines taking longer than MS uncoloured]:milliseconds:' \
+    '--disable-slow-rules[Disable rules that run out the line timeout]' \
+    '--max-line-length=[Leave lines longer than BYTES uncoloured]:bytes:' \
+    '--strip[Remove colours and other escape sequences instead]' \
+    '--engine=[Regex engine]:engine:(auto fast enhanced)' \
+    '--compat=[Read configs as another implementation]:mode:(rgrc grc)' \
+    '--stats[Print rule hit counts at exit]' \
+    '--profile[Print the slowest rules at exit]' \
+    '--allow-hooks[Run the command= hooks of matching rules]' \
+    '--stderr[Colorize stderr of the command too]' \
+    '--tag-stderr[Colorize and tag stderr lines of the command]' \
+    '--timeout=[Stop the command after SECS seconds]:seconds:' \
+    '--exit-on-match=-[Stop at the first match of RULE (or any rule) and exit 0, else 1]:rule:' \
+    '-q[Stop at the first match and exit 0, else 1]' \
+    '--count=-[Print how many lines RULE (or any rule) matched at exit]:rule:' \
+    '--fail-above=[Exit with status 1 when the count is over N]:count:' \
+    '--only-matching-lines[Print only the lines a rule matched]' \
+    '--drop-matching=[Leave out the lines RULES (or all rules) matched]:rules:' \
+    '--timestamps=-[Start each line with the time of day or since the start]:timestamps:(absolute relative)' \
+    '--bell-on=[Ring the terminal bell on lines matching REGEX]:regex:' \
+    '--notify-on=[Send a desktop notification for lines matching REGEX]:regex:' \
+    '--sample=[Sample output for new-config and suggest]:file:_files' \
     '--help[Show help]' \
     '--version[Show version]' \
     '--completions=[Print completions for shell]:shell:(bash zsh fish ash)' \
@@ -341,10 +1023,45 @@ compdef _rgrc rgrc
         "fish" => Some(
             r#"# fish completion for rgrc
 complete -c rgrc -l color -d 'Override color output (on,off,auto)'
+complete -c rgrc -l color-stdout -x -d 'Override color output on stdout' -a 'on off auto'
+complete -c rgrc -l color-stderr -x -d 'Override color output on stderr' -a 'on off auto'
 complete -c rgrc -l aliases -d 'Output shell aliases for detected binaries'
 complete -c rgrc -l all-aliases -d 'Output all aliases'
 complete -c rgrc -l except -r -d 'Exclude commands from alias generation' -a '(__rgrc_list_commands)'
+complete -c rgrc -l shell -x -d 'Shell to write aliases for, or command line to run' -a 'sh bash zsh fish ash powershell cmd'
+complete -c rgrc -l install -d 'Install the aliases (powershell, cmd)'
+complete -c rgrc -l paths -d 'Show the config search order'
+complete -c rgrc -l explain -d 'Explain how a command resolves to configs and rules'
+complete -c rgrc -l show-config -d 'Print the effective rules as a config file'
 complete -c rgrc -l flush-cache -d 'Flush cache (embed-configs only)'
+complete -c rgrc -l cache-dir -r -a '(__fish_complete_directories)' -d 'Use DIR as the cache directory'
+complete -c rgrc -l conf -r -F -d 'Map commands to configs with FILE'
+complete -c rgrc -l log -r -F -d 'Write a plain-text copy of the output to FILE'
+complete -c rgrc -l format -x -d 'Output format' -a 'ansi jsonl markup'
+complete -c rgrc -l format-in -x -d 'Input format' -a 'text csv tsv json'
+complete -c rgrc -l shade-rows -d 'Shade every second row of CSV/TSV input'
+complete -c rgrc -l pretty -d 'Pretty-print JSON input'
+complete -c rgrc -l line-timeout -x -d 'Leave lines taking longer than MS uncoloured'
+complete -c rgrc -l disable-slow-rules -d 'Disable rules that run out the line timeout'
+complete -c rgrc -l max-line-length -x -d 'Leave lines longer than BYTES uncoloured'
+complete -c rgrc -l strip -d 'Remove colours and other escape sequences instead'
+complete -c rgrc -l engine -x -d 'Regex engine' -a 'auto fast enhanced'
+complete -c rgrc -l compat -x -d 'Read configs as another implementation' -a 'rgrc grc'
+complete -c rgrc -l stats -d 'Print rule hit counts at exit'
+complete -c rgrc -l profile -d 'Print the slowest rules at exit'
+complete -c rgrc -l allow-hooks -d 'Run the command= hooks of matching rules'
+complete -c rgrc -l stderr -d 'Colorize stderr of the command too'
+complete -c rgrc -l tag-stderr -d 'Colorize and tag stderr lines of the command'
+complete -c rgrc -l timeout -x -d 'Stop the command after SECS seconds'
+complete -c rgrc -s q -l exit-on-match -d 'Stop at the first match and exit 0, else 1'
+complete -c rgrc -l count -d 'Print how many lines matched at exit'
+complete -c rgrc -l fail-above -x -d 'Exit with status 1 when the count is over N'
+complete -c rgrc -l only-matching-lines -d 'Print only the lines a rule matched'
+complete -c rgrc -l drop-matching -x -d 'Leave out the lines RULES (or all rules) matched'
+complete -c rgrc -l timestamps -d 'Start each line with the time' -a 'absolute relative'
+complete -c rgrc -l bell-on -x -d 'Ring the terminal bell on lines matching REGEX'
+complete -c rgrc -l notify-on -x -d 'Send a desktop notification for lines matching REGEX'
+complete -c rgrc -l sample -r -F -d 'Sample output for new-config and suggest'
 complete -c rgrc -l help -d 'Show help'
 complete -c rgrc -l version -s v -d 'Show version'
 complete -c rgrc -l completions -d 'Print completions for shell' -a 'bash zsh fish ash'
@@ -361,7 +1078,7 @@ end
         ),
         "ash" => Some(
             r#"# ash / sh completion helper (simple - may need shell support)
-complete -W "--color --aliases --all-aliases --except --flush-cache --help -h --version -v --completions" rgrc
+complete -W "--color --color-stdout --color-stderr --aliases --all-aliases --except --shell --install --paths --explain --show-config --flush-cache --cache-dir --config --conf --log --format --format-in --shade-rows --pretty --line-timeout --disable-slow-rules --max-line-length --strip --engine --compat --stats --profile --allow-hooks --stderr --tag-stderr --timeout --exit-on-match -q --count --fail-above --only-matching-lines --drop-matching --timestamps --bell-on --notify-on --sample --help -h --version -v --completions" rgrc
 "#,
         ),
         _ => None,
@@ -373,20 +1090,86 @@ fn print_help() {
     println!("Rusty Generic Colouriser");
     println!();
     println!("Usage: rgrc [OPTIONS] COMMAND [ARGS...]");
+    println!("       rgrc [OPTIONS] --shell 'COMMAND LINE'   Run the line with $SHELL -c");
+    println!("       rgrc repl [-c NAME]   Try rules interactively on typed or pasted lines");
+    println!(
+        "       rgrc fetch-configs [URL]   Install the configs of an index ($RGRC_CONFIG_INDEX)"
+    );
+    println!(
+        "       rgrc config list|install|remove|update [NAME[@VERSION]...]   Manage installed configs"
+    );
+    println!(
+        "       rgrc self-update   Replace rgrc with its latest release (checksummed, not signed)"
+    );
+    println!("       rgrc new-config [--sample FILE] CMD [ARGS...]   Write a starter conf.CMD");
+    println!("       rgrc suggest < SAMPLE   Suggest rules for sample output");
+    println!(
+        "       rgrc plugin bash|zsh|fish   Print a shell plugin with aliases and completions"
+    );
     println!();
     println!("Options:");
     println!("  --color, --colour    Override color output (on|off|auto)");
+    println!("  --color-stdout MODE  Override color output on stdout only");
+    println!("  --color-stderr MODE  With --stderr, override color output on stderr only");
     println!("  --aliases            Output shell aliases for available binaries");
     println!("  --all-aliases        Output all shell aliases");
     println!("  --except CMD,..      Exclude commands from alias generation");
+    println!("  --shell SHELL        Write aliases for SHELL: sh (default), powershell or cmd");
+    println!("  --shell 'LINE'       Without --aliases: run LINE with $SHELL -c (pipes, globs)");
+    println!("  --install            Add the aliases to the PowerShell profile or a doskey file");
     println!("  --completions SHELL  Print shell completion script for SHELL (bash|zsh|fish|ash)");
+    println!("  --paths              Show every config file and directory searched, in order");
+    println!("  --explain COMMAND    Show how COMMAND resolves to configs and rules, then exit");
+    println!("  --show-config        Print the rules for COMMAND (or --config) in conf syntax");
     #[cfg(feature = "embed-configs")]
     println!("  --flush-cache        Flush and rebuild cache directory");
+    #[cfg(feature = "embed-configs")]
+    println!("  --cache-dir DIR      Use DIR as the cache directory (default: $RGRC_CACHE_DIR,");
+    #[cfg(feature = "embed-configs")]
+    println!("                       $XDG_CACHE_HOME/rgrc or ~/.cache/rgrc)");
     println!("  --config, -c NAME    Explicit config file name (e.g., df to load conf.df)");
+    println!("  --conf FILE          Map commands to configs with FILE instead of rgrc.conf");
+    println!(
+        "  --log FILE           Also write a plain-text (uncolored) copy of the output to FILE"
+    );
+    println!("  --format FORMAT      Output format: ansi (default), jsonl match records or markup");
+    println!("  --format-in FORMAT   Input format: text (default), csv/tsv or json (also -c json)");
+    println!("  --shade-rows         Shade every second row of CSV/TSV input");
+    println!("  --pretty             Re-indent JSON input (with --format-in=json)");
+    println!("  --line-timeout MS    Write lines taking over MS ms of regex matching uncoloured");
+    println!("  --disable-slow-rules Stop running a rule once it ran out the line timeout");
+    println!("  --max-line-length N  Write lines over N bytes uncoloured ($RGRC_MAX_LINE_LENGTH)");
+    println!("  --strip              Remove colours and other escape sequences from the output of");
+    println!("                       COMMAND, or of stdin without one");
+    println!("  --engine ENGINE      Regex engine: auto (default), fast or enhanced");
+    println!("  --compat grc         Read configs as Python grc does, quirks included");
+    println!("  --stats              Print how many lines each rule matched (to stderr) at exit");
+    println!("  --profile            Print the rules with the most regex time (to stderr) at exit");
+    println!("  --allow-hooks        Run the command= hooks of matching rules (off by default)");
+    println!("  --stderr             Colorize the command's stderr too, keeping lines whole");
+    println!("  --tag-stderr         Like --stderr, starting each stderr line with [stderr]");
+    println!("  --timeout SECS       Stop the command after SECS seconds and exit with 124");
+    println!("  -q, --exit-on-match[=RULE]");
+    println!("                       Print nothing; stop the command at the first line a rule");
+    println!("                       (RULE, numbered as by --stats) matches and exit 0, else 1");
+    println!("  --count[=RULE]       Print how many lines RULE (or any rule) matched, at exit");
+    println!("  --fail-above N       With --count, exit with 1 when more than N lines matched");
+    println!("  --only-matching-lines");
+    println!("                       Print only the lines a rule matched (or inside a block)");
+    println!("  --drop-matching RULES");
+    println!("                       Leave out the lines matched by RULES (1,3 as numbered by");
+    println!("                       --stats) or by any rule (all), colouring the others");
+    println!("  --timestamps[=absolute|relative]");
+    println!("                       Start each line with the time of day, or since the start");
+    println!("  --bell-on REGEX      Ring the terminal bell on coloured lines matching REGEX");
+    println!("  --notify-on REGEX    Send a desktop notification for lines matching REGEX");
+    println!("  --sample FILE        Read the output new-config and suggest start from in FILE");
     println!("  --help, -h           Show this help message");
     println!("  --version, -V        Show installed rgrc version and exit");
     #[cfg(feature = "debug")]
     println!("  --verbose, -v, -vv   Enable debug mode (0=off, 1=basic, 2=verbose)");
+    #[cfg(feature = "debug")]
+    println!("  --verbose-format FMT Debug output format: text (default) or json match events");
     println!();
     #[cfg(feature = "debug")]
     {
@@ -406,9 +1189,15 @@ fn print_help() {
     println!("  rgrc ping -c 4 google.com");
     println!("  rgrc --color=off ls -la");
     println!("  rgrc --aliases");
+    println!("  rgrc --explain kubectl get pods  # Why is (or isn't) this colorized?");
+    println!(
+        "  rgrc --show-config -c df > conf.df  # Start a custom config from the effective rules"
+    );
     println!();
     println!("  echo 'some text' | rgrc -c df  # Apply df config to piped input");
     println!("  /bin/df | rgrc --config=df     # Colorize output using explicit config");
+    println!("  rgrc --log=ping.log ping host  # Colorize on screen, keep a plain copy");
+    println!("  rgrc --format=jsonl ping host  # Emit matched spans as JSON Lines");
 }
 
 #[cfg(test)]
@@ -457,6 +1246,100 @@ mod tests {
         let args = result.unwrap();
         assert_eq!(args.except_aliases, vec!["cmd1", "cmd2"]);
 
+        // Test --paths flag
+        let args = parse_args_helper(vec!["--paths"]).unwrap();
+        assert!(args.show_paths);
+        assert!(args.command.is_empty());
+        assert!(!parse_args_helper(vec!["ls"]).unwrap().show_paths);
+
+        // Test --explain, which takes the rest of the line as the command
+        let args = parse_args_helper(vec!["--explain", "kubectl", "get", "pods"]).unwrap();
+        assert!(args.explain);
+        assert_eq!(args.command, vec!["kubectl", "get", "pods"]);
+        assert!(parse_args_helper(vec!["--explain"]).is_err());
+
+        // Test --show-config with a command or an explicit config
+        let args = parse_args_helper(vec!["--show-config", "ping", "host"]).unwrap();
+        assert!(args.show_config);
+        assert_eq!(args.command, vec!["ping", "host"]);
+        let args = parse_args_helper(vec!["--show-config", "-c", "df"]).unwrap();
+        assert!(args.show_config);
+        assert_eq!(args.config, Some("df".to_string()));
+        assert!(parse_args_helper(vec!["--show-config"]).is_err());
+
+        // Test the repl subcommand, with and without a config
+        let args = parse_args_helper(vec!["repl"]).unwrap();
+        assert!(args.repl);
+        assert!(args.command.is_empty());
+        let args = parse_args_helper(vec!["repl", "-c", "df"]).unwrap();
+        assert!(args.repl);
+        assert_eq!(args.config, Some("df".to_string()));
+        assert!(parse_args_helper(vec!["repl", "ls"]).is_err());
+        assert!(!parse_args_helper(vec!["ls", "repl"]).unwrap().repl);
+
+        // Test the fetch-configs subcommand, with and without an index URL
+        let args = parse_args_helper(vec!["fetch-configs"]).unwrap();
+        assert!(args.fetch_configs);
+        assert_eq!(args.config_index, None);
+        let args = parse_args_helper(vec!["fetch-configs", "https://x/index.txt"]).unwrap();
+        assert_eq!(args.config_index.as_deref(), Some("https://x/index.txt"));
+        assert!(args.command.is_empty());
+        assert!(parse_args_helper(vec!["fetch-configs", "a", "b"]).is_err());
+
+        // Test the config subcommand
+        let args = parse_args_helper(vec!["config", "install", "tofu@1.2.0"]).unwrap();
+        assert_eq!(
+            args.config_command,
+            Some(ConfigCommand::Install(vec!["tofu@1.2.0".to_string()]))
+        );
+        assert!(args.command.is_empty());
+        assert!(parse_args_helper(vec!["config"]).is_err());
+        assert!(parse_args_helper(vec!["config", "remove"]).is_err());
+        assert_eq!(
+            parse_args_helper(vec!["ls", "config"])
+                .unwrap()
+                .config_command,
+            None
+        );
+
+        // Test the self-update subcommand
+        assert!(parse_args_helper(vec!["self-update"]).unwrap().self_update);
+        assert!(parse_args_helper(vec!["self-update", "now"]).is_err());
+        assert!(
+            !parse_args_helper(vec!["ls", "self-update"])
+                .unwrap()
+                .self_update
+        );
+
+        // Test the new-config subcommand, with a command to run or a sample
+        let args = parse_args_helper(vec!["new-config", "tofu", "plan"]).unwrap();
+        assert!(args.new_config);
+        assert_eq!(args.command, vec!["tofu", "plan"]);
+        let args = parse_args_helper(vec!["new-config", "--sample", "out.txt", "tofu"]).unwrap();
+        assert_eq!(args.sample.as_deref(), Some("out.txt"));
+        assert!(parse_args_helper(vec!["new-config"]).is_err());
+        assert!(parse_args_helper(vec!["new-config", "--sample=out.txt", "tofu", "plan"]).is_err());
+        assert!(parse_args_helper(vec!["--sample", "out.txt", "tofu"]).is_err());
+
+        // Test the suggest subcommand
+        assert!(parse_args_helper(vec!["suggest"]).unwrap().suggest);
+        let args = parse_args_helper(vec!["suggest", "--sample=out.txt"]).unwrap();
+        assert_eq!(args.sample.as_deref(), Some("out.txt"));
+        assert!(parse_args_helper(vec!["suggest", "tofu"]).is_err());
+
+        // Test the plugin subcommand
+        let args = parse_args_helper(vec!["plugin", "zsh"]).unwrap();
+        assert_eq!(args.plugin_shell, Some(PluginShell::Zsh));
+        assert!(args.command.is_empty());
+        assert!(parse_args_helper(vec!["plugin"]).is_err());
+        assert!(parse_args_helper(vec!["plugin", "tcsh"]).is_err());
+        assert!(parse_args_helper(vec!["plugin", "zsh", "bash"]).is_err());
+        let args = parse_args_helper(vec!["new-config", "config"]).unwrap();
+        assert_eq!(
+            (args.config_command, args.command),
+            (None, vec!["config".to_string()])
+        );
+
         // Test --flush-cache flag
         let result = parse_args_helper(vec!["--flush-cache"]);
         assert!(result.is_ok());
@@ -608,6 +1491,15 @@ mod tests {
             let args = result.unwrap();
             assert_eq!(args.debug_level, DebugLevel::Verbose);
 
+            // Test --verbose-format, which is not a --verbose level
+            let args = parse_args_helper(vec!["--verbose-format=json", "ls"]).unwrap();
+            assert_eq!(args.verbose_format, VerboseFormat::Json);
+            assert_eq!(args.debug_level, DebugLevel::Off);
+            assert_eq!(args.command, vec!["ls"]);
+            let args = parse_args_helper(vec!["--verbose-format", "text", "ls"]).unwrap();
+            assert_eq!(args.verbose_format, VerboseFormat::Text);
+            assert!(parse_args_helper(vec!["--verbose-format=xml", "ls"]).is_err());
+
             // Test invalid verbose level
             let result = parse_args_helper(vec!["--verbose=3", "ls"]);
             assert!(result.is_err());
@@ -649,10 +1541,268 @@ mod tests {
         let args = result.unwrap();
         assert_eq!(args.color, ColorMode::On);
         assert_eq!(args.config, Some("ps".to_string()));
+
+        // Test --log with space-separated and equals forms
+        let result = parse_args_helper(vec!["--log", "out.log", "ping", "host"]);
+        assert!(result.is_ok());
+        let args = result.unwrap();
+        assert_eq!(args.log_file, Some("out.log".to_string()));
+        assert_eq!(args.command, vec!["ping", "host"]);
+
+        let result = parse_args_helper(vec!["--log=out.log", "-c", "df"]);
+        assert!(result.is_ok());
+        let args = result.unwrap();
+        assert_eq!(args.log_file, Some("out.log".to_string()));
+        assert_eq!(args.config, Some("df".to_string()));
+
+        // Flags merely starting with --log are commands, not the log option
+        let result = parse_args_helper(vec!["--logger", "x"]);
+        assert!(result.is_ok());
+        let args = result.unwrap();
+        assert_eq!(args.log_file, None);
+        assert_eq!(args.command, vec!["--logger", "x"]);
+
+        // Test --conf, which must not be confused with --config
+        let result = parse_args_helper(vec!["--conf", "work.conf", "kubectl", "get"]);
+        let args = result.unwrap();
+        assert_eq!(args.conf, Some("work.conf".to_string()));
+        assert_eq!(args.config, None);
+        assert_eq!(args.command, vec!["kubectl", "get"]);
+
+        let args = parse_args_helper(vec!["--conf=work.conf", "--config=df"]).unwrap();
+        assert_eq!(args.conf, Some("work.conf".to_string()));
+        assert_eq!(args.config, Some("df".to_string()));
+        assert!(parse_args_helper(vec!["--conf"]).is_err());
+
+        // Test --cache-dir, which also works together with --flush-cache
+        let result = parse_args_helper(vec!["--cache-dir", "/tmp/rgrc", "--flush-cache"]);
+        let args = result.unwrap();
+        assert_eq!(args.cache_dir, Some("/tmp/rgrc".to_string()));
+        assert!(args.flush_cache);
+
+        let result = parse_args_helper(vec!["--cache-dir=/tmp/rgrc", "ping", "host"]);
+        let args = result.unwrap();
+        assert_eq!(args.cache_dir, Some("/tmp/rgrc".to_string()));
+        assert_eq!(args.command, vec!["ping", "host"]);
+        assert_eq!(parse_args_helper(vec!["ping"]).unwrap().cache_dir, None);
+        assert!(parse_args_helper(vec!["--cache-dir"]).is_err());
+
+        // Test --format
+        let result = parse_args_helper(vec!["echo", "hi"]);
+        assert_eq!(result.unwrap().format, OutputFormat::Ansi);
+
+        let result = parse_args_helper(vec!["--format=jsonl", "ping", "host"]);
+        assert!(result.is_ok());
+        let args = result.unwrap();
+        assert_eq!(args.format, OutputFormat::Jsonl);
+        assert_eq!(args.command, vec!["ping", "host"]);
+
+        let result = parse_args_helper(vec!["--format", "ansi", "-c", "df"]);
+        assert_eq!(result.unwrap().format, OutputFormat::Ansi);
+
+        let result = parse_args_helper(vec!["--format=markup", "-c", "df"]);
+        assert_eq!(result.unwrap().format, OutputFormat::Markup);
+
+        // Test --stats
+        let result = parse_args_helper(vec!["--stats", "ping", "host"]);
+        assert!(result.is_ok());
+        let args = result.unwrap();
+        assert!(args.stats);
+        assert_eq!(args.command, vec!["ping", "host"]);
+        assert!(!parse_args_helper(vec!["ping"]).unwrap().stats);
+
+        // Test --profile combined with --stats
+        let result = parse_args_helper(vec!["--profile", "--stats", "-c", "dockerps"]);
+        assert!(result.is_ok());
+        let args = result.unwrap();
+        assert!(args.profile);
+        assert!(args.stats);
+        assert!(!parse_args_helper(vec!["ping"]).unwrap().profile);
+
+        // Test --allow-hooks
+        let args = parse_args_helper(vec!["--allow-hooks", "ping", "host"]).unwrap();
+        assert!(args.allow_hooks);
+        assert_eq!(args.command, vec!["ping", "host"]);
+        assert!(!parse_args_helper(vec!["ping"]).unwrap().allow_hooks);
+
+        // Test --stderr and --tag-stderr
+        let args = parse_args_helper(vec!["--stderr", "make"]).unwrap();
+        assert!(args.stderr && !args.tag_stderr);
+        let args = parse_args_helper(vec!["--tag-stderr", "make"]).unwrap();
+        assert!(args.stderr && args.tag_stderr);
+        assert!(parse_args_helper(vec!["--stderr", "-c", "make"]).is_err());
+
+        // Test --color-stdout and --color-stderr
+        let args = parse_args_helper(vec!["--color-stderr=off", "--stderr", "make"]).unwrap();
+        assert_eq!(args.color_stderr, Some(ColorMode::Off));
+        assert_eq!(args.color_stdout, None);
+        let args = parse_args_helper(vec!["--colour-stdout", "on", "make"]).unwrap();
+        assert_eq!(args.color_stdout, Some(ColorMode::On));
+        assert!(parse_args_helper(vec!["--color-stderr=on", "make"]).is_err());
+        assert!(parse_args_helper(vec!["--color-stdout=bad", "make"]).is_err());
+        assert!(parse_args_helper(vec!["--color-stdx=on", "make"]).is_err());
+
+        // Test --timeout
+        let args = parse_args_helper(vec!["--timeout=1.5", "make"]).unwrap();
+        assert_eq!(args.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(parse_args_helper(vec!["make"]).unwrap().timeout, None);
+        for bad in ["0", "-1", "soon", "inf"] {
+            assert!(parse_args_helper(vec!["--timeout", bad, "make"]).is_err());
+        }
+
+        // Test -q and --exit-on-match
+        let args = parse_args_helper(vec!["-q", "make"]).unwrap();
+        assert_eq!(args.exit_on_match, Some(RuleSelector::Any));
+        let args = parse_args_helper(vec!["--exit-on-match=3", "make"]).unwrap();
+        assert_eq!(args.exit_on_match, Some(RuleSelector::Rule(3)));
+        assert_eq!(args.command, vec!["make"]);
+        assert_eq!(parse_args_helper(vec!["make"]).unwrap().exit_on_match, None);
+        assert!(parse_args_helper(vec!["--exit-on-match=error", "make"]).is_err());
+        assert!(parse_args_helper(vec!["-q"]).is_err());
+
+        // Test --count and --fail-above
+        let args = parse_args_helper(vec!["--count", "--fail-above", "0", "make"]).unwrap();
+        assert_eq!(args.count, Some(RuleSelector::Any));
+        assert_eq!(args.fail_above, Some(0));
+        let args = parse_args_helper(vec!["--count=2", "-c", "gcc"]).unwrap();
+        assert_eq!(args.count, Some(RuleSelector::Rule(2)));
+        assert_eq!(args.fail_above, None);
+        assert!(parse_args_helper(vec!["--fail-above=3", "make"]).is_err());
+        assert!(parse_args_helper(vec!["--count", "--fail-above=-1", "make"]).is_err());
+
+        // Test --only-matching-lines
+        let args = parse_args_helper(vec!["--only-matching-lines", "make"]).unwrap();
+        assert!(args.only_matching_lines);
+        assert!(!parse_args_helper(vec!["make"]).unwrap().only_matching_lines);
+
+        // Test --drop-matching
+        let args = parse_args_helper(vec!["--drop-matching", "1,3", "ping"]).unwrap();
+        assert_eq!(
+            args.drop_matching,
+            [RuleSelector::Rule(1), RuleSelector::Rule(3)]
+        );
+        let args = parse_args_helper(vec!["--drop-matching=all", "ping"]).unwrap();
+        assert_eq!(args.drop_matching, [RuleSelector::Any]);
+        assert!(parse_args_helper(vec!["--drop-matching=1,debug", "ping"]).is_err());
+
+        // Test --timestamps
+        let args = parse_args_helper(vec!["--timestamps", "ping"]).unwrap();
+        assert_eq!(args.timestamps, Some(TimestampFormat::Absolute));
+        let args = parse_args_helper(vec!["--timestamps=relative", "ping"]).unwrap();
+        assert_eq!(args.timestamps, Some(TimestampFormat::Relative));
+        assert!(parse_args_helper(vec!["--timestamps=utc", "ping"]).is_err());
+        assert!(parse_args_helper(vec!["--timestamps", "--format=jsonl", "ping"]).is_err());
+
+        // Test --bell-on
+        let args = parse_args_helper(vec!["--bell-on", "timeout|unreachable", "ping"]).unwrap();
+        assert_eq!(args.bell_on.as_deref(), Some("timeout|unreachable"));
+        assert!(parse_args_helper(vec!["--bell-on=(", "ping"]).is_err());
+
+        // Test --notify-on
+        let args = parse_args_helper(vec!["--notify-on=CrashLoop", "kubectl"]).unwrap();
+        assert_eq!(args.notify_on.as_deref(), Some("CrashLoop"));
+        assert!(parse_args_helper(vec!["--notify-on", "[", "kubectl"]).is_err());
+
+        // Test --format-in and --shade-rows
+        let args =
+            parse_args_helper(vec!["--format-in=csv", "--shade-rows", "psql", "--csv"]).unwrap();
+        assert_eq!(args.input_format, InputFormat::Csv);
+        assert!(args.shade_rows);
+        assert_eq!(args.command, vec!["psql", "--csv"]);
+        let args = parse_args_helper(vec!["--format-in", "tsv", "-c", "df"]).unwrap();
+        assert_eq!(args.input_format, InputFormat::Tsv);
+        assert!(!args.shade_rows);
+        assert_eq!(
+            parse_args_helper(vec!["ping"]).unwrap().input_format,
+            InputFormat::Text
+        );
+
+        // Test --pretty; -c json selects JSON input
+        let args = parse_args_helper(vec!["--pretty", "-c", "json"]).unwrap();
+        assert_eq!(args.input_format, InputFormat::Json);
+        assert!(args.pretty);
+        let args = parse_args_helper(vec!["--format-in=csv", "-c", "json"]).unwrap();
+        assert_eq!(args.input_format, InputFormat::Csv);
+        let args = parse_args_helper(vec!["--format-in=json", "kubectl", "get", "-o", "json"]);
+        assert_eq!(args.unwrap().input_format, InputFormat::Json);
+
+        // Test --line-timeout and --disable-slow-rules
+        let args =
+            parse_args_helper(vec!["--line-timeout=50", "--disable-slow-rules", "tail"]).unwrap();
+        assert_eq!(args.line_timeout, Some(Duration::from_millis(50)));
+        assert!(args.disable_slow_rules);
+        assert_eq!(parse_args_helper(vec!["ping"]).unwrap().line_timeout, None);
+        assert!(parse_args_helper(vec!["--line-timeout", "0", "ping"]).is_err());
+        assert!(parse_args_helper(vec!["--line-timeout=1s", "ping"]).is_err());
+
+        // Test --strip, with a command or on stdin
+        assert!(
+            parse_args_helper(vec!["--strip", "ls", "--color"])
+                .unwrap()
+                .strip
+        );
+        assert!(parse_args_helper(vec!["--strip"]).unwrap().strip);
+        assert!(parse_args_helper(vec!["--strip", "-c", "ping"]).is_err());
+
+        // Test --max-line-length
+        let args = parse_args_helper(vec!["--max-line-length", "4096", "kubectl"]).unwrap();
+        assert_eq!(args.max_line_length, Some(4096));
+        assert_eq!(
+            parse_args_helper(vec!["ping"]).unwrap().max_line_length,
+            None
+        );
+        assert!(parse_args_helper(vec!["--max-line-length=-1", "ping"]).is_err());
+
+        // Test --engine
+        let args = parse_args_helper(vec!["--engine=enhanced", "ping"]).unwrap();
+        assert_eq!(args.engine, RegexEngine::Enhanced);
+        assert_eq!(
+            parse_args_helper(vec!["ping"]).unwrap().engine,
+            RegexEngine::Auto
+        );
+        assert!(parse_args_helper(vec!["--engine", "pcre", "ping"]).is_err());
+
+        // Test --compat
+        let args = parse_args_helper(vec!["--compat=grc", "ping"]).unwrap();
+        assert_eq!(args.compat, Compat::Grc);
+        assert_eq!(
+            parse_args_helper(vec!["ping"]).unwrap().compat,
+            Compat::Rgrc
+        );
+        assert!(parse_args_helper(vec!["--compat", "grcat", "ping"]).is_err());
+
+        // Test --shell and --install
+        let args = parse_args_helper(vec!["--all-aliases", "--shell=pwsh", "--install"]).unwrap();
+        assert_eq!(args.alias_shell, AliasShell::PowerShell);
+        assert!(args.install_aliases);
+        let args = parse_args_helper(vec!["--aliases", "--shell", "cmd"]).unwrap();
+        assert_eq!(args.alias_shell, AliasShell::Cmd);
+        assert!(!args.install_aliases);
+        assert!(parse_args_helper(vec!["--shell", "cmd", "ping"]).is_err());
+        assert!(parse_args_helper(vec!["--aliases", "--install"]).is_err());
+        assert!(parse_args_helper(vec!["--aliases", "--shell", "tcsh"]).is_err());
+
+        // Test --shell with a command line
+        let args = parse_args_helper(vec!["--stderr", "--shell", "dmesg | tail -n 50"]).unwrap();
+        assert_eq!(args.shell_command.as_deref(), Some("dmesg | tail -n 50"));
+        assert!(args.command.is_empty());
+        assert!(args.stderr);
+        let args = parse_args_helper(vec!["--shell=cmd", "--aliases"]).unwrap();
+        assert_eq!(args.alias_shell, AliasShell::Cmd);
+        assert_eq!(args.shell_command, None);
     }
 
     #[test]
     fn test_parse_args_errors() {
+        // Test invalid output format
+        let result = parse_args_helper(vec!["--format=xml", "echo"]);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid output format"));
+
+        // Test invalid input format
+        let result = parse_args_helper(vec!["--format-in=xlsx", "echo"]);
+        assert!(result.unwrap_err().contains("Invalid input format"));
+
         // Test invalid color mode
         let result = parse_args_helper(vec!["--color=invalid", "echo"]);
         assert!(result.is_err());
@@ -730,6 +1880,11 @@ mod tests {
         let result = parse_args_helper(vec!["--config="]);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Missing value for --config"));
+
+        // Missing value for --log
+        let result = parse_args_helper(vec!["--log"]);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Missing value for --log"));
     }
 
     // Helper function to test parse_args without std::env::args dependency
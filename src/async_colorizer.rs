@@ -0,0 +1,108 @@
+//! # async_colorizer.rs - Colorizing tokio streams
+//!
+//! `Colorizer::colorize_async` runs a `Colorizer` from a tokio `AsyncRead` into an
+//! `AsyncWrite`, so services that capture subprocess output with `tokio::process`
+//! can reuse rgrc's rules without parking a thread on the pipe.
+//!
+//! Lines go through the same engine as `Colorizer::colorize` (see
+//! `colorizer::LineColorizer`); only the I/O is asynchronous. Each line is matched
+//! on the calling task as soon as it has been read, and written before the next
+//! one is awaited.
+//!
+//! ```
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! use rgrc::{ColorMode, Colorizer, grc::GrcatConfigReader};
+//! use std::io::BufRead;
+//!
+//! let conf = "regexp=ERROR\ncolours=bold red\n";
+//! let rules = GrcatConfigReader::new(conf.as_bytes().lines()).collect();
+//! let colorizer = Colorizer::builder()
+//!     .rules(rules)
+//!     .color_mode(ColorMode::On)
+//!     .build();
+//!
+//! let mut output = Vec::new();
+//! colorizer
+//!     .colorize_async(&b"ERROR: disk full\n"[..], &mut output)
+//!     .await?;
+//! assert_eq!(output, b"\x1b[1;31mERROR\x1b[0m: disk full\n");
+//! # Ok::<(), rgrc::RgrcError>(())
+//! # }).unwrap();
+//! ```
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::colorizer::{ColorizeOptions, DelimitedColumns, LineColorizer};
+use crate::json::JsonHighlighter;
+use crate::{Colorizer, InputFormat, RgrcError};
+
+impl Colorizer {
+    /// Colorize `reader` into `writer` line by line, as `colorize()` does.
+    ///
+    /// The returned future is `Send`, so it may be spawned. Re-indenting JSON
+    /// (`pretty`) and the `debug` feature's rule report are not applied, and
+    /// `command=` hooks (when enabled) still run as blocking child processes.
+    pub async fn colorize_async<R, W>(&self, reader: R, writer: &mut W) -> Result<(), RgrcError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let columns =
+            DelimitedColumns::new(self.input_format, self.shade_rows).filter(|_| self.enabled);
+        let mut json =
+            (self.enabled && self.input_format == InputFormat::Json).then(JsonHighlighter::new);
+        let rules = if self.enabled || self.runs_rules_uncoloured() {
+            &self.rules[..]
+        } else {
+            &[]
+        };
+        let mut line_colorizer = LineColorizer::new(rules);
+
+        // What the options carry from one line to the next; the options themselves
+        // hold (unused) `dyn Write` sinks, so they must not live across an await
+        let mut block = None;
+        let mut disabled_rules = Vec::new();
+        let mut last_bell = None;
+
+        // Split on \n only, so CRLF line endings are written back as they were
+        let mut lines = BufReader::new(reader).split(b'\n');
+        let mut output = Vec::new();
+        while let Some(line) = lines.next_segment().await? {
+            let line = String::from_utf8(line).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            })?;
+            {
+                let mut options = ColorizeOptions {
+                    format: self.renderer,
+                    block,
+                    hooks: self.hooks,
+                    columns: columns.as_ref(),
+                    json: json.as_mut(),
+                    line_timeout: self.line_timeout,
+                    disable_slow_rules: self.disable_slow_rules,
+                    disabled_rules: std::mem::take(&mut disabled_rules),
+                    max_line_length: self.max_line_length,
+                    last_bell,
+                    count_only: !self.enabled && self.runs_rules_uncoloured(),
+                    only_matching: self.only_matching_lines,
+                    drop_matching: &self.drop_matching,
+                    ..Default::default()
+                };
+                let result = line_colorizer.colorize_line(line, &mut output, &mut options);
+                block = options.block;
+                disabled_rules = options.disabled_rules;
+                last_bell = options.last_bell;
+                // Writing to a Vec cannot fail, so neither can the line
+                result.map_err(|e| RgrcError::Io(std::io::Error::other(e.to_string())))?;
+            }
+
+            writer.write_all(&output).await?;
+            output.clear();
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+}
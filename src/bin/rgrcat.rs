@@ -0,0 +1,85 @@
+// rgrcat: grcat-compatible filter
+//
+// `rgrcat CONFNAME` colorizes stdin to stdout with one grcat config, as
+// `grcat CONFNAME` from Python grc does, so scripts and pipelines calling
+// grcat directly work unchanged.
+
+use rgrc::{ColorMode, Colorizer, ConfigLoader, RgrcError};
+use std::io::{self, Write};
+use std::path::Path;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let prog = args.first().map_or("rgrcat", String::as_str);
+
+    let name = match args.get(1..).unwrap_or_default() {
+        [flag] if flag == "--help" || flag == "-h" => {
+            print_help(prog);
+            return;
+        }
+        [flag] if flag == "--version" || flag == "-V" => {
+            println!("rgrcat {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        [name] => name,
+        _ => {
+            eprintln!("Usage: {} CONFNAME", prog);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(name) {
+        eprintln!("rgrcat: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+/// Print help message
+fn print_help(prog: &str) {
+    println!("rgrcat - colorize stdin with a grcat config");
+    println!();
+    println!("Usage: {} CONFNAME", prog);
+    println!();
+    println!("CONFNAME is a config name looked up like rgrc's (e.g. conf.ping),");
+    println!("or the path of a config file.");
+}
+
+/// Colorize stdin to stdout with the config `name`.
+fn run(name: &str) -> Result<(), RgrcError> {
+    let rules = load_rules(name)?;
+    // grcat always colours; it is only run where colours are wanted
+    rgrc::utils::enable_ansi_support();
+    let colorizer = Colorizer::builder()
+        .rules(rules)
+        .color_mode(ColorMode::On)
+        .build();
+
+    let stdout = io::stdout();
+    // Flushed by the colorizer whenever it has written the lines read so far
+    let mut writer = io::BufWriter::new(stdout.lock());
+    let result = colorizer
+        .colorize(&mut io::stdin().lock(), &mut writer)
+        .map_err(|e| match e.downcast::<io::Error>() {
+            Ok(e) => RgrcError::Io(*e),
+            Err(e) => RgrcError::Io(io::Error::other(e.to_string())),
+        })
+        .and_then(|()| writer.flush().map_err(RgrcError::Io));
+
+    match result {
+        // The reader went away (e.g. `| head`): not an error for a filter
+        Err(RgrcError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// The rules of `name`: a file when it is a path (as with grcat), otherwise a
+/// config looked up in rgrc's search paths.
+fn load_rules(name: &str) -> Result<Vec<rgrc::grc::GrcatConfigEntry>, RgrcError> {
+    if name.contains('/') {
+        if !Path::new(name).is_file() {
+            return Err(RgrcError::ConfigNotFound(name.to_string()));
+        }
+        return Ok(rgrc::load_grcat_config(name));
+    }
+    ConfigLoader::from_env().load_config(name)
+}
@@ -20,6 +20,13 @@ fn main() {
     match command.as_str() {
         "grc" => validate_grc_config(&args),
         "conf" => validate_conf_files(&args),
+        "test" => run_sample_tests(&args),
+        #[cfg(feature = "embed-configs")]
+        "embedded" => check_embedded(&args),
+        #[cfg(feature = "toml-configs")]
+        "convert" => convert(&args),
+        #[cfg(feature = "tui")]
+        "preview" => preview(&args),
         "--help" | "-h" => print_help(&args[0]),
         "--version" | "-V" => println!("rgrc-validate 0.1.0"),
         _ => {
@@ -38,7 +45,19 @@ fn print_help(prog: &str) {
     println!();
     println!("Commands:");
     println!("  grc [PATH]        Validate grc.conf configuration file");
-    println!("  conf [PATH ...]   Validate color configuration files (conf.*)");
+    println!("  conf [--fix] [PATH ...]");
+    println!("                    Validate color configuration files (conf.*); --fix repairs");
+    println!("                    common mistakes in place, keeping a .bak copy");
+    println!("  test [--update] [PATH ...]");
+    println!("                    Check conf.NAME.sample files against conf.NAME.expected");
+    #[cfg(feature = "embed-configs")]
+    println!(
+        "  embedded [SHARE]  Compare the embedded configs with SHARE (default share/) and the cache"
+    );
+    #[cfg(feature = "toml-configs")]
+    println!("  convert PATH [OUT] Convert a conf file to TOML, or a .toml file back");
+    #[cfg(feature = "tui")]
+    println!("  preview CONF SAMPLE  Edit CONF interactively, recoloring SAMPLE as you go");
     println!("  --help, -h        Show this help message");
     println!("  --version, -v     Show version");
     println!();
@@ -56,6 +75,410 @@ fn print_help(prog: &str) {
         "  {} conf share/conf.*      # Validate all conf files",
         prog
     );
+    println!(
+        "  {} test tests/samples     # Run the golden-sample tests",
+        prog
+    );
+}
+
+/// Open the interactive previewer for a conf file and a sample output file
+#[cfg(feature = "tui")]
+fn preview(args: &[String]) {
+    if args.len() != 4 {
+        eprintln!("Usage: {} preview CONF SAMPLE", args[0]);
+        std::process::exit(1);
+    }
+
+    if let Err(e) = rgrc::preview::run(Path::new(&args[2]), Path::new(&args[3])) {
+        eprintln!("{} {}", Style::new().red().apply_to("✗"), e);
+        std::process::exit(1);
+    }
+}
+
+/// Compare the configs compiled into this binary with the share/ tree and the cache
+///
+/// Drift means the binary was built from an older share/ (or a file was added or
+/// removed since), or a cached copy no longer matches what is embedded. User
+/// configs in the search path that shadow an embedded config are listed too,
+/// as they explain why edits to share/ or the cache seem to have no effect.
+#[cfg(feature = "embed-configs")]
+fn check_embedded(args: &[String]) {
+    let share = args
+        .get(2)
+        .map_or_else(|| PathBuf::from("share"), PathBuf::from);
+    if !share.is_dir() {
+        eprintln!(
+            "{} {} is not a directory",
+            Style::new().red().apply_to("✗"),
+            share.display()
+        );
+        std::process::exit(1);
+    }
+    let cache = rgrc::get_cache_dir();
+
+    println!(
+        "{}Comparing embedded configs...",
+        Style::new().bold().apply_to("")
+    );
+    println!("  Share: {}", share.display());
+    match &cache {
+        Some(cache) => println!("  Cache: {}", cache.display()),
+        None => println!("  Cache: (none)"),
+    }
+    println!();
+
+    let mut names: Vec<String> = rgrc::EMBEDDED_CONFIG_NAMES
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    names.extend(
+        fs::read_dir(&share)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("conf.") && is_conf_file_name(name)),
+    );
+    names.sort();
+    names.dedup();
+
+    // rgrc.conf is embedded from etc/, next to share/
+    let grc_conf = share
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join("etc")
+        .join("rgrc.conf");
+    let mut entries = vec![(
+        "rgrc.conf".to_string(),
+        Some(rgrc::EMBEDDED_GRC_CONF.to_string()),
+        grc_conf,
+        cache.as_ref().map(|dir| dir.join("rgrc.conf")),
+    )];
+    for name in names {
+        let embedded = rgrc::embedded_config(&name).map(|text| text.into_owned());
+        let cached = cache.as_ref().map(|dir| dir.join("conf").join(&name));
+        entries.push((name.clone(), embedded, share.join(&name), cached));
+    }
+
+    let mut drifted = 0;
+    for (name, embedded, on_disk, cached) in &entries {
+        let problems = embedded_drift(embedded.as_deref(), on_disk, cached.as_deref());
+        if problems.is_empty() {
+            println!("  {} {}", Style::new().green().apply_to("✓"), name);
+        } else {
+            drifted += 1;
+            println!("  {} {}", Style::new().red().apply_to("✗"), name);
+            for problem in problems {
+                println!("      {}", problem);
+            }
+        }
+
+        // A user config found earlier in the search path wins over all of the above
+        if let Some(user) = rgrc::resource_paths()
+            .iter()
+            .filter(|dir| !same_dir(dir, &share))
+            .map(|dir| dir.join(name))
+            .find(|path| name.starts_with("conf.") && path.is_file())
+        {
+            println!(
+                "      {} shadowed by {}",
+                Style::new().yellow().apply_to("note:"),
+                user.display()
+            );
+        }
+    }
+
+    println!();
+    println!(
+        "Summary: {} files compared, {} drifted",
+        entries.len(),
+        drifted
+    );
+    if drifted > 0 {
+        println!(
+            "Rebuild rgrc to embed the current share/, or run `rgrc --flush-cache` to reset the cache"
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Differences between an embedded config, its share/ copy and its cached copy
+#[cfg(feature = "embed-configs")]
+fn embedded_drift(embedded: Option<&str>, on_disk: &Path, cached: Option<&Path>) -> Vec<String> {
+    let disk = fs::read_to_string(on_disk).ok();
+    let mut problems = Vec::new();
+    match (embedded, disk.as_deref()) {
+        (Some(_), None) => problems.push(format!("embedded, but {} is missing", on_disk.display())),
+        (None, Some(_)) => problems.push(format!("{} is not embedded", on_disk.display())),
+        (Some(embedded), Some(disk)) if embedded != disk => {
+            problems.push(format!("embedded copy differs from {}", on_disk.display()))
+        }
+        _ => {}
+    }
+    // A missing cached copy is written on first use, so only differing ones count
+    if let (Some(embedded), Some(cached)) = (embedded, cached)
+        && let Ok(text) = fs::read_to_string(cached)
+        && text != embedded
+    {
+        problems.push(format!(
+            "cached copy {} differs (edited locally, or left from another build)",
+            cached.display()
+        ));
+    }
+    problems
+}
+
+/// Whether `a` and `b` name the same directory
+#[cfg(feature = "embed-configs")]
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Convert a conf file between the grcat and TOML formats
+///
+/// A `.toml` PATH is converted to grcat syntax, anything else to TOML. The
+/// result goes to OUT, or to stdout.
+#[cfg(feature = "toml-configs")]
+fn convert(args: &[String]) {
+    if !(3..=4).contains(&args.len()) {
+        eprintln!("Usage: {} convert PATH [OUT]", args[0]);
+        std::process::exit(1);
+    }
+
+    let path = Path::new(&args[2]);
+    let converted = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                rgrc::toml_config::toml_to_grc(&text)
+            } else {
+                rgrc::toml_config::grc_to_toml(&text)
+            }
+        })
+        .and_then(|converted| match args.get(3) {
+            Some(out) => fs::write(out, converted).map_err(|e| e.to_string()),
+            None => {
+                print!("{}", converted);
+                Ok(())
+            }
+        });
+
+    if let Err(e) = converted {
+        eprintln!(
+            "{} {}: {}",
+            Style::new().red().apply_to("✗"),
+            path.display(),
+            e
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Run golden-sample tests
+///
+/// Each `conf.NAME.sample` holds real output of a command; it is colorized with
+/// `conf.NAME` (next to the sample, else from the config search path) and the
+/// result, in `--format=markup` syntax, is compared with `conf.NAME.expected`.
+/// PATH may be a sample, a conf file or a directory of samples. With
+/// `--update` the expected files are (re)written instead.
+fn run_sample_tests(args: &[String]) {
+    let update = args[2..].iter().any(|arg| arg == "--update");
+    let mut paths: Vec<PathBuf> = args[2..]
+        .iter()
+        .filter(|arg| *arg != "--update")
+        .map(PathBuf::from)
+        .collect();
+    if paths.is_empty() {
+        paths.push(find_conf_dir());
+    }
+
+    let mut samples = Vec::new();
+    for path in &paths {
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = fs::read_dir(path)
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "sample"))
+                .collect();
+            found.sort();
+            if found.is_empty() {
+                println!("No samples found in {}", path.display());
+            }
+            samples.extend(found);
+        } else if path.extension().is_some_and(|ext| ext == "sample") {
+            samples.push(path.clone());
+        } else {
+            samples.push(with_suffix(path, ".sample"));
+        }
+    }
+
+    println!(
+        "{}Running golden-sample tests...",
+        Style::new().bold().apply_to("")
+    );
+    println!();
+
+    let mut failed = 0;
+    let mut updated = 0;
+    for sample in &samples {
+        let name = sample
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        match check_sample(sample, &name, update) {
+            Ok(SampleResult::Passed) => {
+                println!("  {} {}", Style::new().green().apply_to("✓"), name);
+            }
+            Ok(SampleResult::Updated) => {
+                updated += 1;
+                println!(
+                    "  {} {} (expected output written)",
+                    Style::new().yellow().apply_to("✎"),
+                    name
+                );
+            }
+            Ok(SampleResult::Failed(diffs)) => {
+                println!(
+                    "  {} {} ({} lines differ)",
+                    Style::new().red().apply_to("✗"),
+                    name,
+                    diffs.len()
+                );
+                print_sample_diffs(&diffs);
+                failed += 1;
+            }
+            Err(message) => {
+                println!(
+                    "  {} {} ({})",
+                    Style::new().red().apply_to("✗"),
+                    name,
+                    message
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    println!();
+    if update {
+        println!(
+            "Summary: {} samples, {} updated, {} failed",
+            samples.len(),
+            updated,
+            failed
+        );
+    } else {
+        println!(
+            "Summary: {} samples, {} passed, {} failed",
+            samples.len(),
+            samples.len() - failed,
+            failed
+        );
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Outcome of one golden-sample test
+enum SampleResult {
+    Passed,
+    Updated,
+    /// Differing lines: (line number, expected, actual)
+    Failed(Vec<(usize, Option<String>, Option<String>)>),
+}
+
+/// Colorize `sample` with the config `name` and compare it with the expected file
+fn check_sample(sample: &Path, name: &str, update: bool) -> Result<SampleResult, String> {
+    let input = fs::read_to_string(sample).map_err(|e| format!("read error: {}", e))?;
+
+    let sibling = sample.with_file_name(name);
+    let conf = if conf_file_exists(&sibling) {
+        sibling
+    } else {
+        rgrc::resource_paths()
+            .into_iter()
+            .map(|dir| dir.join(name))
+            .find(|path| conf_file_exists(path))
+            .ok_or_else(|| format!("{} not found", name))?
+    };
+    let rules = rgrc::load_grcat_config(conf.to_string_lossy());
+
+    let mut output = Vec::new();
+    let mut options = rgrc::colorizer::ColorizeOptions {
+        format: rgrc::OutputFormat::Markup,
+        ..Default::default()
+    };
+    rgrc::colorizer::colorize_regex_with_options(
+        &mut input.as_bytes(),
+        &mut output,
+        &rules,
+        &mut options,
+    )
+    .map_err(|e| format!("colorize error: {}", e))?;
+    let actual = String::from_utf8_lossy(&output).into_owned();
+
+    let expected_path = sample.with_file_name(format!("{}.expected", name));
+    if update {
+        fs::write(&expected_path, &actual).map_err(|e| format!("write error: {}", e))?;
+        return Ok(SampleResult::Updated);
+    }
+    let expected = fs::read_to_string(&expected_path).map_err(|_| {
+        format!(
+            "missing {}, create it with --update",
+            expected_path.display()
+        )
+    })?;
+
+    let (expected, actual): (Vec<&str>, Vec<&str>) =
+        (expected.lines().collect(), actual.lines().collect());
+    let diffs: Vec<_> = (0..expected.len().max(actual.len()))
+        .filter(|&i| expected.get(i) != actual.get(i))
+        .map(|i| {
+            (
+                i + 1,
+                expected.get(i).map(|s| s.to_string()),
+                actual.get(i).map(|s| s.to_string()),
+            )
+        })
+        .collect();
+
+    if diffs.is_empty() {
+        Ok(SampleResult::Passed)
+    } else {
+        Ok(SampleResult::Failed(diffs))
+    }
+}
+
+/// Print the differing lines of a golden-sample test
+fn print_sample_diffs(diffs: &[(usize, Option<String>, Option<String>)]) {
+    for (line, expected, actual) in diffs {
+        println!("    line {}:", line);
+        let missing = "(no line)".to_string();
+        println!(
+            "      {} {}",
+            Style::new().red().apply_to("-"),
+            expected.as_ref().unwrap_or(&missing)
+        );
+        println!(
+            "      {} {}",
+            Style::new().green().apply_to("+"),
+            actual.as_ref().unwrap_or(&missing)
+        );
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
 }
 
 /// Validate grc.conf file
@@ -101,19 +524,23 @@ fn validate_grc_config(args: &[String]) {
 }
 
 /// Validate conf.* files
+///
+/// With `--fix`, common mistakes are repaired in place first (see `fix_conf_content`).
 fn validate_conf_files(args: &[String]) {
     let mut total_errors = 0;
     let mut validated_files = 0;
+    let fix = args[2..].iter().any(|arg| arg == "--fix");
+    let files: Vec<&String> = args[2..].iter().filter(|arg| *arg != "--fix").collect();
 
     // If specific files are provided, validate only those
-    if args.len() > 2 {
+    if !files.is_empty() {
         println!(
             "{}Validating color configuration files...",
             Style::new().bold().apply_to("")
         );
         println!();
 
-        for arg in &args[2..] {
+        for arg in files {
             let path = PathBuf::from(arg);
 
             if !path.exists() {
@@ -128,6 +555,11 @@ fn validate_conf_files(args: &[String]) {
 
             match fs::read_to_string(&path) {
                 Ok(content) => {
+                    let content = if fix {
+                        apply_fixes(&path, content)
+                    } else {
+                        content
+                    };
                     let mut errors = Vec::new();
                     validate_conf_content(&content, &path, &mut errors);
 
@@ -185,7 +617,7 @@ fn validate_conf_files(args: &[String]) {
                 .filter(|e| {
                     e.file_name()
                         .to_str()
-                        .map(|n| n.starts_with("conf."))
+                        .map(is_conf_file_name)
                         .unwrap_or(false)
                 })
                 .collect();
@@ -196,6 +628,11 @@ fn validate_conf_files(args: &[String]) {
                 let path = entry.path();
                 match fs::read_to_string(&path) {
                     Ok(content) => {
+                        let content = if fix {
+                            apply_fixes(&path, content)
+                        } else {
+                            content
+                        };
                         let mut errors = Vec::new();
                         validate_conf_content(&content, &path, &mut errors);
 
@@ -249,6 +686,132 @@ fn validate_conf_files(args: &[String]) {
     }
 }
 
+/// Whether `name` is a grcat config, rather than a sample or a backup next to one
+fn is_conf_file_name(name: &str) -> bool {
+    name.starts_with("conf.")
+        && ![".sample", ".expected", ".bak", ".new"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// Repair `content` with `fix_conf_content`, saving the result over `path`
+/// and the original as `path.bak`; returns the content to validate
+fn apply_fixes(path: &Path, content: String) -> String {
+    // The fixes are for mistakes in the grcat syntax
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        return content;
+    }
+    let (fixed, changes) = fix_conf_content(&content);
+    if changes.is_empty() {
+        return content;
+    }
+
+    let backup = with_suffix(path, ".bak");
+    if let Err(e) = fs::write(&backup, &content).and_then(|_| fs::write(path, &fixed)) {
+        eprintln!(
+            "  {} {} (not fixed: {})",
+            Style::new().red().apply_to("✗"),
+            path.display(),
+            e
+        );
+        return content;
+    }
+
+    println!(
+        "  {} {}: {} fixes (backup: {})",
+        Style::new().yellow().apply_to("✎"),
+        path.display(),
+        changes.len(),
+        backup.display()
+    );
+    for (line, change) in &changes {
+        println!("      line {}: {}", line, change);
+    }
+    fixed
+}
+
+/// Repair common mistakes in a conf file
+///
+/// - `colors=`/`color=` keys are renamed to `colours=`;
+/// - hyphenated style names (`bright-red`) get underscores (`bright_red`);
+/// - unescaped dots between digits in IP-like patterns (`\d+.\d+`) are escaped;
+/// - trailing whitespace is removed from values, except in `regexp=` and
+///   `replace=` where it may be meant.
+///
+/// Returns the new content and a description of each change with its line number.
+fn fix_conf_content(content: &str) -> (String, Vec<(usize, String)>) {
+    let mut changes = Vec::new();
+    let mut out = String::with_capacity(content.len());
+
+    for (index, line) in content.lines().enumerate() {
+        let mut fixed = line.to_string();
+        if let Some((key, value)) = line.split_once('=')
+            && line.starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let mut key = key.to_string();
+            let mut value = value.to_string();
+
+            if matches!(key.trim(), "colors" | "color") {
+                changes.push((index + 1, format!("{}= → colours=", key.trim())));
+                key = "colours".to_string();
+            }
+
+            if !matches!(key.trim(), "regexp" | "replace") && value.ends_with(char::is_whitespace) {
+                value.truncate(value.trim_end().len());
+                changes.push((index + 1, "removed trailing whitespace".to_string()));
+            }
+
+            match key.trim() {
+                "colours" | "colour" => {
+                    for word in value.clone().split(|c: char| c == ',' || c.is_whitespace()) {
+                        let underscored = word.replace('-', "_");
+                        if word.contains('-') && rgrc::grc::style_from_str(&underscored).is_ok() {
+                            value = value.replace(word, &underscored);
+                            changes.push((index + 1, format!("{} → {}", word, underscored)));
+                        }
+                    }
+                }
+                "regexp" => {
+                    let escaped = escape_ip_dots(&value);
+                    if escaped != value {
+                        changes.push((
+                            index + 1,
+                            format!("escaped dots in IP pattern: {}", escaped),
+                        ));
+                        value = escaped;
+                    }
+                }
+                _ => {}
+            }
+            fixed = format!("{}={}", key, value);
+        }
+        out.push_str(&fixed);
+        out.push('\n');
+    }
+
+    // Keep a missing final newline missing
+    if !content.ends_with('\n') {
+        out.pop();
+    }
+    (out, changes)
+}
+
+/// Escape literal-looking dots between digit tokens, e.g. `\d{1,3}.\d{1,3}` → `\d{1,3}\.\d{1,3}`
+fn escape_ip_dots(pattern: &str) -> String {
+    let re =
+        regex_lite::Regex::new(r"((?:\\d|\[0-9\])(?:[+*]|\{\d+(?:,\d*)?\})?)\.((?:\\d|\[0-9\]))")
+            .unwrap();
+    let mut pattern = pattern.to_string();
+    // Each pass escapes non-overlapping dots; repeat for chains like \d.\d.\d
+    loop {
+        let next = re.replace_all(&pattern, r"${1}\.${2}").into_owned();
+        if next == pattern {
+            return pattern;
+        }
+        pattern = next;
+    }
+}
+
 /// Validate grc.conf format
 fn validate_grc_content(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
     let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
@@ -315,29 +878,385 @@ fn validate_grc_content(content: &str, path: &Path, errors: &mut Vec<ValidationE
             continue;
         }
 
-        // Check if config file exists
-        let config_path = Path::new(config_line);
-        if !config_path.exists() && !config_line.starts_with("conf.") {
-            // Try in share directory
-            let share_path = Path::new("share").join(config_line);
-            if !share_path.exists() {
-                errors.push(ValidationError {
-                    path: path.to_path_buf(),
-                    line: next_line_num,
-                    error_type: "FileNotFound".to_string(),
-                    message: format!("Config file not found: {}", config_line),
-                    suggestion: Some(format!("Create {} or check file name", config_line)),
-                });
-            }
+        // Check that each listed config resolves the way rgrc looks it up
+        for config_name in rgrc::config_names(config_line) {
+            check_config_reference(config_name, path, next_line_num, errors);
         }
 
         i += 1;
     }
 }
 
+/// Check that the config `config_name`, listed in the grc.conf at `path` on `line`,
+/// resolves the way rgrc looks it up
+fn check_config_reference(
+    config_name: &str,
+    path: &Path,
+    line: usize,
+    errors: &mut Vec<ValidationError>,
+) {
+    let config_path = Path::new(config_name);
+    if config_name.starts_with("conf.") {
+        if !conf_exists(config_name) {
+            let suggestion = match closest_conf_name(config_name) {
+                Some(name) => format!("Did you mean {}?", name),
+                None => format!("Create {} in one of the config directories", config_name),
+            };
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line,
+                error_type: "FileNotFound".to_string(),
+                message: format!(
+                    "Config file {} not found in any config directory{}",
+                    config_name,
+                    if cfg!(feature = "embed-configs") {
+                        " or the embedded configs"
+                    } else {
+                        ""
+                    }
+                ),
+                suggestion: Some(suggestion),
+            });
+        }
+    } else if !config_path.exists() {
+        // Try in share directory
+        let share_path = Path::new("share").join(config_name);
+        if !share_path.exists() {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line,
+                error_type: "FileNotFound".to_string(),
+                message: format!("Config file not found: {}", config_name),
+                suggestion: Some(format!("Create {} or check file name", config_name)),
+            });
+        }
+    }
+}
+
+/// Names of the configs rgrc can load: files in the config search path
+/// (see `rgrc::resource_paths`) plus the embedded ones
+fn available_conf_names() -> Vec<String> {
+    let mut names: Vec<String> = rgrc::resource_paths()
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| is_conf_file_name(name))
+        .collect();
+    #[cfg(feature = "embed-configs")]
+    names.extend(
+        rgrc::EMBEDDED_CONFIG_NAMES
+            .iter()
+            .map(|name| name.to_string()),
+    );
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Whether the config `name` (e.g. `conf.ping`) resolves in the search path or the embedded set
+fn conf_exists(name: &str) -> bool {
+    #[cfg(feature = "embed-configs")]
+    if rgrc::EMBEDDED_CONFIG_NAMES.contains(&name) {
+        return true;
+    }
+    rgrc::resource_paths()
+        .iter()
+        .any(|dir| conf_file_exists(&dir.join(name)))
+}
+
+/// Whether rgrc finds a config at `path`, either the file or its TOML form
+fn conf_file_exists(path: &Path) -> bool {
+    #[cfg(feature = "toml-configs")]
+    if rgrc::toml_config::toml_path(path).is_file() {
+        return true;
+    }
+    path.is_file()
+}
+
+/// The available config closest to the misspelled `name`, if any is close
+fn closest_conf_name(name: &str) -> Option<String> {
+    available_conf_names()
+        .into_iter()
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between `a` and `b`, counting a swap of adjacent characters as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j - 1] + cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Validate a conf.NAME.toml file: its schema, then the regex and styles of each rule
+#[cfg(feature = "toml-configs")]
+fn validate_toml_content(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    let line_of = |offset: usize| content[..offset.min(content.len())].lines().count().max(1);
+
+    let config: rgrc::toml_config::TomlConfig = match toml::from_str(content) {
+        Ok(config) => config,
+        Err(e) => {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: e.span().map_or(1, |span| line_of(span.start)),
+                error_type: "FormatError".to_string(),
+                message: e.message().to_string(),
+                suggestion: Some(
+                    "Each rule is a [[rule]] table with regexp, flags, colours, count, skip, replace, command, priority, thresholds, gradient, sizes, fields, delimiter and engine"
+                        .to_string(),
+                ),
+            });
+            return;
+        }
+    };
+
+    // Line of each rule's regexp key, for the error locations
+    let mut regexp_lines = Vec::new();
+    let mut in_rule = false;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line == "[[rule]]" {
+            in_rule = true;
+        } else if in_rule && line.starts_with("regexp") {
+            regexp_lines.push(index + 1);
+            in_rule = false;
+        }
+    }
+
+    for (index, rule) in config.rules.iter().enumerate() {
+        let line_num = regexp_lines.get(index).copied().unwrap_or(1);
+        if let Err(e) = rgrc::grc::CompiledRegex::new(&rule.regexp) {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: line_num,
+                error_type: "RegexError".to_string(),
+                message: format!("Invalid regex: {}", e),
+                suggestion: Some(
+                    "Check regex syntax (escape special characters with \\)".to_string(),
+                ),
+            });
+        } else if !rule.flags.is_empty() {
+            validate_flags(&rule.flags, &rule.regexp, line_num, path, errors);
+        }
+        if !rule.colours.is_empty() {
+            validate_colours(
+                &rule.colours.join(","),
+                Some(&rule.regexp),
+                line_num,
+                path,
+                errors,
+                &[],
+            );
+        }
+        if !rule.thresholds.is_empty() {
+            validate_thresholds(&rule.thresholds.join(","), line_num, path, errors);
+        }
+        if !rule.gradient.is_empty() {
+            validate_gradient(&rule.gradient, line_num, path, errors);
+        }
+        if !rule.sizes.is_empty() {
+            validate_sizes(&rule.sizes, line_num, path, errors);
+        }
+        if !rule.fields.is_empty() {
+            validate_fields(&rule.fields.join(","), line_num, path, errors);
+        }
+        if let Some(engine) = rule.engine {
+            validate_engine_for(&rule.regexp, engine, line_num, path, errors);
+        }
+    }
+}
+
+/// TOML configs are only understood with the `toml-configs` feature
+#[cfg(not(feature = "toml-configs"))]
+fn validate_toml_content(_content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    errors.push(ValidationError {
+        path: path.to_path_buf(),
+        line: 1,
+        error_type: "FormatError".to_string(),
+        message: "TOML configs are not supported by this build".to_string(),
+        suggestion: Some("Rebuild with --features toml-configs".to_string()),
+    });
+}
+
+/// Style macros (`define NAME=STYLES`) of a conf file, with the line from which each
+/// is in effect; defines in included files take effect at the `include=` line.
+///
+/// The styles of each define are validated on the way.
+fn collect_style_macros(
+    content: &str,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) -> Vec<(usize, String, String)> {
+    let mut macros = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line_num = index + 1;
+        if line.starts_with("include=") {
+            let (included, _) = rgrc::expand_includes(path, line);
+            for included_line in included.lines() {
+                if let Some((name, styles)) = rgrc::grc::parse_style_macro(included_line) {
+                    macros.push((line_num, name.to_string(), styles.to_string()));
+                }
+            }
+        } else if let Some((name, styles)) = rgrc::grc::parse_style_macro(line) {
+            validate_colours(styles, None, line_num, path, errors, &macros);
+            macros.push((line_num, name.to_string(), styles.to_string()));
+        } else if line.starts_with("define ") {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: line_num,
+                error_type: "MacroError".to_string(),
+                message: format!("Malformed style macro: {}", line),
+                suggestion: Some("Use define NAME=STYLES, e.g. define ok=bold green".to_string()),
+            });
+        }
+    }
+    macros
+}
+
+/// Validate a `colours` value, resolving `@NAME` with the macros defined before `line_num`
+/// and checking the groups named in it against `pattern`
+fn validate_colours(
+    style_def: &str,
+    pattern: Option<&str>,
+    line_num: usize,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+    macros: &[(usize, String, String)],
+) {
+    let defined: std::collections::HashMap<String, String> = macros
+        .iter()
+        .filter(|(line, ..)| *line < line_num)
+        .map(|(_, name, styles)| (name.clone(), styles.clone()))
+        .collect();
+    match rgrc::grc::expand_style_macros(style_def, &defined) {
+        Ok(expanded) => {
+            validate_style_definition(&expanded, line_num, path, errors);
+            if let Some(pattern) = pattern {
+                validate_group_names(&expanded, pattern, line_num, path, errors);
+            }
+        }
+        Err(message) => {
+            let undefined = style_def
+                .split([',', ' '])
+                .filter_map(|word| word.strip_prefix('@'))
+                .find(|name| !defined.contains_key(*name))
+                .unwrap_or_default();
+            let later = macros
+                .iter()
+                .find(|(line, name, _)| *line >= line_num && name == undefined);
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: line_num,
+                error_type: "MacroError".to_string(),
+                message: match later {
+                    Some((line, ..)) => format!("{} (defined later, at line {})", message, line),
+                    None => message,
+                },
+                suggestion: Some(
+                    "Define the macro above its first use: define NAME=STYLES".to_string(),
+                ),
+            });
+        }
+    }
+}
+
+/// Validate the letters of a `flags=` value and the regexp they apply to
+fn validate_flags(
+    flags: &str,
+    pattern: &str,
+    line_num: usize,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    let (error_type, message, suggestion) = match rgrc::grc::apply_regex_flags(pattern, flags) {
+        Err(message) => (
+            "FlagError",
+            format!("Invalid flags: {}", message),
+            "Combine i (case-insensitive), m (multi-line), s (dot matches newline) and x (verbose)",
+        ),
+        Ok(flagged) => match rgrc::grc::CompiledRegex::new(&flagged) {
+            Err(e) => (
+                "RegexError",
+                format!("Invalid regex with flags={}: {}", flags.trim(), e),
+                "With x, whitespace in the regexp is ignored and # starts a comment",
+            ),
+            Ok(_) => return,
+        },
+    };
+    errors.push(ValidationError {
+        path: path.to_path_buf(),
+        line: line_num,
+        error_type: error_type.to_string(),
+        message,
+        suggestion: Some(suggestion.to_string()),
+    });
+}
+
+/// Check that each `name:GROUP=STYLE` item of a `colours` value names a group of `pattern`
+fn validate_group_names(
+    style_def: &str,
+    pattern: &str,
+    line_num: usize,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    let names = rgrc::grc::capture_group_names(pattern);
+    for item in style_def.split(',') {
+        let Some(named) = item.trim_start().strip_prefix("name:") else {
+            continue;
+        };
+        let message = match named.split_once('=') {
+            None => format!("Expected name:GROUP=STYLE, got '{}'", item.trim()),
+            Some((group, _)) if !names.iter().flatten().any(|name| name == group.trim()) => {
+                format!("No capture group named '{}' in the regexp", group.trim())
+            }
+            Some(_) => continue,
+        };
+        let available: Vec<&str> = names.iter().flatten().map(String::as_str).collect();
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line: line_num,
+            error_type: "StyleError".to_string(),
+            message,
+            suggestion: Some(if available.is_empty() {
+                "Name a group with (?P<name>...) to style it by name".to_string()
+            } else {
+                format!("Named groups: {}", available.join(", "))
+            }),
+        });
+    }
+}
+
 /// Validate conf.* file format
 fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        validate_toml_content(content, path, errors);
+        return;
+    }
+
     let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+    let macros = collect_style_macros(content, path, errors);
     let mut i = 0;
 
     while i < lines.len() {
@@ -363,6 +1282,17 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                         "Check regex syntax (escape special characters with \\)".to_string(),
                     ),
                 });
+            } else {
+                // Python syntax that compiles, but not as grc would run it
+                for note in rgrc::grc::translate_python_regex(regex_part).1 {
+                    errors.push(ValidationError {
+                        path: path.to_path_buf(),
+                        line: line_num,
+                        error_type: "RegexError".to_string(),
+                        message: format!("Unsupported Python regex syntax: {}", note),
+                        suggestion: Some("Rewrite this part of the regexp".to_string()),
+                    });
+                }
             }
 
             // Look for colours= line
@@ -388,7 +1318,14 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                     };
 
                     // Validate styles
-                    validate_style_definition(style_part, next_line_num, path, errors);
+                    validate_colours(
+                        style_part,
+                        Some(regex_part),
+                        next_line_num,
+                        path,
+                        errors,
+                        &macros,
+                    );
                     i += 1;
 
                     // Continue to check for additional config lines after colours=
@@ -402,8 +1339,18 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                         }
 
                         if config_line.starts_with("count=")
+                            || config_line.starts_with("flags=")
+                            || config_line.starts_with("command=")
+                            || config_line.starts_with("priority=")
+                            || config_line.starts_with("thresholds=")
+                            || config_line.starts_with("gradient=")
+                            || config_line.starts_with("sizes=")
+                            || config_line.starts_with("fields=")
+                            || config_line.starts_with("delimiter=")
+                            || config_line.starts_with("engine=")
                             || config_line.starts_with("skip=")
                             || config_line.starts_with("replace=")
+                            || config_line.starts_with("define ")
                             || config_line.starts_with("#")
                         {
                             // Valid config lines or comments after colours=, skip them
@@ -416,6 +1363,8 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                             || config_line.starts_with(".........")
                             || config_line.starts_with("==")
                             || config_line.starts_with("%%%%%%%")
+                            || config_line.starts_with("include=")
+                            || config_line.starts_with("loglevel=")
                         {
                             // End of rule
                             break;
@@ -427,7 +1376,7 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                                 error_type: "FormatError".to_string(),
                                 message: format!("Unexpected line after colours=: {}", config_line),
                                 suggestion: Some(
-                                    "Expected count=, skip=, replace=, regexp= lines or separator"
+                                    "Expected count=, flags=, skip=, replace=, command=, priority=, thresholds=, gradient=, sizes=, fields=, delimiter=, engine=, regexp= lines or separator"
                                         .to_string(),
                                 ),
                             });
@@ -436,8 +1385,18 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                     }
                     break;
                 } else if next_line.starts_with("count=")
+                    || next_line.starts_with("flags=")
+                    || next_line.starts_with("command=")
+                    || next_line.starts_with("priority=")
+                    || next_line.starts_with("thresholds=")
+                    || next_line.starts_with("gradient=")
+                    || next_line.starts_with("sizes=")
+                    || next_line.starts_with("fields=")
+                    || next_line.starts_with("delimiter=")
+                    || next_line.starts_with("engine=")
                     || next_line.starts_with("skip=")
                     || next_line.starts_with("replace=")
+                    || next_line.starts_with("define ")
                     || next_line.starts_with("#")
                 {
                     // Valid config lines or comments, skip them
@@ -450,6 +1409,8 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                     || next_line.starts_with(".........")
                     || next_line.starts_with("==")
                     || next_line.starts_with("%%%%%%%")
+                    || next_line.starts_with("include=")
+                    || next_line.starts_with("loglevel=")
                 {
                     // End of rule
                     break;
@@ -460,16 +1421,22 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
                         line: next_line_num,
                         error_type: "FormatError".to_string(),
                         message: format!("Unexpected line after regexp: {}", next_line),
-                        suggestion: Some("Expected colours=, count=, skip=, replace=, regexp= lines or ======= / - / ......... / == / %%%%%%% separator".to_string()),
+                        suggestion: Some("Expected colours=, count=, flags=, skip=, replace=, command=, priority=, thresholds=, gradient=, sizes=, fields=, delimiter=, engine=, regexp= lines or ======= / - / ......... / == / %%%%%%% separator".to_string()),
                     });
                     i += 1;
                 }
             }
+        } else if line.starts_with("define ") || line.starts_with("flags=") {
+            // Style macro, checked by collect_style_macros, or flags of the
+            // regexp below, checked by check_regex_flags
+            i += 1;
         } else if line.starts_with("=======")
             || line.starts_with("-")
             || line.starts_with(".........")
             || line.starts_with("==")
             || line.starts_with("%%%%%%%")
+            || line.starts_with("include=")
+            || line.starts_with("loglevel=")
         {
             // Rule separator, continue
             i += 1;
@@ -527,6 +1494,371 @@ fn validate_conf_content(content: &str, path: &Path, errors: &mut Vec<Validation
             i += 1;
         }
     }
+
+    for (line, message) in rgrc::expand_includes(path, content).1 {
+        let loglevel = content
+            .lines()
+            .nth(line - 1)
+            .is_some_and(|text| text.starts_with("loglevel="));
+        let suggestion = if loglevel {
+            "loglevel=token styles severity words, loglevel=line also whole warning and error lines"
+        } else {
+            "include=NAME reads conf.NAME next to this file or from the config directories"
+        };
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line,
+            error_type: "IncludeError".to_string(),
+            message,
+            suggestion: Some(suggestion.to_string()),
+        });
+    }
+
+    check_regex_flags(content, path, errors);
+    check_regex_engines(content, path, errors);
+    check_priorities(content, path, errors);
+    for (index, line) in content.lines().enumerate() {
+        if let Some(value) = line.strip_prefix("thresholds=") {
+            validate_thresholds(value, index + 1, path, errors);
+        } else if let Some(value) = line.strip_prefix("gradient=") {
+            validate_gradient(value, index + 1, path, errors);
+        } else if let Some(value) = line.strip_prefix("sizes=") {
+            validate_sizes(value, index + 1, path, errors);
+        } else if let Some(value) = line.strip_prefix("fields=") {
+            validate_fields(value, index + 1, path, errors);
+        } else if let Some(value) = line.strip_prefix("engine=")
+            && let Err(message) = value.trim().parse::<rgrc::grc::RegexEngine>()
+        {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: index + 1,
+                error_type: "FormatError".to_string(),
+                message: format!("Invalid engine: {}", message),
+                suggestion: Some("Format: engine=auto, engine=fast or engine=enhanced".to_string()),
+            });
+        }
+    }
+    check_rule_reachability(content, path, errors);
+}
+
+/// Validate a `thresholds` value: `N:STYLE` items with numeric thresholds
+fn validate_thresholds(
+    value: &str,
+    line_num: usize,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Err(message) = rgrc::grc::thresholds_from_str(value) {
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line: line_num,
+            error_type: "StyleError".to_string(),
+            message: format!("Invalid thresholds: {}", message),
+            suggestion: Some("Format: thresholds=70:yellow,90:bold red".to_string()),
+        });
+    }
+}
+
+/// Validate a `gradient` value: `MIN:MAX` bounds, optionally with `:ansi`
+fn validate_gradient(value: &str, line_num: usize, path: &Path, errors: &mut Vec<ValidationError>) {
+    if let Err(message) = rgrc::grc::gradient_from_str(value) {
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line: line_num,
+            error_type: "FormatError".to_string(),
+            message: format!("Invalid gradient: {}", message),
+            suggestion: Some("Format: gradient=0:200 or gradient=0:200:ansi".to_string()),
+        });
+    }
+}
+
+/// Validate a `sizes` value: `MIN:MAX` sizes with units, optionally with `:ansi`
+fn validate_sizes(value: &str, line_num: usize, path: &Path, errors: &mut Vec<ValidationError>) {
+    if let Err(message) = rgrc::grc::sizes_from_str(value) {
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line: line_num,
+            error_type: "FormatError".to_string(),
+            message: format!("Invalid sizes: {}", message),
+            suggestion: Some("Format: sizes=1K:1T or sizes=1M:100G:ansi".to_string()),
+        });
+    }
+}
+
+/// Validate a `fields` value: `N:STYLE` items with non-zero field numbers
+fn validate_fields(value: &str, line_num: usize, path: &Path, errors: &mut Vec<ValidationError>) {
+    if let Err(message) = rgrc::grc::fields_from_str(value) {
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line: line_num,
+            error_type: "StyleError".to_string(),
+            message: format!("Invalid fields: {}", message),
+            suggestion: Some(
+                "Format: fields=1:cyan,5:bold yellow (-1 is the last field)".to_string(),
+            ),
+        });
+    }
+}
+
+/// Check that each `priority=` value is an integer
+fn check_priorities(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    for (index, line) in content.lines().enumerate() {
+        if let Some(value) = line.strip_prefix("priority=")
+            && value.trim().parse::<i32>().is_err()
+        {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: index + 1,
+                error_type: "FormatError".to_string(),
+                message: format!("Invalid priority: {}", value.trim()),
+                suggestion: Some(
+                    "Use an integer; rules with a higher priority run first (default 0)"
+                        .to_string(),
+                ),
+            });
+        }
+    }
+}
+
+/// Validate the `flags=` of each rule together with its regexp
+fn check_regex_flags(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    for block in parse_rule_blocks(content) {
+        if let Some((line, flags)) = &block.flags
+            && rgrc::grc::CompiledRegex::new(&block.pattern).is_ok()
+        {
+            validate_flags(flags, &block.pattern, *line, path, errors);
+        }
+    }
+}
+
+/// Check that the regexp of each rule with `engine=` compiles with that engine
+fn check_regex_engines(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    for block in parse_rule_blocks(content) {
+        if let Some(engine) = block.engine {
+            validate_engine_for(&block.pattern, engine, block.line, path, errors);
+        }
+    }
+}
+
+/// Report a regexp that compiles but not with the engine its rule asks for
+fn validate_engine_for(
+    pattern: &str,
+    engine: rgrc::grc::RegexEngine,
+    line_num: usize,
+    path: &Path,
+    errors: &mut Vec<ValidationError>,
+) {
+    if rgrc::grc::CompiledRegex::new(pattern).is_err() {
+        return;
+    }
+    if let Err(e) = rgrc::grc::CompiledRegex::with_engine(pattern, engine) {
+        errors.push(ValidationError {
+            path: path.to_path_buf(),
+            line: line_num,
+            error_type: "RegexError".to_string(),
+            message: format!("Invalid regex with engine={}: {}", engine.as_str(), e),
+            suggestion: Some(
+                "Lookaround and backreferences need engine=auto or engine=enhanced".to_string(),
+            ),
+        });
+    }
+}
+
+/// A rule of a conf file, as far as reachability is concerned
+struct RuleBlock {
+    /// Line of the `regexp=` key
+    line: usize,
+    pattern: String,
+    /// Line and value of the `flags=` key
+    flags: Option<(usize, String)>,
+    /// Key that ends processing of a line once the rule matches (`count=stop`,
+    /// `count=block`, `count=unblock` or `skip=true`)
+    ends_line: Option<&'static str>,
+    /// Value of the `priority=` key (0 when missing or invalid)
+    priority: i32,
+    /// Value of the `engine=` key, when valid
+    engine: Option<rgrc::grc::RegexEngine>,
+}
+
+/// Split a conf file into rules the way `GrcatConfigReader` does: an entry is a
+/// run of lines starting with an alphanumeric character. The rules are in the
+/// order rgrc runs them, by priority then source order.
+fn parse_rule_blocks(content: &str) -> Vec<RuleBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<RuleBlock> = None;
+    let mut in_entry = false;
+
+    for (index, line) in content.lines().enumerate() {
+        // include= and loglevel= pull in whole entries, so they end the current one
+        if !line.starts_with(|c: char| c.is_ascii_alphanumeric())
+            || line.starts_with("include=")
+            || line.starts_with("loglevel=")
+        {
+            blocks.extend(current.take());
+            in_entry = false;
+            continue;
+        }
+        if !in_entry {
+            blocks.extend(current.take());
+            in_entry = true;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        match key.trim() {
+            "regexp" => {
+                // A second regexp= in an entry replaces the first, as in the reader
+                let previous = current.take();
+                current = Some(RuleBlock {
+                    line: index + 1,
+                    pattern: value.to_string(),
+                    flags: previous.as_ref().and_then(|b| b.flags.clone()),
+                    ends_line: previous.as_ref().and_then(|b| b.ends_line),
+                    priority: previous.as_ref().map_or(0, |b| b.priority),
+                    engine: previous.as_ref().and_then(|b| b.engine),
+                });
+            }
+            key => {
+                let block = current.get_or_insert_with(|| RuleBlock {
+                    line: 0,
+                    pattern: String::new(),
+                    flags: None,
+                    ends_line: None,
+                    priority: 0,
+                    engine: None,
+                });
+                match key {
+                    "flags" => block.flags = Some((index + 1, value.to_string())),
+                    "priority" => block.priority = value.trim().parse().unwrap_or(0),
+                    "engine" => block.engine = value.trim().parse().ok(),
+                    "count" if value == "stop" => block.ends_line = Some("count=stop"),
+                    "count" if value == "block" => block.ends_line = Some("count=block"),
+                    "count" if value == "unblock" => block.ends_line = Some("count=unblock"),
+                    "skip" if matches!(value.to_lowercase().as_str(), "true" | "1" | "yes") => {
+                        block.ends_line = Some("skip=true");
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    blocks.extend(current);
+    blocks.retain(|block| block.line > 0);
+    blocks.sort_by_key(|block| std::cmp::Reverse(block.priority));
+    blocks
+}
+
+/// Whether `pattern` can only match at particular positions, so matching a
+/// substring of a line does not imply matching the line
+fn is_anchored(pattern: &str) -> bool {
+    ["^", "$", "\\b", "\\B", "\\A", "\\z", "(?=", "(?!", "(?<"]
+        .iter()
+        .any(|anchor| pattern.contains(anchor))
+}
+
+/// Whether `regex` matches every non-empty line (probed with assorted text)
+fn matches_every_line(regex: &rgrc::grc::CompiledRegex) -> bool {
+    ["a", "Z", "0", " ", "\t", "-", "[", ".", "x y", "é"]
+        .iter()
+        .all(|probe| regex.is_match(probe))
+}
+
+/// Flag rules that can never fire
+///
+/// - rules after a rule that matches every line and ends processing of it
+///   (`count=stop`, `count=block`, `count=unblock` or `skip=true`);
+/// - rules whose required literal (see `rgrc::prefilter::required_literal`) is
+///   matched by an earlier unanchored rule that ends processing, so every
+///   line they match was already claimed;
+/// - repeated patterns.
+fn check_rule_reachability(content: &str, path: &Path, errors: &mut Vec<ValidationError>) {
+    let mut blocks = parse_rule_blocks(content);
+    // Compare the patterns rgrc compiles, with the flags applied
+    for block in &mut blocks {
+        if let Some((_, flags)) = &block.flags
+            && let Ok(pattern) = rgrc::grc::apply_regex_flags(&block.pattern, flags)
+        {
+            block.pattern = pattern;
+        }
+    }
+    let mut seen: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    // Earlier rules that end processing, with their compiled regex
+    let mut enders: Vec<(&RuleBlock, rgrc::grc::CompiledRegex)> = Vec::new();
+    let mut catch_all: Option<&RuleBlock> = None;
+
+    for block in &blocks {
+        let mut report = |error_type: &str, message: String, suggestion: String| {
+            errors.push(ValidationError {
+                path: path.to_path_buf(),
+                line: block.line,
+                error_type: error_type.to_string(),
+                message,
+                suggestion: Some(suggestion),
+            });
+        };
+
+        if let Some(ender) = catch_all {
+            report(
+                "UnreachableRule",
+                format!(
+                    "Rule can never fire: the rule at line {} matches every line and has {}",
+                    ender.line,
+                    ender.ends_line.unwrap_or_default()
+                ),
+                format!(
+                    "Move this rule above line {} or narrow that rule's regexp",
+                    ender.line
+                ),
+            );
+            continue;
+        }
+
+        if let Some(first) = seen.get(block.pattern.as_str()) {
+            report(
+                "DuplicateRule",
+                format!(
+                    "Duplicate pattern: same regexp as the rule at line {}",
+                    first
+                ),
+                format!(
+                    "Remove this rule or merge its colours into the rule at line {}",
+                    first
+                ),
+            );
+            continue;
+        }
+        seen.insert(&block.pattern, block.line);
+
+        let shadow = rgrc::prefilter::required_literal(&block.pattern).and_then(|literal| {
+            enders
+                .iter()
+                .find(|(ender, regex)| !is_anchored(&ender.pattern) && regex.is_match(&literal))
+                .map(|(ender, _)| *ender)
+        });
+        if let Some(ender) = shadow {
+            report(
+                "ShadowedRule",
+                format!(
+                    "Rule is shadowed: every line it matches is matched first by the rule at line {} ({}), which has {}",
+                    ender.line,
+                    ender.pattern,
+                    ender.ends_line.unwrap_or_default()
+                ),
+                format!("Move this rule above line {} or remove it", ender.line),
+            );
+            continue;
+        }
+
+        if block.ends_line.is_some()
+            && let Ok(regex) = rgrc::grc::CompiledRegex::new(&block.pattern)
+        {
+            if matches_every_line(&regex) {
+                catch_all = Some(block);
+            }
+            enders.push((block, regex));
+        }
+    }
 }
 
 /// Validate style definition
@@ -536,75 +1868,37 @@ fn validate_style_definition(
     path: &Path,
     errors: &mut Vec<ValidationError>,
 ) {
-    let valid_styles = vec![
-        // Special keywords
-        "unchanged",
-        "default",
-        "dark",
-        "none",
-        // Foreground colors
-        "black",
-        "red",
-        "green",
-        "yellow",
-        "blue",
-        "magenta",
-        "cyan",
-        "white",
-        // Bright colors
-        "bright_black",
-        "bright_red",
-        "bright_green",
-        "bright_yellow",
-        "bright_blue",
-        "bright_magenta",
-        "bright_cyan",
-        "bright_white",
-        // Background colors
-        "on_black",
-        "on_red",
-        "on_green",
-        "on_yellow",
-        "on_blue",
-        "on_magenta",
-        "on_cyan",
-        "on_white",
-        // Text attributes
-        "bold",
-        "dim",
-        "italic",
-        "underline",
-        "blink",
-        "reverse",
-    ];
-
     // Split by comma first, then by space for each style group
     for style_group in style_def.split(',') {
+        // name:GROUP=STYLE styles a named group; the name is checked by validate_colours
+        let style_group = match style_group.trim_start().strip_prefix("name:") {
+            Some(named) => named.split_once('=').map_or("", |(_, style)| style),
+            None => style_group,
+        };
         for style in style_group.split_whitespace() {
             let trimmed_style = style.trim();
             if trimmed_style.is_empty() {
                 continue;
             }
-            // Allow empty quotes for no styling
-            if trimmed_style == "''" {
-                continue;
-            }
-            // Allow ANSI escape sequences
-            if trimmed_style.starts_with('"') && trimmed_style.contains("\\033[") {
-                continue;
-            }
-            // Normalize hyphenated style names to underscored versions
-            let normalized_style = trimmed_style.replace('-', "_");
-            if !valid_styles.contains(&normalized_style.as_str()) {
+            // rgrc's own parser decides; rgrv keeps no keyword list of its own
+            if rgrc::grc::style_from_str(trimmed_style).is_err() {
+                let underscored = trimmed_style.replace('-', "_");
+                let suggestion = if underscored != trimmed_style
+                    && rgrc::grc::style_from_str(&underscored).is_ok()
+                {
+                    format!("Use '{}' (rgrv conf --fix rewrites it)", underscored)
+                } else {
+                    format!(
+                        "Valid styles include: {}",
+                        rgrc::grc::STYLE_KEYWORDS[0..12].join(", ")
+                    )
+                };
                 errors.push(ValidationError {
                     path: path.to_path_buf(),
                     line: line_num,
                     error_type: "StyleError".to_string(),
                     message: format!("Unknown style: '{}'", trimmed_style),
-                    suggestion: Some(format!(
-                        "Valid styles include: {}",
-                        valid_styles[0..12].join(", ")
-                    )),
+                    suggestion: Some(suggestion),
                 });
             }
         }
@@ -612,53 +1906,23 @@ fn validate_style_definition(
 }
 
 /// Find grc.conf file
+///
+/// Uses the same search path as rgrc itself (see `rgrc::config_paths`).
 fn find_grc_conf() -> PathBuf {
-    let candidates = vec![
-        "etc/rgrc.conf",
-        "~/.config/rgrc/rgrc.conf",
-        "/etc/rgrc/rgrc.conf",
-    ];
-
-    for candidate in candidates {
-        let path = if candidate.starts_with("~") {
-            if let Ok(home) = std::env::var("HOME") {
-                PathBuf::from(candidate.replace("~", &home))
-            } else {
-                continue;
-            }
-        } else {
-            PathBuf::from(candidate)
-        };
-
-        if path.exists() {
-            return path;
-        }
-    }
-
-    PathBuf::from("etc/rgrc.conf")
+    rgrc::config_paths()
+        .into_iter()
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("etc/rgrc.conf"))
 }
 
 /// Find conf directory
+///
+/// Uses the same search path as rgrc itself (see `rgrc::resource_paths`).
 fn find_conf_dir() -> PathBuf {
-    let candidates = vec!["share/", "~/.config/rgrc/", "/etc/rgrc/"];
-
-    for candidate in candidates {
-        let path = if candidate.starts_with("~") {
-            if let Ok(home) = std::env::var("HOME") {
-                PathBuf::from(candidate.replace("~", &home))
-            } else {
-                continue;
-            }
-        } else {
-            PathBuf::from(candidate)
-        };
-
-        if path.exists() && path.is_dir() {
-            return path;
-        }
-    }
-
-    PathBuf::from("share/")
+    rgrc::resource_paths()
+        .into_iter()
+        .find(|path| path.is_dir())
+        .unwrap_or_else(|| PathBuf::from("share/"))
 }
 
 /// Validation error structure
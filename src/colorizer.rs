@@ -19,30 +19,39 @@
 //!    - Handle overlapping matches and edge cases
 //!    - Support count and replace functionality
 //!
-//! 2. **Style Mapping** (Phase 2):
-//!    - Build per-character style map
+//! 2. **Span Resolution** (Phase 2):
+//!    - Flatten overlapping matches into non-overlapping `(range, style)` spans
 //!    - Later rules override earlier ones where they overlap
-//!    - Implements simple precedence strategy
+//!    - Merge adjacent spans with the same style
 //!
-//! 3. **ANSI Encoding** (Phase 3):
-//!    - Merge adjacent characters with same style
-//!    - Minimize ANSI escape sequences
-//!    - Apply console styling using the `console` crate
+//! 3. **Rendering** (Phase 3):
+//!    - Write the line and its spans to the output in a single pass
+//!    - Only styled spans get ANSI escape sequences
+//!    - JSON Lines output uses the raw matches instead
 //!
 //! ## Key Optimizations
 //!
 //! - **Match result caching**: Tracks rightmost end positions to avoid redundant checks
 //! - **Zero-width match handling**: Prevents infinite loops on empty matches
-//! - **Style merging**: Combines adjacent styled segments to reduce escape sequences
-//! - **Count field support**: once/more/stop matching control
+//! - **Span merging**: Combines adjacent styled segments to reduce escape sequences
+//! - **Count field support**: once/more/stop matching control, block/unblock regions
 //! - **Replace field support**: Text substitution functionality
+//! - **Skip field support**: Lines matched by a `skip=true` rule are dropped
+//! - **Command hooks**: `command=` runs a shell command when a rule matches (opt-in)
 
+use std::collections::BinaryHeap;
 use std::io::{BufRead, BufReader, Read, Write};
-#[cfg(feature = "debug")]
-use std::time::Instant;
+use std::ops::Range;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
-use crate::grc::GrcatConfigEntry;
+use crate::grc::{GrcatConfigEntry, field_ranges};
+use crate::json::JsonHighlighter;
+use crate::notify::Notifier;
+use crate::prefilter::AdaptivePrefilter;
 use crate::style::Style;
+use crate::utils::{json_quote, strip_ansi};
+use crate::{InputFormat, OutputFormat};
 
 /// Regex-optimized colorizer with advanced caching and pattern matching optimizations.
 ///
@@ -111,6 +120,718 @@ pub fn colorize_regex<R, W>(
     writer: &mut W,
     rules: &[GrcatConfigEntry],
 ) -> Result<(), Box<dyn std::error::Error>>
+where
+    R: Read,
+    W: Write,
+{
+    colorize_regex_with_options(reader, writer, rules, &mut ColorizeOptions::default())
+}
+
+/// Optional outputs and behaviours for `colorize_regex_with_options()`.
+///
+/// The default value reproduces plain `colorize_regex()`: styled text goes to
+/// the writer and nothing else happens.
+#[derive(Default)]
+pub struct ColorizeOptions<'a> {
+    /// Secondary sink receiving an ANSI-free copy of every output line
+    /// (used by `--log FILE` to keep a plain-text transcript).
+    pub log: Option<&'a mut dyn Write>,
+    /// Format written to the main writer (`--format`).
+    pub format: OutputFormat,
+    /// Per-rule hit counters, filled in while lines are processed (`--stats`).
+    pub stats: Option<&'a mut RuleStats>,
+    /// Cumulative regex evaluation time per rule (`--profile`).
+    pub profile: Option<&'a mut RuleProfile>,
+    /// Sink receiving one JSON match event per input line (`--verbose-format=json`).
+    pub events: Option<&'a mut dyn Write>,
+    /// Collects the styled capture groups of every processed line (`rgrc repl`).
+    pub matches: Option<&'a mut Vec<RuleMatch>>,
+    /// Index of the `count=block` rule whose region is open. Kept here rather
+    /// than per call so the region carries over when lines are fed one at a time.
+    pub block: Option<usize>,
+    /// Run the `command=` hooks of matching rules (`--allow-hooks`).
+    pub hooks: bool,
+    /// Colours the columns of CSV/TSV input under the rule matches (`--format-in`).
+    pub columns: Option<&'a DelimitedColumns>,
+    /// Colours the tokens of JSON input under the rule matches (`--format-in=json`).
+    /// It follows the nesting, so it must see every line in order.
+    pub json: Option<&'a mut JsonHighlighter>,
+    /// Time a line may spend in regex matching (`--line-timeout`); a line that
+    /// takes longer is written uncoloured, with a warning. It is checked between
+    /// regex calls; a single call is only cut short by the bound set with
+    /// `grc::set_match_timeout`, which counts as running out of time too.
+    pub line_timeout: Option<Duration>,
+    /// Stop running a rule that ran out the line timeout for the rest of the
+    /// input (`--disable-slow-rules`).
+    pub disable_slow_rules: bool,
+    /// Indices of the rules disabled so far by `disable_slow_rules`.
+    pub disabled_rules: Vec<usize>,
+    /// Run every rule on every line, without the literal prefilter (to compare
+    /// the two, as `benches/prefilter_bench.rs` does).
+    pub disable_prefilter: bool,
+    /// Lines longer than this many bytes are written as they are, without
+    /// running any rule (`--max-line-length`). A `count=block` region stays open.
+    pub max_line_length: Option<usize>,
+    /// Stop reading the input after the first line that these rules match
+    /// (`--exit-on-match`). Matches are seen through `stats`, which must be set.
+    pub stop_on_match: Option<RuleSelector>,
+    /// Run the rules only for `stats`, `only_matching` and `drop_matching`,
+    /// writing every line kept as it was read, with no style, `replace=` or
+    /// `skip=` applied (`--count`, `--only-matching-lines` and `--drop-matching`
+    /// with colours off).
+    pub count_only: bool,
+    /// Drop the lines that no rule matched, except those inside an open
+    /// `count=block` region (`--only-matching-lines`).
+    pub only_matching: bool,
+    /// Drop the lines that these rules matched, like `skip=` (`--drop-matching`).
+    pub drop_matching: &'a [RuleSelector],
+    /// When the bell last rang for a `beep` style, kept here like `block` so that
+    /// bells stay `BELL_INTERVAL` apart across calls.
+    pub last_bell: Option<Instant>,
+    /// Sends a desktop notification for the input lines matching its regex,
+    /// whatever the rules do with them (`--notify-on`).
+    pub notify: Option<&'a mut Notifier>,
+}
+
+/// Shortest time between two bells rung for `beep` styles, so that a burst of
+/// matching lines (a flood of ping timeouts) rings once.
+pub const BELL_INTERVAL: Duration = Duration::from_secs(1);
+
+impl ColorizeOptions<'_> {
+    /// Write the plain-text representation of an output line to the log sink, if any.
+    fn log_line(&mut self, line: &str) -> std::io::Result<()> {
+        if let Some(log) = self.log.as_mut() {
+            writeln!(log, "{}", strip_ansi(line))?;
+        }
+        Ok(())
+    }
+
+    /// Write the match event for input line `number` to the events sink, if any.
+    fn record_event(
+        &mut self,
+        number: usize,
+        line: &str,
+        spans: &[StyleRange],
+    ) -> std::io::Result<()> {
+        if let Some(events) = self.events.as_mut() {
+            write_json_record(events, Some(number), line, spans)?;
+        }
+        Ok(())
+    }
+
+    /// Append the styled capture groups of `line` to the matches sink, if any.
+    fn record_matches(&mut self, line: &str, spans: &[StyleRange]) {
+        if let Some(matches) = self.matches.as_mut() {
+            matches.extend(spans.iter().map(|&(start, end, _, rule, group)| RuleMatch {
+                rule,
+                group,
+                text: line.get(start..end).unwrap_or_default().to_string(),
+                range: start..end,
+            }));
+        }
+    }
+
+    /// Count one processed line and whether any rule matched it.
+    fn record_line(&mut self, matched: bool) {
+        if let Some(stats) = self.stats.as_mut() {
+            stats.lines += 1;
+            if !matched {
+                stats.unmatched_lines += 1;
+            }
+        }
+    }
+
+    /// Ring the terminal bell for a `beep` style, unless it rang less than
+    /// `BELL_INTERVAL` ago.
+    fn ring_bell<W: Write>(&mut self, writer: &mut W) -> std::io::Result<()> {
+        let now = Instant::now();
+        if self
+            .last_bell
+            .is_some_and(|last| now.duration_since(last) < BELL_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_bell = Some(now);
+        writer.write_all(b"\x07")
+    }
+
+    /// Hand an input line to the `--notify-on` notifier, if any.
+    fn notify_line(&mut self, line: &str) {
+        if let Some(notify) = self.notify.as_deref_mut() {
+            notify.check(line);
+        }
+    }
+
+    /// Whether `stop_on_match` ends the input after the line just processed.
+    fn should_stop(&self) -> bool {
+        match (self.stop_on_match, self.stats.as_deref()) {
+            (Some(selector), Some(stats)) => stats.matched_lines(selector) > 0,
+            _ => false,
+        }
+    }
+
+    /// Count a line matched by the rule at `rule_index`.
+    fn record_hit(&mut self, rule_index: usize) {
+        if let Some(stats) = self.stats.as_mut() {
+            if stats.rule_hits.len() <= rule_index {
+                stats.rule_hits.resize(rule_index + 1, 0);
+            }
+            stats.rule_hits[rule_index] += 1;
+        }
+    }
+}
+
+/// A capture group that a rule styled, as collected by `ColorizeOptions::matches`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleMatch {
+    /// Index of the rule in the rule list
+    pub rule: usize,
+    /// Capture group index (0 = whole match)
+    pub group: usize,
+    /// Byte range of the group in the (possibly replaced) line
+    pub range: Range<usize>,
+    /// Text of the group
+    pub text: String,
+}
+
+/// The rules whose matches `--exit-on-match`, `--count` and `--drop-matching`
+/// look for: any rule, or the one at an index of the rule list, as `--stats`
+/// numbers them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleSelector {
+    #[default]
+    Any,
+    Rule(usize),
+}
+
+impl std::str::FromStr for RuleSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(RuleSelector::Any),
+            _ => s.parse().map(RuleSelector::Rule).map_err(|_| {
+                format!(
+                    "invalid rule '{}' (expected a rule number, as --stats lists them, or any)",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl RuleSelector {
+    /// Whether the rule at `rule_index` is one of those selected.
+    pub fn selects(self, rule_index: usize) -> bool {
+        match self {
+            RuleSelector::Any => true,
+            RuleSelector::Rule(index) => index == rule_index,
+        }
+    }
+}
+
+/// Rule hit counters reported by `--stats`.
+///
+/// A rule counts at most once per line, however many times it matched on it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RuleStats {
+    /// Number of input lines processed
+    pub lines: usize,
+    /// Lines on which no rule matched
+    pub unmatched_lines: usize,
+    /// Lines matched by each rule, indexed like the rule list
+    pub rule_hits: Vec<usize>,
+}
+
+impl RuleStats {
+    /// Create counters for a list of `rule_count` rules.
+    pub fn new(rule_count: usize) -> Self {
+        RuleStats {
+            rule_hits: vec![0; rule_count],
+            ..Default::default()
+        }
+    }
+
+    /// Number of lines matched by the rules `selector` picks.
+    pub fn matched_lines(&self, selector: RuleSelector) -> usize {
+        match selector {
+            RuleSelector::Any => self.lines - self.unmatched_lines,
+            RuleSelector::Rule(index) => self.rule_hits.get(index).copied().unwrap_or(0),
+        }
+    }
+
+    /// Write a human-readable table of the counters, one row per rule.
+    ///
+    /// Rules that never matched are flagged so dead rules stand out.
+    pub fn write_report<W: Write>(
+        &self,
+        writer: &mut W,
+        rules: &[GrcatConfigEntry],
+    ) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "rgrc: {} line(s), {} matched no rule",
+            self.lines, self.unmatched_lines
+        )?;
+        if rules.is_empty() {
+            return writeln!(writer, "rgrc: no rules loaded");
+        }
+
+        writeln!(writer, "{:>5}  {:>8}  pattern", "rule", "lines")?;
+        for (index, rule) in rules.iter().enumerate() {
+            let hits = self.rule_hits.get(index).copied().unwrap_or(0);
+            let note = if hits == 0 {
+                "  (never matched)"
+            } else if rule.skip {
+                "  (lines dropped)"
+            } else {
+                ""
+            };
+            writeln!(
+                writer,
+                "{:>5}  {:>8}  {}{}",
+                index,
+                hits,
+                rule.regex.as_str(),
+                note
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Cumulative regex evaluation cost per rule, reported by `--profile`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RuleProfile {
+    /// Total time spent in each rule's regex, indexed like the rule list
+    pub time: Vec<Duration>,
+    /// Number of regex evaluations per rule
+    pub calls: Vec<usize>,
+}
+
+impl RuleProfile {
+    /// Create an empty profile for a list of `rule_count` rules.
+    pub fn new(rule_count: usize) -> Self {
+        RuleProfile {
+            time: vec![Duration::ZERO; rule_count],
+            calls: vec![0; rule_count],
+        }
+    }
+
+    fn record(&mut self, rule_index: usize, elapsed: Duration) {
+        if self.time.len() <= rule_index {
+            self.time.resize(rule_index + 1, Duration::ZERO);
+            self.calls.resize(rule_index + 1, 0);
+        }
+        self.time[rule_index] += elapsed;
+        self.calls[rule_index] += 1;
+    }
+
+    /// Write the `limit` most expensive rules, slowest first.
+    pub fn write_report<W: Write>(
+        &self,
+        writer: &mut W,
+        rules: &[GrcatConfigEntry],
+        limit: usize,
+    ) -> std::io::Result<()> {
+        let total: Duration = self.time.iter().sum();
+        writeln!(writer, "rgrc: {:?} spent in rule regexes", total)?;
+
+        let mut order: Vec<usize> = (0..rules.len().min(self.time.len())).collect();
+        order.sort_by(|a, b| self.time[*b].cmp(&self.time[*a]));
+
+        writeln!(
+            writer,
+            "{:>5}  {:>12}  {:>9}  {:>6}  pattern",
+            "rule", "time", "calls", "share"
+        )?;
+        for index in order.into_iter().take(limit) {
+            let time = self.time[index];
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() * 100.0 / total.as_secs_f64()
+            };
+            writeln!(
+                writer,
+                "{:>5}  {:>12}  {:>9}  {:>5.1}%  {}",
+                index,
+                format!("{:?}", time),
+                self.calls[index],
+                share,
+                rules[index].regex.as_str()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A styled region of a line: byte range, style, and the rule/capture group that produced it.
+pub type StyleRange<'r> = (usize, usize, &'r Style, usize, usize);
+
+/// A run of a line rendered in a single style, as produced by `resolve_spans()`.
+pub type Span<'r> = (Range<usize>, &'r Style);
+
+/// Flatten the (possibly overlapping) matches of a line into styled runs.
+///
+/// Later ranges override earlier ones where they overlap. The result is
+/// sorted, non-overlapping and clipped to `len`; adjacent runs of equal style
+/// are merged and unstyled text is left out, so a renderer only has to fill
+/// the gaps with the raw line.
+pub fn resolve_spans<'r>(len: usize, ranges: &[StyleRange<'r>]) -> Vec<Span<'r>> {
+    // Every start/end is a point where the winning style may change
+    let mut bounds: Vec<usize> = Vec::with_capacity(ranges.len() * 2);
+    let mut starts: Vec<usize> = Vec::with_capacity(ranges.len());
+    for (index, &(start, end, ..)) in ranges.iter().enumerate() {
+        let (start, end) = (start.min(len), end.min(len));
+        if start < end {
+            bounds.push(start);
+            bounds.push(end);
+            starts.push(index);
+        }
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+    starts.sort_by_key(|&index| ranges[index].0);
+
+    // Sweep the bounds, keeping the ranges started so far in a heap keyed by
+    // their position, so the top is the latest one; ranges that have ended
+    // are only dropped once they reach the top.
+    let mut active = BinaryHeap::new();
+    let mut starts = starts.into_iter().peekable();
+    let mut spans: Vec<Span<'r>> = Vec::new();
+    for window in bounds.windows(2) {
+        let (from, to) = (window[0], window[1]);
+        while let Some(index) = starts.next_if(|&index| ranges[index].0 <= from) {
+            active.push(index);
+        }
+        while active
+            .peek()
+            .is_some_and(|&index| ranges[index].1.min(len) <= from)
+        {
+            active.pop();
+        }
+        let Some(&index) = active.peek() else {
+            continue;
+        };
+        let style = ranges[index].2;
+        if *style == Style::new() {
+            continue;
+        }
+        match spans.last_mut() {
+            Some((range, last)) if range.end == from && **last == *style => range.end = to,
+            _ => spans.push((from..to, style)),
+        }
+    }
+    spans
+}
+
+/// Column colours for delimited input (`--format-in=csv|tsv`).
+///
+/// Every column of a line takes the next colour of a fixed palette, cycling
+/// when there are more columns than colours, and with `shade_rows` every
+/// second line also gets a dark background. Rule matches are painted over the
+/// columns, so a config can still highlight values within them.
+pub struct DelimitedColumns {
+    delimiter: &'static str,
+    palette: Vec<Style>,
+    /// The palette on the shading background, with the background alone last
+    shaded: Option<Vec<Style>>,
+}
+
+/// Palette number of the background of shaded rows
+const SHADE_BACKGROUND: u8 = 236;
+
+impl DelimitedColumns {
+    /// Column colours for `format`, or `None` for free-form text.
+    pub fn new(format: InputFormat, shade_rows: bool) -> Option<Self> {
+        let delimiter = format.delimiter()?;
+        let palette = vec![
+            Style::new().cyan(),
+            Style::new().green(),
+            Style::new().yellow(),
+            Style::new().blue(),
+            Style::new().magenta(),
+            Style::new().red(),
+        ];
+        let shaded = shade_rows.then(|| {
+            palette
+                .iter()
+                .chain(std::iter::once(&Style::new()))
+                .map(|style| style.on_color256(SHADE_BACKGROUND))
+                .collect()
+        });
+        Some(DelimitedColumns {
+            delimiter,
+            palette,
+            shaded,
+        })
+    }
+
+    /// Style ranges of the columns of input line `number` (1-based). The
+    /// rule index of a range is `usize::MAX`, and its group the column number.
+    fn style_ranges(&self, line: &str, number: usize) -> Vec<StyleRange<'_>> {
+        let (palette, background) = match &self.shaded {
+            Some(shaded) if number.is_multiple_of(2) => shaded.split_at(self.palette.len()),
+            _ => (self.palette.as_slice(), &[][..]),
+        };
+        let mut ranges: Vec<StyleRange> = background
+            .iter()
+            .map(|style| (0, line.len(), style, usize::MAX, 0))
+            .collect();
+        for (index, range) in field_ranges(line, self.delimiter).into_iter().enumerate() {
+            let style = &palette[index % palette.len()];
+            ranges.push((range.start, range.end, style, usize::MAX, index + 1));
+        }
+        ranges
+    }
+}
+
+/// Start a rule's `command=` hook through the shell, with the matched text in
+/// `RGRC_MATCH`, without waiting for it.
+///
+/// The hook gets no input and its output is discarded so it cannot garble the
+/// colorized stream; errors it prints still reach stderr.
+fn run_hook(command: &str, text: &str) {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let spawned = shell
+        .arg(command)
+        .env("RGRC_MATCH", text)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn();
+    match spawned {
+        // Reap the hook in the background so finished hooks do not linger
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("rgrc: failed to run hook '{}': {}", command, e),
+    }
+}
+
+/// Expand a `replace=` template for a match: `\N` stands for the text of capture
+/// group N (empty when the group did not take part), any other character is kept.
+fn expand_replacement(template: &str, line: &str, matches: &crate::grc::Captures) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek().and_then(|next| next.to_digit(10)) {
+            Some(group) if c == '\\' => {
+                chars.next();
+                if let Some(m) = matches.get(group as usize) {
+                    output.push_str(&line[m.start()..m.end()]);
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Move `ranges` to where their text is after `replaced` was rewritten to end at
+/// `new_end`. Ranges enclosing the rewritten text stretch over the replacement;
+/// parts inside it are dropped, as are ranges left empty.
+fn shift_ranges(ranges: &mut Vec<StyleRange>, replaced: Range<usize>, new_end: usize) {
+    let moved = |pos: usize| pos - replaced.end + new_end;
+    for range in ranges.iter_mut() {
+        range.0 = match range.0 {
+            pos if pos < replaced.start => pos,
+            pos if pos >= replaced.end => moved(pos),
+            _ => new_end,
+        };
+        range.1 = match range.1 {
+            pos if pos <= replaced.start => pos,
+            pos if pos >= replaced.end => moved(pos),
+            _ => replaced.start,
+        };
+    }
+    ranges.retain(|range| range.0 < range.1);
+}
+
+/// Write `line` with ANSI escapes around each span, followed by a newline.
+fn write_ansi_line<W: Write>(
+    writer: &mut W,
+    line: &str,
+    spans: &[Span],
+    ending: &str,
+) -> std::io::Result<()> {
+    let mut pos = 0;
+    for (range, style) in spans {
+        writer.write_all(&line.as_bytes()[pos..range.start])?;
+        write!(writer, "{}", style.apply_to(&line[range.clone()]))?;
+        pos = range.end;
+    }
+    writer.write_all(&line.as_bytes()[pos..])?;
+    writer.write_all(ending.as_bytes())
+}
+
+/// Write `line` with each styled span wrapped in `[keywords]...[/]` tags.
+///
+/// Literal `[` characters are doubled so the tags stay unambiguous.
+fn write_markup_line<W: Write>(
+    writer: &mut W,
+    line: &str,
+    spans: &[Span],
+    ending: &str,
+) -> std::io::Result<()> {
+    let escape = |text: &str| text.replace('[', "[[");
+    let mut pos = 0;
+    for (range, style) in spans {
+        write!(
+            writer,
+            "{}[{}]{}[/]",
+            escape(&line[pos..range.start]),
+            style.keywords().join(" "),
+            escape(&line[range.clone()])
+        )?;
+        pos = range.end;
+    }
+    write!(writer, "{}{}", escape(&line[pos..]), ending)
+}
+
+/// Remove the `\r` of a CRLF line ending from `line`, so that rules (and `$`)
+/// do not see it, and return the line ending to write the line back with.
+fn take_line_ending(line: &mut String) -> &'static str {
+    if line.ends_with('\r') {
+        line.pop();
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Bytes read from the input at a time; each read brings in the lines the
+/// command has written so far, and their output is written out together.
+const INPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The lines of an input without their `\n`, unlike `BufRead::lines` keeping
+/// the `\r` of a CRLF line ending for `LineColorizer::colorize_line` to restore.
+struct InputLines<R> {
+    reader: BufReader<R>,
+}
+
+impl<R: Read> InputLines<R> {
+    fn new(reader: R) -> Self {
+        InputLines {
+            reader: BufReader::with_capacity(INPUT_BUFFER_SIZE, reader),
+        }
+    }
+
+    /// The next line, or `None` at the end of the input.
+    fn next_line(&mut self) -> Option<std::io::Result<String>> {
+        let mut line = Vec::new();
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                }
+                Some(String::from_utf8(line).map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "stream did not contain valid UTF-8",
+                    )
+                }))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Whether another complete line has already been read, so the next one
+    /// comes without waiting for the input.
+    fn line_ready(&self) -> bool {
+        self.reader.buffer().contains(&b'\n')
+    }
+}
+
+/// Flush `writer` once the lines read so far are all written, before the
+/// input is read again (and may keep rgrc waiting): the output of lines that
+/// arrived together goes out in one write, and nothing stays held back while
+/// the command is quiet.
+fn flush_when_drained<R: Read, W: Write>(
+    input: &InputLines<R>,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    if input.line_ready() {
+        Ok(())
+    } else {
+        writer.flush()
+    }
+}
+
+/// Write one JSON Lines record for `line` and the spans matched on it.
+///
+/// Offsets are byte positions into `text`; `rule` is the index of the rule in
+/// the loaded rule list and `group` the capture group (0 = whole match).
+fn write_jsonl<W: Write>(writer: &mut W, line: &str, spans: &[StyleRange]) -> std::io::Result<()> {
+    write_json_record(writer, None, line, spans)
+}
+
+/// Write a JSON record for `line`, led by its 1-based input line `number` when given
+/// (the match events of `--verbose-format=json`).
+fn write_json_record<W: Write + ?Sized>(
+    writer: &mut W,
+    number: Option<usize>,
+    line: &str,
+    spans: &[StyleRange],
+) -> std::io::Result<()> {
+    write!(writer, "{{")?;
+    if let Some(number) = number {
+        write!(writer, "\"line\":{},", number)?;
+    }
+    write!(writer, "\"text\":{},\"matches\":[", json_quote(line))?;
+    for (n, (start, end, style, rule, group)) in spans.iter().enumerate() {
+        if n > 0 {
+            write!(writer, ",")?;
+        }
+        let keywords: Vec<String> = style
+            .keywords()
+            .iter()
+            .map(|word| json_quote(word))
+            .collect();
+        write!(
+            writer,
+            "{{\"rule\":{},\"group\":{},\"start\":{},\"end\":{},\"style\":[{}]}}",
+            rule,
+            group,
+            start,
+            end,
+            keywords.join(",")
+        )?;
+    }
+    writeln!(writer, "]}}")
+}
+
+/// Same as `colorize_regex()`, with extra outputs controlled by `options`.
+///
+/// Each line is rendered twice when a log sink is configured: the styled form
+/// goes to `writer` and the raw form (after `replace` substitutions, with any
+/// escape sequences emitted by the child removed) goes to `options.log`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut log = Vec::new();
+/// let mut options = ColorizeOptions { log: Some(&mut log), ..Default::default() };
+/// colorize_regex_with_options(&mut reader, &mut output, &rules, &mut options)?;
+/// ```
+pub fn colorize_regex_with_options<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    rules: &[GrcatConfigEntry],
+    options: &mut ColorizeOptions,
+) -> Result<(), Box<dyn std::error::Error>>
 where
     R: Read,
     W: Write,
@@ -132,65 +853,241 @@ where
     // ═══════════════════════════════════════════════════════════════════════════════
 
     // Wrap input in BufReader to reduce I/O syscall overhead and enable line iteration
-    let reader = BufReader::new(reader).lines();
+    let mut input = InputLines::new(reader);
 
     // ═══════════════════════════════════════════════════════════════════════════════
     // FAST PATH: No rules to apply - stream input directly to output unchanged
     // ═══════════════════════════════════════════════════════════════════════════════
 
-    if rules.is_empty() {
-        for line in reader {
-            writeln!(writer, "{}", line?)?;
+    // 1-based number of the current input line, for match events
+    let mut line_number = 0;
+
+    if rules.is_empty() && options.columns.is_none() && options.json.is_none() {
+        while let Some(line) = input.next_line() {
+            let mut line = line?;
+            let ending = take_line_ending(&mut line);
+            line_number += 1;
+            options.notify_line(&line);
+            if options.only_matching {
+                options.record_line(false);
+                continue;
+            }
+            options.record_event(line_number, &line, &[])?;
+            match options.format {
+                OutputFormat::Ansi => write!(writer, "{}{}", line, ending)?,
+                OutputFormat::Jsonl => write_jsonl(writer, &line, &[])?,
+                OutputFormat::Markup => write_markup_line(writer, &line, &[], ending)?,
+            }
+            options.log_line(&line)?;
+            options.record_line(false);
+            flush_when_drained(&input, writer)?;
         }
         return Ok(());
     }
 
-    // Default style for unstyled text (no color, no attributes)
-    let default_style = Style::new();
-
-    // ═══════════════════════════════════════════════════════════════════════════════
-    // PHASE 2: LINE-BY-LINE PROCESSING - Apply colorization rules to each line
-    // ═══════════════════════════════════════════════════════════════════════════════
-
-    for line in reader {
+    let mut lines = LineColorizer::new(rules);
+    while let Some(line) = input.next_line() {
         // Extract line content, propagating any I/O errors
-        let mut line = line?;
+        let line = line?;
         #[cfg(feature = "debug")]
         if record_time {
             lines_processed += 1;
         }
+        lines.colorize_line(line, writer, options)?;
+        if options.should_stop() {
+            writer.flush()?;
+            break;
+        }
+        flush_when_drained(&input, writer)?;
+    }
+
+    #[cfg(feature = "debug")]
+    if let Some(s) = overall_start.filter(|_| record_time) {
+        eprintln!(
+            "[rgrc:time] colorizer total processed {} lines in {:?}",
+            lines_processed,
+            s.elapsed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Colorizes lines fed one at a time, keeping what `colorize_regex_with_options()`
+/// keeps between lines: the rule prefilters and the input line number.
+///
+/// State that outlives a single call (an open `count=block` region, rules disabled
+/// by the line timeout, the JSON nesting) stays in the `ColorizeOptions` passed to
+/// each call, so the same options must be used for every line of an input.
+///
+/// # Examples
+///
+/// ```
+/// use rgrc::colorizer::{ColorizeOptions, LineColorizer};
+/// use rgrc::grc::{CompiledRegex, GrcatConfigEntry};
+/// use rgrc::Style;
+///
+/// let rules = [GrcatConfigEntry::new(
+///     CompiledRegex::new("ERROR").unwrap(),
+///     vec![Style::new().red()],
+/// )];
+/// let mut lines = LineColorizer::new(&rules);
+/// let mut options = ColorizeOptions::default();
+/// let mut output = Vec::new();
+/// lines.colorize_line("ERROR: disk full".to_string(), &mut output, &mut options)?;
+/// assert_eq!(output, b"\x1b[31mERROR\x1b[0m: disk full\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct LineColorizer<'r> {
+    rules: &'r [GrcatConfigEntry],
+    /// One Aho-Corasick pass per line finds which rules' required literals occur,
+    /// for the rules demoted after a long stretch without a match
+    literal_prefilter: Option<AdaptivePrefilter>,
+    /// One RegexSet pass per line tells which Fast rules can match at all
+    #[cfg(feature = "regex-set")]
+    rule_set: Option<crate::prefilter::RuleSet>,
+    /// Cached DFAs rule out Fast rules without compiling their regexes
+    #[cfg(feature = "dfa-cache")]
+    dfa_screen: Option<crate::dfa_cache::DfaScreen>,
+    /// 1-based number of the last line colorized, for match events
+    line_number: usize,
+}
+
+impl<'r> LineColorizer<'r> {
+    /// Prepare to colorize lines with `rules`.
+    pub fn new(rules: &'r [GrcatConfigEntry]) -> Self {
+        LineColorizer {
+            rules,
+            literal_prefilter: AdaptivePrefilter::new(rules.iter().map(|rule| rule.regex.as_str())),
+            #[cfg(feature = "regex-set")]
+            rule_set: crate::prefilter::RuleSet::new(rules),
+            #[cfg(feature = "dfa-cache")]
+            dfa_screen: crate::get_cache_dir()
+                .and_then(|dir| crate::dfa_cache::DfaScreen::new(rules, &dir)),
+            line_number: 0,
+        }
+    }
+
+    /// Colorize the next input `line` (without its line ending) into `writer`,
+    /// which receives nothing for a line dropped by a `skip=true` rule.
+    ///
+    /// A trailing `\r`, left by a CRLF line ending, is kept out of the rules'
+    /// sight and written back before the `\n`.
+    pub fn colorize_line<W: Write>(
+        &mut self,
+        mut line: String,
+        writer: &mut W,
+        options: &mut ColorizeOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.line_number += 1;
+        let line_number = self.line_number;
+        let ending = take_line_ending(&mut line);
+        options.notify_line(&line);
+        let original = options.count_only.then(|| line.clone());
+        let rules = self.rules;
+        let literal_prefilter = self
+            .literal_prefilter
+            .as_mut()
+            .filter(|_| !options.disable_prefilter);
+        #[cfg(feature = "regex-set")]
+        let rule_set = &self.rule_set;
+        #[cfg(feature = "dfa-cache")]
+        let dfa_screen = &self.dfa_screen;
+
+        // Copied out so the column styles may be borrowed while the options are updated
+        let columns = options.columns;
 
         // ═══════════════════════════════════════════════════════════════════════════════
-        // FAST PATH: Empty lines - preserve as single newline without processing
+        // FAST PATH: Empty lines, and lines over the length limit (minified JSON,
+        // base64 blobs) - output unchanged without running any rule
         // ═══════════════════════════════════════════════════════════════════════════════
 
-        if line.is_empty() {
-            writeln!(writer)?;
-            continue;
+        if line.is_empty() || options.max_line_length.is_some_and(|max| line.len() > max) {
+            // JSON is still tokenized, to keep track of the nesting
+            if let Some(json) = options.json.as_deref_mut() {
+                json.style_ranges(&line);
+            }
+            if options.only_matching && options.block.is_none() {
+                options.record_line(false);
+                return Ok(());
+            }
+            options.record_event(line_number, &line, &[])?;
+            match options.format {
+                OutputFormat::Ansi => write!(writer, "{}{}", line, ending)?,
+                OutputFormat::Jsonl => write_jsonl(writer, &line, &[])?,
+                OutputFormat::Markup => write_markup_line(writer, &line, &[], ending)?,
+            }
+            options.log_line(&line)?;
+            options.record_line(false);
+            return Ok(());
         }
 
         // ═══════════════════════════════════════════════════════════════════════════════
         // PHASE 2A: MATCH COLLECTION - Find all regex matches with intelligent caching
         // ═══════════════════════════════════════════════════════════════════════════════
 
-        // Vector to collect all (start_pos, end_pos, style) ranges for matched patterns
-        let mut style_ranges: Vec<(usize, usize, &Style)> = Vec::new();
+        // Vector to collect all (start_pos, end_pos, style, rule, group) ranges for matched patterns
+        let mut style_ranges: Vec<StyleRange> = Vec::new();
 
         // Track whether to stop processing the entire line (for count=stop)
         let mut stop_line_processing = false;
 
-        // Process each rule (regex pattern + associated styles)
-        'outer_loop: for rule in rules {
-            // Skip rules marked with skip=true
-            if rule.skip {
-                continue;
-            }
+        // Track whether any rule matched this line (for --stats)
+        let mut line_matched = false;
 
+        // Track whether a skip=true rule matched, dropping the line from the output
+        let mut drop_line = false;
+        // Whether a rule of `drop_matching` matched, which drops the line in any case
+        let mut dropped = false;
+
+        let mut literal_prefilter = literal_prefilter;
+        let mut literal_candidates = literal_prefilter.as_mut().and_then(|p| p.candidates(&line));
+
+        // With a time budget, the line as read is kept to be written out if it runs out
+        let deadline = options
+            .line_timeout
+            .map(|timeout| (Instant::now() + timeout, line.clone()));
+
+        // Index of the rule that was running when the budget ran out
+        let mut timed_out: Option<usize> = None;
+
+        #[cfg(feature = "regex-set")]
+        let mut candidates = rule_set.as_ref().map(|set| set.candidates(&line));
+
+        // Process each rule (regex pattern + associated styles)
+        for (rule_index, rule) in rules.iter().enumerate() {
             // Stop processing if a previous rule had count=stop
             if stop_line_processing {
                 break;
             }
 
+            // Rules that ran out the line timeout before are not run again
+            if options.disabled_rules.contains(&rule_index) {
+                continue;
+            }
+
+            // Rules ruled out by the set pass cannot match this line
+            #[cfg(feature = "regex-set")]
+            if candidates.as_ref().is_some_and(|c| !c[rule_index]) {
+                continue;
+            }
+
+            // Rules whose required literal is absent cannot match this line
+            if literal_candidates.as_ref().is_some_and(|c| !c[rule_index]) {
+                if let Some(prefilter) = literal_prefilter.as_mut() {
+                    prefilter.record(rule_index, false);
+                }
+                continue;
+            }
+
+            #[cfg(feature = "dfa-cache")]
+            if dfa_screen
+                .as_ref()
+                .is_some_and(|screen| !screen.may_match(rule_index, &line))
+            {
+                continue;
+            }
+
             // Current search offset in the line (advances as we find matches)
             let mut offset = 0;
 
@@ -201,6 +1098,12 @@ where
             // Track whether this rule should match only once (for count=once)
             let mut rule_matched_once = false;
 
+            // Track whether this rule matched anywhere on the line (for --stats)
+            let mut rule_hit = false;
+
+            // Text of the rule's first match on the line, handed to its command= hook
+            let mut hook_text: Option<String> = None;
+
             // Scan the line for all matches of this rule's regex pattern
             while offset < line.len() && !rule_matched_once {
                 // ═══════════════════════════════════════════════════════════════════════════════
@@ -215,64 +1118,130 @@ where
                 }
 
                 // Attempt regex match starting from current offset position
-                if let Some(matches) = rule.regex.captures_from_pos(&line, offset) {
+                let captures = match options.profile.as_mut() {
+                    Some(profile) => {
+                        let started = Instant::now();
+                        let captures = rule.regex.try_captures_from_pos(&line, offset);
+                        profile.record(rule_index, started.elapsed());
+                        captures
+                    }
+                    None => rule.regex.try_captures_from_pos(&line, offset),
+                };
+
+                // Out of time, or a match given up on: give up on the line, styles and all
+                let out_of_time = deadline
+                    .as_ref()
+                    .is_some_and(|(deadline, _)| Instant::now() >= *deadline);
+                let captures = match captures {
+                    Ok(captures) if !out_of_time => captures,
+                    Err(_) if deadline.is_none() => None,
+                    _ => {
+                        timed_out = Some(rule_index);
+                        stop_line_processing = true;
+                        break;
+                    }
+                };
+
+                if let Some(matches) = captures {
+                    rule_hit = true;
+                    if options.hooks && !rule.command.is_empty() && hook_text.is_none() {
+                        let full_match = matches.get(0).unwrap();
+                        hook_text = Some(line[full_match.start()..full_match.end()].to_string());
+                    }
+
+                    // A skip=true rule hides the whole line; nothing else needs to run
+                    if rule.skip {
+                        drop_line = true;
+                        stop_line_processing = true;
+                        break;
+                    }
+
+                    // ═══════════════════════════════════════════════════════════════════════════════
+                    // REPLACE FUNCTIONALITY: Rewrite the match, then keep colouring the new line
+                    // ═══════════════════════════════════════════════════════════════════════════════
+
+                    if !rule.replace.is_empty() {
+                        let full_match = matches.get(0).unwrap();
+                        let (start, end) = (full_match.start(), full_match.end());
+                        let replacement = expand_replacement(&rule.replace, &line, &matches);
+                        line.replace_range(start..end, &replacement);
+                        let new_end = start + replacement.len();
+
+                        // Earlier styles follow the text they were given to
+                        shift_ranges(&mut style_ranges, start..end, new_end);
+                        if let Some(style) = rule.colors.first() {
+                            style_ranges.push((start, new_end, style, rule_index, 0));
+                        }
+
+                        // Later rules see the rewritten line, which may hold new literals
+                        literal_candidates =
+                            literal_prefilter.as_mut().and_then(|p| p.candidates(&line));
+                        #[cfg(feature = "regex-set")]
+                        {
+                            candidates = rule_set.as_ref().map(|set| set.candidates(&line));
+                        }
+
+                        // Resume after the replacement text, so it is never rewritten again
+                        last_end = new_end;
+                        offset = if end > start {
+                            new_end
+                        } else {
+                            new_end + line[new_end..].chars().next().map_or(1, char::len_utf8)
+                        };
+                        match rule.count {
+                            crate::grc::GrcatConfigEntryCount::More => {}
+                            crate::grc::GrcatConfigEntryCount::Once => rule_matched_once = true,
+                            _ => {
+                                stop_line_processing = true;
+                                rule_matched_once = true;
+                            }
+                        }
+                        continue;
+                    }
+
                     // ═══════════════════════════════════════════════════════════════════════════════
                     // CAPTURE GROUP PROCESSING: Extract each matched subgroup
                     // ═══════════════════════════════════════════════════════════════════════════════
 
+                    // A fields= rule styles the columns of the line it matches, once
+                    if !rule.fields.is_empty() {
+                        for (field, range, style) in rule.field_styles(&line) {
+                            style_ranges.push((range.start, range.end, style, rule_index, field));
+                        }
+                        last_end = line.len();
+                    }
+
+                    // With thresholds=, gradient= or sizes=, the number in the first
+                    // group (or the whole match) is styled by its value
+                    let value_group = usize::from(matches.len() > 1);
+
                     // Iterate through all capture groups (index 0 = full match, 1+ = subgroups)
                     for (i, mmatch) in matches.iter().into_iter().enumerate() {
                         if let Some(mmatch) = mmatch {
                             let start = mmatch.start();
                             let end = mmatch.end();
+                            let value_style = if i == value_group {
+                                let text = &line[start..end];
+                                rule.threshold_style(text)
+                                    .or_else(|| rule.gradient_style(text))
+                                    .or_else(|| rule.size_style(text))
+                            } else {
+                                None
+                            };
 
                             // Only apply styling if this capture group index has a corresponding style
                             // Most rules only style the full match (index 0) or first few groups
-                            if i < rule.colors.len() {
-                                let style = &rule.colors[i];
-
+                            if rule.fields.is_empty()
+                                && let Some(style) = value_style.or_else(|| rule.colors.get(i))
+                            {
                                 // Record this styled range for later application
-                                style_ranges.push((start, end, style));
+                                style_ranges.push((start, end, style, rule_index, i));
 
                                 // Update cache: track rightmost position covered by any match
                                 last_end = last_end.max(end);
                             }
                         }
 
-                        // ═══════════════════════════════════════════════════════════════════════════════
-                        // REPLACE FUNCTIONALITY: Text substitution with capture group support
-                        // ═══════════════════════════════════════════════════════════════════════════════
-
-                        // Get the full match (capture group 0) for replacement operations
-                        let full_match = matches.get(0).unwrap();
-
-                        // If replace field is specified, perform text substitution
-                        if !rule.replace.is_empty() {
-                            // Build replacement string with capture group substitution
-                            let mut replacement = rule.replace.clone();
-
-                            // Replace \1, \2, etc. with corresponding capture groups
-                            for (i, capture) in matches.iter().into_iter().enumerate() {
-                                if let Some(capture_match) = capture {
-                                    let capture_text =
-                                        &line[capture_match.start()..capture_match.end()];
-                                    let placeholder = format!("\\{}", i);
-                                    replacement = replacement.replace(&placeholder, capture_text);
-                                }
-                            }
-
-                            // Replace the matched text in the line
-                            // Note: This modifies the line, which may affect subsequent rule matching
-                            // We rebuild the line with the replacement
-                            let before = &line[..full_match.start()];
-                            let after = &line[full_match.end()..];
-                            line = format!("{}{}{}", before, replacement, after);
-
-                            // Since we modified the line, we need to restart processing from the beginning
-                            // This is a simplified approach - in practice, we might want more sophisticated handling
-                            break 'outer_loop;
-                        }
-
                         // ═══════════════════════════════════════════════════════════════════════════════
                         // COUNT CONTROL: Handle once/more/stop matching behavior
                         // ═══════════════════════════════════════════════════════════════════════════════
@@ -291,6 +1260,19 @@ where
                                 stop_line_processing = true;
                                 rule_matched_once = true;
                             }
+                            crate::grc::GrcatConfigEntryCount::Block => {
+                                // Open a region: this line and the following ones take
+                                // the rule's first style until an unblock rule matches
+                                options.block = Some(rule_index);
+                                stop_line_processing = true;
+                                rule_matched_once = true;
+                            }
+                            crate::grc::GrcatConfigEntryCount::Unblock => {
+                                // Close the region; this line keeps its own styles
+                                options.block = None;
+                                stop_line_processing = true;
+                                rule_matched_once = true;
+                            }
                         }
                     }
 
@@ -315,78 +1297,131 @@ where
                     break;
                 }
             }
+
+            if let Some(prefilter) = literal_prefilter.as_mut() {
+                prefilter.record(rule_index, rule_hit);
+            }
+            if rule_hit {
+                options.record_hit(rule_index);
+                line_matched = true;
+                dropped |= options
+                    .drop_matching
+                    .iter()
+                    .any(|selector| selector.selects(rule_index));
+            }
+            if let Some(text) = hook_text {
+                run_hook(&rule.command, &text);
+            }
+        }
+
+        if let (Some(rule_index), Some((_, raw))) = (timed_out, deadline) {
+            let timeout = options.line_timeout.unwrap_or_default();
+            eprintln!(
+                "rgrc: line {} took over {}ms in rule {} ({}), written uncoloured{}",
+                line_number,
+                timeout.as_millis(),
+                rule_index,
+                rules[rule_index].regex.as_str(),
+                if options.disable_slow_rules {
+                    "; rule disabled"
+                } else {
+                    ""
+                }
+            );
+            if options.disable_slow_rules {
+                options.disabled_rules.push(rule_index);
+            }
+            line = raw;
+            style_ranges.clear();
+            drop_line = false;
+        }
+
+        // Inside a block region the whole line takes the block rule's style
+        if let Some(block) = options.block.filter(|_| timed_out.is_none()) {
+            style_ranges.clear();
+            if let Some(style) = rules.get(block).and_then(|rule| rule.colors.first()) {
+                style_ranges.push((0, line.len(), style, block, 0));
+            }
+        }
+
+        // Tokenized even when the line is dropped, to keep track of the nesting
+        let json_ranges = options
+            .json
+            .as_deref_mut()
+            .map(|json| json.style_ranges(&line))
+            .filter(|_| timed_out.is_none());
+
+        options.record_line(line_matched);
+        if dropped || options.only_matching && !line_matched && options.block.is_none() {
+            return Ok(());
+        }
+        if let Some(original) = original {
+            write!(writer, "{}{}", original, ending)?;
+            options.log_line(&original)?;
+            return Ok(());
+        }
+        if drop_line {
+            return Ok(());
+        }
+        options.record_event(line_number, &line, &style_ranges)?;
+        options.record_matches(&line, &style_ranges);
+        if options.format == OutputFormat::Ansi
+            && style_ranges.iter().any(|range| range.2.rings_bell())
+        {
+            options.ring_bell(writer)?;
         }
 
         // ═══════════════════════════════════════════════════════════════════════════════
         // FAST PATH: No matches found - output line unchanged to avoid processing
         // ═══════════════════════════════════════════════════════════════════════════════
 
-        if style_ranges.is_empty() {
-            writeln!(writer, "{}", line)?;
-            continue;
+        if options.format == OutputFormat::Jsonl {
+            write_jsonl(writer, &line, &style_ranges)?;
+            options.log_line(&line)?;
+            return Ok(());
         }
 
-        // ═══════════════════════════════════════════════════════════════════════════════
-        // PHASE 2B: STYLE APPLICATION - Build per-character style mapping
-        // ═══════════════════════════════════════════════════════════════════════════════
+        // Delimited and JSON input are coloured first, so the rule matches win
+        if let Some(columns) = columns.filter(|_| timed_out.is_none()) {
+            let mut layered = columns.style_ranges(&line, line_number);
+            layered.append(&mut style_ranges);
+            style_ranges = layered;
+        }
+        if let Some(json_ranges) = json_ranges {
+            let mut layered: Vec<StyleRange> = json_ranges
+                .into_iter()
+                .map(|(range, style)| (range.start, range.end, style, usize::MAX, 0))
+                .collect();
+            layered.append(&mut style_ranges);
+            style_ranges = layered;
+        }
 
-        // Create per-character style array (one style reference per character)
-        // Initialize all characters to default style (unstyled)
-        let mut char_styles: Vec<&Style> = vec![&default_style; line.len()];
+        if options.format == OutputFormat::Markup {
+            let spans = resolve_spans(line.len(), &style_ranges);
+            write_markup_line(writer, &line, &spans, ending)?;
+            options.log_line(&line)?;
+            return Ok(());
+        }
 
-        // Apply all collected style ranges to the character array
-        // Later ranges override earlier ones (simple precedence rule)
-        for (start, end, style) in style_ranges {
-            // Bounds check: ensure we don't exceed line length
-            for item in char_styles.iter_mut().take(end.min(line.len())).skip(start) {
-                *item = style;
-            }
+        if style_ranges.is_empty() {
+            write!(writer, "{}{}", line, ending)?;
+            options.log_line(&line)?;
+            return Ok(());
         }
 
         // ═══════════════════════════════════════════════════════════════════════════════
-        // PHASE 2C: OUTPUT GENERATION - Write styled text with run-length encoding
+        // PHASE 2B: SPAN RESOLUTION - Flatten overlapping matches into styled runs
         // ═══════════════════════════════════════════════════════════════════════════════
 
-        // Run-length encoding: merge consecutive characters with same style
-        // This minimizes ANSI escape sequence overhead
-        let mut prev_style = &default_style;
-        let mut offset = 0;
-
-        // Scan through characters and detect style boundaries
-        for i in 0..line.len() {
-            let this_style = char_styles[i];
-
-            // Style boundary detected - output previous styled segment
-            if this_style != prev_style {
-                if i > 0 {
-                    // Apply previous style to characters from offset to current position
-                    // Style::apply_to() generates appropriate ANSI escape codes
-                    write!(writer, "{}", prev_style.apply_to(&line[offset..i]))?;
-                }
-
-                // Update tracking for next segment
-                prev_style = this_style;
-                offset = i;
-            }
-        }
+        let spans = resolve_spans(line.len(), &style_ranges);
 
-        // Output the final segment (from last boundary to end of line)
-        if offset < line.len() {
-            write!(writer, "{}", prev_style.apply_to(&line[offset..]))?;
-        }
+        // ═══════════════════════════════════════════════════════════════════════════════
+        // PHASE 2C: OUTPUT GENERATION - Render the line and its spans in one pass
+        // ═══════════════════════════════════════════════════════════════════════════════
 
-        // Always terminate line with newline (matches input format)
-        writeln!(writer)?;
-    }
+        write_ansi_line(writer, &line, &spans, ending)?;
+        options.log_line(&line)?;
 
-    #[cfg(feature = "debug")]
-    if let Some(s) = overall_start.filter(|_| record_time) {
-        eprintln!(
-            "[rgrc:time] colorizer total processed {} lines in {:?}",
-            lines_processed,
-            s.elapsed()
-        );
+        Ok(())
     }
-
-    Ok(())
 }
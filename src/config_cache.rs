@@ -0,0 +1,317 @@
+//! # config_cache.rs - Parsed grcat configs in the cache directory (`config-cache` feature)
+//!
+//! Parsing a `conf.*` file line by line is a noticeable share of the startup
+//! time of short-lived commands. With this feature the parsed rule list of
+//! each config is stored in a compact binary form under `<cache>/parsed/`,
+//! keyed by a hash of the config text, and read back on later runs.
+//!
+//! Regexes are not stored: entries come back with lazily compiled patterns
+//! (see `CompiledRegex::lazy`), exactly as `GrcatConfigReader::lazy()` yields them.
+//!
+//! ## File format
+//!
+//! Each config is stored in `<cache>/parsed/<fnv64 of source>.rules`; all
+//! integers are little endian and strings are a `u32` length plus UTF-8 bytes:
+//!
+//! ```text
+//! header: "RGRCCFG\0" | format version (u32) | source length (u64) | entry count (u32)
+//! entry:  pattern | style count (u32) | styles (keywords) | skip (u8) | count (u8) | replace
+//! ```
+//!
+//! A file whose version or source length does not match is ignored and
+//! rewritten, as is any file that fails to decode.
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use crate::grc::{
+    CompiledRegex, Gradient, GrcatConfigEntry, GrcatConfigEntryCount, GrcatConfigReader,
+    RegexEngine, SizeScale, style_from_str,
+};
+use crate::utils::{fnv1a, write_atomic};
+
+/// Version of the on-disk layout; files with any other version are rebuilt.
+///
+/// Also bumped when the reader yields different entries for the same source
+/// (version 2: `count=block` and `count=unblock`, formerly read as `more`;
+/// version 3: `command=` hooks; version 4: `priority=`; version 5: `thresholds=`;
+/// version 6: `gradient=`; version 7: `fields=` and `delimiter=`; version 8: `sizes=`;
+/// version 9: `engine=`; version 10: the `beep` colour).
+pub const CONFIG_FORMAT_VERSION: u32 = 10;
+
+const MAGIC: &[u8; 8] = b"RGRCCFG\0";
+
+/// Return the rules of the grcat config `source`, from the cache when possible.
+///
+/// On a miss the text is parsed and the result stored for the next run.
+pub fn load_or_parse(cache_dir: &Path, source: &str) -> Vec<GrcatConfigEntry> {
+    let path = cache_path(cache_dir, source);
+    if let Some(entries) = std::fs::read(&path)
+        .ok()
+        .and_then(|bytes| decode(&bytes, source))
+    {
+        return entries;
+    }
+
+    let entries: Vec<GrcatConfigEntry> = GrcatConfigReader::new(source.as_bytes().lines())
+        .lazy()
+        .collect();
+
+    if let Some(dir) = path.parent()
+        && std::fs::create_dir_all(dir).is_ok()
+    {
+        let _ = write_atomic(&path, &encode(source, &entries));
+    }
+    entries
+}
+
+/// Location of the cache file for the config text `source`.
+pub fn cache_path(cache_dir: &Path, source: &str) -> PathBuf {
+    cache_dir
+        .join("parsed")
+        .join(format!("{:016x}.rules", fnv1a(source.as_bytes())))
+}
+
+fn encode(source: &str, entries: &[GrcatConfigEntry]) -> Vec<u8> {
+    fn put_str(out: &mut Vec<u8>, text: &str) {
+        out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        out.extend_from_slice(text.as_bytes());
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&CONFIG_FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&(source.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        put_str(&mut out, entry.regex.as_str());
+        out.extend_from_slice(&(entry.colors.len() as u32).to_le_bytes());
+        for style in &entry.colors {
+            put_str(&mut out, &style.keywords().join(" "));
+        }
+        out.push(entry.skip as u8);
+        out.push(match entry.count {
+            GrcatConfigEntryCount::Once => 0,
+            GrcatConfigEntryCount::More => 1,
+            GrcatConfigEntryCount::Stop => 2,
+            GrcatConfigEntryCount::Block => 3,
+            GrcatConfigEntryCount::Unblock => 4,
+        });
+        put_str(&mut out, &entry.replace);
+        put_str(&mut out, &entry.command);
+        out.extend_from_slice(&entry.priority.to_le_bytes());
+        out.extend_from_slice(&(entry.thresholds.len() as u32).to_le_bytes());
+        for (value, style) in &entry.thresholds {
+            out.extend_from_slice(&value.to_le_bytes());
+            put_str(&mut out, &style.keywords().join(" "));
+        }
+        match &entry.gradient {
+            Some(gradient) => {
+                out.push(1 + gradient.ansi as u8);
+                out.extend_from_slice(&gradient.min.to_le_bytes());
+                out.extend_from_slice(&gradient.max.to_le_bytes());
+            }
+            None => out.push(0),
+        }
+        match &entry.sizes {
+            Some(sizes) => {
+                out.push(1 + sizes.ansi as u8);
+                out.extend_from_slice(&sizes.min.to_le_bytes());
+                out.extend_from_slice(&sizes.max.to_le_bytes());
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&(entry.fields.len() as u32).to_le_bytes());
+        for (field, style) in &entry.fields {
+            out.extend_from_slice(&(*field as i64).to_le_bytes());
+            put_str(&mut out, &style.keywords().join(" "));
+        }
+        put_str(&mut out, &entry.delimiter);
+        out.push(match entry.engine {
+            RegexEngine::Auto => 0,
+            RegexEngine::Fast => 1,
+            RegexEngine::Enhanced => 2,
+        });
+    }
+    out
+}
+
+/// Decode a cache file for `source`; `None` when it is stale or corrupt.
+fn decode(bytes: &[u8], source: &str) -> Option<Vec<GrcatConfigEntry>> {
+    let mut reader = Reader(bytes.strip_prefix(MAGIC.as_slice())?);
+    if reader.u32()? != CONFIG_FORMAT_VERSION || reader.u64()? != source.len() as u64 {
+        return None;
+    }
+
+    let count = reader.u32()? as usize;
+    let mut entries = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let pattern = reader.str()?;
+        let color_count = reader.u32()? as usize;
+        let colors = (0..color_count)
+            .map(|_| style_from_str(reader.str()?).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let skip = reader.u8()? != 0;
+        let count = match reader.u8()? {
+            0 => GrcatConfigEntryCount::Once,
+            1 => GrcatConfigEntryCount::More,
+            2 => GrcatConfigEntryCount::Stop,
+            3 => GrcatConfigEntryCount::Block,
+            4 => GrcatConfigEntryCount::Unblock,
+            _ => return None,
+        };
+        let replace = reader.str()?.to_string();
+        let command = reader.str()?.to_string();
+        let priority = reader.u32()? as i32;
+        let threshold_count = reader.u32()? as usize;
+        let thresholds = (0..threshold_count)
+            .map(|_| {
+                let value = reader.f64()?;
+                Some((value, style_from_str(reader.str()?).ok()?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let gradient = match reader.u8()? {
+            0 => None,
+            kind @ (1 | 2) => Some(Gradient {
+                min: reader.f64()?,
+                max: reader.f64()?,
+                ansi: kind == 2,
+            }),
+            _ => return None,
+        };
+        let sizes = match reader.u8()? {
+            0 => None,
+            kind @ (1 | 2) => Some(SizeScale {
+                min: reader.f64()?,
+                max: reader.f64()?,
+                ansi: kind == 2,
+            }),
+            _ => return None,
+        };
+        let field_count = reader.u32()? as usize;
+        let fields = (0..field_count)
+            .map(|_| {
+                let field = reader.u64()? as i64 as isize;
+                Some((field, style_from_str(reader.str()?).ok()?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let delimiter = reader.str()?.to_string();
+        let engine = match reader.u8()? {
+            0 => RegexEngine::Auto,
+            1 => RegexEngine::Fast,
+            2 => RegexEngine::Enhanced,
+            _ => return None,
+        };
+        entries.push(GrcatConfigEntry {
+            regex: CompiledRegex::lazy_with_engine(pattern, engine),
+            colors,
+            skip,
+            count,
+            replace,
+            command,
+            priority,
+            thresholds,
+            gradient,
+            sizes,
+            fields,
+            delimiter,
+            engine,
+        });
+    }
+
+    reader.0.is_empty().then_some(entries)
+}
+
+/// Cursor over the bytes of a cache file.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (head, rest) = self.0.split_at_checked(len)?;
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn str(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "regexp=^(ERROR|WARN) (\\d+ms)$\ncolours=bold red,bright_yellow on_blue\ncount=once\n-\nregexp=secret\nreplace=***\nskip=true\ncommand=beep\npriority=-2\nthresholds=70:yellow,90:bold red\ngradient=500:0:ansi\nsizes=1M:1.5T\nfields=1:cyan,-1:bold green\ndelimiter=\\t\nengine=enhanced\n";
+
+    #[test]
+    fn test_roundtrip_through_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let parsed = load_or_parse(dir.path(), SOURCE);
+        assert!(cache_path(dir.path(), SOURCE).exists());
+
+        let cached = load_or_parse(dir.path(), SOURCE);
+        assert_eq!(cached.len(), 2);
+        for (a, b) in parsed.iter().zip(&cached) {
+            assert_eq!(a.regex.as_str(), b.regex.as_str());
+            assert_eq!(a.colors, b.colors);
+            assert_eq!(a.skip, b.skip);
+            assert_eq!(a.count, b.count);
+            assert_eq!(a.replace, b.replace);
+            assert_eq!(a.command, b.command);
+            assert_eq!(a.priority, b.priority);
+            assert_eq!(a.thresholds, b.thresholds);
+            assert_eq!(a.gradient, b.gradient);
+            assert_eq!(a.sizes, b.sizes);
+            assert_eq!(a.fields, b.fields);
+            assert_eq!(a.delimiter, b.delimiter);
+            assert_eq!(a.engine, b.engine);
+        }
+        assert_eq!(cached[1].command, "beep");
+        assert_eq!(cached[1].priority, -2);
+        assert!(!cached[0].regex.is_compiled());
+        assert!(matches!(
+            cached[1].regex.resolve(),
+            Some(CompiledRegex::Enhanced(_))
+        ));
+        assert!(cached[0].regex.is_match("WARN 12ms"));
+    }
+
+    #[test]
+    fn test_stale_files_are_rejected() {
+        let entries: Vec<GrcatConfigEntry> = GrcatConfigReader::new(SOURCE.as_bytes().lines())
+            .lazy()
+            .collect();
+        let bytes = encode(SOURCE, &entries);
+        assert!(decode(&bytes, SOURCE).is_some());
+
+        // Same hash slot, different text length
+        assert!(decode(&bytes, "regexp=x\n").is_none());
+
+        let mut old = bytes.clone();
+        old[8..12].copy_from_slice(&(CONFIG_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(decode(&old, SOURCE).is_none());
+
+        // Truncated and padded files
+        assert!(decode(&bytes[..bytes.len() - 1], SOURCE).is_none());
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(decode(&padded, SOURCE).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! # dfa_cache.rs - Persisted DFAs for Fast rules (`dfa-cache` feature)
+//!
+//! Compiling the regexes of a config is a noticeable share of the startup time
+//! of short-lived commands. With this feature, every Fast rule also gets a dense
+//! DFA (from `regex-automata`), which is serialized into `<cache>/dfa/` once and
+//! deserialized on later runs instead of being rebuilt.
+//!
+//! The DFAs only answer "can this rule match this line?". A rule's own regex,
+//! which is compiled lazily (see `CompiledRegex::lazy`), still extracts the
+//! captures, but it is no longer compiled for rules the DFA rules out.
+//!
+//! ## File format
+//!
+//! Each pattern is stored in `<cache>/dfa/<fnv64 of pattern>.dfa`:
+//!
+//! ```text
+//! "RGRCDFA\0" | format version (u32 LE) | pattern length (u32 LE) | pattern | DFA bytes
+//! ```
+//!
+//! The pattern text is stored in full and compared on load, so a hash
+//! collision or an edited pattern simply rebuilds the file. An empty DFA part
+//! records that no DFA could be built, so the attempt is not repeated each run.
+//! Bumping `DFA_FORMAT_VERSION` invalidates every file, and a DFA that no
+//! longer deserializes (e.g. after a `regex-automata` upgrade) is rebuilt too.
+
+use std::path::{Path, PathBuf};
+
+use regex_automata::dfa::{Automaton, dense};
+use regex_automata::{Input, util::syntax};
+
+use crate::grc::{CompiledRegex, GrcatConfigEntry};
+use crate::prefilter::set_compatible;
+use crate::utils::{fnv1a, write_atomic};
+
+/// Version of the on-disk layout; files with any other version are rebuilt.
+pub const DFA_FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 8] = b"RGRCDFA\0";
+
+/// Upper bound on the size of a single DFA; larger ones are not built.
+const DFA_SIZE_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Per-rule DFA screen, indexed like the rule list.
+#[derive(Debug, Clone)]
+pub struct DfaScreen {
+    dfas: Vec<Option<dense::DFA<Vec<u32>>>>,
+}
+
+impl DfaScreen {
+    /// Load (or build and store) the DFAs of the Fast rules in `rules`.
+    ///
+    /// Returns `None` when no rule has a DFA.
+    pub fn new(rules: &[GrcatConfigEntry], cache_dir: &Path) -> Option<Self> {
+        let dir = cache_dir.join("dfa");
+        let dfas: Vec<_> = rules
+            .iter()
+            .map(|rule| match rule.regex {
+                // Lookaround rules cannot be expressed as a DFA
+                CompiledRegex::Enhanced(_) => None,
+                _ => load_or_build(&dir, rule.regex.as_str()),
+            })
+            .collect();
+
+        if dfas.iter().all(Option::is_none) {
+            return None;
+        }
+        Some(DfaScreen { dfas })
+    }
+
+    /// Whether the rule at `rule_index` may match `line`.
+    ///
+    /// Rules without a DFA, and searches the DFA gives up on, always pass.
+    pub fn may_match(&self, rule_index: usize, line: &str) -> bool {
+        match self.dfas.get(rule_index) {
+            Some(Some(dfa)) => dfa
+                .try_search_fwd(&Input::new(line).earliest(true))
+                .map_or(true, |found| found.is_some()),
+            _ => true,
+        }
+    }
+}
+
+/// Return the cached DFA for `pattern`, building and storing it on a miss.
+pub fn load_or_build(dir: &Path, pattern: &str) -> Option<dense::DFA<Vec<u32>>> {
+    // The DFA engine is Unicode-aware; only use it where that cannot cause misses
+    if !set_compatible(pattern) {
+        return None;
+    }
+
+    let path = dfa_path(dir, pattern);
+    if let Some(cached) = std::fs::read(&path)
+        .ok()
+        .and_then(|bytes| decode(&bytes, pattern))
+    {
+        return cached;
+    }
+
+    let dfa = build(pattern);
+    let encoded = encode(pattern, dfa.as_ref());
+    if std::fs::create_dir_all(dir).is_ok() {
+        let _ = write_atomic(&path, &encoded);
+    }
+    dfa
+}
+
+/// Location of the cache file for `pattern` inside `dir`.
+pub fn dfa_path(dir: &Path, pattern: &str) -> PathBuf {
+    dir.join(format!("{:016x}.dfa", fnv1a(pattern.as_bytes())))
+}
+
+fn build(pattern: &str) -> Option<dense::DFA<Vec<u32>>> {
+    dense::Builder::new()
+        .configure(
+            dense::DFA::config()
+                .dfa_size_limit(Some(DFA_SIZE_LIMIT))
+                .determinize_size_limit(Some(DFA_SIZE_LIMIT)),
+        )
+        .syntax(syntax::Config::new())
+        .build(pattern)
+        .ok()
+}
+
+fn encode(pattern: &str, dfa: Option<&dense::DFA<Vec<u32>>>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16 + pattern.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&DFA_FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&(pattern.len() as u32).to_le_bytes());
+    out.extend_from_slice(pattern.as_bytes());
+    if let Some(dfa) = dfa {
+        let (bytes, pad) = dfa.to_bytes_native_endian();
+        out.extend_from_slice(&bytes[pad..]);
+    }
+    out
+}
+
+/// Decode a cache file for `pattern`.
+///
+/// Returns `None` when the file is stale or corrupt, `Some(None)` when it
+/// records that no DFA exists, and `Some(Some(dfa))` otherwise.
+fn decode(bytes: &[u8], pattern: &str) -> Option<Option<dense::DFA<Vec<u32>>>> {
+    let rest = bytes.strip_prefix(MAGIC.as_slice())?;
+    let (version, rest) = rest.split_first_chunk::<4>()?;
+    if u32::from_le_bytes(*version) != DFA_FORMAT_VERSION {
+        return None;
+    }
+    let (len, rest) = rest.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len) as usize;
+    if rest.get(..len)? != pattern.as_bytes() {
+        return None;
+    }
+
+    let dfa_bytes = &rest[len..];
+    if dfa_bytes.is_empty() {
+        return Some(None);
+    }
+
+    // Deserialization needs the DFA bytes aligned to 4 bytes
+    let mut aligned = vec![0u8; dfa_bytes.len() + 3];
+    let start = aligned.as_ptr().align_offset(4);
+    let aligned = aligned.get_mut(start..start + dfa_bytes.len())?;
+    aligned.copy_from_slice(dfa_bytes);
+    let (dfa, _) = dense::DFA::from_bytes(aligned).ok()?;
+    Some(Some(dfa.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_through_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = r"bytes from ([0-9.]+)";
+
+        let built = load_or_build(dir.path(), pattern).expect("dfa built");
+        assert!(dfa_path(dir.path(), pattern).exists());
+
+        let loaded = load_or_build(dir.path(), pattern).expect("dfa loaded");
+        for line in ["64 bytes from host", "PING host"] {
+            let input = Input::new(line);
+            assert_eq!(
+                built.try_search_fwd(&input).unwrap(),
+                loaded.try_search_fwd(&input).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_stale_files_are_rejected() {
+        let dfa = build("abc");
+        let bytes = encode("abc", dfa.as_ref());
+        assert!(decode(&bytes, "abc").is_some_and(|d| d.is_some()));
+        // A different pattern with the same file name is a miss
+        assert!(decode(&bytes, "abd").is_none());
+
+        let mut old = bytes.clone();
+        old[8..12].copy_from_slice(&(DFA_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(decode(&old, "abc").is_none());
+
+        // Recorded build failures are remembered
+        assert_eq!(
+            decode(&encode("x", None), "x").map(|d| d.is_none()),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_incompatible_patterns_have_no_dfa() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_or_build(dir.path(), r"\bword\b").is_none());
+        assert!(load_or_build(dir.path(), r"foo(?=bar)").is_none());
+    }
+}
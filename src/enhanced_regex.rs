@@ -13,13 +13,15 @@
 //! - * Positive lookbehind: `(?<=pattern)` (fixed-length only)
 //! - * Negative lookahead: `(?!pattern)`
 //! - * Negative lookbehind: `(?<!pattern)` (fixed-length only)
-//! - x Backreferences: `\1`, `\2`, etc. (not supported)
+//! - * Backreferences: `\1`, `\2`, etc. (to a group that closes before them)
 //! - x Variable-length lookbehind (not supported)
 //!
 
 use regex::Regex;
 use regex_lite as regex;
 use std::fmt;
+use std::ops::Range;
+use std::time::Instant;
 
 /// Represents a lookaround assertion (lookahead or lookbehind)
 #[derive(Debug, Clone)]
@@ -267,6 +269,85 @@ impl Lookaround {
     }
 }
 
+/// Backreferences of a pattern (`(\w+)\s+\1`), matched in two passes.
+///
+/// The main regex is the pattern with each `\N` replaced by a copy of group N,
+/// which finds candidate matches. A candidate is confirmed by matching the
+/// pattern again at its start, with each `\N` replaced by the text group N
+/// captured there.
+#[derive(Debug, Clone)]
+struct Backrefs {
+    /// The main pattern, with its backreferences
+    pattern: String,
+    /// Byte range of each backreference in `pattern`, with its group number
+    refs: Vec<(Range<usize>, usize)>,
+}
+
+impl Backrefs {
+    /// The backreferences of `pattern` and the pattern finding their candidates,
+    /// or `None` if it has none or one the two passes cannot handle (to a group
+    /// that is not closed before it, or that holds a backreference itself).
+    fn parse(pattern: &str) -> Option<(Self, String)> {
+        let groups = capture_groups(pattern);
+        let refs = find_backrefs(pattern, groups.len());
+        if refs.is_empty() {
+            return None;
+        }
+        let mut candidates = String::new();
+        let mut last = 0;
+        for (range, group) in &refs {
+            let body = groups.get(group - 1)?.clone();
+            if body.end > range.start
+                || refs
+                    .iter()
+                    .any(|(inner, _)| body.start <= inner.start && inner.end <= body.end)
+            {
+                return None;
+            }
+            candidates.push_str(&pattern[last..range.start]);
+            candidates.push_str(&format!("(?:{})", uncaptured(&pattern[body])));
+            last = range.end;
+        }
+        candidates.push_str(&pattern[last..]);
+        let backrefs = Backrefs {
+            pattern: pattern.to_string(),
+            refs,
+        };
+        Some((backrefs, candidates))
+    }
+
+    /// Confirm the `candidate` match in `text`: the captures of the match at
+    /// its start whose backreferences repeat the text of their groups, if any.
+    fn confirm<'t>(
+        &self,
+        text: &'t str,
+        candidate: &regex::Captures<'t>,
+    ) -> Option<regex::Captures<'t>> {
+        let start = candidate.get(0)?.start();
+        let mut literal = String::new();
+        let mut last = 0;
+        for (range, group) in &self.refs {
+            literal.push_str(&self.pattern[last..range.start]);
+            literal.push_str(&format!(
+                "(?:{})",
+                regex::escape(candidate.get(*group)?.as_str())
+            ));
+            last = range.end;
+        }
+        literal.push_str(&self.pattern[last..]);
+
+        let caps = Regex::new(&literal).ok()?.captures_at(text, start)?;
+        let repeated = self.refs.iter().all(|(_, group)| {
+            caps.get(*group).map(|m| m.as_str()) == candidate.get(*group).map(|m| m.as_str())
+        });
+        (caps.get(0)?.start() == start && repeated).then_some(caps)
+    }
+}
+
+/// A search given up at its deadline (see `EnhancedRegex::captures_at_until`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
 /// An enhanced regex that supports basic lookaround assertions
 ///
 /// This struct wraps a standard `regex::Regex` and adds support for common
@@ -277,6 +358,9 @@ pub struct EnhancedRegex {
     main_regex: Regex,
     /// Optional lookaround assertions to verify
     lookarounds: Vec<Lookaround>,
+    /// Backreferences each match is confirmed against (`main_regex` then only
+    /// finds candidates)
+    backrefs: Option<Backrefs>,
     /// Original pattern for debugging
     original_pattern: String,
 }
@@ -297,16 +381,26 @@ impl EnhancedRegex {
     /// then compile the main pattern separately.
     pub fn new(pattern: &str) -> Result<Self, regex::Error> {
         let (main_pattern, lookarounds) = parse_pattern(pattern)?;
+        let (backrefs, main_pattern) = match Backrefs::parse(&main_pattern) {
+            Some((backrefs, candidates)) => (Some(backrefs), candidates),
+            None => (None, main_pattern),
+        };
 
         Ok(EnhancedRegex {
             main_regex: Regex::new(&main_pattern)?,
             lookarounds,
+            backrefs,
             original_pattern: pattern.to_string(),
         })
     }
 
     /// Find the first match in the text, starting from position `start`
     pub fn find_from_pos<'t>(&self, text: &'t str, start: usize) -> Option<regex::Match<'t>> {
+        // Backreferences: only confirmed captures are matches
+        if self.backrefs.is_some() {
+            return self.captures_from_pos(text, start)?.get(0);
+        }
+
         // Fast path: no lookarounds
         if self.lookarounds.is_empty() {
             return self.main_regex.find_at(text, start);
@@ -378,27 +472,74 @@ impl EnhancedRegex {
     }
 
     /// Get captures for the first match, starting from position `start`
-    pub fn captures_from_pos<'t>(
+    ///
+    /// As with `Regex::captures_at`, the text before `start` is still seen by
+    /// anchors, word boundaries and lookbehinds.
+    pub fn captures_at<'t>(&self, text: &'t str, start: usize) -> Option<regex::Captures<'t>> {
+        self.captures_at_until(text, start, None).ok().flatten()
+    }
+
+    /// Like `captures_at`, giving up once `deadline` has passed.
+    ///
+    /// Every candidate the lookarounds or backreferences reject costs another
+    /// search from the next position, so a single call can take quadratic time.
+    pub fn captures_at_until<'t>(
         &self,
         text: &'t str,
         start: usize,
-    ) -> Option<regex::Captures<'t>> {
+        deadline: Option<Instant>,
+    ) -> Result<Option<regex::Captures<'t>>, TimedOut> {
         let mut pos = start;
 
         while pos < text.len() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(TimedOut);
+            }
             if let Some(caps) = self.main_regex.captures_at(text, pos) {
-                let mat = caps.get(0).unwrap();
+                let match_start = caps.get(0).unwrap().start();
+                let confirmed = match &self.backrefs {
+                    Some(backrefs) => backrefs.confirm(text, &caps),
+                    None => Some(caps),
+                };
                 // Verify all lookaround conditions
-                if self.verify_lookarounds(text, mat.start(), mat.end()) {
-                    return Some(caps);
+                if let Some(caps) = confirmed {
+                    let mat = caps.get(0).unwrap();
+                    if self.verify_lookarounds(text, mat.start(), mat.end()) {
+                        return Ok(Some(caps));
+                    }
                 }
                 // Move past this match and continue searching
-                pos = mat.start() + 1;
+                pos = match_start + 1;
             } else {
                 break;
             }
         }
-        None
+        Ok(None)
+    }
+
+    /// Get captures for the first match, starting from position `start`
+    /// (same as `captures_at`)
+    pub fn captures_from_pos<'t>(
+        &self,
+        text: &'t str,
+        start: usize,
+    ) -> Option<regex::Captures<'t>> {
+        self.captures_at(text, start)
+    }
+
+    /// Get captures for all successive non-overlapping matches in the text
+    pub fn captures_iter<'t>(&self, text: &'t str) -> EnhancedCaptureMatches<'_, 't> {
+        EnhancedCaptureMatches {
+            regex: self,
+            text,
+            last_pos: 0,
+            last_end: None,
+        }
+    }
+
+    /// Names of the capture groups by index (`None` for group 0 and unnamed groups)
+    pub fn capture_names(&self) -> Vec<Option<&str>> {
+        self.main_regex.capture_names().collect()
     }
 
     /// Verify all lookaround conditions for a match
@@ -450,6 +591,38 @@ impl<'r, 't> Iterator for EnhancedMatches<'r, 't> {
     }
 }
 
+/// Iterator over the captures of all matches in a text
+pub struct EnhancedCaptureMatches<'r, 't> {
+    regex: &'r EnhancedRegex,
+    text: &'t str,
+    last_pos: usize,
+    /// End of the previous match
+    last_end: Option<usize>,
+}
+
+impl<'r, 't> Iterator for EnhancedCaptureMatches<'r, 't> {
+    type Item = regex::Captures<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let caps = self.regex.captures_at(self.text, self.last_pos)?;
+            let (start, end) = (caps.get(0)?.start(), caps.get(0)?.end());
+            // Step over an empty match so the next search makes progress
+            self.last_pos = if end > start {
+                end
+            } else {
+                end + self.text[end..].chars().next().map_or(1, char::len_utf8)
+            };
+            // As in `regex`, an empty match right after the previous one is skipped
+            if start == end && self.last_end == Some(end) {
+                continue;
+            }
+            self.last_end = Some(end);
+            return Some(caps);
+        }
+    }
+}
+
 /// Extract lookaround with proper bracket matching
 fn extract_lookaround_content(pattern: &str, start: usize) -> Option<(usize, String)> {
     let chars: Vec<char> = pattern.chars().collect();
@@ -799,10 +972,130 @@ fn fix_variable_length_lookbehind(pattern: &str) -> String {
     output
 }
 
+/// Byte ranges of the bodies of the capture groups of `pattern`, in group order
+/// (an unclosed group runs to the end).
+fn capture_groups(pattern: &str) -> Vec<Range<usize>> {
+    let bytes = pattern.as_bytes();
+    let mut groups = Vec::new();
+    let mut open = Vec::new();
+    let mut in_char_class = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'[' => in_char_class = true,
+            b']' => in_char_class = false,
+            b'(' if !in_char_class => match capture_body_start(pattern, i) {
+                Some(start) => {
+                    open.push(Some(groups.len()));
+                    groups.push(start..pattern.len());
+                    i = start - 1;
+                }
+                None => open.push(None),
+            },
+            b')' if !in_char_class => {
+                if let Some(Some(group)) = open.pop() {
+                    groups[group].end = i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    groups
+}
+
+/// Where the body of the group opening at `open` starts, or `None` if the
+/// group does not capture. Named groups (`(?P<name>`, `(?<name>`) capture.
+fn capture_body_start(pattern: &str, open: usize) -> Option<usize> {
+    let rest = &pattern[open + 1..];
+    let Some(flags) = rest.strip_prefix('?') else {
+        return Some(open + 1);
+    };
+    let name = flags
+        .strip_prefix("P<")
+        .or_else(|| flags.strip_prefix('<'))
+        .filter(|name| !name.starts_with(['=', '!']))?;
+    let end = name.find('>')?;
+    Some(pattern.len() - name.len() + end + 1)
+}
+
+/// `pattern` with its capture groups made non-capturing, for a copy of a
+/// group that must not shift the numbers of the groups after it.
+fn uncaptured(pattern: &str) -> String {
+    let mut output = String::new();
+    let mut last = 0;
+    for group in capture_groups(pattern) {
+        let open = pattern[..group.start].rfind('(').unwrap_or(0);
+        output.push_str(&pattern[last..open]);
+        output.push_str("(?:");
+        last = group.start;
+    }
+    output.push_str(&pattern[last..]);
+    output
+}
+
+/// Backreferences (`\1` to `\99`) in `pattern` outside character classes, with
+/// the group each refers to. Two digits are read as one number only if the
+/// pattern has that many groups, as in Python.
+fn find_backrefs(pattern: &str, group_count: usize) -> Vec<(Range<usize>, usize)> {
+    let bytes = pattern.as_bytes();
+    let mut refs = Vec::new();
+    let mut in_char_class = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if !in_char_class && matches!(bytes.get(i + 1), Some(b'1'..=b'9')) => {
+                let mut end = i + 2;
+                let mut group = (bytes[i + 1] - b'0') as usize;
+                if let Some(digit @ b'0'..=b'9') = bytes.get(end) {
+                    let number = group * 10 + (digit - b'0') as usize;
+                    if number <= group_count {
+                        group = number;
+                        end += 1;
+                    }
+                }
+                refs.push((i..end, group));
+                i = end;
+                continue;
+            }
+            b'\\' => i += 1,
+            b'[' => in_char_class = true,
+            b']' => in_char_class = false,
+            _ => {}
+        }
+        i += 1;
+    }
+    refs
+}
+
+/// Inline flags that open the pattern, such as `(?i)` in `(?i)x(?=y)`.
+///
+/// They apply to the whole pattern, so they are repeated for each lookaround
+/// compiled on its own.
+fn leading_flags(pattern: &str) -> &str {
+    let Some(rest) = pattern.strip_prefix("(?") else {
+        return "";
+    };
+    match rest.find(')') {
+        Some(end)
+            if end > 0
+                && rest[..end]
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic() || c == '-') =>
+        {
+            &pattern[..end + 3]
+        }
+        _ => "",
+    }
+}
+
 /// Parse a regex pattern and extract lookaround assertions
 ///
 /// Returns: (main_pattern, lookarounds)
 fn parse_pattern(pattern: &str) -> Result<(String, Vec<Lookaround>), regex::Error> {
+    let flags = leading_flags(pattern);
+
     // Preprocess the pattern first to handle invalid syntax
     let processed_pattern = preprocess_pattern(pattern);
 
@@ -849,6 +1142,7 @@ fn parse_pattern(pattern: &str) -> Result<(String, Vec<Lookaround>), regex::Erro
             if let Some((type_str, _type_len)) = lookaround_type
                 && let Some((end_pos, inner_pattern)) = extract_lookaround_content(pattern, i)
             {
+                let inner_pattern = format!("{}{}", flags, inner_pattern);
                 let lookaround = match type_str {
                     "=" => Lookaround::ahead(&inner_pattern)?,
                     "!" => {
@@ -979,6 +1273,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_captures_iter_and_names() {
+        let re = EnhancedRegex::new(r"(?P<key>\w+)=(?P<value>\d+)(?=;)").unwrap();
+        let pairs: Vec<_> = re
+            .captures_iter("a=1; b=x; c=22; d=3")
+            .map(|caps| (caps["key"].to_string(), caps["value"].to_string()))
+            .collect();
+        assert_eq!(pairs, [("a".into(), "1".into()), ("c".into(), "22".into())]);
+        assert_eq!(re.capture_names(), [None, Some("key"), Some("value")]);
+
+        // The text before the start position is still seen by \b
+        let re = EnhancedRegex::new(r"\b(\d+)(?=ms)").unwrap();
+        assert!(re.captures_at("x12ms 34ms", 2).unwrap()[1].eq("34"));
+        // Empty matches do not stall the iterator
+        let re = EnhancedRegex::new(r"\d*(?=;)").unwrap();
+        assert_eq!(re.captures_iter("1;;2;").count(), 3);
+    }
+
     #[test]
     fn test_find_iter() {
         let re = EnhancedRegex::new(r"\d+(?=\s)").unwrap();
@@ -1127,4 +1439,57 @@ mod tests {
         // May succeed or fail depending on how much we simplified
         let _ = re;
     }
+
+    #[test]
+    fn test_leading_flags_apply_to_lookarounds() {
+        let re = EnhancedRegex::new(r"(?i)x(?= at)").unwrap();
+        assert!(re.is_match("X AT"));
+        let re = EnhancedRegex::new(r"(?i)(?<=at )x").unwrap();
+        assert!(re.is_match("AT X"));
+        assert!(!re.is_match("AX X"));
+        // Flags scoped to a group do not leak into the lookaround
+        let re = EnhancedRegex::new(r"(?i:x)(?= at)").unwrap();
+        assert!(!re.is_match("X AT"));
+        assert_eq!(leading_flags("(?im)^x"), "(?im)");
+        assert_eq!(leading_flags("(?:x)"), "");
+        assert_eq!(leading_flags("(?P<a>x)"), "");
+    }
+    #[test]
+    fn test_backreferences() {
+        let re = EnhancedRegex::new(r"\b(\w+)\s+\1\b").unwrap();
+        let caps = re.captures_from_pos("this is is a test", 0).unwrap();
+        assert_eq!(caps.get(0).unwrap().as_str(), "is is");
+        assert_eq!(caps.get(1).unwrap().as_str(), "is");
+        assert!(!re.is_match("this is a test"));
+
+        // Groups after a backreference keep their numbers
+        let re = EnhancedRegex::new(r#"(['"])(?P<text>.*?)\1 (\d+)"#).unwrap();
+        let caps = re.captures_from_pos(r#"x = "a'b" 42"#, 0).unwrap();
+        assert_eq!(caps.get(2).unwrap().as_str(), "a'b");
+        assert_eq!(caps.get(3).unwrap().as_str(), "42");
+        assert_eq!(re.find_iter("'a' 1, \"b' 2, \"c\" 3").count(), 2);
+    }
+
+    #[test]
+    fn test_backreference_with_lookaround() {
+        let re = EnhancedRegex::new(r"(?<=: )(\d+)-\1(?=$)").unwrap();
+        assert!(re.is_match("ports: 80-80"));
+        assert!(!re.is_match("ports: 80-81"));
+        assert!(!re.is_match("80-80"));
+    }
+
+    #[test]
+    fn test_unsupported_backreferences_fail() {
+        // To a group that is still open, or that does not exist
+        assert!(EnhancedRegex::new(r"(a\1)").is_err());
+        assert!(EnhancedRegex::new(r"(a)\2").is_err());
+    }
+
+    #[test]
+    fn test_find_backrefs() {
+        assert_eq!(find_backrefs(r"(a)\1[\1]\\1", 1), vec![(3..5, 1)]);
+        assert_eq!(find_backrefs(r"(a)\10", 1), vec![(3..5, 1)]);
+        assert_eq!(capture_groups(r"(a(?:b)(?P<c>d))"), vec![1..15, 13..14]);
+        assert_eq!(uncaptured(r"(a)(?<n>b)(?:c)"), "(?:a)(?:b)(?:c)");
+    }
 }
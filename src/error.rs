@@ -0,0 +1,127 @@
+//! # error.rs - Error type for rgrc
+//!
+//! `RgrcError` covers the failures a caller of the library (or the `rgrc`
+//! binary) may want to tell apart, and the exit status the binary uses for each.
+
+use crate::grc::RegexError;
+use std::fmt;
+use std::io;
+
+/// An error from parsing arguments, loading configs or running a command.
+#[derive(Debug)]
+pub enum RgrcError {
+    /// Invalid command-line arguments
+    Args(String),
+    /// A config or mapping file that was asked for by name does not exist
+    ConfigNotFound(String),
+    /// A pattern that does not compile
+    Regex(RegexError),
+    /// The command to run is not on `PATH`
+    CommandNotFound(String),
+    /// The command exists but could not be started (e.g. not executable)
+    Spawn { command: String, source: io::Error },
+    /// Reading input, writing output or waiting for the command failed
+    Io(io::Error),
+    /// Downloading or checking configs or a release failed (`rgrc fetch-configs`,
+    /// `rgrc config`, `rgrc self-update`)
+    Fetch(String),
+}
+
+impl RgrcError {
+    /// The exit status `rgrc` uses for this error.
+    ///
+    /// Follows the shell conventions for commands that cannot be run: 127 when
+    /// the command is not found and 126 when it cannot be executed. Usage
+    /// errors exit with 2 and everything else with 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RgrcError::Args(_) => 2,
+            RgrcError::CommandNotFound(_) => 127,
+            RgrcError::Spawn { .. } => 126,
+            RgrcError::ConfigNotFound(_)
+            | RgrcError::Regex(_)
+            | RgrcError::Io(_)
+            | RgrcError::Fetch(_) => 1,
+        }
+    }
+
+    /// Classify a failure to spawn `command`: `CommandNotFound` when the
+    /// executable does not exist, `Spawn` otherwise.
+    pub fn spawn(command: &str, source: io::Error) -> Self {
+        if source.kind() == io::ErrorKind::NotFound {
+            RgrcError::CommandNotFound(command.to_string())
+        } else {
+            RgrcError::Spawn {
+                command: command.to_string(),
+                source,
+            }
+        }
+    }
+}
+
+impl fmt::Display for RgrcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RgrcError::Args(msg) => write!(f, "{}", msg),
+            RgrcError::ConfigNotFound(name) => write!(f, "config not found: '{}'", name),
+            RgrcError::Regex(err) => write!(f, "{}", err),
+            RgrcError::CommandNotFound(command) => write!(f, "command not found: '{}'", command),
+            RgrcError::Spawn { command, source } => {
+                write!(f, "cannot run '{}': {}", command, source)
+            }
+            RgrcError::Io(err) => write!(f, "{}", err),
+            RgrcError::Fetch(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RgrcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RgrcError::Regex(err) => Some(err),
+            RgrcError::Spawn { source, .. } => Some(source),
+            RgrcError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RgrcError {
+    fn from(err: io::Error) -> Self {
+        RgrcError::Io(err)
+    }
+}
+
+impl From<RegexError> for RgrcError {
+    fn from(err: RegexError) -> Self {
+        RgrcError::Regex(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_errors_follow_shell_conventions() {
+        let missing = RgrcError::spawn("nope", io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(missing, RgrcError::CommandNotFound(_)));
+        assert_eq!(missing.exit_code(), 127);
+        assert_eq!(missing.to_string(), "command not found: 'nope'");
+
+        let denied = RgrcError::spawn("./x", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(denied.exit_code(), 126);
+        assert!(denied.to_string().starts_with("cannot run './x': "));
+        assert!(std::error::Error::source(&denied).is_some());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(RgrcError::Args("bad".into()).exit_code(), 2);
+        assert_eq!(RgrcError::ConfigNotFound("conf.x".into()).exit_code(), 1);
+        let regex = crate::grc::CompiledRegex::new("(").unwrap_err();
+        assert_eq!(RgrcError::from(regex).exit_code(), 1);
+        assert_eq!(RgrcError::from(io::Error::other("x")).exit_code(), 1);
+        assert_eq!(RgrcError::Fetch("x".into()).exit_code(), 1);
+    }
+}
@@ -0,0 +1,739 @@
+//! # fetch.rs - Installing configs from a config index (`rgrc fetch-configs`, `rgrc config`)
+//!
+//! `rgrc fetch-configs [URL]` reads an index of community configs, downloads
+//! each config it lists, checks it against the index's SHA-256 checksum and
+//! installs it into the user config directory, then maps its commands in
+//! `rgrc.local` there, so support for a new tool needs no new release. The
+//! index is a text file with one config per line, optionally versioned:
+//!
+//! ```text
+//! # sha256 (of the config)                                         config          command regex
+//! 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 conf.tofu@1.2.0 ^tofu\b
+//! ```
+//!
+//! Configs are fetched from the index's own directory (`.../index.txt` lists
+//! `.../conf.tofu@1.2.0`). Downloads go through `curl`, or `wget` where curl is
+//! missing, which both also read `file://` URLs.
+//!
+//! Every installed config is recorded in `rgrc.lock` with its version, checksum
+//! and index (see `Installed`), which `rgrc config` manages: `list`, `install
+//! NAME[@VERSION]` (a version pins the config), `remove NAME` and `update`.
+//! `rgrc config install` without names installs exactly what the lockfile
+//! records, so a team can share one setup.
+
+use std::cmp::Ordering;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::RgrcError;
+use crate::grc::CompiledRegex;
+use crate::utils::write_atomic;
+
+/// Variable holding the URL of the config index, when none is given.
+pub const INDEX_ENV: &str = "RGRC_CONFIG_INDEX";
+
+/// The mapping file `fetch-configs` keeps in the user config directory,
+/// searched right after the user's own `rgrc.conf`.
+pub const LOCAL_MAPPING: &str = "rgrc.local";
+
+/// The lockfile recording the configs installed in the user config directory.
+pub const LOCK_FILE: &str = "rgrc.lock";
+
+/// A config listed in an index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// SHA-256 of the config, in lowercase hex
+    pub sha256: String,
+    /// File name of the config (`conf.NAME`)
+    pub name: String,
+    /// Version of the config; empty when the index gives none
+    pub version: String,
+    /// Regex matching the commands the config is for
+    pub regexp: String,
+}
+
+impl IndexEntry {
+    /// The name of the config's file next to the index, `conf.NAME@VERSION`
+    /// or `conf.NAME`.
+    pub fn file_name(&self) -> String {
+        if self.version.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}@{}", self.name, self.version)
+        }
+    }
+}
+
+/// A `rgrc config` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// List the installed configs
+    List,
+    /// Install configs (`NAME[@VERSION]`), or everything in the lockfile
+    Install(Vec<String>),
+    /// Remove installed configs
+    Remove(Vec<String>),
+    /// Update the unpinned configs, all of them or those named
+    Update(Vec<String>),
+}
+
+impl ConfigCommand {
+    /// Parse the words after `rgrc config`.
+    pub fn parse(words: &[String]) -> Result<Self, String> {
+        let Some((command, names)) = words.split_first() else {
+            return Err("config needs a command: list, install, remove or update".to_string());
+        };
+        let names = names.to_vec();
+        match command.as_str() {
+            "list" if names.is_empty() => Ok(ConfigCommand::List),
+            "list" => Err(format!("Unexpected argument for config list: {}", names[0])),
+            "install" => Ok(ConfigCommand::Install(names)),
+            "remove" if names.is_empty() => Err("config remove needs a config name".to_string()),
+            "remove" => Ok(ConfigCommand::Remove(names)),
+            "update" => Ok(ConfigCommand::Update(names)),
+            _ => Err(format!(
+                "Unknown config command: {} (expected list, install, remove or update)",
+                command
+            )),
+        }
+    }
+}
+
+/// A config installed from an index, as recorded in `rgrc.lock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Installed {
+    pub entry: IndexEntry,
+    /// Installed at a version asked for, so `update` leaves it alone
+    pub pinned: bool,
+    /// URL of the index it came from
+    pub index: String,
+}
+
+/// Parse the index `text`. Fails on a malformed line, naming it.
+pub fn parse_index(text: &str) -> Result<Vec<IndexEntry>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let problem = |message: &str| format!("index line {}: {}", number + 1, message);
+        // The regex is the rest of the line, spaces included
+        let (sha256, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        let (file, regexp) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let regexp = regexp.trim();
+        if file.is_empty() || regexp.is_empty() {
+            return Err(problem("expected a checksum, a config and a regex"));
+        }
+        if !is_sha256(sha256) {
+            return Err(problem("the checksum is not a SHA-256 in hex"));
+        }
+        let (name, version) = file.split_once('@').unwrap_or((file, ""));
+        if !is_config_name(name) || !is_version(version) {
+            return Err(problem(&format!(
+                "'{}' is not a conf.NAME[@VERSION] file name",
+                file
+            )));
+        }
+        if let Err(e) = CompiledRegex::new(regexp) {
+            return Err(problem(&e.to_string()));
+        }
+        entries.push(IndexEntry {
+            sha256: sha256.to_ascii_lowercase(),
+            name: name.to_string(),
+            version: version.to_string(),
+            regexp: regexp.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+fn is_sha256(text: &str) -> bool {
+    text.len() == 64 && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Whether `name` is a plain `conf.NAME` file name, which cannot reach outside
+/// the directory it is installed into.
+fn is_config_name(name: &str) -> bool {
+    name.strip_prefix("conf.").is_some_and(|rest| {
+        !rest.is_empty()
+            && rest
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'))
+            && !rest.contains("..")
+    })
+}
+
+/// Whether `version` is empty or made of the characters of a version number.
+fn is_version(version: &str) -> bool {
+    version
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+}
+
+/// Compare two versions part by part, numerically where both parts are
+/// numbers (`1.10` is newer than `1.9`).
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split(['.', '-', '+']);
+    let mut b_parts = b.split(['.', '-', '+']);
+    loop {
+        let order = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+/// The newest version of each config of `entries`, in the order the configs
+/// first appear.
+fn newest_of_each(entries: &[IndexEntry]) -> Vec<&IndexEntry> {
+    let mut newest: Vec<&IndexEntry> = Vec::new();
+    for entry in entries {
+        match newest.iter_mut().find(|seen| seen.name == entry.name) {
+            Some(seen) => {
+                if compare_versions(&entry.version, &seen.version) != Ordering::Less {
+                    *seen = entry;
+                }
+            }
+            None => newest.push(entry),
+        }
+    }
+    newest
+}
+
+/// `conf.NAME` for a config named with or without its `conf.` prefix.
+fn config_name(name: &str) -> String {
+    if name.starts_with("conf.") {
+        name.to_string()
+    } else {
+        format!("conf.{}", name)
+    }
+}
+
+/// The URL of the file `name` next to the index at `index_url`.
+pub fn sibling_url(index_url: &str, name: &str) -> String {
+    match index_url.rfind('/') {
+        Some(slash) => format!("{}{}", &index_url[..=slash], name),
+        None => name.to_string(),
+    }
+}
+
+/// Download `url` with curl, or wget when curl is not installed.
+pub fn download(url: &str) -> Result<Vec<u8>, RgrcError> {
+    let tools: [(&str, &[&str]); 2] = [("curl", &["-fsSL", "--"]), ("wget", &["-qO-", "--"])];
+    for (tool, args) in tools {
+        let output = Command::new(tool)
+            .args(args)
+            .arg(url)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => return Ok(output.stdout),
+            Ok(_) => return Err(RgrcError::Fetch(format!("failed to download {}", url))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(RgrcError::spawn(tool, e)),
+        }
+    }
+    Err(RgrcError::Fetch(
+        "downloading needs curl or wget on PATH".to_string(),
+    ))
+}
+
+/// Download and parse the index at `url`.
+pub fn fetch_index(url: &str) -> Result<Vec<IndexEntry>, RgrcError> {
+    let index = String::from_utf8(download(url)?)
+        .map_err(|_| RgrcError::Fetch(format!("{}: the index is not UTF-8", url)))?;
+    parse_index(&index).map_err(|e| RgrcError::Fetch(format!("{}: {}", url, e)))
+}
+
+/// The configs installed in a user config directory: the `conf.*` files, their
+/// mappings in `rgrc.local` and their record in `rgrc.lock`.
+pub struct ConfigStore {
+    dir: PathBuf,
+}
+
+impl ConfigStore {
+    /// The configs installed in `dir`.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        ConfigStore { dir: dir.into() }
+    }
+
+    /// The configs recorded in the lockfile, in the order installed.
+    pub fn installed(&self) -> io::Result<Vec<Installed>> {
+        let text = match std::fs::read_to_string(self.dir.join(LOCK_FILE)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(parse_lock(&text))
+    }
+
+    /// Download `configs` from their indexes, check them and install them,
+    /// recording them in the lockfile in place of earlier versions.
+    ///
+    /// Every config is downloaded and checked before any is written, so a bad
+    /// checksum leaves the installed configs as they were.
+    pub fn install(&self, configs: &[Installed]) -> Result<(), RgrcError> {
+        let mut downloaded = Vec::new();
+        for config in configs {
+            let url = sibling_url(&config.index, &config.entry.file_name());
+            let data = download(&url)?;
+            let sha256 = sha256_hex(&data);
+            if sha256 != config.entry.sha256 {
+                return Err(RgrcError::Fetch(format!(
+                    "{}: checksum mismatch (expected {}, got {})",
+                    url, config.entry.sha256, sha256
+                )));
+            }
+            downloaded.push(data);
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+        for (config, data) in configs.iter().zip(downloaded) {
+            write_atomic(&self.dir.join(&config.entry.name), &data)?;
+        }
+        let mut installed = self.installed()?;
+        for config in configs {
+            match installed
+                .iter_mut()
+                .find(|old| old.entry.name == config.entry.name)
+            {
+                Some(old) => *old = config.clone(),
+                None => installed.push(config.clone()),
+            }
+        }
+        self.write(&installed)
+    }
+
+    /// Remove the installed config `name`, its mapping and its record.
+    pub fn remove(&self, name: &str) -> Result<(), RgrcError> {
+        let name = config_name(name);
+        let mut installed = self.installed()?;
+        let count = installed.len();
+        installed.retain(|config| config.entry.name != name);
+        if installed.len() == count {
+            return Err(RgrcError::ConfigNotFound(name));
+        }
+        match std::fs::remove_file(self.dir.join(&name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        self.write(&installed)
+    }
+
+    /// Write the lockfile and `rgrc.local` for `installed`.
+    fn write(&self, installed: &[Installed]) -> Result<(), RgrcError> {
+        let mut lock = String::from(
+            "# Configs installed by rgrc; `rgrc config install` reinstalls them as they are\n\
+             # config  version  pinned  sha256  index  commands\n",
+        );
+        let mut mapping = String::from(
+            "# Written by `rgrc config` and `rgrc fetch-configs`; edits are overwritten\n",
+        );
+        for config in installed {
+            let entry = &config.entry;
+            lock.push_str(&format!(
+                "{} {} {} {} {} {}\n",
+                entry.name,
+                if entry.version.is_empty() {
+                    "-"
+                } else {
+                    &entry.version
+                },
+                if config.pinned { "pinned" } else { "-" },
+                entry.sha256,
+                config.index,
+                entry.regexp
+            ));
+            mapping.push_str(&format!("\n{}\n{}\n", entry.regexp, entry.name));
+        }
+        write_atomic(&self.dir.join(LOCK_FILE), lock.as_bytes())?;
+        write_atomic(&self.dir.join(LOCAL_MAPPING), mapping.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The records of the lockfile `text`; malformed lines are reported on stderr
+/// and left out.
+fn parse_lock(text: &str) -> Vec<Installed> {
+    let mut installed = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut rest = line;
+        let mut word = || {
+            let (word, tail) = rest.split_once(char::is_whitespace)?;
+            rest = tail.trim_start();
+            Some(word)
+        };
+        let fields = (word(), word(), word(), word(), word());
+        let regexp = rest.trim();
+        match fields {
+            (Some(name), Some(version), Some(pinned), Some(sha256), Some(index))
+                if is_config_name(name) && is_sha256(sha256) && !regexp.is_empty() =>
+            {
+                installed.push(Installed {
+                    entry: IndexEntry {
+                        sha256: sha256.to_string(),
+                        name: name.to_string(),
+                        version: if version == "-" { "" } else { version }.to_string(),
+                        regexp: regexp.to_string(),
+                    },
+                    pinned: pinned == "pinned",
+                    index: index.to_string(),
+                })
+            }
+            _ => eprintln!(
+                "rgrc: {}:{}: malformed line left out",
+                LOCK_FILE,
+                number + 1
+            ),
+        }
+    }
+    installed
+}
+
+/// Install the newest version of every config of the index at `index_url`
+/// into `dir` (`rgrc fetch-configs`), returning them.
+pub fn fetch_configs(index_url: &str, dir: &Path) -> Result<Vec<Installed>, RgrcError> {
+    let entries = fetch_index(index_url)?;
+    let configs: Vec<Installed> = newest_of_each(&entries)
+        .into_iter()
+        .map(|entry| Installed {
+            entry: entry.clone(),
+            pinned: false,
+            index: index_url.to_string(),
+        })
+        .collect();
+    ConfigStore::new(dir).install(&configs)?;
+    Ok(configs)
+}
+
+/// Install the configs `names` (`NAME` or `NAME@VERSION`, which pins it) from
+/// the index at `index_url` into `dir`, returning them (`rgrc config install`).
+/// Without names, reinstall what the lockfile records, checksums included.
+pub fn install_configs(
+    dir: &Path,
+    index_url: Option<&str>,
+    names: &[String],
+) -> Result<Vec<Installed>, RgrcError> {
+    let store = ConfigStore::new(dir);
+    if names.is_empty() {
+        let installed = store.installed()?;
+        store.install(&installed)?;
+        return Ok(installed);
+    }
+
+    let Some(index_url) = index_url else {
+        return Err(RgrcError::Args(format!(
+            "config install needs {} set to the URL of a config index",
+            INDEX_ENV
+        )));
+    };
+    let entries = fetch_index(index_url)?;
+    let mut configs = Vec::new();
+    for spec in names {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (config_name(name), Some(version)),
+            None => (config_name(spec), None),
+        };
+        let candidates: Vec<IndexEntry> = entries
+            .iter()
+            .filter(|entry| entry.name == name)
+            .filter(|entry| version.is_none_or(|version| entry.version == version))
+            .cloned()
+            .collect();
+        let Some(entry) = newest_of_each(&candidates).pop() else {
+            return Err(RgrcError::ConfigNotFound(spec.clone()));
+        };
+        configs.push(Installed {
+            entry: entry.clone(),
+            pinned: version.is_some(),
+            index: index_url.to_string(),
+        });
+    }
+    store.install(&configs)?;
+    Ok(configs)
+}
+
+/// Update the unpinned installed configs, all of them or those in `names`, to
+/// the newest version their index lists, returning the updated ones with the
+/// version each had (`rgrc config update`).
+pub fn update_configs(dir: &Path, names: &[String]) -> Result<Vec<(Installed, String)>, RgrcError> {
+    let store = ConfigStore::new(dir);
+    let installed = store.installed()?;
+    let names: Vec<String> = names.iter().map(|name| config_name(name)).collect();
+    if let Some(name) = names
+        .iter()
+        .find(|name| !installed.iter().any(|config| config.entry.name == **name))
+    {
+        return Err(RgrcError::ConfigNotFound(name.clone()));
+    }
+
+    let mut indexes: Vec<(String, Vec<IndexEntry>)> = Vec::new();
+    let mut updates = Vec::new();
+    for config in &installed {
+        if config.pinned || !(names.is_empty() || names.contains(&config.entry.name)) {
+            continue;
+        }
+        if !indexes.iter().any(|(url, _)| *url == config.index) {
+            indexes.push((config.index.clone(), fetch_index(&config.index)?));
+        }
+        let (_, entries) = indexes
+            .iter()
+            .find(|(url, _)| *url == config.index)
+            .expect("index fetched above");
+        let candidates: Vec<IndexEntry> = entries
+            .iter()
+            .filter(|entry| entry.name == config.entry.name)
+            .cloned()
+            .collect();
+        if let Some(newest) = newest_of_each(&candidates).pop()
+            && newest.sha256 != config.entry.sha256
+        {
+            let update = Installed {
+                entry: newest.clone(),
+                ..config.clone()
+            };
+            updates.push((update, config.entry.version.clone()));
+        }
+    }
+    let configs: Vec<Installed> = updates.iter().map(|(config, _)| config.clone()).collect();
+    store.install(&configs)?;
+    Ok(updates)
+}
+
+/// The SHA-256 of `data`, in lowercase hex.
+pub fn sha256_hex(data: &[u8]) -> String {
+    sha256(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The SHA-256 of `data` (FIPS 180-4).
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // The message, a 1 bit, zeros up to 8 bytes short of a block, and its length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks once padded
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let sum = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let entries = parse_index(&format!(
+            "# configs\n\n{} conf.tofu ^tofu\\b\n{}  conf.my-tool  ^(my-tool|mt) \n",
+            sum,
+            sum.to_uppercase()
+        ))
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "conf.tofu");
+        assert_eq!(entries[0].regexp, "^tofu\\b");
+        assert_eq!(entries[1].sha256, sum);
+        assert_eq!(entries[1].regexp, "^(my-tool|mt)");
+
+        for (line, problem) in [
+            ("abc conf.x ^x", "the checksum is not a SHA-256 in hex"),
+            (&format!("{} ../rgrc.conf ^x", sum), "'../rgrc.conf' is not"),
+            (&format!("{} rgrc.conf ^x", sum), "'rgrc.conf' is not"),
+            (&format!("{} conf.x", sum), "expected a checksum"),
+            (&format!("{} conf.x (", sum), "index line 1: "),
+        ] {
+            let error = parse_index(line).unwrap_err();
+            assert!(error.contains(problem), "{}: {}", line, error);
+        }
+    }
+
+    #[test]
+    fn test_sibling_url() {
+        assert_eq!(
+            sibling_url("https://example.com/rgrc/index.txt", "conf.x"),
+            "https://example.com/rgrc/conf.x"
+        );
+        assert_eq!(sibling_url("index.txt", "conf.x"), "conf.x");
+    }
+
+    #[test]
+    fn test_index_versions() {
+        let sum = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let entries = parse_index(&format!(
+            "{sum} conf.tofu@1.9.0 ^tofu\\b\n{sum} conf.tofu@1.10.0 ^tofu\\b\n{sum} conf.x ^x\n"
+        ))
+        .unwrap();
+        assert_eq!(entries[0].name, "conf.tofu");
+        assert_eq!(entries[0].version, "1.9.0");
+        assert_eq!(entries[0].file_name(), "conf.tofu@1.9.0");
+        assert_eq!(entries[2].file_name(), "conf.x");
+        let newest: Vec<_> = newest_of_each(&entries)
+            .iter()
+            .map(|entry| entry.file_name())
+            .collect();
+        assert_eq!(newest, ["conf.tofu@1.10.0", "conf.x"]);
+        assert!(parse_index(&format!("{sum} conf.x@1/2 ^x")).is_err());
+
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0-rc1", "2.0-rc1"), Ordering::Equal);
+        assert_eq!(compare_versions("", "0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_config_command() {
+        let words =
+            |text: &str| -> Vec<String> { text.split_whitespace().map(String::from).collect() };
+        assert_eq!(
+            ConfigCommand::parse(&words("list")),
+            Ok(ConfigCommand::List)
+        );
+        assert_eq!(
+            ConfigCommand::parse(&words("install tofu@1.2.0")),
+            Ok(ConfigCommand::Install(words("tofu@1.2.0")))
+        );
+        assert_eq!(
+            ConfigCommand::parse(&words("update")),
+            Ok(ConfigCommand::Update(vec![]))
+        );
+        assert!(ConfigCommand::parse(&words("remove")).is_err());
+        assert!(ConfigCommand::parse(&words("list tofu")).is_err());
+        assert!(ConfigCommand::parse(&words("upgrade")).is_err());
+        assert!(ConfigCommand::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn test_lockfile_and_mapping() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = ConfigStore::new(dir.path());
+        assert!(store.installed().unwrap().is_empty());
+        let config = |name: &str, version: &str, pinned: bool| Installed {
+            entry: IndexEntry {
+                sha256: sha256_hex(name.as_bytes()),
+                name: name.to_string(),
+                version: version.to_string(),
+                regexp: format!("^({}| x)", name),
+            },
+            pinned,
+            index: "https://example.com/rgrc/index.txt".to_string(),
+        };
+        let installed = [
+            config("conf.tofu", "1.2.0", true),
+            config("conf.x", "", false),
+        ];
+        store.write(&installed).unwrap();
+        assert_eq!(store.installed().unwrap(), installed);
+        let mapping = std::fs::read_to_string(dir.path().join(LOCAL_MAPPING)).unwrap();
+        assert!(mapping.contains("\n^(conf.tofu| x)\nconf.tofu\n"));
+
+        std::fs::write(dir.path().join("conf.x"), "regexp=x\n").unwrap();
+        store.remove("x").unwrap();
+        assert!(!dir.path().join("conf.x").exists());
+        assert_eq!(store.installed().unwrap(), installed[..1]);
+        assert!(
+            !std::fs::read_to_string(dir.path().join(LOCAL_MAPPING))
+                .unwrap()
+                .contains("conf.x")
+        );
+        assert!(matches!(
+            store.remove("conf.x"),
+            Err(RgrcError::ConfigNotFound(_))
+        ));
+    }
+}
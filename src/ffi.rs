@@ -0,0 +1,154 @@
+//! # ffi.rs - C interface to the colorization core
+//!
+//! A small C ABI so terminal emulators and tools not written in Rust can link
+//! the engine instead of spawning `rgrc` for every command. The declarations
+//! are in `include/rgrc.h`; build the library with
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib --crate-type staticlib
+//! ```
+//!
+//! (`make ffi`). A handle from `rgrc_load_rules()` colorizes the lines of one
+//! stream in order, keeping `count=block` regions open between calls, and is
+//! not safe to use from two threads at once.
+
+use std::ffi::{CStr, CString, c_char};
+use std::mem::ManuallyDrop;
+
+use crate::colorizer::{ColorizeOptions, LineColorizer};
+use crate::grc::GrcatConfigEntry;
+
+/// The rules for one command and the state of the stream they colorize
+/// (`rgrc_rules` in C).
+pub struct RgrcRules {
+    /// Borrows `rules`, so it is dropped first
+    lines: ManuallyDrop<LineColorizer<'static>>,
+    /// Owned; allocated by `Box::leak` in `rgrc_load_rules`
+    rules: *mut [GrcatConfigEntry],
+    block: Option<usize>,
+    disabled_rules: Vec<usize>,
+}
+
+impl RgrcRules {
+    fn new(rules: Vec<GrcatConfigEntry>) -> Self {
+        let rules: &'static mut [GrcatConfigEntry] = Box::leak(rules.into_boxed_slice());
+        let rules: *mut [GrcatConfigEntry] = rules;
+        RgrcRules {
+            // SAFETY: `rules` stays allocated until `drop`, after `lines` is gone
+            lines: ManuallyDrop::new(LineColorizer::new(unsafe { &*rules })),
+            rules,
+            block: None,
+            disabled_rules: Vec::new(),
+        }
+    }
+
+    /// The colorized form of `line`, without a line ending; `None` when a
+    /// `skip=` rule hides it.
+    fn colorize(&mut self, line: &str) -> Option<String> {
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            block: self.block,
+            disabled_rules: std::mem::take(&mut self.disabled_rules),
+            ..Default::default()
+        };
+        let result = self
+            .lines
+            .colorize_line(line.to_string(), &mut output, &mut options);
+        self.block = options.block;
+        self.disabled_rules = options.disabled_rules;
+        result.ok()?;
+
+        let mut output = String::from_utf8(output).ok()?;
+        // Lines are only written with their line ending
+        output.pop()?;
+        Some(output)
+    }
+}
+
+impl Drop for RgrcRules {
+    fn drop(&mut self) {
+        // SAFETY: `lines` is not used again, and was the only borrower of `rules`,
+        // which came from `Box::leak`
+        unsafe {
+            ManuallyDrop::drop(&mut self.lines);
+            drop(Box::from_raw(self.rules));
+        }
+    }
+}
+
+/// Load the rules `rgrc COMMAND` would use for `command` (a command line such
+/// as `"ping -c 1 host"`).
+///
+/// Returns a handle to pass to `rgrc_colorize_line()` and release with
+/// `rgrc_free_rules()`, or NULL when `command` is NULL or not UTF-8. A command
+/// without rules still gets a handle; its lines come back unchanged.
+///
+/// # Safety
+///
+/// `command` must be NULL or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rgrc_load_rules(command: *const c_char) -> *mut RgrcRules {
+    if command.is_null() {
+        return std::ptr::null_mut();
+    }
+    // SAFETY: checked for NULL; the caller guarantees the terminator
+    let Ok(command) = unsafe { CStr::from_ptr(command) }.to_str() else {
+        return std::ptr::null_mut();
+    };
+    Box::into_raw(Box::new(RgrcRules::new(crate::load_rules_for_command(
+        command,
+    ))))
+}
+
+/// Colorize the next `line` (without its line ending) of the stream `rules`
+/// colorizes, with ANSI escape sequences.
+///
+/// Returns a new string to release with `rgrc_free()`, or NULL when an
+/// argument is NULL, `line` is not UTF-8 or a `skip=` rule hides the line.
+///
+/// # Safety
+///
+/// `rules` must be NULL or a live handle from `rgrc_load_rules()`, not in use
+/// by another thread, and `line` NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rgrc_colorize_line(
+    rules: *mut RgrcRules,
+    line: *const c_char,
+) -> *mut c_char {
+    if rules.is_null() || line.is_null() {
+        return std::ptr::null_mut();
+    }
+    // SAFETY: checked for NULL; the caller guarantees the handle and terminator
+    let (rules, line) = unsafe { (&mut *rules, CStr::from_ptr(line)) };
+    line.to_str()
+        .ok()
+        .and_then(|line| rules.colorize(line))
+        .and_then(|colored| CString::new(colored).ok())
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Release a string returned by `rgrc_colorize_line()`. NULL is ignored.
+///
+/// # Safety
+///
+/// `text` must be NULL or a string from `rgrc_colorize_line()` not yet released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rgrc_free(text: *mut c_char) {
+    if !text.is_null() {
+        // SAFETY: the caller guarantees it came from `CString::into_raw`
+        drop(unsafe { CString::from_raw(text) });
+    }
+}
+
+/// Release a handle returned by `rgrc_load_rules()`. NULL is ignored.
+///
+/// # Safety
+///
+/// `rules` must be NULL or a handle from `rgrc_load_rules()` not yet released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rgrc_free_rules(rules: *mut RgrcRules) {
+    if !rules.is_null() {
+        // SAFETY: the caller guarantees it came from `Box::into_raw`
+        drop(unsafe { Box::from_raw(rules) });
+    }
+}
@@ -17,10 +17,13 @@
 //! The module uses a hybrid regex engine approach for optimal performance:
 //!
 
+use std::collections::HashMap;
 use std::io::{BufRead, Lines};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 #[cfg(not(feature = "fancy-regex"))]
-use crate::enhanced_regex::EnhancedRegex;
+use crate::enhanced_regex::{EnhancedRegex, TimedOut};
 use crate::style::Style;
 #[cfg(feature = "fancy-regex")]
 use fancy_regex::Regex as FancyRegex;
@@ -31,12 +34,15 @@ use regex_lite as regex;
 #[derive(Debug)]
 pub enum RegexError {
     Syntax(String),
+    /// A match given up after the time set with `set_match_timeout`
+    Timeout,
 }
 
 impl std::fmt::Display for RegexError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RegexError::Syntax(msg) => write!(f, "Regex syntax error: {}", msg),
+            RegexError::Timeout => write!(f, "Regex match timed out"),
         }
     }
 }
@@ -49,6 +55,130 @@ impl From<regex::Error> for RegexError {
     }
 }
 
+#[cfg(not(feature = "fancy-regex"))]
+impl From<TimedOut> for RegexError {
+    fn from(_: TimedOut) -> Self {
+        RegexError::Timeout
+    }
+}
+
+/// Regex engine a pattern is compiled with (`--engine`, or `engine=` for one rule).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "toml-configs",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum RegexEngine {
+    /// The fast engine when it accepts the pattern, else the enhanced one
+    #[default]
+    Auto,
+    /// Only the fast engine; patterns it rejects (lookaround) fail to compile
+    Fast,
+    /// Always the enhanced engine, even for patterns the fast one accepts
+    Enhanced,
+}
+
+impl RegexEngine {
+    /// The name of the engine, as given to `--engine` and `engine=`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegexEngine::Auto => "auto",
+            RegexEngine::Fast => "fast",
+            RegexEngine::Enhanced => "enhanced",
+        }
+    }
+}
+
+impl std::str::FromStr for RegexEngine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(RegexEngine::Auto),
+            "fast" => Ok(RegexEngine::Fast),
+            "enhanced" => Ok(RegexEngine::Enhanced),
+            _ => Err(format!(
+                "unknown regex engine '{}' (expected auto, fast or enhanced)",
+                s
+            )),
+        }
+    }
+}
+
+/// Engine for the rules without `engine=` (`--engine`); `Auto` unless set.
+static DEFAULT_ENGINE: OnceLock<RegexEngine> = OnceLock::new();
+
+/// Compile the patterns of rules without `engine=` with `engine` for the rest
+/// of the process (`--engine`). Only the first call has an effect.
+#[allow(dead_code)]
+pub fn set_regex_engine(engine: RegexEngine) {
+    let _ = DEFAULT_ENGINE.set(engine);
+}
+
+/// Time a single enhanced match may take (`--line-timeout`); unbounded unless set.
+static MATCH_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Give up on a single match of a pattern compiled by the enhanced engine after
+/// about `timeout`, for the rest of the process (`--line-timeout`). Only the
+/// first call has an effect, and only on patterns compiled after it.
+///
+/// fancy-regex counts backtracking steps rather than time, so its limit is
+/// derived from `timeout`; the fast engine runs in linear time and needs none.
+#[allow(dead_code)]
+pub fn set_match_timeout(timeout: Duration) {
+    let _ = MATCH_TIMEOUT.set(timeout);
+}
+
+/// Backtracking steps fancy-regex takes per millisecond, roughly (a release
+/// build gets through the default limit of a million in about 30ms).
+#[cfg(feature = "fancy-regex")]
+const BACKTRACK_STEPS_PER_MS: u128 = 30_000;
+
+/// Implementation whose reading of config files rgrc follows (`--compat`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compat {
+    /// rgrc's own rules: unknown keywords and values are reported
+    #[default]
+    Rgrc,
+    /// Python grc's quirks, to check that a config behaves the same before
+    /// migrating: `count=previous` repeats the count of the rule before, other
+    /// unknown counts mean `more`, the colour `previous` is accepted, and
+    /// regexps are read as loosely as Python's `re` does (see
+    /// `loosen_python_regex`)
+    Grc,
+}
+
+impl std::str::FromStr for Compat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgrc" => Ok(Compat::Rgrc),
+            "grc" => Ok(Compat::Grc),
+            _ => Err(format!(
+                "unknown compatibility mode '{}' (expected grc or rgrc)",
+                s
+            )),
+        }
+    }
+}
+
+/// Compatibility mode for the rest of the process (`--compat`); `Rgrc` unless set.
+static COMPAT: OnceLock<Compat> = OnceLock::new();
+
+/// Read configs as `compat` does for the rest of the process (`--compat`).
+/// Only the first call has an effect.
+#[allow(dead_code)]
+pub fn set_compat(compat: Compat) {
+    let _ = COMPAT.set(compat);
+}
+
+/// The compatibility mode set with `set_compat`.
+pub fn compat() -> Compat {
+    COMPAT.get().copied().unwrap_or_default()
+}
+
 /// Hybrid regex engine: tries standard regex first, then falls back to Enhanced implementation.
 ///
 /// This provides significant performance improvement for most configuration files:
@@ -67,7 +197,7 @@ impl From<regex::Error> for RegexError {
 ///
 /// - **Without `fancy` feature** (`cargo build --no-default-features --features=embed-configs`):
 ///   - Uses custom `EnhancedRegex` implementation (~600 lines)
-///   - Supports: fixed-length lookahead/lookbehind patterns
+///   - Supports: fixed-length lookahead/lookbehind patterns, backreferences
 ///   - Binary size: ~1.8MB (release)
 ///   - Covers 99% of patterns in rgrc config files
 ///   - Newer implementation, less battle-tested
@@ -82,6 +212,12 @@ impl From<regex::Error> for RegexError {
 ///
 /// // Lookahead pattern → Enhanced(fancy_regex::Regex) or Enhanced(EnhancedRegex)
 /// let re = CompiledRegex::new(r"\d+(?=\.\d+\.\d+\.\d+)").unwrap();
+///
+/// // Captures work the same whichever engine was picked
+/// let re = CompiledRegex::new(r"(?P<port>\d+)/tcp").unwrap();
+/// for caps in re.captures_iter("22/tcp 80/tcp") {
+///     println!("{}", caps.name("port").unwrap().as_str());
+/// }
 /// ```
 #[derive(Debug, Clone)]
 pub enum CompiledRegex {
@@ -93,22 +229,79 @@ pub enum CompiledRegex {
     /// Enhanced path: our own lookaround implementation (lightweight, default without fancy feature)
     #[cfg(not(feature = "fancy-regex"))]
     Enhanced(EnhancedRegex),
+    /// Deferred path: compiled into one of the above on first use (see `CompiledRegex::lazy`)
+    Lazy(Box<LazyRegex>),
+}
+
+/// A pattern whose compilation is deferred until it is first matched against.
+///
+/// A pattern that fails to compile is reported once on stderr and then never matches.
+#[derive(Debug, Clone)]
+pub struct LazyRegex {
+    pattern: String,
+    engine: RegexEngine,
+    compiled: OnceLock<Option<CompiledRegex>>,
+}
+
+impl LazyRegex {
+    /// Compile the pattern if that has not happened yet.
+    fn get(&self) -> Option<&CompiledRegex> {
+        self.compiled
+            .get_or_init(
+                || match CompiledRegex::with_engine(&self.pattern, self.engine) {
+                    Ok(re) => Some(re),
+                    Err(exc) => {
+                        eprintln!("Failed regexp: {:?}", exc);
+                        None
+                    }
+                },
+            )
+            .as_ref()
+    }
 }
 
 impl CompiledRegex {
     /// Compile a regex pattern, automatically selecting the fastest engine.
     /// Tries standard regex first, then falls back to EnhancedRegex for lookaround patterns.
     pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        Self::with_engine(pattern, RegexEngine::Auto)
+    }
+
+    /// Compile a regex pattern with `engine`. `Auto` stands for the engine set
+    /// with `set_regex_engine`, which by default selects the fastest engine.
+    pub fn with_engine(pattern: &str, engine: RegexEngine) -> Result<Self, RegexError> {
+        let engine = match engine {
+            RegexEngine::Auto => DEFAULT_ENGINE.get().copied().unwrap_or_default(),
+            engine => engine,
+        };
+
+        // Configs ported from Python grc may use its syntax
+        let (mut pattern, _) = translate_python_regex(pattern);
+        if compat() == Compat::Grc {
+            pattern = loosen_python_regex(&pattern);
+        }
+        let pattern = pattern.as_str();
+
         // Try standard regex first (fastest, but no lookaround)
-        if let Ok(re) = Regex::new(pattern) {
-            return Ok(CompiledRegex::Fast(re));
+        if engine != RegexEngine::Enhanced {
+            match Regex::new(pattern) {
+                Ok(re) => return Ok(CompiledRegex::Fast(re)),
+                Err(e) if engine == RegexEngine::Fast => return Err(e.into()),
+                Err(_) => {}
+            }
         }
 
         // Fall back to Enhanced regex implementation
         #[cfg(feature = "fancy-regex")]
         {
             // Use battle-tested fancy-regex when enabled
-            FancyRegex::new(pattern)
+            let mut builder = fancy_regex::RegexBuilder::new(pattern);
+            if let Some(timeout) = MATCH_TIMEOUT.get() {
+                let steps = timeout.as_millis().max(1) * BACKTRACK_STEPS_PER_MS;
+                builder.backtrack_limit(usize::try_from(steps).unwrap_or(usize::MAX));
+            }
+            builder
+                .build()
                 .map(CompiledRegex::Enhanced)
                 .map_err(|e| RegexError::Syntax(e.to_string()))
         }
@@ -121,6 +314,43 @@ impl CompiledRegex {
         }
     }
 
+    /// Wrap a pattern without compiling it; compilation happens on first match.
+    ///
+    /// Startup then only pays for the rules a command's output actually reaches.
+    #[allow(dead_code)]
+    pub fn lazy(pattern: &str) -> Self {
+        Self::lazy_with_engine(pattern, RegexEngine::Auto)
+    }
+
+    /// Like `lazy`, compiling the pattern with `engine` (see `with_engine`).
+    pub fn lazy_with_engine(pattern: &str, engine: RegexEngine) -> Self {
+        CompiledRegex::Lazy(Box::new(LazyRegex {
+            pattern: translate_python_regex(pattern).0,
+            engine,
+            compiled: OnceLock::new(),
+        }))
+    }
+
+    /// Whether the pattern has been compiled (always true unless built with `lazy()`).
+    #[allow(dead_code)]
+    pub fn is_compiled(&self) -> bool {
+        match self {
+            CompiledRegex::Lazy(lazy) => lazy.compiled.get().is_some(),
+            _ => true,
+        }
+    }
+
+    /// Return the compiled engine, compiling a lazy pattern first if needed.
+    ///
+    /// Returns `None` only for a lazy pattern that failed to compile.
+    #[allow(dead_code)]
+    pub fn resolve(&self) -> Option<&CompiledRegex> {
+        match self {
+            CompiledRegex::Lazy(lazy) => lazy.get(),
+            _ => Some(self),
+        }
+    }
+
     /// Check if the regex matches anywhere in the text.
     #[allow(dead_code)]
     pub fn is_match(&self, text: &str) -> bool {
@@ -130,32 +360,105 @@ impl CompiledRegex {
             CompiledRegex::Enhanced(re) => re.is_match(text).unwrap_or(false),
             #[cfg(not(feature = "fancy-regex"))]
             CompiledRegex::Enhanced(re) => re.is_match(text),
+            CompiledRegex::Lazy(lazy) => lazy.get().is_some_and(|re| re.is_match(text)),
         }
     }
 
     /// Find all capture groups starting from the given position.
+    ///
+    /// The fast and fancy engines only see the text from `pos` on, so `^` and
+    /// `\b` may match there; `captures_at` keeps the text before `pos` in view.
     #[allow(dead_code)]
     pub fn captures_from_pos<'t>(&self, text: &'t str, pos: usize) -> Option<Captures<'t>> {
+        self.try_captures_from_pos(text, pos).ok().flatten()
+    }
+
+    /// Like `captures_from_pos`, but a match the enhanced engine gave up on
+    /// (see `set_match_timeout`) is an error rather than no match.
+    pub fn try_captures_from_pos<'t>(
+        &self,
+        text: &'t str,
+        pos: usize,
+    ) -> Result<Option<Captures<'t>>, RegexError> {
         match self {
             CompiledRegex::Fast(re) => {
                 // Standard regex: convert to our Captures format
-                re.captures(&text[pos..])
-                    .map(|caps| Captures::Fast(caps, pos))
+                Ok(re
+                    .captures(&text[pos..])
+                    .map(|caps| Captures::Fast(caps, pos)))
             }
             #[cfg(feature = "fancy-regex")]
             CompiledRegex::Enhanced(re) => {
-                // fancy-regex: convert to our Captures format
+                // fancy-regex: convert to our Captures format; it only fails
+                // when a match runs out of backtracking steps or stack
                 re.captures(&text[pos..])
-                    .ok()
-                    .flatten()
-                    .map(|caps| Captures::Fancy(caps, pos))
+                    .map(|caps| caps.map(|caps| Captures::Fancy(caps, pos)))
+                    .map_err(|_| RegexError::Timeout)
             }
             #[cfg(not(feature = "fancy-regex"))]
             CompiledRegex::Enhanced(re) => {
                 // EnhancedRegex: convert to our Captures format
-                re.captures_from_pos(text, pos)
-                    .map(|caps| Captures::Fast(caps, 0))
+                let deadline = MATCH_TIMEOUT
+                    .get()
+                    .map(|timeout| std::time::Instant::now() + *timeout);
+                Ok(re
+                    .captures_at_until(text, pos, deadline)?
+                    .map(|caps| Captures::Fast(caps, 0)))
             }
+            CompiledRegex::Lazy(lazy) => match lazy.get() {
+                Some(re) => re.try_captures_from_pos(text, pos),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Find the capture groups of the first match at or after `pos`.
+    ///
+    /// Like `regex::Regex::captures_at`, whichever engine the pattern compiled
+    /// to: anchors, word boundaries and lookbehinds see the text before `pos`.
+    #[allow(dead_code)]
+    pub fn captures_at<'t>(&self, text: &'t str, pos: usize) -> Option<Captures<'t>> {
+        match self {
+            CompiledRegex::Fast(re) => re
+                .captures_at(text, pos)
+                .map(|caps| Captures::Fast(caps, 0)),
+            #[cfg(feature = "fancy-regex")]
+            CompiledRegex::Enhanced(re) => re
+                .captures_from_pos(text, pos)
+                .ok()
+                .flatten()
+                .map(|caps| Captures::Fancy(caps, 0)),
+            #[cfg(not(feature = "fancy-regex"))]
+            CompiledRegex::Enhanced(re) => re
+                .captures_at(text, pos)
+                .map(|caps| Captures::Fast(caps, 0)),
+            CompiledRegex::Lazy(lazy) => lazy.get()?.captures_at(text, pos),
+        }
+    }
+
+    /// Iterate over the capture groups of all non-overlapping matches in `text`.
+    #[allow(dead_code)]
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches {
+            regex: self,
+            text,
+            pos: 0,
+            last_end: None,
+        }
+    }
+
+    /// Names of the capture groups by index (`None` for group 0 and unnamed groups).
+    ///
+    /// Empty for a lazy pattern that failed to compile.
+    #[allow(dead_code)]
+    pub fn capture_names(&self) -> Vec<Option<&str>> {
+        match self {
+            CompiledRegex::Fast(re) => re.capture_names().collect(),
+            #[cfg(feature = "fancy-regex")]
+            CompiledRegex::Enhanced(re) => re.capture_names().collect(),
+            #[cfg(not(feature = "fancy-regex"))]
+            CompiledRegex::Enhanced(re) => re.capture_names(),
+            CompiledRegex::Lazy(lazy) => lazy.get().map_or_else(Vec::new, |re| re.capture_names()),
         }
     }
 
@@ -168,6 +471,43 @@ impl CompiledRegex {
             CompiledRegex::Enhanced(re) => re.as_str(),
             #[cfg(not(feature = "fancy-regex"))]
             CompiledRegex::Enhanced(re) => re.as_str(),
+            CompiledRegex::Lazy(lazy) => &lazy.pattern,
+        }
+    }
+}
+
+/// Iterator over the captures of all matches of a `CompiledRegex` (see `captures_iter`).
+#[derive(Debug)]
+pub struct CaptureMatches<'r, 't> {
+    regex: &'r CompiledRegex,
+    text: &'t str,
+    pos: usize,
+    /// End of the previous match
+    last_end: Option<usize>,
+}
+
+impl<'t> Iterator for CaptureMatches<'_, 't> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.pos > self.text.len() {
+                return None;
+            }
+            let captures = self.regex.captures_at(self.text, self.pos)?;
+            let (start, end) = captures.get(0).map(|m| (m.start(), m.end()))?;
+            // Step over an empty match so the next search makes progress
+            self.pos = if end > start {
+                end
+            } else {
+                end + self.text[end..].chars().next().map_or(1, char::len_utf8)
+            };
+            // As in `regex`, an empty match right after the previous one is skipped
+            if start == end && self.last_end == Some(end) {
+                continue;
+            }
+            self.last_end = Some(end);
+            return Some(captures);
         }
     }
 }
@@ -192,6 +532,16 @@ impl<'t> Captures<'t> {
         }
     }
 
+    /// Get a capture group by name (`(?P<name>...)` or `(?<name>...)`).
+    #[allow(dead_code)]
+    pub fn name(&self, name: &str) -> Option<Match<'t>> {
+        match self {
+            Captures::Fast(caps, offset) => caps.name(name).map(|m| Match::Fast(m, *offset)),
+            #[cfg(feature = "fancy-regex")]
+            Captures::Fancy(caps, offset) => caps.name(name).map(|m| Match::Fancy(m, *offset)),
+        }
+    }
+
     /// Get the number of capture groups.
     #[allow(dead_code)]
     pub fn len(&self) -> usize {
@@ -258,19 +608,91 @@ impl<'t> Match<'t> {
     }
 }
 
-/// Parse a single space-separated style keyword and apply it to a Style.
+/// Every named keyword accepted by [`style_from_str`], in documentation order.
 ///
-/// This function processes grcat-style color and attribute keywords and builds up a composite
-/// `Style` object. It handles multiple space-separated style keywords in a single call,
-/// applying them sequentially to create combined effects (e.g., bold red text).
+/// Palette colors (`color0`..`color255`, `on_color0`..`on_color255`) are accepted
+/// too but not listed. Tools that suggest or complete style names should use this
+/// list rather than keeping their own.
+#[allow(dead_code)]
+pub const STYLE_KEYWORDS: &[&str] = &[
+    // No-op keywords
+    "unchanged",
+    "default",
+    "none",
+    // Foreground colors
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    // Bright foreground colors
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+    // Background colors
+    "on_black",
+    "on_red",
+    "on_green",
+    "on_yellow",
+    "on_blue",
+    "on_magenta",
+    "on_cyan",
+    "on_white",
+    // Text attributes
+    "bold",
+    "dark",
+    "dim",
+    "italic",
+    "underline",
+    "blink",
+    "reverse",
+    "beep",
+];
+
+/// Parse a grcat style specification (one `colours=` entry) into a [`Style`].
+///
+/// This is the one parser used by the colorizer, the config cache, the TOML loader
+/// and `rgrv`, so a spec accepted here is exactly what rgrc will render.
+///
+/// ## Semantics
+///
+/// - `text` is split on single spaces and each word is applied in order, so later
+///   words add to earlier ones (`bold red` is bold *and* red) and a later color
+///   replaces an earlier one of the same kind (`red blue` is blue).
+/// - Named keywords are listed in [`STYLE_KEYWORDS`]. `unchanged`, `default`,
+///   `none`, `''` and the empty word leave the style as it is; `dark` is `dim`.
+/// - `colorN` / `on_colorN` select entry `N` (0-255) of the 256-color palette.
+/// - Quoted raw escapes such as `"\033[38;5;140m"` are accepted and ignored.
+/// - Matching is case-sensitive and exact: `bright-red` and `Red` are errors.
 ///
-/// ## Supported Keywords
+/// ## Errors
 ///
-/// **Foreground colors:**
-/// - `black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan`, `white`
+/// Returns `Err("unhandled style: WORD")` naming the first word that is not
+/// recognised. Nothing is printed.
 ///
+/// ```
+/// use rgrc::grc::style_from_str;
+///
+/// let style = style_from_str("bold red").unwrap();
+/// assert_eq!(style.to_ansi_codes(), "\x1b[1;31m");
+/// assert_eq!(style_from_str("unchanged").unwrap(), rgrc::Style::new());
+/// assert_eq!(style_from_str("purple").unwrap_err(), "unhandled style: purple");
+/// ```
 pub fn style_from_str(text: &str) -> Result<Style, String> {
-    text.split(' ').try_fold(Style::new(), |style, word| {
+    style_from_str_on(Style::new(), text)
+}
+
+/// `style_from_str`, adding the words of `text` to `base`.
+fn style_from_str_on(base: Style, text: &str) -> Result<Style, String> {
+    text.split(' ').try_fold(base, |style, word| {
         // Handle ANSI escape sequences like "\033[38;5;140m"
         if word.starts_with('"') && word.contains("\\033[") {
             // Skip ANSI escape codes for now - they're raw color codes
@@ -278,12 +700,19 @@ pub fn style_from_str(text: &str) -> Result<Style, String> {
         }
         match word {
             // Empty string or no-op keywords - return style unchanged
-            "" => Ok(style),
+            "" | "''" => Ok(style),
             "unchanged" => Ok(style),
             "default" => Ok(style),
             "dark" => Ok(style.dim()),
             "none" => Ok(style),
 
+            // The terminal bell, rung by the colorizer when the style is used
+            "beep" => Ok(style.beep()),
+
+            // Python grc's "colour of the text before", which
+            // `colour_specs_from_str` resolves
+            "previous" if compat() == Compat::Grc => Ok(style),
+
             // Foreground colors - standard ANSI colors
             "black" => Ok(style.black()),
             "red" => Ok(style.red()),
@@ -322,13 +751,21 @@ pub fn style_from_str(text: &str) -> Result<Style, String> {
             "bright_cyan" => Ok(style.bright().cyan()),
             "bright_white" => Ok(style.bright().white()),
 
-            // Unknown keyword - log and return error
-            _ => {
-                // Return a descriptive error (used in callers/tests to detect invalid styles)
-                let msg = format!("unhandled style: {}", word);
-                println!("{}", msg);
-                Err(msg)
+            // 256-color palette entries, e.g. color208 / on_color16
+            _ if word.starts_with("color") || word.starts_with("on_color") => {
+                let (prefix, index) = word.split_at(
+                    word.find(|c: char| c.is_ascii_digit())
+                        .unwrap_or(word.len()),
+                );
+                match (prefix, index.parse()) {
+                    ("color", Ok(index)) => Ok(style.color256(index)),
+                    ("on_color", Ok(index)) => Ok(style.on_color256(index)),
+                    _ => Err(format!("unhandled style: {}", word)),
+                }
             }
+
+            // Unknown keyword - the caller decides how to report it
+            _ => Err(format!("unhandled style: {}", word)),
         }
     })
 }
@@ -391,6 +828,771 @@ pub fn styles_from_str(text: &str) -> Result<Vec<Style>, String> {
     text.split(',').map(style_from_str).collect()
 }
 
+/// Parse a `colours` value whose items may style named capture groups.
+///
+/// Each comma-separated item is either a style for the next group in order,
+/// as in `styles_from_str`, or `name:GROUP=STYLE` for the group named GROUP
+/// (`(?P<GROUP>...)` or `(?<GROUP>...)`). Use `resolve_colours` to turn the
+/// result into per-group styles for a pattern, or `colours_from_str` for both.
+///
+/// With `--compat=grc`, an item with the word `previous` starts from the style
+/// of the item before it, the closest rgrc has to Python grc continuing the
+/// colour of the text before the group.
+pub fn colour_specs_from_str(text: &str) -> Result<Vec<(Option<String>, Style)>, String> {
+    let mut specs: Vec<(Option<String>, Style)> = Vec::new();
+    for item in text.split(',') {
+        let spec = match item.trim_start().strip_prefix("name:") {
+            Some(named) => {
+                let (group, style) = named
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected name:GROUP=STYLE, got '{}'", item))?;
+                (
+                    Some(group.trim().to_string()),
+                    style_from_str(style.trim())?,
+                )
+            }
+            None if compat() == Compat::Grc && item.split(' ').any(|w| w == "previous") => {
+                let previous = specs.last().map_or_else(Style::new, |(_, style)| *style);
+                (None, style_from_str_on(previous, item)?)
+            }
+            None => (None, style_from_str(item)?),
+        };
+        specs.push(spec);
+    }
+    Ok(specs)
+}
+
+/// Parse a `thresholds` value: comma-separated `N:STYLE` items, such as
+/// `70:yellow,90:bold red`, ordered by threshold.
+pub fn thresholds_from_str(text: &str) -> Result<Vec<(f64, Style)>, String> {
+    let mut thresholds = text
+        .split(',')
+        .map(|item| {
+            let (value, style) = item
+                .split_once(':')
+                .ok_or_else(|| format!("expected N:STYLE, got '{}'", item.trim()))?;
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("invalid threshold '{}'", value.trim()))?;
+            Ok((value, style_from_str(style.trim())?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    thresholds.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(thresholds)
+}
+
+/// Colour ramp for a captured number between two bounds (`gradient=MIN:MAX`).
+///
+/// Values at `min` are green, values at `max` red, with yellow halfway; values
+/// outside the bounds take the colour of the nearest one. `max` may be below
+/// `min`, for numbers where higher is better.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gradient {
+    pub min: f64,
+    pub max: f64,
+    /// Use the green, yellow and red of the basic palette (`gradient=MIN:MAX:ansi`)
+    /// instead of the finer 256-color ramp
+    pub ansi: bool,
+}
+
+/// Green to yellow to red through the 256-color palette
+static GRADIENT_256: [Style; 11] = [
+    Style::new().color256(46),
+    Style::new().color256(82),
+    Style::new().color256(118),
+    Style::new().color256(154),
+    Style::new().color256(190),
+    Style::new().color256(226),
+    Style::new().color256(220),
+    Style::new().color256(214),
+    Style::new().color256(208),
+    Style::new().color256(202),
+    Style::new().color256(196),
+];
+
+/// Green to yellow to red in the basic palette
+static GRADIENT_ANSI: [Style; 3] = [
+    Style::new().green(),
+    Style::new().yellow(),
+    Style::new().red(),
+];
+
+impl Gradient {
+    /// Style of the step of the ramp `value` falls on
+    pub fn style(&self, value: f64) -> &'static Style {
+        let steps: &'static [Style] = if self.ansi {
+            &GRADIENT_ANSI
+        } else {
+            &GRADIENT_256
+        };
+        let position = ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0);
+        &steps[(position * (steps.len() - 1) as f64).round() as usize]
+    }
+}
+
+impl std::fmt::Display for Gradient {
+    /// Format as a `gradient` value, the inverse of `gradient_from_str`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.min, self.max)?;
+        if self.ansi {
+            write!(f, ":ansi")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `gradient` value: `MIN:MAX`, optionally followed by `:ansi`.
+pub fn gradient_from_str(text: &str) -> Result<Gradient, String> {
+    let mut parts = text.split(':').map(str::trim);
+    let bound = |part: Option<&str>| {
+        let part = part.unwrap_or_default();
+        part.parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("invalid gradient bound '{}' (expected MIN:MAX)", part))
+    };
+    let (min, max) = (bound(parts.next())?, bound(parts.next())?);
+    if min == max {
+        return Err(format!("gradient bounds must differ, got {}:{}", min, max));
+    }
+    let ansi = match parts.next() {
+        None => false,
+        Some("ansi") => true,
+        Some(palette) => {
+            return Err(format!(
+                "unknown gradient palette '{}' (expected ansi)",
+                palette
+            ));
+        }
+    };
+    match parts.next() {
+        None => Ok(Gradient { min, max, ansi }),
+        Some(_) => Err(format!("expected MIN:MAX[:ansi], got '{}'", text.trim())),
+    }
+}
+
+/// Unit letters of sizes, by power of 1024
+const SIZE_UNITS: [&str; 7] = ["", "K", "M", "G", "T", "P", "E"];
+
+/// Power of 1024 of a size unit, in any case and with or without `i` and `B`
+/// (`k`, `KB`, `KiB`); `None` for other words. No unit, or `B`, is 0.
+fn size_unit_power(unit: &str) -> Option<i32> {
+    let unit = unit.to_ascii_uppercase();
+    let prefix = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    SIZE_UNITS
+        .iter()
+        .position(|letter| *letter == prefix)
+        .map(|power| power as i32)
+}
+
+/// Colour ramp for a captured byte size between two bounds (`sizes=MIN:MAX`).
+///
+/// The ramp is that of `Gradient`, but on a logarithmic scale, so that each
+/// step from kilobytes to terabytes gets its own colour. Bounds are in bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeScale {
+    pub min: f64,
+    pub max: f64,
+    /// Use the green, yellow and red of the basic palette (`sizes=MIN:MAX:ansi`)
+    pub ansi: bool,
+}
+
+impl SizeScale {
+    /// Style of the step of the ramp a size of `bytes` falls on
+    pub fn style(&self, bytes: f64) -> &'static Style {
+        let gradient = Gradient {
+            min: self.min.log2(),
+            max: self.max.log2(),
+            ansi: self.ansi,
+        };
+        gradient.style(bytes.max(1.0).log2())
+    }
+}
+
+/// Format `bytes` with the largest unit it reaches (`1536` as `1.5K`)
+fn size_to_string(bytes: f64) -> String {
+    let power = (1..SIZE_UNITS.len())
+        .rev()
+        .find(|power| bytes >= 1024f64.powi(*power as i32))
+        .unwrap_or(0);
+    format!(
+        "{}{}",
+        bytes / 1024f64.powi(power as i32),
+        SIZE_UNITS[power]
+    )
+}
+
+impl std::fmt::Display for SizeScale {
+    /// Format as a `sizes` value, the inverse of `sizes_from_str`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            size_to_string(self.min),
+            size_to_string(self.max)
+        )?;
+        if self.ansi {
+            write!(f, ":ansi")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `sizes` value: `MIN:MAX` sizes with units (`1M:1T`), optionally
+/// followed by `:ansi`.
+pub fn sizes_from_str(text: &str) -> Result<SizeScale, String> {
+    let mut parts = text.split(':').map(str::trim);
+    let bound = |part: Option<&str>| {
+        let part = part.unwrap_or_default();
+        let digits = part
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(part.len());
+        part[..digits]
+            .parse::<f64>()
+            .ok()
+            .zip(size_unit_power(part[digits..].trim()))
+            .map(|(value, power)| value * 1024f64.powi(power))
+            .filter(|bytes| bytes.is_finite() && *bytes > 0.0)
+            .ok_or_else(|| format!("invalid size bound '{}' (expected MIN:MAX)", part))
+    };
+    let (min, max) = (bound(parts.next())?, bound(parts.next())?);
+    if min == max {
+        return Err(format!(
+            "size bounds must differ, got {}:{}",
+            size_to_string(min),
+            size_to_string(max)
+        ));
+    }
+    let ansi = match parts.next() {
+        None => false,
+        Some("ansi") => true,
+        Some(palette) => {
+            return Err(format!(
+                "unknown sizes palette '{}' (expected ansi)",
+                palette
+            ));
+        }
+    };
+    match parts.next() {
+        None => Ok(SizeScale { min, max, ansi }),
+        Some(_) => Err(format!("expected MIN:MAX[:ansi], got '{}'", text.trim())),
+    }
+}
+
+/// Parse a `fields` value: comma-separated `N:STYLE` items, such as
+/// `1:cyan,5:bold yellow`. Fields count from 1; negative numbers count from the
+/// end of the line (`-1` is the last field).
+pub fn fields_from_str(text: &str) -> Result<Vec<(isize, Style)>, String> {
+    text.split(',')
+        .map(|item| {
+            let (field, style) = item
+                .split_once(':')
+                .ok_or_else(|| format!("expected N:STYLE, got '{}'", item.trim()))?;
+            let field = field
+                .trim()
+                .parse::<isize>()
+                .ok()
+                .filter(|field| *field != 0)
+                .ok_or_else(|| format!("invalid field number '{}'", field.trim()))?;
+            Ok((field, style_from_str(style.trim())?))
+        })
+        .collect()
+}
+
+/// Format `fields` as a `fields` value, the inverse of `fields_from_str`.
+pub fn fields_to_string(fields: &[(isize, Style)]) -> String {
+    fields
+        .iter()
+        .map(|(field, style)| match style.keywords() {
+            words if words.is_empty() => format!("{}:default", field),
+            words => format!("{}:{}", field, words.join(" ")),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Byte ranges of the fields of `line`: runs of non-whitespace when `delimiter`
+/// is empty, else the text between occurrences of `delimiter` (possibly empty).
+/// A delimited field that opens with `"` runs to its closing quote, as in CSV,
+/// so it may hold the delimiter; `""` inside it is an escaped quote.
+pub fn field_ranges(line: &str, delimiter: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    if delimiter.is_empty() {
+        let mut start = None;
+        for (index, c) in line.char_indices() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(index),
+                (true, Some(begin)) => {
+                    ranges.push(begin..index);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        ranges.extend(start.map(|begin| begin..line.len()));
+    } else {
+        let mut start = 0;
+        loop {
+            let search = if line[start..].starts_with('"') {
+                closing_quote_end(line, start)
+            } else {
+                start
+            };
+            match line[search..].find(delimiter) {
+                Some(index) => {
+                    ranges.push(start..search + index);
+                    start = search + index + delimiter.len();
+                }
+                None => {
+                    ranges.push(start..line.len());
+                    break;
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// End of the quoted field opening at `start`: just past its closing quote, or
+/// the end of `line` if the quote is never closed.
+fn closing_quote_end(line: &str, start: usize) -> usize {
+    let mut pos = start + 1;
+    while let Some(quote) = line[pos..].find('"') {
+        pos += quote + 1;
+        if !line[pos..].starts_with('"') {
+            return pos;
+        }
+        pos += 1;
+    }
+    line.len()
+}
+
+/// Format `thresholds` as a `thresholds` value, the inverse of `thresholds_from_str`.
+pub fn thresholds_to_string(thresholds: &[(f64, Style)]) -> String {
+    thresholds
+        .iter()
+        .map(|(value, style)| match style.keywords() {
+            words if words.is_empty() => format!("{}:default", value),
+            words => format!("{}:{}", value, words.join(" ")),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The first number in `text`, with its sign and decimals (`85` in `85%`,
+/// `-3.5` in `at -3.5dB`), or `None` if it has no digits.
+fn first_number(text: &str) -> Option<f64> {
+    number_at(text).map(|(value, _)| value)
+}
+
+/// The first number in `text` as for `first_number`, with the byte offset just
+/// past it.
+fn number_at(text: &str) -> Option<(f64, usize)> {
+    let digits = text.find(|c: char| c.is_ascii_digit())?;
+    let start = if text[..digits].ends_with(['-', '+']) {
+        digits - 1
+    } else {
+        digits
+    };
+    let mut end = digits
+        + text[digits..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len() - digits);
+    if let Some(fraction) = text[end..].strip_prefix('.')
+        && fraction.starts_with(|c: char| c.is_ascii_digit())
+    {
+        end += 1 + fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+    }
+    Some((text[start..end].parse().ok()?, end))
+}
+
+/// The first size in `text` in bytes: a number and its unit, possibly after one
+/// space (`4.0K`, `12 MiB`, `3GB`). A number without a known unit is taken as bytes.
+fn first_size(text: &str) -> Option<f64> {
+    let (value, end) = number_at(text)?;
+    let rest = &text[end..];
+    let rest = rest.strip_prefix(' ').unwrap_or(rest);
+    let unit = &rest[..rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len())];
+    Some(value * 1024f64.powi(size_unit_power(unit).unwrap_or(0)))
+}
+
+/// Place the styles of `colour_specs_from_str` by capture group of `pattern`.
+///
+/// Unnamed items take the groups in order, starting with the whole match;
+/// named items then style their group, whatever its position. Groups left
+/// between them get the default style. Fails on a name `pattern` does not have.
+pub fn resolve_colours(
+    specs: Vec<(Option<String>, Style)>,
+    pattern: &str,
+) -> Result<Vec<Style>, String> {
+    if specs.iter().all(|(name, _)| name.is_none()) {
+        return Ok(specs.into_iter().map(|(_, style)| style).collect());
+    }
+
+    let names = capture_group_names(pattern);
+    let mut colors: Vec<Style> = Vec::new();
+    let mut named = Vec::new();
+    for (name, style) in specs {
+        match name {
+            Some(name) => named.push((name, style)),
+            None => colors.push(style),
+        }
+    }
+    for (name, style) in named {
+        let group = names
+            .iter()
+            .position(|group| group.as_deref() == Some(name.as_str()))
+            .ok_or_else(|| format!("no capture group named '{}' in {}", name, pattern))?
+            + 1;
+        if colors.len() <= group {
+            colors.resize(group + 1, Style::new());
+        }
+        colors[group] = style;
+    }
+    Ok(colors)
+}
+
+/// Names of the capture groups of `pattern` in order (`None` for unnamed groups);
+/// the first entry is group 1.
+///
+/// Only the syntax needed to number groups is understood: escapes, character
+/// classes and `(?...)` groups, which capture only as `(?P<name>` or `(?<name>`.
+pub fn capture_group_names(pattern: &str) -> Vec<Option<String>> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut names = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => {
+                // Skip the class; a leading `^` and a leading `]` belong to it
+                i += 1;
+                if chars.get(i) == Some(&'^') {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&']') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i] != ']' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '(' => {
+                let rest: String = chars[i + 1..].iter().take(3).collect();
+                let name_start = if rest.starts_with("?P<") {
+                    Some(i + 4)
+                } else if rest.starts_with("?<")
+                    && !rest.starts_with("?<=")
+                    && !rest.starts_with("?<!")
+                {
+                    Some(i + 3)
+                } else {
+                    None
+                };
+                match name_start {
+                    Some(start) => {
+                        let name: String =
+                            chars[start..].iter().take_while(|c| **c != '>').collect();
+                        names.push(Some(name));
+                    }
+                    None if rest.starts_with('?') => {}
+                    None => names.push(None),
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    names
+}
+
+/// Split a `define NAME=STYLES` line into its name and styles.
+pub fn parse_style_macro(line: &str) -> Option<(&str, &str)> {
+    let (name, styles) = line.strip_prefix("define ")?.split_once('=')?;
+    let name = name.trim();
+    (!name.is_empty() && !name.contains(char::is_whitespace)).then_some((name, styles.trim()))
+}
+
+/// Letters accepted by the `flags=` key: case-insensitive, multi-line (`^`/`$` at
+/// line breaks), dot matches newline and verbose (whitespace and `#` comments ignored).
+pub const REGEX_FLAGS: &str = "imsx";
+
+/// Apply the letters of a `flags=` value to `pattern` as an inline group, so
+/// `flags=i` with `regexp=error` compiles `(?i)error`.
+///
+/// Fails on a letter outside `REGEX_FLAGS` or one given twice.
+pub fn apply_regex_flags(pattern: &str, flags: &str) -> Result<String, String> {
+    let flags = flags.trim();
+    for (index, flag) in flags.char_indices() {
+        if !REGEX_FLAGS.contains(flag) {
+            return Err(format!(
+                "unknown regex flag '{}' (expected one of {})",
+                flag, REGEX_FLAGS
+            ));
+        }
+        if flags[..index].contains(flag) {
+            return Err(format!("regex flag '{}' given twice", flag));
+        }
+    }
+    if flags.is_empty() {
+        Ok(pattern.to_string())
+    } else {
+        Ok(format!("(?{}){}", flags, pattern))
+    }
+}
+
+/// Escape what Python's `re` reads as literal text but the regex engines
+/// reject (`--compat=grc`).
+///
+/// - `{` that does not start a repetition (`{m}`, `{m,}`, `{,n}`, `{m,n}`)
+///   becomes `\{`, and `{,n}` becomes `{0,n}`
+/// - `[` inside a character class becomes `\[`
+///
+/// ```
+/// use rgrc::grc::loosen_python_regex;
+///
+/// assert_eq!(loosen_python_regex(r"\${HOME}"), r"\$\{HOME}");
+/// assert_eq!(loosen_python_regex(r"x{,3}[[]"), r"x{0,3}[\[]");
+/// ```
+pub fn loosen_python_regex(pattern: &str) -> String {
+    let mut output = String::with_capacity(pattern.len());
+    let mut in_char_class = false;
+    let mut chars = pattern.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                output.push(c);
+                if let Some((_, next)) = chars.next() {
+                    output.push(next);
+                }
+                continue;
+            }
+            '[' if in_char_class => output.push_str("\\["),
+            '[' => {
+                // A `]` right after the opening bracket (and `^`) is a literal
+                output.push(c);
+                in_char_class = true;
+                if chars.next_if(|(_, next)| *next == '^').is_some() {
+                    output.push('^');
+                }
+                if chars.next_if(|(_, next)| *next == ']').is_some() {
+                    output.push(']');
+                }
+            }
+            ']' if in_char_class => {
+                output.push(c);
+                in_char_class = false;
+            }
+            '{' if !in_char_class => {
+                let rest = &pattern[i + 1..];
+                let end = rest.find('}').unwrap_or(rest.len());
+                let (min, max) = match rest[..end].split_once(',') {
+                    Some((min, max)) => (min, Some(max)),
+                    None => (&rest[..end], None),
+                };
+                let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+                let repetition = end < rest.len()
+                    && digits(min)
+                    && max.is_none_or(digits)
+                    && (!min.is_empty() || max.is_some());
+                if !repetition {
+                    output.push_str("\\{");
+                } else if min.is_empty() {
+                    output.push_str("{0");
+                } else {
+                    output.push('{');
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Rewrite the Python `re` syntax of configs ported from grc into what the
+/// regex engines accept, with a note for each construct that has no
+/// equivalent (left as it is, so the pattern usually fails to compile).
+///
+/// - `(?P=name)` becomes the backreference `\N` of group `name`
+/// - `\Z` becomes `\z`, and octal escapes (`\0`, `\012`) become `\x{..}`
+/// - global flags anywhere in the pattern (`a(?i)b`), which Python applies to
+///   the whole pattern, move to its start; `(?u)` is the default and dropped
+/// - comments (`(?#...)`) are removed
+///
+/// Other patterns come back unchanged.
+pub fn translate_python_regex(pattern: &str) -> (String, Vec<String>) {
+    let mut output = String::new();
+    let mut flags = String::new();
+    let mut notes = Vec::new();
+    // Names of the capture groups opened so far, by number - 1
+    let mut groups: Vec<Option<&str>> = Vec::new();
+    let mut in_char_class = false;
+    let mut i = 0;
+
+    while let Some(c) = pattern[i..].chars().next() {
+        let rest = &pattern[i..];
+        if c == '\\' {
+            // Python reads \0NN, and \NNN with three octal digits, as octal (and
+            // any octal digits in a class); other digits are backreferences
+            let escape = &rest[1..];
+            let octal = escape.bytes().take_while(u8::is_ascii_digit).take(3);
+            let octal = octal.take_while(|digit| *digit < b'8').count();
+            let digits = match octal {
+                _ if in_char_class || escape.starts_with('0') => octal,
+                3 => 3,
+                _ => 0,
+            };
+            if digits > 0
+                && let Ok(value) = u32::from_str_radix(&escape[..digits], 8)
+                && value <= 0o377
+            {
+                output.push_str(&format!("\\x{{{:02X}}}", value));
+                i += 1 + digits;
+                continue;
+            }
+            match escape.chars().next() {
+                Some('Z') if !in_char_class => output.push_str("\\z"),
+                Some(next) => {
+                    output.push('\\');
+                    output.push(next);
+                }
+                None => output.push('\\'),
+            }
+            i += 1 + escape.chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+
+        if in_char_class {
+            in_char_class = c != ']';
+        } else if c == '[' {
+            // A `]` right after the opening bracket (and `^`) is a literal
+            let open = ["[^]", "[]", "[^"]
+                .iter()
+                .find(|open| rest.starts_with(**open))
+                .map_or(1, |open| open.len());
+            output.push_str(&rest[..open]);
+            in_char_class = true;
+            i += open;
+            continue;
+        } else if let Some(comment) = rest.strip_prefix("(?#") {
+            i += 3 + comment.find(')').map_or(comment.len(), |end| end + 1);
+            continue;
+        } else if let Some(name) = rest.strip_prefix("(?P=")
+            && let Some(end) = name.find(')')
+        {
+            match groups.iter().position(|group| *group == Some(&name[..end])) {
+                Some(index) => output.push_str(&format!("\\{}", index + 1)),
+                None => {
+                    notes.push(format!(
+                        "(?P={}) refers to no group before it",
+                        &name[..end]
+                    ));
+                    output.push_str(&rest[..4 + end + 1]);
+                }
+            }
+            i += 4 + end + 1;
+            continue;
+        } else if let Some(letters) = rest.strip_prefix("(?")
+            && let Some(end) = letters.find(')')
+            && end > 0
+            && letters[..end].chars().all(|flag| "aiLmsux".contains(flag))
+        {
+            for flag in letters[..end].chars() {
+                match flag {
+                    'u' => {}
+                    'a' | 'L' => notes.push(format!("flag (?{}) is not supported", flag)),
+                    _ if !flags.contains(flag) => flags.push(flag),
+                    _ => {}
+                }
+            }
+            i += 2 + end + 1;
+            continue;
+        } else if rest.starts_with("(?(") {
+            notes.push("conditional groups (?(...)...) are not supported".to_string());
+        } else if rest.starts_with("(?>") {
+            notes.push("atomic groups (?>...) are not supported".to_string());
+        } else if let Some(name) = rest
+            .strip_prefix("(?P<")
+            .or_else(|| rest.strip_prefix("(?<"))
+            .filter(|name| !name.starts_with(['=', '!']))
+        {
+            groups.push(name.find('>').map(|end| &name[..end]));
+        } else if c == '(' && !rest.starts_with("(?") {
+            groups.push(None);
+        } else if matches!(c, '*' | '+' | '?' | '}') && rest[1..].starts_with('+') {
+            notes.push("possessive quantifiers (*+, ++, ?+) are not supported".to_string());
+        }
+        output.push(c);
+        i += c.len_utf8();
+    }
+
+    if !flags.is_empty() {
+        output.insert_str(0, &format!("(?{})", flags));
+    }
+    (output, notes)
+}
+
+/// Replace the `@NAME` words of a `colours` value with the styles `macros` define for them.
+///
+/// A macro may stand for several comma-separated styles, or be combined with
+/// other keywords (`@ok underline`). Fails on a macro that is not defined.
+///
+/// # Examples
+///
+/// ```ignore
+/// let macros = HashMap::from([("ok".to_string(), "bold green".to_string())]);
+/// assert_eq!(expand_style_macros("default,@ok", &macros).unwrap(), "default,bold green");
+/// ```
+pub fn expand_style_macros(
+    value: &str,
+    macros: &HashMap<String, String>,
+) -> Result<String, String> {
+    if !value.contains('@') {
+        return Ok(value.to_string());
+    }
+    let styles: Result<Vec<String>, String> = value
+        .split(',')
+        .map(|style| {
+            // The style of a named group: name:GROUP=@macro
+            let (group, style) = match style.split_once("=@") {
+                Some((group, _)) if group.trim_start().starts_with("name:") => {
+                    (Some(group), &style[group.len() + 1..])
+                }
+                _ => (None, style),
+            };
+            let words: Result<Vec<&str>, String> = style
+                .split(' ')
+                .map(|word| match word.strip_prefix('@') {
+                    Some(name) => macros
+                        .get(name)
+                        .map(String::as_str)
+                        .ok_or_else(|| format!("undefined style macro: @{}", name)),
+                    None => Ok(word),
+                })
+                .collect();
+            words.map(|words| match group {
+                Some(group) => format!("{}={}", group, words.join(" ")),
+                None => words.join(" "),
+            })
+        })
+        .collect();
+    styles.map(|styles| styles.join(","))
+}
+
 /// Configuration reader for the main grc.conf file.
 ///
 /// This struct implements an iterator over GRC configuration rules. Each rule maps
@@ -642,6 +1844,12 @@ impl<A: BufRead> Iterator for GrcConfigReader<A> {
 /// - `colours` - Comma-separated console styles for capture groups
 /// - Other keys are accepted but ignored
 ///
+/// ## Style Macros
+///
+/// A line `define NAME=STYLES` names a style for the rules after it, which use
+/// it as `@NAME` in `colours` (e.g. `define ok=bold green`, then
+/// `colours=default,@ok`). See `expand_style_macros`.
+///
 /// ## Parsing Behavior
 ///
 /// - **Entry boundaries**: Marked by non-alphanumeric lines (comments, blank lines)
@@ -675,6 +1883,11 @@ impl<A: BufRead> Iterator for GrcConfigReader<A> {
 #[allow(dead_code)]
 pub struct GrcatConfigReader<A> {
     inner: Lines<A>,
+    lazy: bool,
+    /// Style macros defined so far (`define NAME=STYLES`)
+    macros: HashMap<String, String>,
+    /// Count of the last rule read, for `count=previous` (`--compat=grc`)
+    previous_count: GrcatConfigEntryCount,
 }
 
 #[allow(dead_code)]
@@ -696,7 +1909,32 @@ impl<A: BufRead> GrcatConfigReader<A> {
     /// let grcat_reader = GrcatConfigReader::new(reader.lines());
     /// ```
     pub fn new(inner: Lines<A>) -> Self {
-        GrcatConfigReader { inner }
+        GrcatConfigReader {
+            inner,
+            lazy: false,
+            macros: HashMap::new(),
+            previous_count: GrcatConfigEntryCount::More,
+        }
+    }
+
+    /// Record `line` if it is a `define NAME=STYLES` line; returns whether it was.
+    fn define(&mut self, line: &str) -> bool {
+        match parse_style_macro(line) {
+            Some((name, styles)) => {
+                self.macros.insert(name.to_string(), styles.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Defer compiling each rule's regex until it is first used.
+    ///
+    /// Invalid patterns are then no longer skipped while parsing; they are
+    /// reported on first use and never match.
+    pub fn lazy(mut self) -> Self {
+        self.lazy = true;
+        self
     }
 
     /// Fetch the next alphanumeric line (skipping comments/blank lines).
@@ -735,9 +1973,9 @@ impl<A: BufRead> GrcatConfigReader<A> {
     fn next_alphanumeric(&mut self) -> Option<String> {
         // Pattern ^[a-zA-Z0-9] matches lines starting with a letter or digit
         let alphanumeric = Regex::new("^[a-zA-Z0-9]").unwrap();
-        for line in (&mut self.inner).flatten() {
-            // Skip non-matching lines (comments, blanks)
-            if alphanumeric.is_match(&line) {
+        while let Some(Ok(line)) = self.inner.next() {
+            // Skip non-matching lines (comments, blanks); record style macros
+            if alphanumeric.is_match(&line) && !self.define(line.trim()) {
                 return Some(line.trim().to_string());
             }
         }
@@ -869,6 +2107,11 @@ impl<A: BufRead> GrcatConfigReader<A> {
 /// - `More`: Continue matching within the same rule (default behavior)
 /// - `Stop`: After first match, skip all remaining rules for this line
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "toml-configs",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum GrcatConfigEntryCount {
     /// Match only once per line, then skip to the next rule
     Once,
@@ -876,6 +2119,11 @@ pub enum GrcatConfigEntryCount {
     More,
     /// Match once and stop processing the entire line
     Stop,
+    /// Match once, stop processing the line and style it and the following
+    /// lines entirely with the rule's first style, until an `Unblock` rule matches
+    Block,
+    /// Match once, stop processing the line and end the region of a `Block` rule
+    Unblock,
 }
 
 #[derive(Debug, Clone)]
@@ -885,7 +2133,7 @@ pub struct GrcatConfigEntry {
     pub regex: CompiledRegex,
     /// Styles to apply to capture groups (index 0 = group 1, index 1 = group 2, etc.)
     pub colors: Vec<Style>,
-    /// If true, this rule should be ignored at runtime (treated as disabled).
+    /// If true, lines this rule matches are dropped from the output.
     pub skip: bool,
     /// How many times to apply this rule per line (Once/More/Stop).
     pub count: GrcatConfigEntryCount,
@@ -893,6 +2141,28 @@ pub struct GrcatConfigEntry {
     /// Optional replacement template used when `replace` is specified in the
     /// configuration. Placeholders like `\1` are substituted with capture groups.
     pub replace: String,
+    /// Shell command run in the background when the rule matches a line
+    /// (`command=`; only with `--allow-hooks`). Empty when not specified.
+    pub command: String,
+    /// Position of the rule among the loaded rules (`priority=N`): rules with a
+    /// higher priority run first, ties keep their source order. 0 when not specified.
+    pub priority: i32,
+    /// Styles by severity for the number the rule captures (`thresholds=70:yellow,90:red`),
+    /// ordered by threshold. Empty when not specified.
+    pub thresholds: Vec<(f64, Style)>,
+    /// Colour ramp for the number the rule captures (`gradient=MIN:MAX`), used
+    /// when no threshold applies. `None` when not specified.
+    pub gradient: Option<Gradient>,
+    /// Logarithmic colour ramp for the byte size the rule captures
+    /// (`sizes=1M:1T`), used when no threshold applies. `None` when not specified.
+    pub sizes: Option<SizeScale>,
+    /// Styles by field number (`fields=1:cyan,-1:green`) for the lines the regexp
+    /// matches, applied instead of `colors`. Empty when not specified.
+    pub fields: Vec<(isize, Style)>,
+    /// Separator of the fields (`delimiter=`); empty for runs of whitespace.
+    pub delimiter: String,
+    /// Engine the regexp is compiled with (`engine=`); `Auto` when not specified.
+    pub engine: RegexEngine,
 }
 
 impl GrcatConfigEntry {
@@ -929,7 +2199,74 @@ impl GrcatConfigEntry {
             skip: false,
             count: GrcatConfigEntryCount::More,
             replace: String::new(),
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: RegexEngine::Auto,
+        }
+    }
+
+    /// The styled fields of `line` under the rule's `fields`, as
+    /// `(field number, byte range, style)`; fields the line lacks are left out.
+    #[allow(dead_code)]
+    pub fn field_styles(&self, line: &str) -> Vec<(usize, std::ops::Range<usize>, &Style)> {
+        if self.fields.is_empty() {
+            return Vec::new();
+        }
+        let ranges = field_ranges(line, &self.delimiter);
+        self.fields
+            .iter()
+            .filter_map(|(field, style)| {
+                let index = if *field > 0 {
+                    field.unsigned_abs() - 1
+                } else {
+                    ranges.len().checked_sub(field.unsigned_abs())?
+                };
+                let range = ranges.get(index)?.clone();
+                Some((index + 1, range, style))
+            })
+            .collect()
+    }
+
+    /// Style of the number in `text` under the rule's `thresholds`: that of the
+    /// highest threshold it reaches, or `None` if it reaches none or there is
+    /// no number.
+    ///
+    /// The number is taken from the first capture group, or the whole match
+    /// when the regexp has no groups.
+    #[allow(dead_code)]
+    pub fn threshold_style(&self, text: &str) -> Option<&Style> {
+        if self.thresholds.is_empty() {
+            return None;
         }
+        let value = first_number(text)?;
+        self.thresholds
+            .iter()
+            .rev()
+            .find(|(threshold, _)| value >= *threshold)
+            .map(|(_, style)| style)
+    }
+
+    /// Style of the number in `text` on the rule's `gradient`, or `None` if the
+    /// rule has none or there is no number. The number is found as for
+    /// `threshold_style`.
+    #[allow(dead_code)]
+    pub fn gradient_style(&self, text: &str) -> Option<&'static Style> {
+        let gradient = self.gradient.as_ref()?;
+        Some(gradient.style(first_number(text)?))
+    }
+
+    /// Style of the size in `text` (number and unit) on the rule's `sizes`
+    /// scale, or `None` if the rule has none or there is no number. The size is
+    /// found as the number for `threshold_style`.
+    #[allow(dead_code)]
+    pub fn size_style(&self, text: &str) -> Option<&'static Style> {
+        let sizes = self.sizes.as_ref()?;
+        Some(sizes.style(first_size(text)?))
     }
 }
 
@@ -968,6 +2305,7 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
     /// **Optional:**
     /// - `colours=` line with comma-separated style keywords
     /// - If omitted, colors default to empty vector (no styling applied)
+    /// - `flags=` line with inline regex flags for the regexp (see `apply_regex_flags`)
     ///
     /// **Ignored:**
     /// - Any other keys are silently ignored
@@ -1009,15 +2347,35 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
 
         while let Some(line) = self.next_alphanumeric() {
             ln = line;
-            let mut regex: Option<CompiledRegex> = None;
-            let mut colors: Option<Vec<Style>> = None;
+            let mut pattern: Option<String> = None;
+            let mut flags: Option<String> = None;
+            let mut colors: Option<Vec<(Option<String>, Style)>> = None;
             let mut skip: Option<bool> = None;
             let mut count: Option<GrcatConfigEntryCount> = None;
             let mut replace: Option<String> = None;
+            let mut command: Option<String> = None;
+            let mut priority: Option<i32> = None;
+            let mut thresholds: Option<Vec<(f64, Style)>> = None;
+            let mut gradient: Option<Gradient> = None;
+            let mut sizes: Option<SizeScale> = None;
+            let mut fields: Option<Vec<(isize, Style)>> = None;
+            let mut delimiter: Option<String> = None;
+            let mut engine: Option<RegexEngine> = None;
 
             // Loop over all consecutive alphanumeric lines belonging to this entry
             // until we hit a non-alphanumeric line (entry boundary)
             loop {
+                // A style macro defined among the keys of an entry
+                if self.define(&ln) {
+                    match self.following() {
+                        Some(nline) => {
+                            ln = nline;
+                            continue;
+                        }
+                        None => break,
+                    }
+                }
+
                 // Parse the key=value pair from current line
                 let cap = re.captures(&ln).unwrap();
                 let key = cap.get(1).unwrap().as_str();
@@ -1026,23 +2384,21 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
                 // Process known keys, ignore unknown ones
                 match key {
                     "regexp" => {
-                        // Attempt to compile the regex pattern using hybrid engine
-                        // This automatically selects fast standard regex or fancy-regex
-                        match CompiledRegex::new(value) {
-                            Ok(re) => {
-                                regex = Some(re);
-                            }
-                            Err(_exc) => {
-                                // Log error and skip this entry (regex is required)
-                                eprintln!("Failed regexp: {:?}", _exc);
-                            }
-                        }
+                        // Compiled once the entry is complete, as flags= may follow it
+                        pattern = Some(value.to_string());
+                    }
+                    "flags" => {
+                        // Inline regex flags, e.g. "i" for case-insensitive matching
+                        flags = Some(value.to_string());
                     }
                     // Accept both British and American spelling, singular/plural
                     "colours" | "colors" | "colour" => {
                         // Parse comma-separated style keywords into Style vector
                         // Example: "bold red,yellow,cyan" → [Style::new().bold().red(), Style::new().yellow(), Style::new().cyan()]
-                        match styles_from_str(value) {
+                        // Named groups ("name:port=yellow") are resolved once the regexp is known
+                        match expand_style_macros(value, &self.macros)
+                            .and_then(|value| colour_specs_from_str(&value))
+                        {
                             Ok(styles) => colors = Some(styles),
                             Err(e) => {
                                 eprintln!("Error: Invalid style in configuration: {}", e);
@@ -1057,6 +2413,14 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
                             "once" => Some(GrcatConfigEntryCount::Once),
                             "more" => Some(GrcatConfigEntryCount::More),
                             "stop" => Some(GrcatConfigEntryCount::Stop),
+                            "block" => Some(GrcatConfigEntryCount::Block),
+                            "unblock" => Some(GrcatConfigEntryCount::Unblock),
+                            // Python grc repeats the count of the rule before, and
+                            // treats any other value as more
+                            "previous" if compat() == Compat::Grc => {
+                                Some(self.previous_count.clone())
+                            }
+                            _ if compat() == Compat::Grc => Some(GrcatConfigEntryCount::More),
                             _ => {
                                 eprintln!("Unknown count value: {}", value);
                                 None
@@ -1067,6 +2431,68 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
                         // Store replace string
                         replace = Some(value.to_string());
                     }
+                    "command" => {
+                        // Store hook command; whether it runs is up to the caller
+                        command = Some(value.to_string());
+                    }
+                    "thresholds" => {
+                        // Styles by severity for the captured number; the rule
+                        // keeps its static colours if they are invalid
+                        match thresholds_from_str(value) {
+                            Ok(parsed) => thresholds = Some(parsed),
+                            Err(e) => {
+                                eprintln!("Error: Invalid thresholds in configuration: {}", e)
+                            }
+                        }
+                    }
+                    "gradient" => {
+                        // Colour ramp for the captured number; like thresholds, the
+                        // rule keeps its static colours if it is invalid
+                        match gradient_from_str(value) {
+                            Ok(parsed) => gradient = Some(parsed),
+                            Err(e) => eprintln!("Error: Invalid gradient in configuration: {}", e),
+                        }
+                    }
+                    "sizes" => {
+                        // Logarithmic ramp for the captured size; like gradient,
+                        // the rule keeps its static colours if it is invalid
+                        match sizes_from_str(value) {
+                            Ok(parsed) => sizes = Some(parsed),
+                            Err(e) => eprintln!("Error: Invalid sizes in configuration: {}", e),
+                        }
+                    }
+                    "fields" => {
+                        // Styles by column; like thresholds, the rule keeps its
+                        // static colours if they are invalid
+                        match fields_from_str(value) {
+                            Ok(parsed) => fields = Some(parsed),
+                            Err(e) => eprintln!("Error: Invalid fields in configuration: {}", e),
+                        }
+                    }
+                    "delimiter" => {
+                        // Field separator; \t stands for a tab
+                        delimiter = Some(value.replace("\\t", "\t"));
+                    }
+                    "engine" => {
+                        // Regex engine for this rule: auto/fast/enhanced
+                        engine = match value.trim().parse() {
+                            Ok(engine) => Some(engine),
+                            Err(e) => {
+                                eprintln!("Error: Invalid engine in configuration: {}", e);
+                                None
+                            }
+                        };
+                    }
+                    "priority" => {
+                        // Parse priority value: a (possibly negative) integer
+                        priority = match value.trim().parse() {
+                            Ok(priority) => Some(priority),
+                            Err(_) => {
+                                eprintln!("Unknown priority value: {}, defaulting to 0", value);
+                                None
+                            }
+                        };
+                    }
                     "skip" => {
                         // Parse skip value: true/false
                         skip = match value.to_lowercase().as_str() {
@@ -1092,14 +2518,65 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
                 }
             }
 
+            let pattern = pattern.map(|p| apply_regex_flags(&p, flags.as_deref().unwrap_or("")));
+            if let Some(Ok(pattern)) = &pattern {
+                for note in translate_python_regex(pattern).1 {
+                    eprintln!("Warning: regexp {}: {}", pattern, note);
+                }
+            }
+            let regex = match pattern {
+                Some(Ok(pattern)) if self.lazy => {
+                    // Defer compilation to the first match attempt
+                    Some(CompiledRegex::lazy_with_engine(
+                        &pattern,
+                        engine.unwrap_or_default(),
+                    ))
+                }
+                Some(Ok(pattern)) => {
+                    // Attempt to compile the regex pattern using hybrid engine
+                    // This automatically selects fast standard regex or fancy-regex
+                    match CompiledRegex::with_engine(&pattern, engine.unwrap_or_default()) {
+                        Ok(re) => Some(re),
+                        Err(_exc) => {
+                            // Log error and skip this entry (regex is required)
+                            eprintln!("Failed regexp: {:?}", _exc);
+                            None
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    eprintln!("Error: Invalid flags in configuration: {}", e);
+                    None
+                }
+                None => None,
+            };
+
             // Only emit entry if we successfully parsed a regex (required)
             if let Some(regex) = regex {
+                let colors = match colors.map(|specs| resolve_colours(specs, regex.as_str())) {
+                    Some(Ok(colors)) => colors,
+                    Some(Err(e)) => {
+                        eprintln!("Error: Invalid style in configuration: {}", e);
+                        Vec::new()
+                    }
+                    None => Vec::new(), // Empty color list if not specified
+                };
+                let count = count.unwrap_or(GrcatConfigEntryCount::More); // Default to More if not specified
+                self.previous_count = count.clone();
                 return Some(GrcatConfigEntry {
                     regex,
-                    colors: colors.unwrap_or_default(), // Empty color list if not specified
-                    skip: skip.unwrap_or(false),        // Default to false if not specified
-                    count: count.unwrap_or(GrcatConfigEntryCount::More), // Default to More if not specified
+                    colors,
+                    skip: skip.unwrap_or(false), // Default to false if not specified
+                    count,
                     replace: replace.unwrap_or_default(), // Empty string if not specified
+                    command: command.unwrap_or_default(), // Empty string if not specified
+                    priority: priority.unwrap_or(0),      // Source order if not specified
+                    thresholds: thresholds.unwrap_or_default(), // Static colours if not specified
+                    gradient,
+                    sizes,
+                    fields: fields.unwrap_or_default(), // Match-based colours if not specified
+                    delimiter: delimiter.unwrap_or_default(), // Whitespace if not specified
+                    engine: engine.unwrap_or_default(), // Auto if not specified
                 });
             }
             // This entry lacked a valid regex; skip and try next entry
@@ -1107,3 +2584,75 @@ impl<A: BufRead> Iterator for GrcatConfigReader<A> {
         None // No more entries (EOF)
     }
 }
+
+/// Write `entries` in grcat config syntax, one entry per block separated by `-` lines.
+///
+/// Reading the output back with `GrcatConfigReader` yields the same rules, so it
+/// can be saved and edited as a config file. Unstyled capture groups are written
+/// as `default` to keep the positions of the styles after them.
+#[allow(dead_code)]
+pub fn write_grcat_config<W: std::io::Write>(
+    writer: &mut W,
+    entries: &[GrcatConfigEntry],
+) -> std::io::Result<()> {
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer, "-")?;
+        }
+        writeln!(writer, "regexp={}", entry.regex.as_str())?;
+        if !entry.colors.is_empty() {
+            let colours: Vec<String> = entry
+                .colors
+                .iter()
+                .map(|style| match style.keywords() {
+                    words if words.is_empty() => "default".to_string(),
+                    words => words.join(" "),
+                })
+                .collect();
+            writeln!(writer, "colours={}", colours.join(","))?;
+        }
+        let count = match entry.count {
+            GrcatConfigEntryCount::Once => "once",
+            GrcatConfigEntryCount::More => "more",
+            GrcatConfigEntryCount::Stop => "stop",
+            GrcatConfigEntryCount::Block => "block",
+            GrcatConfigEntryCount::Unblock => "unblock",
+        };
+        writeln!(writer, "count={}", count)?;
+        if !entry.replace.is_empty() {
+            writeln!(writer, "replace={}", entry.replace)?;
+        }
+        if entry.skip {
+            writeln!(writer, "skip=true")?;
+        }
+        if !entry.command.is_empty() {
+            writeln!(writer, "command={}", entry.command)?;
+        }
+        if entry.priority != 0 {
+            writeln!(writer, "priority={}", entry.priority)?;
+        }
+        if !entry.thresholds.is_empty() {
+            writeln!(
+                writer,
+                "thresholds={}",
+                thresholds_to_string(&entry.thresholds)
+            )?;
+        }
+        if let Some(gradient) = &entry.gradient {
+            writeln!(writer, "gradient={}", gradient)?;
+        }
+        if let Some(sizes) = &entry.sizes {
+            writeln!(writer, "sizes={}", sizes)?;
+        }
+        if !entry.fields.is_empty() {
+            writeln!(writer, "fields={}", fields_to_string(&entry.fields))?;
+        }
+        if !entry.delimiter.is_empty() {
+            writeln!(writer, "delimiter={}", entry.delimiter.replace('\t', "\\t"))?;
+        }
+        if entry.engine != RegexEngine::Auto {
+            writeln!(writer, "engine={}", entry.engine.as_str())?;
+        }
+    }
+    Ok(())
+}
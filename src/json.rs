@@ -0,0 +1,390 @@
+//! # json.rs - Streaming JSON highlighting for `--format-in=json`
+//!
+//! Regex rules see one line at a time and cannot tell a key from a string
+//! value once objects nest or span lines. This module tokenizes the input
+//! instead, keeping the nesting between lines, so the colorizer can paint
+//! JSON of any shape.
+//!
+//! ## Highlighting
+//!
+//! `JsonHighlighter` returns the styled tokens of each line: keys, strings,
+//! numbers, booleans and `null`. Text outside any object or array (log
+//! prefixes, messages between documents) is left alone until a `{` or `[`
+//! opens a document.
+//!
+//! ## Pretty-printing
+//!
+//! `PrettyJson` wraps a reader and re-indents every document it contains by
+//! two spaces per level, keeping other lines as they are, so `--pretty`
+//! works on streams of compact documents (`kubectl get -o json`, JSON logs).
+
+use std::collections::VecDeque;
+use std::io::{BufRead, Lines, Read};
+use std::ops::Range;
+
+use crate::style::Style;
+
+/// Style of object keys
+pub static KEY_STYLE: Style = Style::new().bold().blue();
+/// Style of string values
+pub static STRING_STYLE: Style = Style::new().green();
+/// Style of numbers
+pub static NUMBER_STYLE: Style = Style::new().cyan();
+/// Style of `true` and `false`
+pub static BOOLEAN_STYLE: Style = Style::new().yellow();
+/// Style of `null`
+pub static NULL_STYLE: Style = Style::new().magenta();
+
+/// A token of JSON text, as found by `next_token()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Open(u8),
+    Close(u8),
+    Colon,
+    Comma,
+    String,
+    Number,
+    Boolean,
+    Null,
+    /// Anything else (a bare word, a stray character)
+    Other,
+}
+
+/// Find the token of `line` that starts at or after `pos`, skipping
+/// whitespace. Returns the token and its byte range, or `None` at the end.
+fn next_token(line: &str, pos: usize) -> Option<(Token, Range<usize>)> {
+    let bytes = line.as_bytes();
+    let start = pos + bytes[pos..].iter().position(|b| !b.is_ascii_whitespace())?;
+    let end = |len: usize| start + len;
+    let token = match bytes[start] {
+        open @ (b'{' | b'[') => (Token::Open(open), start..end(1)),
+        close @ (b'}' | b']') => (Token::Close(close), start..end(1)),
+        b':' => (Token::Colon, start..end(1)),
+        b',' => (Token::Comma, start..end(1)),
+        b'"' => (Token::String, start..string_end(line, start)),
+        b'-' | b'0'..=b'9' => {
+            let len = bytes[start..]
+                .iter()
+                .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                .unwrap_or(bytes.len() - start);
+            (Token::Number, start..end(len))
+        }
+        b if b.is_ascii_alphabetic() => {
+            let len = bytes[start..]
+                .iter()
+                .position(|b| !b.is_ascii_alphanumeric())
+                .unwrap_or(bytes.len() - start);
+            let token = match &line[start..end(len)] {
+                "true" | "false" => Token::Boolean,
+                "null" => Token::Null,
+                _ => Token::Other,
+            };
+            (token, start..end(len))
+        }
+        _ => {
+            let len = line[start..].chars().next().map_or(1, char::len_utf8);
+            (Token::Other, start..end(len))
+        }
+    };
+    Some(token)
+}
+
+/// End of the string opening at `start`: just past its closing quote, or the
+/// end of `line` if it is not closed there.
+fn string_end(line: &str, start: usize) -> usize {
+    let bytes = line.as_bytes();
+    let mut pos = start + 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => pos += 2,
+            b'"' => return pos + 1,
+            _ => pos += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Position of the first `{` or `[` of `line` at or after `pos`.
+fn document_start(line: &str, pos: usize) -> Option<usize> {
+    line[pos..].find(['{', '[']).map(|index| pos + index)
+}
+
+/// Highlights JSON line by line, remembering the open objects and arrays
+/// between lines.
+#[derive(Debug, Default)]
+pub struct JsonHighlighter {
+    /// Open containers, innermost last (`b'{'` or `b'['`)
+    stack: Vec<u8>,
+    /// Whether the next string in the innermost object is a key
+    expect_key: bool,
+}
+
+impl JsonHighlighter {
+    /// A highlighter outside of any document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Byte ranges and styles of the tokens of the next input line.
+    pub fn style_ranges(&mut self, line: &str) -> Vec<(Range<usize>, &'static Style)> {
+        let mut ranges = Vec::new();
+        let mut pos = 0;
+        loop {
+            // Outside a document, only an opening bracket matters
+            if self.stack.is_empty() {
+                match document_start(line, pos) {
+                    Some(start) => pos = start,
+                    None => break,
+                }
+            }
+            let Some((token, range)) = next_token(line, pos) else {
+                break;
+            };
+            pos = range.end;
+            let style = match token {
+                Token::Open(open) => {
+                    self.stack.push(open);
+                    self.expect_key = open == b'{';
+                    None
+                }
+                Token::Close(_) => {
+                    self.stack.pop();
+                    self.expect_key = false;
+                    None
+                }
+                Token::Colon => {
+                    self.expect_key = false;
+                    None
+                }
+                Token::Comma => {
+                    self.expect_key = self.stack.last() == Some(&b'{');
+                    None
+                }
+                Token::String if self.expect_key => Some(&KEY_STYLE),
+                Token::String => Some(&STRING_STYLE),
+                Token::Number => Some(&NUMBER_STYLE),
+                Token::Boolean => Some(&BOOLEAN_STYLE),
+                Token::Null => Some(&NULL_STYLE),
+                Token::Other => None,
+            };
+            if let Some(style) = style {
+                ranges.push((range, style));
+            }
+        }
+        ranges
+    }
+}
+
+/// Re-indents the JSON documents of the lines fed to it, two spaces per level.
+#[derive(Debug, Default)]
+struct JsonPrinter {
+    depth: usize,
+    /// A container was just opened: it is printed `{}` if closed right away
+    pending_open: bool,
+}
+
+impl JsonPrinter {
+    /// Append the pretty-printed form of input `line` to `out`, one output
+    /// line per `\n`. Lines outside a document are copied unchanged.
+    fn push_line(&mut self, line: &str, out: &mut String) {
+        let mut pos = 0;
+        if self.depth == 0 {
+            match document_start(line, 0) {
+                // Text before a document on the same line is kept as it is
+                Some(start) if line[..start].trim().is_empty() => pos = start,
+                _ => {
+                    out.push_str(line);
+                    out.push('\n');
+                    return;
+                }
+            }
+        }
+        while let Some((token, range)) = next_token(line, pos) {
+            pos = range.end;
+            match token {
+                Token::Open(_) => {
+                    self.break_pending(out);
+                    out.push_str(&line[range]);
+                    self.depth += 1;
+                    self.pending_open = true;
+                }
+                Token::Close(_) => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if !std::mem::take(&mut self.pending_open) {
+                        self.newline(out);
+                    }
+                    out.push_str(&line[range]);
+                    if self.depth == 0 {
+                        // The rest of the line is another document or plain text
+                        out.push('\n');
+                        let rest = &line[pos..];
+                        if !rest.trim().is_empty() {
+                            self.push_line(rest.trim_start(), out);
+                        }
+                        return;
+                    }
+                }
+                Token::Colon => out.push_str(": "),
+                Token::Comma => {
+                    out.push(',');
+                    self.newline(out);
+                }
+                _ => {
+                    self.break_pending(out);
+                    out.push_str(&line[range]);
+                }
+            }
+        }
+    }
+
+    /// Start a new output line indented to the current depth.
+    fn newline(&self, out: &mut String) {
+        out.push('\n');
+        out.extend(std::iter::repeat_n("  ", self.depth));
+    }
+
+    /// Move the first member of a just-opened container onto its own line.
+    fn break_pending(&mut self, out: &mut String) {
+        if std::mem::take(&mut self.pending_open) {
+            self.newline(out);
+        }
+    }
+
+    /// End output cut off inside a document with a newline.
+    fn finish(&mut self, out: &mut String) {
+        if self.depth > 0 {
+            out.push('\n');
+            self.depth = 0;
+            self.pending_open = false;
+        }
+    }
+}
+
+/// A reader yielding the lines of `inner` with their JSON documents
+/// pretty-printed (`--pretty`).
+pub struct PrettyJson<R: BufRead> {
+    lines: Lines<R>,
+    printer: JsonPrinter,
+    buffer: VecDeque<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> PrettyJson<R> {
+    /// Pretty-print the documents read from `inner`.
+    pub fn new(inner: R) -> Self {
+        PrettyJson {
+            lines: inner.lines(),
+            printer: JsonPrinter::default(),
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for PrettyJson<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // Refill a line at a time, so output keeps pace with the input
+        while self.buffer.is_empty() && !self.done {
+            let mut out = String::new();
+            match self.lines.next() {
+                Some(line) => self.printer.push_line(&line?, &mut out),
+                None => {
+                    self.printer.finish(&mut out);
+                    self.done = true;
+                }
+            }
+            self.buffer.extend(out.as_bytes());
+        }
+        self.buffer.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled<'a>(line: &'a str, highlighter: &mut JsonHighlighter) -> Vec<(&'a str, Style)> {
+        highlighter
+            .style_ranges(line)
+            .into_iter()
+            .map(|(range, style)| (&line[range], *style))
+            .collect()
+    }
+
+    fn pretty(input: &str) -> String {
+        let mut out = String::new();
+        PrettyJson::new(input.as_bytes())
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_tokens_are_styled_by_kind() {
+        let mut highlighter = JsonHighlighter::new();
+        assert_eq!(
+            styled(
+                r#"{"a": "x", "n": -1.5e3, "ok": true, "v": null, "l": ["k", false]}"#,
+                &mut highlighter
+            ),
+            [
+                (r#""a""#, KEY_STYLE),
+                (r#""x""#, STRING_STYLE),
+                (r#""n""#, KEY_STYLE),
+                ("-1.5e3", NUMBER_STYLE),
+                (r#""ok""#, KEY_STYLE),
+                ("true", BOOLEAN_STYLE),
+                (r#""v""#, KEY_STYLE),
+                ("null", NULL_STYLE),
+                (r#""l""#, KEY_STYLE),
+                (r#""k""#, STRING_STYLE),
+                ("false", BOOLEAN_STYLE),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nesting_carries_over_lines() {
+        let mut highlighter = JsonHighlighter::new();
+        assert_eq!(styled("{", &mut highlighter), []);
+        assert_eq!(
+            styled(r#"  "say": "a \"quoted\" word","#, &mut highlighter),
+            [
+                (r#""say""#, KEY_STYLE),
+                (r#""a \"quoted\" word""#, STRING_STYLE)
+            ]
+        );
+        assert_eq!(
+            styled(r#"  "list": ["#, &mut highlighter),
+            [(r#""list""#, KEY_STYLE)]
+        );
+        assert_eq!(
+            styled(r#"    "item","#, &mut highlighter),
+            [(r#""item""#, STRING_STYLE)]
+        );
+        assert_eq!(styled("  ]}", &mut highlighter), []);
+        // Text between documents is not JSON
+        assert_eq!(styled("true 42 \"x\"", &mut highlighter), []);
+        assert_eq!(
+            styled(r#"INFO {"id": 7}"#, &mut highlighter),
+            [(r#""id""#, KEY_STYLE), ("7", NUMBER_STYLE)]
+        );
+    }
+
+    #[test]
+    fn test_pretty_indents_documents() {
+        assert_eq!(
+            pretty("{\"a\":1,\"b\":[true,{}],\"c\":{\"d\":\"x, y\"}}\nplain text\n[]\n"),
+            "{\n  \"a\": 1,\n  \"b\": [\n    true,\n    {}\n  ],\n  \"c\": {\n    \"d\": \"x, y\"\n  }\n}\nplain text\n[]\n"
+        );
+        // Documents spanning lines, and several on one line
+        assert_eq!(
+            pretty("{\n\"a\":\n  [1,\n2]}\n{\"b\":2} {\"c\":3}\n"),
+            "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n{\n  \"b\": 2\n}\n{\n  \"c\": 3\n}\n"
+        );
+        // A log prefix is left alone and so is its document
+        assert_eq!(pretty("INFO {\"a\":1}\n"), "INFO {\"a\":1}\n");
+        // Input cut off inside a document still ends with a newline
+        assert_eq!(pretty("{\"a\":[1"), "{\n  \"a\": [\n    1\n");
+    }
+}
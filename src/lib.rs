@@ -8,12 +8,23 @@
 //! The library is organized into the following components:
 //!
 //! - **ColorMode**: Controls whether color output is enabled (On/Off/Auto)
-//! - **Configuration Loading**: Functions to load colorization rules from config files
+//! - **OutputFormat**: Selects ANSI text or JSON Lines output
+//! - **InputFormat**: Marks the input as CSV/TSV (columns are coloured) or JSON (tokens are)
+//! - **Colorizer**: Rules plus the settings above, for embedding rgrc in other tools
+//! - **Configuration Loading**: Functions to load colorization rules from config files,
+//!   and `ConfigLoader` to load them from explicit directories or in-memory configs
 //! - **Submodules**:
 //!   - `style`: Lightweight ANSI styling (replaces console crate)
 //!   - `colorizer`: Text colorization engine
 //!   - `grc`: Config file parsing with hybrid regex engine
 //!   - `enhanced_regex`: Custom lookaround implementation (used when fancy feature is disabled)
+//!   - `error`: `RgrcError`, the error type of fallible library calls, with exit codes
+//!   - `json`: Streaming JSON highlighter and pretty-printer behind `--format-in=json`
+//!   - `loglevel`: Built-in log severity rules referenced with `loglevel=MODE`
+//!   - `prefilter`: Required-literal extraction used to skip rules that cannot match
+//!   - `repl`: Interactive rule tester behind `rgrc repl`
+//!   - `async_colorizer`: `Colorizer::colorize_async` for tokio streams (`tokio` feature)
+//!   - `ffi`: C interface declared in `include/rgrc.h` (`ffi` feature)
 //!
 //! ## Features
 //!
@@ -21,6 +32,19 @@
 //! - **fancy-regex** (default): Use battle-tested fancy-regex for enhanced patterns
 //!   - Disable for smaller binary: `cargo build --no-default-features --features=embed-configs`
 //! - **timetrace**: Enable timing trace for performance profiling
+//! - **regex-set**: Screen each line with a `regex::RegexSet` of all Fast rules so only
+//!   rules that can match are run individually (see `prefilter::RuleSet`)
+//! - **compress-configs**: Embed the configs zstd-compressed; they are decompressed when the
+//!   cache directory is written (see `embedded_config`)
+//! - **config-cache**: Store parsed grcat configs in the cache directory, keyed by a hash
+//!   of their text, so later runs skip parsing (see `config_cache`)
+//! - **dfa-cache**: Persist DFAs of Fast rules in the cache directory and screen lines with
+//!   them before a rule's regex is compiled (see `dfa_cache`)
+//! - **tui**: Interactive config previewer behind `rgrv preview` (see `preview`)
+//! - **toml-configs**: Load `conf.NAME.toml` configs in preference to `conf.NAME`, and
+//!   convert between the formats with `rgrv convert` (see `toml_config`)
+//! - **tokio**: Colorize an `AsyncRead` into an `AsyncWrite` (see `async_colorizer`)
+//! - **ffi**: Export a C ABI for linking the engine from other languages (see `ffi`)
 //!
 //! ## Regex Engine
 //!
@@ -40,17 +64,49 @@
 //!
 //! // Load colorization rules for a specific command
 //! let rules = load_config("~/.config/rgrc/grc.conf", "ping");
+//!
+//! // Colorize with the rules `rgrc ping` would use
+//! let colorizer = rgrc::Colorizer::builder()
+//!     .rules_for_command("ping")
+//!     .color_mode(color_mode)
+//!     .build();
+//! colorizer.colorize(&mut std::io::stdin(), &mut std::io::stdout())?;
 //! ```
 
 pub mod style;
 // Re-export Style for easier access
+pub use error::RgrcError;
 pub use style::Style;
 
+pub mod aliases;
 pub mod args;
+#[cfg(feature = "tokio")]
+pub mod async_colorizer;
 pub mod buffer;
 pub mod colorizer;
+#[cfg(feature = "config-cache")]
+pub mod config_cache;
+#[cfg(feature = "dfa-cache")]
+pub mod dfa_cache;
 pub mod enhanced_regex;
+pub mod error;
+pub mod fetch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod grc;
+pub mod json;
+pub mod loglevel;
+pub mod notify;
+pub mod prefilter;
+#[cfg(feature = "tui")]
+pub mod preview;
+pub mod repl;
+pub mod scaffold;
+pub mod self_update;
+pub mod streams;
+pub mod timestamps;
+#[cfg(feature = "toml-configs")]
+pub mod toml_config;
 pub mod utils;
 
 use std::fs::File;
@@ -69,10 +125,6 @@ fn expand_tilde(path: &str) -> String {
     path.to_string()
 }
 
-// Version constant for cache directory
-#[cfg(feature = "embed-configs")]
-const VERSION: &str = env!("CARGO_PKG_VERSION");
-
 // Use generated `embedded_configs.rs` (created by build.rs) so the list of
 // embedded files is derived from the `share` directory instead of being hard-coded.
 #[cfg(feature = "embed-configs")]
@@ -84,12 +136,37 @@ include!(concat!(env!("OUT_DIR"), "/embedded_configs.rs"));
 #[cfg(feature = "embed-configs")]
 pub const EMBEDDED_GRC_CONF: &str = include_str!("../etc/rgrc.conf");
 
+/// Return the text of the embedded config `name` (e.g. `"conf.ping"`).
+///
+/// With `compress-configs` the file is decompressed on each call; callers
+/// normally go through the cache directory, which is written once.
+#[cfg(feature = "embed-configs")]
+pub fn embedded_config(name: &str) -> Option<std::borrow::Cow<'static, str>> {
+    let (_, content) = EMBEDDED_CONFIGS.iter().find(|(file, _)| *file == name)?;
+    decode_embedded(content)
+}
+
+#[cfg(all(feature = "embed-configs", not(feature = "compress-configs")))]
+fn decode_embedded(content: &'static str) -> Option<std::borrow::Cow<'static, str>> {
+    Some(std::borrow::Cow::Borrowed(content))
+}
+
+#[cfg(feature = "compress-configs")]
+fn decode_embedded(content: &'static [u8]) -> Option<std::borrow::Cow<'static, str>> {
+    let text = zstd::decode_all(content).ok()?;
+    String::from_utf8(text).ok().map(std::borrow::Cow::Owned)
+}
+
 /// Flush and rebuild the cache directory (embed-configs only)
 ///
-/// This function removes the existing cache directory and rebuilds it with
-/// embedded configuration files. Returns the path to the rebuilt cache directory
+/// This function rebuilds the cache directory from the embedded configuration
+/// files, discarding local edits. Returns the path to the rebuilt cache directory
 /// and the number of configuration files created.
 ///
+/// The new cache is written to a staging directory next to the old one and then
+/// swapped in, all while holding the cache lock, so concurrent invocations never
+/// read a half-written cache.
+///
 /// # Returns
 ///
 /// Returns `Some((cache_path, config_count))` on success, `None` on failure.
@@ -97,17 +174,31 @@ pub const EMBEDDED_GRC_CONF: &str = include_str!("../etc/rgrc.conf");
 pub fn flush_and_rebuild_cache() -> Option<(std::path::PathBuf, usize)> {
     // Get cache directory path
     let cache_dir = get_cache_dir()?;
+    let _lock = CacheLock::acquire(&cache_dir)?;
+
+    // Build the new cache in a staging directory
+    let name = cache_dir.file_name()?.to_string_lossy().into_owned();
+    let sibling =
+        |tag: &str| cache_dir.with_file_name(format!("{}-{}-{}", name, tag, std::process::id()));
+    let staging = sibling("staging");
+    let _ = std::fs::remove_dir_all(&staging);
+    if !populate_cache(&staging) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return None;
+    }
 
-    // Remove existing cache directory if it exists
+    // Swap it in place of the existing cache directory
     if cache_dir.exists() {
-        std::fs::remove_dir_all(&cache_dir).ok()?;
+        let old = sibling("old");
+        std::fs::rename(&cache_dir, &old).ok()?;
+        std::fs::rename(&staging, &cache_dir).ok()?;
+        let _ = std::fs::remove_dir_all(&old);
+    } else {
+        std::fs::rename(&staging, &cache_dir).ok()?;
     }
 
-    // Rebuild cache
-    let new_cache_dir = ensure_cache_populated()?;
-
     // Count the number of config files
-    let conf_dir = new_cache_dir.join("conf");
+    let conf_dir = cache_dir.join("conf");
     let config_count = if conf_dir.exists() {
         std::fs::read_dir(&conf_dir)
             .map(|entries| entries.count())
@@ -116,57 +207,310 @@ pub fn flush_and_rebuild_cache() -> Option<(std::path::PathBuf, usize)> {
         0
     };
 
-    Some((new_cache_dir, config_count))
+    Some((cache_dir, config_count))
 }
 
-// Helper function to get cache directory path
+/// How long to wait for another process to finish rebuilding the cache.
 #[cfg(feature = "embed-configs")]
-fn get_cache_dir() -> Option<std::path::PathBuf> {
-    std::env::var("HOME")
-        .ok()
-        .map(std::path::PathBuf::from)
-        .map(|h| h.join(".cache").join("rgrc").join(VERSION))
+const CACHE_LOCK_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Age after which a cache lock is assumed to be left behind by a crashed process.
+#[cfg(feature = "embed-configs")]
+const CACHE_LOCK_STALE: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Lock file serializing cache rebuilds across concurrent rgrc processes.
+///
+/// The lock is a `<cache dir>.lock` file created exclusively next to the cache
+/// directory and removed on drop. A lock older than `CACHE_LOCK_STALE` is
+/// taken over, so a crashed rebuild cannot block the cache forever.
+#[cfg(feature = "embed-configs")]
+struct CacheLock {
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "embed-configs")]
+impl CacheLock {
+    /// Take the lock for `cache_dir`, waiting up to `CACHE_LOCK_WAIT` for it.
+    fn acquire(cache_dir: &std::path::Path) -> Option<Self> {
+        use std::io::Write;
+
+        let mut name = cache_dir.file_name()?.to_os_string();
+        name.push(".lock");
+        let path = cache_dir.with_file_name(name);
+        std::fs::create_dir_all(path.parent()?).ok()?;
+
+        let deadline = std::time::Instant::now() + CACHE_LOCK_WAIT;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Some(CacheLock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if Self::is_stale(&path) {
+                        Self::remove_stale(&path);
+                        continue;
+                    }
+                    if std::time::Instant::now() >= deadline {
+                        return None;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Whether the lock at `path` is older than `CACHE_LOCK_STALE`.
+    fn is_stale(path: &std::path::Path) -> bool {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > CACHE_LOCK_STALE)
+    }
+
+    /// Remove the stale lock at `path`, unless another process took it over first.
+    ///
+    /// The lock is renamed to a name of its own before it is removed, so of two
+    /// processes that found it stale only one moves it away. The other may move
+    /// the fresh lock the first one created meanwhile; seeing that it is not
+    /// stale, it puts it back.
+    fn remove_stale(path: &std::path::Path) {
+        static TAKEOVERS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        let mut moved = path.as_os_str().to_os_string();
+        moved.push(format!(
+            ".{}-{}",
+            std::process::id(),
+            TAKEOVERS.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        let moved = std::path::PathBuf::from(moved);
+        if std::fs::rename(path, &moved).is_err() {
+            return;
+        }
+        if !Self::is_stale(&moved) {
+            // Fails only if yet another lock was created in the meantime
+            let _ = std::fs::hard_link(&moved, path);
+        }
+        let _ = std::fs::remove_file(&moved);
+    }
+}
+
+#[cfg(feature = "embed-configs")]
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Cache directory given with `--cache-dir`, taking precedence over the environment.
+#[cfg(feature = "embed-configs")]
+static CACHE_DIR_OVERRIDE: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+
+/// Use `path` as the cache directory for the rest of the process (`--cache-dir`).
+///
+/// Only the first call has an effect; it must happen before any cache access.
+#[cfg(feature = "embed-configs")]
+pub fn set_cache_dir<P: AsRef<std::path::Path>>(path: P) {
+    let path = expand_tilde(&path.as_ref().to_string_lossy());
+    let _ = CACHE_DIR_OVERRIDE.set(std::path::PathBuf::from(path));
+}
+
+/// Resolve the cache directory.
+///
+/// In order of precedence: `--cache-dir` (see `set_cache_dir`), `$RGRC_CACHE_DIR`,
+/// `$XDG_CACHE_HOME/rgrc` and finally `~/.cache/rgrc`. Empty variables are ignored,
+/// as is a relative `XDG_CACHE_HOME` (the XDG spec requires it to be absolute).
+#[cfg(feature = "embed-configs")]
+pub fn get_cache_dir() -> Option<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    if let Some(dir) = CACHE_DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    if let Some(dir) = var("RGRC_CACHE_DIR") {
+        return Some(PathBuf::from(expand_tilde(&dir)));
+    }
+    if let Some(xdg) = var("XDG_CACHE_HOME").map(PathBuf::from)
+        && xdg.is_absolute()
+    {
+        return Some(xdg.join("rgrc"));
+    }
+    var("HOME").map(|h| PathBuf::from(h).join(".cache").join("rgrc"))
+}
+
+/// Name of the file recording what was written to the cache directory.
+#[cfg(feature = "embed-configs")]
+const CACHE_MANIFEST: &str = "manifest";
+
+/// Contents of the cache manifest.
+///
+/// For every embedded file it records the hash of the embedded data and the
+/// hash of the text written to the cache. A changed embedded hash marks the
+/// cached copy stale; a cached copy whose hash no longer matches what was
+/// written has been edited locally and is left alone as a user override.
+#[cfg(feature = "embed-configs")]
+#[derive(Debug, Default, PartialEq)]
+struct CacheManifest {
+    /// Hash over the names and embedded hashes of all files
+    fingerprint: u64,
+    /// `(embedded hash, written hash)` per file name
+    files: std::collections::BTreeMap<String, (u64, u64)>,
+}
+
+#[cfg(feature = "embed-configs")]
+impl CacheManifest {
+    fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != "# rgrc cache manifest v1" {
+            return None;
+        }
+        let fingerprint = lines.next()?.strip_prefix("fingerprint ")?;
+        let mut manifest = CacheManifest {
+            fingerprint: u64::from_str_radix(fingerprint, 16).ok()?,
+            ..Default::default()
+        };
+        for line in lines {
+            let mut fields = line.splitn(3, ' ');
+            let embedded = u64::from_str_radix(fields.next()?, 16).ok()?;
+            let written = u64::from_str_radix(fields.next()?, 16).ok()?;
+            manifest
+                .files
+                .insert(fields.next()?.to_string(), (embedded, written));
+        }
+        Some(manifest)
+    }
+
+    fn render(&self) -> String {
+        let mut text = format!(
+            "# rgrc cache manifest v1\nfingerprint {:016x}\n",
+            self.fingerprint
+        );
+        for (name, (embedded, written)) in &self.files {
+            text.push_str(&format!("{:016x} {:016x} {}\n", embedded, written, name));
+        }
+        text
+    }
 }
 
 // Ensure cache directory exists and populate it with embedded configs
 #[cfg(feature = "embed-configs")]
 fn ensure_cache_populated() -> Option<std::path::PathBuf> {
     let cache_dir = get_cache_dir()?;
+    populate_cache(&cache_dir).then_some(cache_dir)
+}
+
+// Bring `cache_dir` up to date with the embedded configs; false if it is unusable.
+//
+// Only files whose embedded version changed (or that are missing) are rewritten;
+// see `CacheManifest`. Each is written through a temporary file renamed over it,
+// since rgrc processes read the cache without taking `CacheLock`.
+#[cfg(feature = "embed-configs")]
+fn populate_cache(cache_dir: &std::path::Path) -> bool {
+    use utils::fnv1a;
 
-    // Check if cache directory exists and appears populated (rgrc.conf + at least one conf file)
     let grc_conf_path = cache_dir.join("rgrc.conf");
     let conf_dir = cache_dir.join("conf");
-    if grc_conf_path.exists() {
-        // If conf directory exists and contains at least one file, we assume cache is populated
-        if conf_dir.exists()
-            && let Ok(mut entries) = std::fs::read_dir(&conf_dir)
-            && entries.next().is_some()
-        {
-            return Some(cache_dir);
-        }
-        // rgrc.conf exists but conf dir missing or empty — fall through and repopulate
+    let manifest_path = cache_dir.join(CACHE_MANIFEST);
+
+    // Every embedded file with its cache location, embedded (possibly compressed)
+    // data and index in EMBEDDED_CONFIGS (None for rgrc.conf)
+    let mut embedded: Vec<(&str, std::path::PathBuf, &[u8], Option<usize>)> = vec![(
+        "rgrc.conf",
+        grc_conf_path.clone(),
+        EMBEDDED_GRC_CONF.as_bytes(),
+        None,
+    )];
+    for (index, (filename, content)) in EMBEDDED_CONFIGS.iter().enumerate() {
+        embedded.push((
+            filename,
+            conf_dir.join(filename),
+            AsRef::<[u8]>::as_ref(content),
+            Some(index),
+        ));
     }
 
-    // Create cache directory structure
-    std::fs::create_dir_all(&cache_dir).ok()?;
-    let conf_dir = cache_dir.join("conf");
-    std::fs::create_dir_all(&conf_dir).ok()?;
+    let hashes: Vec<u64> = embedded.iter().map(|(_, _, data, _)| fnv1a(data)).collect();
+    let mut listing = String::new();
+    for ((name, ..), hash) in embedded.iter().zip(&hashes) {
+        listing.push_str(&format!("{:016x} {}\n", hash, name));
+    }
+    let fingerprint = fnv1a(listing.as_bytes());
+
+    let read_manifest = || {
+        std::fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|text| CacheManifest::parse(&text))
+            .unwrap_or_default()
+    };
 
-    // Write rgrc.conf
-    std::fs::write(&grc_conf_path, EMBEDDED_GRC_CONF).ok()?;
+    // Fast path: nothing embedded changed since the cache was written
+    let previous = read_manifest();
+    if previous.fingerprint == fingerprint && grc_conf_path.exists() {
+        return true;
+    }
+
+    // Only one process updates the cache at a time; the others wait for it
+    let Some(_lock) = CacheLock::acquire(cache_dir) else {
+        return false;
+    };
+    let previous = read_manifest();
+    if previous.fingerprint == fingerprint && grc_conf_path.exists() {
+        return true;
+    }
+
+    // Create cache directory structure
+    if std::fs::create_dir_all(&conf_dir).is_err() {
+        return false;
+    }
 
-    // Write all embedded configs
     // Don't fail the entire cache population if a single file fails to write
-    let mut any_success = false;
-    for (filename, content) in EMBEDDED_CONFIGS {
-        let file_path = conf_dir.join(filename);
-        if std::fs::write(file_path, content).is_ok() {
-            any_success = true;
+    let mut manifest = CacheManifest {
+        fingerprint,
+        ..Default::default()
+    };
+    let mut any_config = false;
+    for ((name, path, _, index), embedded_hash) in embedded.into_iter().zip(hashes) {
+        let on_disk = std::fs::read(&path).ok().map(|bytes| fnv1a(&bytes));
+        if let (Some(&(old_embedded, written)), Some(disk)) = (previous.files.get(name), on_disk)
+            && (disk != written || old_embedded == embedded_hash)
+        {
+            // Up to date, or edited locally: keep the cached copy
+            manifest
+                .files
+                .insert(name.to_string(), (old_embedded, written));
+            any_config |= index.is_some();
+            continue;
+        }
+
+        let text = match index {
+            None => Some(std::borrow::Cow::Borrowed(EMBEDDED_GRC_CONF)),
+            Some(index) => decode_embedded(EMBEDDED_CONFIGS[index].1),
+        };
+        let Some(text) = text else {
+            continue;
+        };
+        if utils::write_atomic(&path, text.as_bytes()).is_ok() {
+            manifest
+                .files
+                .insert(name.to_string(), (embedded_hash, fnv1a(text.as_bytes())));
+            any_config |= name != "rgrc.conf";
         }
     }
 
-    // Only return Some if we successfully wrote at least one config file
-    if any_success { Some(cache_dir) } else { None }
+    // rgrc.conf plus at least one config file make a usable cache
+    if !grc_conf_path.exists() || !any_config {
+        return false;
+    }
+    let _ = utils::write_atomic(&manifest_path, manifest.render().as_bytes());
+    true
 }
 
 /// Control whether colored output should be enabled for this run.
@@ -243,9 +587,107 @@ impl FromStr for ColorMode {
     }
 }
 
-/// Standard resource paths searched for grcat config files.
+/// Output format produced by the colorizer.
+///
+/// - **Ansi**: Styled text with ANSI escape sequences (the default)
+/// - **Jsonl**: One JSON object per input line describing the raw text and the
+///   spans matched by each rule, for scripts and log shippers
+/// - **Markup**: Plain text with inline style tags, `[bold red]text[/]` (a literal `[`
+///   is written as `[[`), readable in diffs of expected output (`rgrv test`)
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use rgrc::OutputFormat;
 ///
-/// These paths are searched **in order** when looking for colorization rule files
+/// assert_eq!(OutputFormat::from_str("jsonl"), Ok(OutputFormat::Jsonl));
+/// assert!(OutputFormat::from_str("xml").is_err());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OutputFormat {
+    /// ANSI-colored text
+    #[default]
+    Ansi,
+    /// JSON Lines: `{"text": ..., "matches": [...]}` per input line
+    Jsonl,
+    /// Text with `[style]...[/]` tags around styled spans
+    Markup,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(OutputFormat::Ansi),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            "markup" => Ok(OutputFormat::Markup),
+            _ => Err(format!("Invalid output format: {}", s)),
+        }
+    }
+}
+
+/// Shape of the input read by the colorizer (`--format-in`).
+///
+/// - **Text**: Free-form lines, styled by the rules only (the default)
+/// - **Csv**: Comma-separated values; each column is coloured, under the rule matches
+/// - **Tsv**: Tab-separated values, coloured like CSV
+/// - **Json**: JSON documents; keys, strings, numbers and literals are coloured
+///   by a tokenizer that follows the nesting across lines (see `json`)
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use rgrc::InputFormat;
+///
+/// assert_eq!(InputFormat::from_str("tsv"), Ok(InputFormat::Tsv));
+/// assert_eq!(InputFormat::Csv.delimiter(), Some(","));
+/// assert!(InputFormat::from_str("xlsx").is_err());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum InputFormat {
+    /// Free-form text
+    #[default]
+    Text,
+    /// Comma-separated values
+    Csv,
+    /// Tab-separated values
+    Tsv,
+    /// JSON documents
+    Json,
+}
+
+impl InputFormat {
+    /// The column delimiter of a delimited format, `None` for text.
+    pub fn delimiter(self) -> Option<&'static str> {
+        match self {
+            InputFormat::Text | InputFormat::Json => None,
+            InputFormat::Csv => Some(","),
+            InputFormat::Tsv => Some("\t"),
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(InputFormat::Text),
+            "csv" => Ok(InputFormat::Csv),
+            "tsv" => Ok(InputFormat::Tsv),
+            "json" => Ok(InputFormat::Json),
+            _ => Err(format!("Invalid input format: {}", s)),
+        }
+    }
+}
+
+/// Default resource paths searched for grcat config files.
+///
+/// This is the search path `resource_paths()` produces when `RGRC_CONFIG_DIR`
+/// and the XDG variables are unset. These paths are searched **in order** when looking for colorization rule files
 /// (grcat.conf) that define how to colorize output for specific commands.
 /// **The search stops at the first matching file found.**
 ///
@@ -286,12 +728,147 @@ pub const RESOURCE_PATHS: &[&str] = &[
     "~/.local/share/rgrc",
     "/usr/local/share/rgrc",
     "/usr/share/rgrc",
+    "~/.grc",
     "~/.config/grc",
     "~/.local/share/grc",
     "/usr/local/share/grc",
     "/usr/share/grc",
 ];
 
+/// XDG base directories, resolved with the defaults of the XDG Base Directory
+/// Specification for unset variables.
+///
+/// Relative paths are ignored, as the specification requires.
+struct XdgDirs {
+    config_home: Option<std::path::PathBuf>,
+    config_dirs: Vec<std::path::PathBuf>,
+    data_home: Option<std::path::PathBuf>,
+    data_dirs: Vec<std::path::PathBuf>,
+}
+
+impl XdgDirs {
+    /// Resolve the directories from the variables returned by `env`.
+    fn new(env: &impl Fn(&str) -> Option<String>) -> Self {
+        use std::path::PathBuf;
+
+        let home = env("HOME").filter(|h| !h.is_empty()).map(PathBuf::from);
+        let dir = |name: &str, default: &str| {
+            env(name)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .or_else(|| home.as_ref().map(|h| h.join(default)))
+        };
+        let dirs = |name: &str, default: &str| {
+            let list: Vec<PathBuf> = env(name)
+                .unwrap_or_default()
+                .split(':')
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .collect();
+            if list.is_empty() {
+                default.split(':').map(PathBuf::from).collect()
+            } else {
+                list
+            }
+        };
+
+        XdgDirs {
+            config_home: dir("XDG_CONFIG_HOME", ".config"),
+            config_dirs: dirs("XDG_CONFIG_DIRS", "/etc/xdg"),
+            data_home: dir("XDG_DATA_HOME", ".local/share"),
+            data_dirs: dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"),
+        }
+    }
+}
+
+/// Path in the variable `name`, with a leading `~/` expanded; `None` when unset or empty.
+fn env_path(env: &impl Fn(&str) -> Option<String>, name: &str) -> Option<std::path::PathBuf> {
+    let path = env(name).filter(|path| !path.is_empty())?;
+    match (path.strip_prefix("~/"), env("HOME")) {
+        (Some(rest), Some(home)) => Some(std::path::Path::new(&home).join(rest)),
+        _ => Some(path.into()),
+    }
+}
+
+/// Directory named by `RGRC_CONFIG_DIR`, searched before every other location.
+fn rgrc_config_dir(env: &impl Fn(&str) -> Option<String>) -> Option<std::path::PathBuf> {
+    env_path(env, "RGRC_CONFIG_DIR")
+}
+
+/// The directory user configs are installed into (`rgrc fetch-configs`):
+/// `$RGRC_CONFIG_DIR` when set, else `$XDG_CONFIG_HOME/rgrc`.
+pub fn user_config_dir() -> Option<std::path::PathBuf> {
+    rgrc_config_dir(&process_env).or_else(|| {
+        XdgDirs::new(&process_env)
+            .config_home
+            .map(|dir| dir.join("rgrc"))
+    })
+}
+
+/// grc.conf given with `--conf`, taking precedence over `RGRC_CONF`.
+static GRC_CONF_OVERRIDE: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+
+/// Use `path` as the command→config mapping file for the rest of the process (`--conf`).
+///
+/// The file is searched before every other `config_paths()` entry. Only the
+/// first call has an effect. Fails with `RgrcError::ConfigNotFound` when `path`
+/// is not a file.
+pub fn set_grc_conf<P: AsRef<std::path::Path>>(path: P) -> Result<(), RgrcError> {
+    let path = std::path::PathBuf::from(expand_tilde(&path.as_ref().to_string_lossy()));
+    if !path.is_file() {
+        return Err(RgrcError::ConfigNotFound(path.display().to_string()));
+    }
+    let _ = GRC_CONF_OVERRIDE.set(path);
+    Ok(())
+}
+
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Directories searched for grcat config files (`conf.*`), in priority order.
+///
+/// 1. `$RGRC_CONFIG_DIR`, when set
+/// 2. `share` (development mode)
+/// 3. `$XDG_CONFIG_HOME/rgrc`, `$XDG_DATA_HOME/rgrc`, then `rgrc` in each of `$XDG_DATA_DIRS`
+/// 4. Python grc's directories: `~/.grc`, then the locations of 3 with `grc`
+///    instead of `rgrc`, then `/usr/local/share/grc` and `/usr/share/grc` when
+///    `$XDG_DATA_DIRS` does not already include them
+///
+/// With no variables set this is `RESOURCE_PATHS` with `~` expanded.
+pub fn resource_paths() -> Vec<std::path::PathBuf> {
+    resource_paths_from(&process_env)
+}
+
+fn resource_paths_from(env: &impl Fn(&str) -> Option<String>) -> Vec<std::path::PathBuf> {
+    let xdg = XdgDirs::new(env);
+    let mut paths: Vec<std::path::PathBuf> = rgrc_config_dir(env).into_iter().collect();
+    paths.push("share".into()); // Development mode: relative to project root
+    for app in ["rgrc", "grc"] {
+        if app == "grc" {
+            // grc 1.13+ keeps user configs in ~/.grc/
+            paths.extend(
+                env("HOME")
+                    .filter(|h| !h.is_empty())
+                    .map(|h| std::path::Path::new(&h).join(".grc")),
+            );
+        }
+        paths.extend(xdg.config_home.iter().map(|d| d.join(app)));
+        paths.extend(xdg.data_home.iter().map(|d| d.join(app)));
+        paths.extend(xdg.data_dirs.iter().map(|d| d.join(app)));
+    }
+    // Where grc installs its bundled configs, wherever XDG_DATA_DIRS points
+    for legacy in ["/usr/local/share/grc", "/usr/share/grc"] {
+        if !paths
+            .iter()
+            .any(|p| p.as_path() == std::path::Path::new(legacy))
+        {
+            paths.push(legacy.into());
+        }
+    }
+    paths
+}
+
 /// Load colorization rules for a given command from a grc.conf-style configuration file.
 ///
 /// This function reads a grc.conf configuration file and extracts colorization rules
@@ -360,54 +937,288 @@ pub const RESOURCE_PATHS: &[&str] = &[
 /// 5. Searches RESOURCE_PATHS directories **in order** for the colorization file
 /// 6. Returns rules from the **first matching file found**
 pub fn load_config(path: &str, pseudo_command: &str) -> Vec<GrcatConfigEntry> {
-    // First, try to load from filesystem config file
-    let filesystem_result = File::open(path).ok().and_then(|f| {
-        let bufreader = std::io::BufReader::new(f);
-        let configreader = GrcConfigReader::new(bufreader.lines());
-        // Iterate each rule so we can optionally log which pattern matched
-        for (re, config) in configreader {
-            if re.is_match(pseudo_command) {
-                if std::env::var_os("RGRC_DEBUG").is_some() {
-                    eprintln!(
-                        "rgrc: matched pattern '{}' in {} for '{}'",
-                        re.as_str(),
-                        path,
-                        pseudo_command
-                    );
-                }
-                return Some(config);
+    match match_command(path, pseudo_command) {
+        Some((_, config)) => ConfigLoader::from_env()
+            .locate_configs(&config)
+            .map(|(_, rules)| rules)
+            .unwrap_or_default(),
+        // No configuration found
+        None => Vec::new(),
+    }
+}
+
+/// Find the first entry of the grc.conf file at `path` whose regex matches
+/// `pseudo_command`, returning that regex and the config file it names.
+fn match_command<P: AsRef<std::path::Path>>(
+    path: P,
+    pseudo_command: &str,
+) -> Option<(String, String)> {
+    let path = path.as_ref();
+    let bufreader = std::io::BufReader::new(File::open(path).ok()?);
+    match_command_in(bufreader, path, pseudo_command)
+}
+
+/// `match_command` for the grc.conf text read from `reader`, which came from `path`.
+fn match_command_in<B: BufRead>(
+    reader: B,
+    path: &std::path::Path,
+    pseudo_command: &str,
+) -> Option<(String, String)> {
+    let configreader = GrcConfigReader::new(reader.lines());
+    // Iterate each rule so we can optionally log which pattern matched
+    for (re, config) in configreader {
+        if re.is_match(pseudo_command) {
+            if std::env::var_os("RGRC_DEBUG").is_some() {
+                eprintln!(
+                    "rgrc: matched pattern '{}' in {} for '{}'",
+                    re.as_str(),
+                    path.display(),
+                    pseudo_command
+                );
             }
+            return Some((re.as_str().to_string(), config));
         }
-        None
-    });
+    }
+    None
+}
 
-    if let Some(config) = filesystem_result {
-        // Search RESOURCE_PATHS for the colorization file - **stop at first match**
-        for base_path in RESOURCE_PATHS {
-            let expanded_path = expand_tilde(base_path);
-            let config_path = format!("{}/{}", expanded_path, config);
-            if std::env::var_os("RGRC_DEBUG").is_some() {
-                eprintln!("rgrc: checking for config file {}", config_path);
+/// Split the config reference of a grc.conf entry into the names of the configs
+/// it lists, in order. As in grc, several may be given, separated by commas or
+/// whitespace (e.g. `conf.ping, conf.common`), and their rules are concatenated.
+/// Options such as `colour=off` (see `config_color_mode`) are not names.
+pub fn config_names(config: &str) -> impl Iterator<Item = &str> {
+    config_words(config).filter(|word| !word.contains('='))
+}
+
+/// The colour mode set by the `colour=on|off|auto` (or `color=`) option of a
+/// grc.conf entry's config reference, e.g. `conf.top colour=off`, or just
+/// `colour=off` to leave a command uncoloured. Invalid values are reported on
+/// stderr and ignored.
+///
+/// ```
+/// use rgrc::{ColorMode, config_color_mode};
+///
+/// assert_eq!(config_color_mode("conf.top colour=off"), Some(ColorMode::Off));
+/// assert_eq!(config_color_mode("conf.ping, conf.common"), None);
+/// ```
+pub fn config_color_mode(config: &str) -> Option<ColorMode> {
+    config_words(config)
+        .filter_map(|word| word.split_once('='))
+        .filter(|(key, _)| matches!(*key, "colour" | "color"))
+        .filter_map(|(key, value)| match value.parse() {
+            Ok(mode) => Some(mode),
+            Err(()) => {
+                eprintln!(
+                    "rgrc: invalid {}={} in a command mapping (expected on, off or auto)",
+                    key, value
+                );
+                None
             }
-            // Use file_exists_and_parse to distinguish "file exists but empty" from "file not found"
-            match file_exists_and_parse(&config_path) {
-                Some(rules) => {
-                    if std::env::var_os("RGRC_DEBUG").is_some() {
-                        eprintln!(
-                            "rgrc: found config file {} ({} rules)",
-                            config_path,
-                            rules.len()
-                        );
-                    }
-                    return rules; // File found (even if empty) - STOP
+        })
+        .last()
+}
+
+/// The words of a grc.conf config reference, separated by commas or whitespace.
+fn config_words(config: &str) -> impl Iterator<Item = &str> {
+    config
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+}
+
+/// Parse the grcat rules in `path`, or `None` if the file cannot be opened.
+///
+/// Regexes are compiled lazily. With `config-cache`, the parsed rules are read
+/// from (or stored in) the cache directory instead of re-parsing the text.
+/// With `toml-configs`, `path.toml` is preferred to `path` when it exists.
+///
+/// Includes are looked up as `loader` does (see `ConfigLoader::expand_includes`).
+fn parse_grcat_file<P: AsRef<std::path::Path>>(
+    path: P,
+    loader: &ConfigLoader,
+) -> Option<Vec<GrcatConfigEntry>> {
+    let mut rules = read_grcat_file(path.as_ref(), loader)?;
+    sort_by_priority(&mut rules);
+    Some(rules)
+}
+
+/// Order `rules` by their `priority=`, highest first, keeping the source order
+/// of rules with the same priority.
+fn sort_by_priority(rules: &mut [GrcatConfigEntry]) {
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+}
+
+/// Read the grcat rules in `path` in source order (see `parse_grcat_file`).
+fn read_grcat_file(path: &std::path::Path, loader: &ConfigLoader) -> Option<Vec<GrcatConfigEntry>> {
+    #[cfg(feature = "toml-configs")]
+    if let Some(rules) = toml_config::load_toml_file(path) {
+        return Some(rules);
+    }
+
+    let file = File::open(path).ok()?;
+    let source = std::io::read_to_string(file).ok()?;
+    Some(read_grcat_text(path, &source, loader))
+}
+
+/// Parse the grcat config `source`, read from `path`, in source order.
+fn read_grcat_text(
+    path: &std::path::Path,
+    source: &str,
+    loader: &ConfigLoader,
+) -> Vec<GrcatConfigEntry> {
+    let (source, problems) = loader.expand_includes(path, source);
+    for (line, message) in problems {
+        eprintln!("rgrc: {}:{}: {}", path.display(), line, message);
+    }
+
+    // The cache holds rgrc's reading of the config, not grc's
+    #[cfg(feature = "config-cache")]
+    if grc::compat() == grc::Compat::Rgrc
+        && let Some(cache_dir) = get_cache_dir()
+    {
+        return config_cache::load_or_parse(&cache_dir, &source);
+    }
+
+    GrcatConfigReader::new(source.as_bytes().lines())
+        .lazy()
+        .collect()
+}
+
+/// Replace the `include=NAME` lines of the grcat config `text`, read from `path`,
+/// with the text of the configs they name, recursively, and its `loglevel=MODE`
+/// lines with the built-in severity rules (see `loglevel`).
+///
+/// `NAME` stands for `conf.NAME` unless it already starts with `conf.` or is a
+/// path. It is looked up next to the including file, then in the config search
+/// path (see `resource_paths`), then among the embedded configs. Includes that
+/// cannot be found or would include a file into itself, and unknown loglevel
+/// modes, are left out and returned as `(line, message)`, with the line of the
+/// `include=` or `loglevel=` in `text`.
+pub fn expand_includes<'a>(
+    path: &std::path::Path,
+    text: &'a str,
+) -> (std::borrow::Cow<'a, str>, Vec<(usize, String)>) {
+    ConfigLoader::from_env().expand_includes(path, text)
+}
+
+/// `expand_includes` with the includes looked up as `loader` does.
+fn expand_includes_with<'a>(
+    path: &std::path::Path,
+    text: &'a str,
+    loader: &ConfigLoader,
+) -> (std::borrow::Cow<'a, str>, Vec<(usize, String)>) {
+    if !text
+        .lines()
+        .any(|line| line.starts_with("include=") || line.starts_with("loglevel="))
+    {
+        return (std::borrow::Cow::Borrowed(text), Vec::new());
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut problems = Vec::new();
+    let mut stack = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+    expand_includes_into(
+        path,
+        text,
+        loader,
+        &mut stack,
+        &mut output,
+        &mut |line, message| problems.push((line, message)),
+    );
+    (std::borrow::Cow::Owned(output), problems)
+}
+
+/// Append `text` to `output` with its includes expanded; `stack` holds the
+/// files being expanded, to detect cycles. Problems are reported against the
+/// line of `text` whose include led to them.
+fn expand_includes_into(
+    path: &std::path::Path,
+    text: &str,
+    loader: &ConfigLoader,
+    stack: &mut Vec<std::path::PathBuf>,
+    output: &mut String,
+    report: &mut dyn FnMut(usize, String),
+) {
+    for (index, line) in text.lines().enumerate() {
+        if let Some(mode) = line.strip_prefix("loglevel=").map(str::trim) {
+            match mode.parse() {
+                Ok(mode) => {
+                    output.push_str("-\n");
+                    output.push_str(&loglevel::rules(mode));
                 }
-                None => continue, // File not found - keep searching
+                Err(message) => report(index + 1, message),
             }
+            continue;
         }
+
+        let Some(name) = line.strip_prefix("include=").map(str::trim) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+
+        let Some((source, included)) = find_include(path, name, loader) else {
+            report(index + 1, format!("included config '{}' not found", name));
+            continue;
+        };
+        if stack.contains(&source) {
+            report(
+                index + 1,
+                format!("include cycle: '{}' is already being included", name),
+            );
+            continue;
+        }
+
+        // Keep the included rules out of any entry around the include= line
+        output.push_str("-\n");
+        stack.push(source.clone());
+        expand_includes_into(
+            &source,
+            &included,
+            loader,
+            stack,
+            output,
+            &mut |_, message| report(index + 1, format!("in '{}': {}", name, message)),
+        );
+        stack.pop();
+        output.push_str("-\n");
     }
+}
 
-    // No configuration found
-    Vec::new()
+/// Locate the config named by `include=NAME` in the file at `path`, returning
+/// its (canonical) location and text. The configs given to `loader` as text
+/// come first, then the directory of `path` and the loader's search directories.
+fn find_include(
+    path: &std::path::Path,
+    name: &str,
+    loader: &ConfigLoader,
+) -> Option<(std::path::PathBuf, String)> {
+    let file_name = if name.starts_with("conf.") || name.contains('/') {
+        name.to_string()
+    } else {
+        format!("conf.{}", name)
+    };
+
+    if let Some(text) = loader.configs.get(&file_name) {
+        return Some((ConfigLoader::text_path(&file_name), text.clone()));
+    }
+
+    let here = path.parent().map(std::path::Path::to_path_buf);
+    for dir in here.into_iter().chain(loader.search_dirs.iter().cloned()) {
+        let candidate = dir.join(expand_tilde(&file_name));
+        if let Ok(text) = std::fs::read_to_string(&candidate) {
+            let source = candidate.canonicalize().unwrap_or(candidate);
+            return Some((source, text));
+        }
+    }
+
+    #[cfg(feature = "embed-configs")]
+    if loader.embedded
+        && let Some(text) = embedded_config(&file_name)
+    {
+        let source = std::path::PathBuf::from("<embedded>").join(&file_name);
+        return Some((source, text.into_owned()));
+    }
+    None
 }
 
 /// Check if a file exists and parse it for colorization rules.
@@ -418,13 +1229,9 @@ pub fn load_config(path: &str, pseudo_command: &str) -> Vec<GrcatConfigEntry> {
 ///
 /// This distinguishes between "file doesn't exist" (None) and
 /// "file exists but has no rules" (Some([])).
-fn file_exists_and_parse(filename: &str) -> Option<Vec<GrcatConfigEntry>> {
+fn file_exists_and_parse(filename: &str, loader: &ConfigLoader) -> Option<Vec<GrcatConfigEntry>> {
     // Try to open the file
-    if let Ok(grcat_config_file) = File::open(filename) {
-        let bufreader = std::io::BufReader::new(grcat_config_file);
-        // Parse all rules from the configuration file
-        let configreader = GrcatConfigReader::new(bufreader.lines());
-        let entries: Vec<_> = configreader.collect();
+    if let Some(entries) = parse_grcat_file(filename, loader) {
         // Return Some (even if empty) - file exists
         return Some(entries);
     }
@@ -439,10 +1246,7 @@ fn file_exists_and_parse(filename: &str) -> Option<Vec<GrcatConfigEntry>> {
         if let Some(cache_dir) = ensure_cache_populated() {
             let conf_dir = cache_dir.join("conf");
             let config_path = conf_dir.join(config_name);
-            if let Ok(grcat_config_file) = File::open(&config_path) {
-                let bufreader = std::io::BufReader::new(grcat_config_file);
-                let configreader = GrcatConfigReader::new(bufreader.lines());
-                let entries: Vec<_> = configreader.collect();
+            if let Some(entries) = parse_grcat_file(&config_path, loader) {
                 // Return Some (embedded file found, even if empty)
                 return Some(entries);
             }
@@ -525,14 +1329,10 @@ pub fn load_grcat_config<T: AsRef<str>>(filename: T) -> Vec<GrcatConfigEntry> {
     if filename_str.is_empty() {
         return Vec::new();
     }
+    let loader = ConfigLoader::from_env();
 
     // First, try to load from filesystem
-    if let Ok(grcat_config_file) = File::open(filename_str) {
-        let bufreader = std::io::BufReader::new(grcat_config_file);
-        // Parse all rules from the configuration file
-        let configreader = GrcatConfigReader::new(bufreader.lines());
-        let entries: Vec<_> = configreader.collect();
-
+    if let Some(entries) = parse_grcat_file(filename_str, &loader) {
         // If we successfully loaded from filesystem and got entries, return them
         if !entries.is_empty() {
             return entries;
@@ -549,13 +1349,10 @@ pub fn load_grcat_config<T: AsRef<str>>(filename: T) -> Vec<GrcatConfigEntry> {
         if let Some(cache_dir) = ensure_cache_populated() {
             let conf_dir = cache_dir.join("conf");
             let config_path = conf_dir.join(config_name);
-            if let Ok(grcat_config_file) = File::open(&config_path) {
-                let bufreader = std::io::BufReader::new(grcat_config_file);
-                let configreader = GrcatConfigReader::new(bufreader.lines());
-                let entries: Vec<_> = configreader.collect();
-                if !entries.is_empty() {
-                    return entries;
-                }
+            if let Some(entries) = parse_grcat_file(&config_path, &loader)
+                && !entries.is_empty()
+            {
+                return entries;
             }
         }
     }
@@ -566,19 +1363,71 @@ pub fn load_grcat_config<T: AsRef<str>>(filename: T) -> Vec<GrcatConfigEntry> {
 
 /// Configuration file paths in priority order.
 /// The program searches these paths to find grc.conf (or rgrc.conf) which maps
-/// commands to their colorization profiles. Paths prefixed with ~ are expanded using shellexpand.
-/// Typical flow: try ~/.grc first (user config), then system-wide configs (/etc/grc.conf).
-const CONFIG_PATHS: &[&str] = &[
-    "etc/rgrc.conf", // Development mode: relative to project root when develop with cargo run
-    "~/.rgrc",
-    "~/.config/rgrc/rgrc.conf",
-    "/usr/local/etc/rgrc.conf",
-    "/etc/rgrc.conf",
-    "~/.grc",
-    "~/.config/grc/grc.conf",
-    "/usr/local/etc/grc.conf",
-    "/etc/grc.conf",
-];
+/// commands to their colorization profiles.
+///
+/// The first entries are the user configs, which `load_rules_for_command` reads
+/// before the embedded config: the file given with `--conf` or `$RGRC_CONF`,
+/// `$RGRC_CONFIG_DIR/rgrc.conf` (when set) and `$XDG_CONFIG_HOME/rgrc/rgrc.conf`,
+/// then the `rgrc.local` of `rgrc fetch-configs` in the same two directories.
+/// The rest follow in order:
+///
+/// ```text
+/// etc/rgrc.conf (development mode), ~/.rgrc, $XDG_CONFIG_DIRS/rgrc/rgrc.conf,
+/// /usr/local/etc/rgrc.conf, /etc/rgrc.conf, ~/.grc, ~/.grc/grc.conf,
+/// $XDG_CONFIG_HOME/grc/grc.conf, $XDG_CONFIG_DIRS/grc/grc.conf,
+/// /usr/local/etc/grc.conf, /etc/grc.conf
+/// ```
+///
+/// `~/.grc` is the mapping file of older grc releases; grc 1.13 and later keep
+/// it in the `~/.grc/` directory instead, along with their `conf.*` files.
+pub fn config_paths() -> Vec<std::path::PathBuf> {
+    config_paths_from(&process_env).0
+}
+
+/// `config_paths()` for the variables returned by `env`, along with the number
+/// of leading user configs.
+fn config_paths_from(env: &impl Fn(&str) -> Option<String>) -> (Vec<std::path::PathBuf>, usize) {
+    use std::path::PathBuf;
+
+    let xdg = XdgDirs::new(env);
+    let home = |file: &str| {
+        env("HOME")
+            .filter(|h| !h.is_empty())
+            .map(|h| PathBuf::from(h).join(file))
+    };
+
+    let explicit = GRC_CONF_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(|| env_path(env, "RGRC_CONF"));
+    // The user's own rgrc.conf files come before the rgrc.local files mapping the
+    // configs installed by `rgrc fetch-configs`
+    let user_dirs: Vec<PathBuf> = rgrc_config_dir(env)
+        .into_iter()
+        .chain(xdg.config_home.as_ref().map(|d| d.join("rgrc")))
+        .collect();
+    let mut paths: Vec<PathBuf> = explicit
+        .into_iter()
+        .chain(user_dirs.iter().map(|dir| dir.join("rgrc.conf")))
+        .chain(user_dirs.iter().map(|dir| dir.join(fetch::LOCAL_MAPPING)))
+        .collect();
+    let user_count = paths.len();
+
+    // Development mode: relative to project root when develop with cargo run
+    paths.push("etc/rgrc.conf".into());
+    for (app, dotfile) in [("rgrc", ".rgrc"), ("grc", ".grc")] {
+        let file = format!("{}/{}.conf", app, app);
+        paths.extend(home(dotfile));
+        if app == "grc" {
+            paths.extend(home(".grc/grc.conf"));
+            paths.extend(xdg.config_home.as_ref().map(|d| d.join(&file)));
+        }
+        paths.extend(xdg.config_dirs.iter().map(|d| d.join(&file)));
+        paths.push(format!("/usr/local/etc/{}.conf", app).into());
+        paths.push(format!("/etc/{}.conf", app).into());
+    }
+    (paths, user_count)
+}
 
 /// Load colorization rules for a given pseudo-command by searching all configuration paths.
 ///
@@ -589,9 +1438,10 @@ const CONFIG_PATHS: &[&str] = &[
 /// # Priority Resolution
 ///
 /// Configuration files are searched in priority order:
-/// 1. User configs (`~/.rgrc`, `~/.config/rgrc/rgrc.conf`) checked first
-/// 2. System configs (`/etc/rgrc.conf`, `/usr/local/etc/rgrc.conf`) as fallback
-/// 3. Legacy grc configs checked last for backward compatibility
+/// 1. User configs (`--conf`/`$RGRC_CONF`, `$RGRC_CONFIG_DIR/rgrc.conf`,
+///    `~/.config/rgrc/rgrc.conf`) checked first
+/// 2. The embedded config (with `embed-configs`)
+/// 3. The remaining `config_paths()`: `~/.rgrc`, system configs, then legacy grc configs
 ///
 /// # Arguments
 ///
@@ -611,36 +1461,822 @@ const CONFIG_PATHS: &[&str] = &[
 /// ```
 #[allow(dead_code)]
 pub fn load_rules_for_command(pseudo_command: &str) -> Vec<GrcatConfigEntry> {
-    // Always prioritize user config first
-    let user_config_path = "~/.config/rgrc/rgrc.conf";
-    let expanded_user_config = expand_tilde(user_config_path);
-    let rules = load_config(&expanded_user_config, pseudo_command);
-    if !rules.is_empty() {
-        return rules;
+    ConfigLoader::from_env().load_rules_for_command(pseudo_command)
+}
+
+/// The `.rgrc.toml` of the project holding the working directory, with its
+/// command mappings as rgrc.conf text (see `toml_config::ProjectConfig`),
+/// unless `RGRC_NO_PROJECT_CONFIG` is set. Read once per run.
+#[cfg(feature = "toml-configs")]
+fn project_mapping() -> Option<&'static (std::path::PathBuf, String)> {
+    static MAPPING: std::sync::OnceLock<Option<(std::path::PathBuf, String)>> =
+        std::sync::OnceLock::new();
+    MAPPING
+        .get_or_init(|| {
+            if std::env::var_os("RGRC_NO_PROJECT_CONFIG").is_some() {
+                return None;
+            }
+            let path = toml_config::find_project_config(&std::env::current_dir().ok()?)?;
+            let mapping = toml_config::load_project_mapping(&path)?;
+            Some((path, mapping))
+        })
+        .as_ref()
+}
+
+/// Whether `pseudo_command` is mapped by an `rgrc.local` (the configs of
+/// `rgrc fetch-configs`) or, with `toml-configs`, by the `.rgrc.toml` of the
+/// working directory's project, which makes the command one rgrc colours even
+/// when it does not know it.
+pub fn locally_maps_command(pseudo_command: &str) -> bool {
+    #[cfg(feature = "toml-configs")]
+    if project_mapping().is_some_and(|(path, mapping)| {
+        match_command_in(mapping.as_bytes(), path, pseudo_command).is_some()
+    }) {
+        return true;
+    }
+    config_paths_from(&process_env)
+        .0
+        .iter()
+        .filter(|path| path.file_name() == Some(std::ffi::OsStr::new(fetch::LOCAL_MAPPING)))
+        .any(|path| match_command(path, pseudo_command).is_some())
+}
+
+/// The colour mode the command mappings set for `pseudo_command` with a
+/// `colour=` option, searched as `load_rules_for_command()` searches them.
+pub fn color_mode_for_command(pseudo_command: &str) -> Option<ColorMode> {
+    ConfigLoader::from_env().color_mode_for_command(pseudo_command)
+}
+
+/// A source of command→config mappings searched by `ConfigLoader`.
+#[derive(Debug, Clone)]
+enum MappingSource {
+    /// A grc.conf-style file
+    File(std::path::PathBuf),
+    /// grc.conf-style text held in memory
+    Text(String),
+    /// The embedded `rgrc.conf` and configs, read through the cache directory
+    #[cfg(feature = "embed-configs")]
+    Embedded,
+}
+
+/// Finds the rules for a command from explicit locations.
+///
+/// `ConfigLoader::from_env()` searches what `load_rules_for_command()` does:
+/// the mapping files of `config_paths()` (with the embedded config after the
+/// user ones), and `resource_paths()` for the configs they name.
+/// `ConfigLoader::new()` searches nothing until told where to look, so tests
+/// and sandboxed callers need neither `HOME` nor files on disk:
+///
+/// ```
+/// use rgrc::ConfigLoader;
+///
+/// let loader = ConfigLoader::new()
+///     .mapping_str("^deploy\\b\nconf.deploy\n")
+///     .config_str("conf.deploy", "regexp=FAILED\ncolours=bold red\n");
+/// assert_eq!(loader.load_rules_for_command("deploy --prod").len(), 1);
+/// assert!(loader.load_rules_for_command("ping").is_empty());
+/// ```
+///
+/// Mappings are searched in the order they were added, and the first whose
+/// configs hold any rule wins. The configs a mapping names, and `include=`
+/// lines, are looked up among the configs given as text, then in the search
+/// directories in order (then among the embedded configs, with `embedded()`).
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    mappings: Vec<MappingSource>,
+    search_dirs: Vec<std::path::PathBuf>,
+    configs: std::collections::HashMap<String, String>,
+    /// Whether `include=` may name an embedded config
+    #[cfg(feature = "embed-configs")]
+    embedded: bool,
+}
+
+impl ConfigLoader {
+    /// A loader that searches nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The loader behind `load_rules_for_command()`, following the environment
+    /// (`RGRC_CONF`, `RGRC_CONFIG_DIR`, the XDG variables and `HOME`) and `--conf`.
+    pub fn from_env() -> Self {
+        let (config_paths, user_count) = config_paths_from(&process_env);
+        let (user, rest) = config_paths.split_at(user_count);
+
+        // A project's .rgrc.toml comes first, and so do the configs next to it
+        #[cfg(feature = "toml-configs")]
+        let loader = match project_mapping() {
+            Some((path, mapping)) => Self::new()
+                .mapping_str(mapping)
+                .search_dir(path.parent().unwrap_or(std::path::Path::new("."))),
+            None => Self::new(),
+        };
+        #[cfg(not(feature = "toml-configs"))]
+        let loader = Self::new();
+
+        // User mappings come before the embedded config, the other files after it
+        let mut loader = user
+            .iter()
+            .fold(loader, |loader, path| loader.mapping_file(path));
+        #[cfg(feature = "embed-configs")]
+        {
+            loader = loader.embedded();
+        }
+        loader = rest
+            .iter()
+            .fold(loader, |loader, path| loader.mapping_file(path));
+        resource_paths()
+            .into_iter()
+            .fold(loader, |loader, dir| loader.search_dir(dir))
+    }
+
+    /// Search the grc.conf-style mapping file at `path`.
+    pub fn mapping_file<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        let path = expand_tilde(&path.as_ref().to_string_lossy());
+        self.mappings.push(MappingSource::File(path.into()));
+        self
+    }
+
+    /// Search the grc.conf-style mappings in `text` (`REGEX` and config lines).
+    pub fn mapping_str(mut self, text: &str) -> Self {
+        self.mappings.push(MappingSource::Text(text.to_string()));
+        self
+    }
+
+    /// Look for the configs the mappings name (`conf.NAME`) in `dir`.
+    pub fn search_dir<P: AsRef<std::path::Path>>(mut self, dir: P) -> Self {
+        let dir = expand_tilde(&dir.as_ref().to_string_lossy());
+        self.search_dirs.push(dir.into());
+        self
+    }
+
+    /// Use `text` as the grcat config `name` (e.g. `conf.ping`), before any file
+    /// of that name.
+    pub fn config_str(mut self, name: &str, text: &str) -> Self {
+        self.configs.insert(name.to_string(), text.to_string());
+        self
+    }
+
+    /// Search the embedded `rgrc.conf` next, and let `include=` name embedded configs.
+    #[cfg(feature = "embed-configs")]
+    pub fn embedded(mut self) -> Self {
+        self.mappings.push(MappingSource::Embedded);
+        self.embedded = true;
+        self
+    }
+
+    /// The rules for `pseudo_command` from the first mapping that matches it
+    /// and names configs holding rules, ordered by priority; empty if none does.
+    pub fn load_rules_for_command(&self, pseudo_command: &str) -> Vec<GrcatConfigEntry> {
+        for mapping in &self.mappings {
+            let matched = match mapping {
+                MappingSource::File(path) => match_command(path, pseudo_command),
+                MappingSource::Text(text) => match_command_in(
+                    text.as_bytes(),
+                    std::path::Path::new("<memory>"),
+                    pseudo_command,
+                ),
+                #[cfg(feature = "embed-configs")]
+                MappingSource::Embedded => {
+                    let rules = load_config_from_embedded(pseudo_command);
+                    if !rules.is_empty() {
+                        return rules;
+                    }
+                    continue;
+                }
+            };
+            if let Some((_, rules)) = matched.and_then(|(_, config)| self.locate_configs(&config))
+                && !rules.is_empty()
+            {
+                return rules;
+            }
+        }
+        Vec::new()
+    }
+
+    /// The colour mode set for `pseudo_command` by the first mapping entry
+    /// matching it that has a `colour=` option (see `config_color_mode`).
+    pub fn color_mode_for_command(&self, pseudo_command: &str) -> Option<ColorMode> {
+        self.mappings.iter().find_map(|mapping| {
+            let matched = match mapping {
+                MappingSource::File(path) => match_command(path, pseudo_command),
+                MappingSource::Text(text) => match_command_in(
+                    text.as_bytes(),
+                    std::path::Path::new("<memory>"),
+                    pseudo_command,
+                ),
+                // The bundled mapping sets no options
+                #[cfg(feature = "embed-configs")]
+                MappingSource::Embedded => None,
+            };
+            matched.and_then(|(_, config)| config_color_mode(&config))
+        })
+    }
+
+    /// The rules of the grcat config `name` (e.g. `conf.ping`, or several
+    /// separated by commas as in grc.conf), ordered by priority.
+    ///
+    /// Unlike `load_rules_for_command`, finding none of the configs is an
+    /// error rather than no rules.
+    pub fn load_config(&self, name: &str) -> Result<Vec<GrcatConfigEntry>, RgrcError> {
+        self.locate_configs(name)
+            .map(|(_, rules)| rules)
+            .ok_or_else(|| RgrcError::ConfigNotFound(name.to_string()))
+    }
+
+    /// Replace the `include=` and `loglevel=` lines of `text`, read from `path`,
+    /// as `expand_includes()` does, with includes looked up by this loader.
+    pub fn expand_includes<'a>(
+        &self,
+        path: &std::path::Path,
+        text: &'a str,
+    ) -> (std::borrow::Cow<'a, str>, Vec<(usize, String)>) {
+        expand_includes_with(path, text, self)
+    }
+
+    /// Where a config given as text is reported to come from.
+    fn text_path(name: &str) -> std::path::PathBuf {
+        std::path::PathBuf::from("<memory>").join(name)
+    }
+
+    /// Locate every config listed in the grc.conf reference `config` (see
+    /// `config_names`) and concatenate their rules in order, then by priority,
+    /// returning the files found. Configs that cannot be found are left out;
+    /// `None` if none is found.
+    fn locate_configs(
+        &self,
+        config: &str,
+    ) -> Option<(Vec<std::path::PathBuf>, Vec<GrcatConfigEntry>)> {
+        let mut found = None;
+        for name in config_names(config) {
+            match self.locate_config(name) {
+                Some((path, rules)) => {
+                    let (paths, all_rules) = found.get_or_insert_with(|| (Vec::new(), Vec::new()));
+                    paths.push(path);
+                    all_rules.extend(rules);
+                }
+                None if std::env::var_os("RGRC_DEBUG").is_some() => {
+                    eprintln!("rgrc: config file {} not found", name);
+                }
+                None => {}
+            }
+        }
+        if let Some((_, rules)) = &mut found {
+            sort_by_priority(rules);
+        }
+        found
+    }
+
+    /// Find the grcat config `config` among the configs given as text, then in
+    /// the search directories - **stop at first match** - returning where it was
+    /// found and its rules (possibly none).
+    fn locate_config(&self, config: &str) -> Option<(std::path::PathBuf, Vec<GrcatConfigEntry>)> {
+        if let Some(text) = self.configs.get(config) {
+            let path = Self::text_path(config);
+            let mut rules = read_grcat_text(&path, text, self);
+            sort_by_priority(&mut rules);
+            return Some((path, rules));
+        }
+
+        for base_path in &self.search_dirs {
+            let config_path = base_path.join(config);
+            if std::env::var_os("RGRC_DEBUG").is_some() {
+                eprintln!("rgrc: checking for config file {}", config_path.display());
+            }
+            // Use file_exists_and_parse to distinguish "file exists but empty" from "file not found"
+            if let Some(rules) = file_exists_and_parse(&config_path.to_string_lossy(), self) {
+                if std::env::var_os("RGRC_DEBUG").is_some() {
+                    eprintln!(
+                        "rgrc: found config file {} ({} rules)",
+                        config_path.display(),
+                        rules.len()
+                    );
+                }
+                return Some((config_path, rules)); // File found (even if empty) - STOP
+            }
+        }
+        None
+    }
+}
+
+/// Write one `--paths` line for `path`, annotated with whether it exists and is readable.
+fn write_path_entry<W: std::io::Write>(
+    writer: &mut W,
+    path: &std::path::Path,
+    is_dir: bool,
+    note: &str,
+) -> std::io::Result<()> {
+    let readable = if is_dir {
+        std::fs::read_dir(path).is_ok()
+    } else {
+        File::open(path).is_ok()
+    };
+    let status = match (path.exists(), readable) {
+        (false, _) => "[missing]",
+        (true, true) => "[ok]",
+        (true, false) => "[unreadable]",
+    };
+    writeln!(writer, "  {:<13}{}{}", status, path.display(), note)
+}
+
+/// Write the full config search order to `writer` (`--paths`).
+///
+/// Lists the mapping files in the order `load_rules_for_command` reads them,
+/// the directories searched for `conf.*` files and the cache directory, each
+/// annotated with whether it exists and is readable. Nothing is created.
+pub fn write_search_paths<W: std::io::Write>(writer: &mut W) -> std::io::Result<()> {
+    let (config_paths, user_count) = config_paths_from(&process_env);
+    #[cfg(feature = "embed-configs")]
+    let cache_dir = get_cache_dir();
+
+    writeln!(writer, "Command mappings (rgrc.conf), first match wins:")?;
+    #[cfg(feature = "toml-configs")]
+    if let Some((path, _)) = project_mapping() {
+        write_path_entry(writer, path, false, "  (project)")?;
+    }
+    for path in &config_paths[..user_count] {
+        write_path_entry(writer, path, false, "")?;
+    }
+    #[cfg(feature = "embed-configs")]
+    if let Some(dir) = &cache_dir {
+        write_path_entry(writer, &dir.join("rgrc.conf"), false, "  (embedded)")?;
+    }
+    for path in &config_paths[user_count..] {
+        write_path_entry(writer, path, false, "")?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "Config directories (conf.*), first match wins:")?;
+    #[cfg(feature = "toml-configs")]
+    if let Some(dir) = project_mapping().and_then(|(path, _)| path.parent()) {
+        write_path_entry(writer, dir, true, "  (project)")?;
+    }
+    for path in resource_paths() {
+        write_path_entry(writer, &path, true, "")?;
+    }
+    #[cfg(feature = "embed-configs")]
+    if let Some(dir) = &cache_dir {
+        write_path_entry(writer, &dir.join("conf"), true, "  (embedded)")?;
     }
 
-    // Then, if embed-configs is enabled, try embedded cache
     #[cfg(feature = "embed-configs")]
     {
-        let embedded_rules = load_config_from_embedded(pseudo_command);
-        if !embedded_rules.is_empty() {
-            return embedded_rules;
+        writeln!(writer)?;
+        writeln!(writer, "Cache directory:")?;
+        match &cache_dir {
+            Some(dir) => write_path_entry(writer, dir, true, "")?,
+            None => writeln!(writer, "  (none: HOME is not set)")?,
         }
     }
+    Ok(())
+}
 
-    // Fallback to other file system configuration paths - **stop at first match**
-    for config_path in CONFIG_PATHS {
-        if *config_path == "~/.config/rgrc/rgrc.conf" {
-            continue; // Already checked above
+/// Write how `command` resolves to a config and rules to `writer` (`--explain`).
+///
+/// Walks the mapping files in the order `load_rules_for_command` reads them,
+/// showing which one matched with which regex, where the named config file was
+/// found and how many of its rules compile.
+pub fn write_explanation<W: std::io::Write>(
+    writer: &mut W,
+    command: &[String],
+) -> std::io::Result<()> {
+    let pseudo_command = command.join(" ");
+    writeln!(writer, "Pseudo-command: {:?}", pseudo_command)?;
+    if let Some(name) = command.first() {
+        let colorized = if !utils::should_use_colorization_for_command_supported(name)
+            && !locally_maps_command(&pseudo_command)
+        {
+            format!("no, '{}' is not a supported command", name)
+        } else if utils::pseudo_command_excluded(&pseudo_command) {
+            "no, this pseudo-command is excluded".to_string()
+        } else {
+            "yes".to_string()
+        };
+        writeln!(writer, "Colorized:      {}", colorized)?;
+    }
+
+    // Mapping files in search order, with the directory holding the configs
+    // they name when it is not the resource path search (the embedded cache)
+    let (config_paths, user_count) = config_paths_from(&process_env);
+    let loader = ConfigLoader::from_env();
+    #[cfg(feature = "embed-configs")]
+    let embedded = ensure_cache_populated()
+        .map(|cache_dir| (cache_dir.join("rgrc.conf"), Some(cache_dir.join("conf"))));
+    #[cfg(not(feature = "embed-configs"))]
+    let embedded = None;
+
+    // The project's .rgrc.toml comes first, read as the mapping text it stands for
+    #[cfg(feature = "toml-configs")]
+    let project = project_mapping().map(|(path, mapping)| (path.clone(), mapping.as_str()));
+    #[cfg(not(feature = "toml-configs"))]
+    let project: Option<(std::path::PathBuf, &str)> = None;
+
+    let (user, rest) = config_paths.split_at(user_count);
+    let mappings: Vec<(std::path::PathBuf, Option<std::path::PathBuf>)> = user
+        .iter()
+        .map(|path| (path.clone(), None))
+        .chain(embedded)
+        .chain(rest.iter().map(|path| (path.clone(), None)))
+        .collect();
+    let mappings = project
+        .map(|(path, mapping)| (path, None, Some(mapping)))
+        .into_iter()
+        .chain(mappings.into_iter().map(|(path, dir)| (path, dir, None)));
+
+    writeln!(writer)?;
+    writeln!(writer, "Command mappings:")?;
+    for (path, conf_dir, text) in mappings {
+        let note = if conf_dir.is_some() {
+            "  (embedded)"
+        } else if text.is_some() {
+            "  (project)"
+        } else {
+            ""
+        };
+        if text.is_none() && !path.is_file() {
+            writeln!(writer, "  {:<13}{}{}", "[missing]", path.display(), note)?;
+            continue;
         }
-        let expanded_path = expand_tilde(config_path);
-        let rules = load_config(&expanded_path, pseudo_command);
-        if !rules.is_empty() {
-            return rules; // Stop at first matching config file
+        let matched = match text {
+            Some(text) => match_command_in(text.as_bytes(), &path, &pseudo_command),
+            None => match_command(&path, &pseudo_command),
+        };
+        let Some((pattern, config)) = matched else {
+            writeln!(writer, "  {:<13}{}{}", "[no match]", path.display(), note)?;
+            continue;
+        };
+        writeln!(writer, "  {:<13}{}{}", "[matched]", path.display(), note)?;
+        writeln!(writer, "  {:<13}'{}' -> {}", "", pattern, config)?;
+
+        // Each listed config is looked up on its own and their rules concatenated
+        let mut files = Vec::new();
+        let mut rules = Vec::new();
+        for name in config_names(&config) {
+            let found = match &conf_dir {
+                Some(dir) => {
+                    parse_grcat_file(dir.join(name), &loader).map(|rules| (dir.join(name), rules))
+                }
+                None => loader.locate_config(name),
+            };
+            match found {
+                Some((file, file_rules)) => {
+                    files.push(file);
+                    rules.extend(file_rules);
+                }
+                None => writeln!(writer, "  {:<13}{} not found", "", name)?,
+            }
+        }
+        sort_by_priority(&mut rules);
+        match files.as_slice() {
+            // An entry may only set options, e.g. `colour=off`
+            [] if config_names(&config).next().is_none() => {
+                writeln!(writer, "  {:<13}no config named, continuing", "")?
+            }
+            [] => writeln!(writer, "  {:<13}{} not found, continuing", "", config)?,
+            _ if rules.is_empty() => {
+                for file in &files {
+                    writeln!(writer, "  {:<13}{} has no rules", "", file.display())?;
+                }
+                writeln!(writer, "  {:<13}continuing", "")?;
+            }
+            _ => return write_rule_summary(writer, &files, &rules),
         }
     }
 
-    Vec::new()
+    writeln!(writer)?;
+    writeln!(writer, "No config matched; the output is not colorized.")
+}
+
+/// Write where the rules came from and how many of them compile, for `--explain`.
+fn write_rule_summary<W: std::io::Write>(
+    writer: &mut W,
+    files: &[std::path::PathBuf],
+    rules: &[GrcatConfigEntry],
+) -> std::io::Result<()> {
+    let failed: Vec<(usize, &GrcatConfigEntry)> = rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| rule.regex.resolve().is_none())
+        .collect();
+
+    writeln!(writer)?;
+    for (index, file) in files.iter().enumerate() {
+        let label = if index == 0 { "Config file:" } else { "" };
+        writeln!(writer, "{:<16}{}", label, file.display())?;
+    }
+    writeln!(
+        writer,
+        "Rules:          {} loaded, {} compiled",
+        rules.len(),
+        rules.len() - failed.len()
+    )?;
+    for (index, rule) in failed {
+        writeln!(
+            writer,
+            "  rule #{} failed to compile: '{}'",
+            index,
+            rule.regex.as_str()
+        )?;
+    }
+    Ok(())
+}
+
+/// A configured colorizer, for embedding rgrc's colorization in other programs.
+///
+/// It bundles the rules with the settings the binary takes from the command
+/// line (colour mode, output format, input format, hooks, time and length
+/// limits); `rgrc` itself runs its input through one.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::BufRead;
+/// use rgrc::{ColorMode, Colorizer, OutputFormat};
+/// use rgrc::grc::GrcatConfigReader;
+///
+/// let config = "regexp=ERROR\ncolours=bold red\n";
+/// let rules = GrcatConfigReader::new(config.as_bytes().lines()).collect();
+/// let colorizer = Colorizer::builder()
+///     .rules(rules)
+///     .color_mode(ColorMode::On)
+///     .renderer(OutputFormat::Ansi)
+///     .build();
+///
+/// let output = colorizer.colorize_str("ERROR: disk full\n").unwrap();
+/// assert_eq!(output, "\x1b[1;31mERROR\x1b[0m: disk full\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Colorizer {
+    rules: Vec<GrcatConfigEntry>,
+    enabled: bool,
+    renderer: OutputFormat,
+    input_format: InputFormat,
+    shade_rows: bool,
+    pretty: bool,
+    hooks: bool,
+    line_timeout: Option<std::time::Duration>,
+    disable_slow_rules: bool,
+    max_line_length: Option<usize>,
+    count_matches: bool,
+    only_matching_lines: bool,
+    drop_matching: Vec<colorizer::RuleSelector>,
+    #[cfg(feature = "debug")]
+    debug_level: crate::args::DebugLevel,
+}
+
+/// Builder for `Colorizer`, started with `Colorizer::builder()`.
+///
+/// Every setting is optional: without rules the input is copied unchanged, and
+/// the colour mode defaults to `Auto` (colours when stdout is a terminal).
+#[derive(Debug, Clone)]
+pub struct ColorizerBuilder {
+    colorizer: Colorizer,
+    color_mode: ColorMode,
+}
+
+impl Colorizer {
+    /// Start building a colorizer.
+    pub fn builder() -> ColorizerBuilder {
+        ColorizerBuilder {
+            colorizer: Colorizer {
+                rules: Vec::new(),
+                enabled: true,
+                renderer: OutputFormat::Ansi,
+                input_format: InputFormat::Text,
+                shade_rows: false,
+                pretty: false,
+                hooks: false,
+                line_timeout: None,
+                disable_slow_rules: false,
+                max_line_length: None,
+                count_matches: false,
+                only_matching_lines: false,
+                drop_matching: Vec::new(),
+                #[cfg(feature = "debug")]
+                debug_level: crate::args::DebugLevel::Off,
+            },
+            color_mode: ColorMode::Auto,
+        }
+    }
+
+    /// The rules the colorizer runs.
+    pub fn rules(&self) -> &[GrcatConfigEntry] {
+        &self.rules
+    }
+
+    /// Whether the colour mode resolved to colouring; when not, the input is
+    /// written as it is read (in the chosen output format).
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Colorize everything read from `reader` into `writer`.
+    ///
+    /// `writer` is flushed each time the lines read so far have all been written,
+    /// so a `BufWriter` sends the output of lines that arrived together in one
+    /// write, and shows each line of a slow command as soon as it is colorized.
+    pub fn colorize<R: std::io::Read, W: std::io::Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.colorize_with_options(reader, writer, colorizer::ColorizeOptions::default())
+    }
+
+    /// Colorize `text` into a string.
+    pub fn colorize_str(&self, text: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut output = Vec::new();
+        self.colorize(&mut text.as_bytes(), &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    /// Whether the rules run even when not colouring, to count matches or pick
+    /// the lines written (`--count`, `--only-matching-lines`, `--drop-matching`).
+    fn runs_rules_uncoloured(&self) -> bool {
+        self.count_matches || self.only_matching_lines || !self.drop_matching.is_empty()
+    }
+
+    /// Same as `colorize()`, with the extra outputs of `options` (the plain-text
+    /// log, stats, profile, match events and matches).
+    ///
+    /// The settings of the colorizer replace those of `options`.
+    pub fn colorize_with_options<R: std::io::Read, W: std::io::Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        options: colorizer::ColorizeOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let columns = colorizer::DelimitedColumns::new(self.input_format, self.shade_rows)
+            .filter(|_| self.enabled);
+        let json = self.enabled && self.input_format == InputFormat::Json;
+        let mut highlighter = json.then(json::JsonHighlighter::new);
+        let mut pretty;
+        let mut input: &mut dyn std::io::Read = if json && self.pretty {
+            pretty = json::PrettyJson::new(std::io::BufReader::new(reader));
+            &mut pretty
+        } else {
+            reader
+        };
+        let mut options = colorizer::ColorizeOptions {
+            // Reborrow so the options may also hold the local column and JSON state
+            log: options.log.map(|log| log as &mut dyn std::io::Write),
+            format: self.renderer,
+            stats: options.stats,
+            profile: options.profile,
+            events: options
+                .events
+                .map(|events| events as &mut dyn std::io::Write),
+            matches: options.matches,
+            block: options.block,
+            hooks: self.hooks,
+            columns: columns.as_ref(),
+            json: highlighter.as_mut(),
+            line_timeout: self.line_timeout,
+            disable_slow_rules: self.disable_slow_rules,
+            disabled_rules: options.disabled_rules,
+            disable_prefilter: false,
+            max_line_length: self.max_line_length,
+            stop_on_match: options.stop_on_match,
+            count_only: !self.enabled && self.runs_rules_uncoloured(),
+            only_matching: self.only_matching_lines,
+            drop_matching: &self.drop_matching,
+            last_bell: options.last_bell,
+            notify: options.notify,
+        };
+        let rules = if self.enabled || self.runs_rules_uncoloured() {
+            &self.rules[..]
+        } else {
+            &[]
+        };
+
+        #[cfg(feature = "debug")]
+        if self.debug_level != crate::args::DebugLevel::Off && options.events.is_none() {
+            return colorize_regex_with_debug(
+                &mut input,
+                writer,
+                rules,
+                self.debug_level,
+                &mut options,
+            );
+        }
+
+        colorizer::colorize_regex_with_options(&mut input, writer, rules, &mut options)
+    }
+}
+
+impl ColorizerBuilder {
+    /// The rules to run, e.g. from `load_rules_for_command()` or `GrcatConfigReader`.
+    pub fn rules(mut self, rules: Vec<GrcatConfigEntry>) -> Self {
+        self.colorizer.rules = rules;
+        self
+    }
+
+    /// Load the rules that `rgrc COMMAND` would use (see `load_rules_for_command()`).
+    pub fn rules_for_command(self, command: &str) -> Self {
+        self.rules(load_rules_for_command(command))
+    }
+
+    /// Whether to colour: `Auto` colours when stdout is a terminal (`--color`).
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.color_mode = color_mode;
+        self
+    }
+
+    /// The output format: ANSI text, JSON Lines or markup (`--format`).
+    pub fn renderer(mut self, renderer: OutputFormat) -> Self {
+        self.colorizer.renderer = renderer;
+        self
+    }
+
+    /// The shape of the input; CSV/TSV columns and JSON tokens are coloured
+    /// under the rule matches (`--format-in`).
+    pub fn input_format(mut self, input_format: InputFormat) -> Self {
+        self.colorizer.input_format = input_format;
+        self
+    }
+
+    /// Give every second row of CSV/TSV input a dark background (`--shade-rows`).
+    pub fn shade_rows(mut self, shade_rows: bool) -> Self {
+        self.colorizer.shade_rows = shade_rows;
+        self
+    }
+
+    /// Re-indent JSON input (`--pretty`).
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.colorizer.pretty = pretty;
+        self
+    }
+
+    /// Run the `command=` hooks of matching rules (`--allow-hooks`).
+    pub fn hooks(mut self, hooks: bool) -> Self {
+        self.colorizer.hooks = hooks;
+        self
+    }
+
+    /// Write lines that take longer than this in regex matching uncoloured
+    /// (`--line-timeout`).
+    pub fn line_timeout(mut self, line_timeout: Option<std::time::Duration>) -> Self {
+        self.colorizer.line_timeout = line_timeout;
+        self
+    }
+
+    /// Disable a rule once it ran out the line timeout (`--disable-slow-rules`).
+    pub fn disable_slow_rules(mut self, disable_slow_rules: bool) -> Self {
+        self.colorizer.disable_slow_rules = disable_slow_rules;
+        self
+    }
+
+    /// Write lines longer than this many bytes uncoloured (`--max-line-length`).
+    pub fn max_line_length(mut self, max_line_length: Option<usize>) -> Self {
+        self.colorizer.max_line_length = max_line_length;
+        self
+    }
+
+    /// Run the rules even when not colouring, for the match counters of
+    /// `ColorizeOptions::stats` (`--count`); the lines are then written as read.
+    pub fn count_matches(mut self, count_matches: bool) -> Self {
+        self.colorizer.count_matches = count_matches;
+        self
+    }
+
+    /// Write only the lines that a rule matched, and those inside a
+    /// `count=block` region (`--only-matching-lines`). The rules run even when
+    /// not colouring; the lines kept are then written as read.
+    pub fn only_matching_lines(mut self, only_matching_lines: bool) -> Self {
+        self.colorizer.only_matching_lines = only_matching_lines;
+        self
+    }
+
+    /// Leave out the lines that these rules matched, as `skip=` does, while the
+    /// other lines are coloured (`--drop-matching`). The rules run even when not
+    /// colouring; the lines kept are then written as read.
+    pub fn drop_matching(mut self, drop_matching: Vec<colorizer::RuleSelector>) -> Self {
+        self.colorizer.drop_matching = drop_matching;
+        self
+    }
+
+    /// Print which rules match each line to stderr (`--verbose`).
+    #[cfg(feature = "debug")]
+    pub fn debug_level(mut self, debug_level: crate::args::DebugLevel) -> Self {
+        self.colorizer.debug_level = debug_level;
+        self
+    }
+
+    /// Finish the colorizer, resolving an `Auto` colour mode against stdout
+    /// (a terminal that accepts ANSI sequences; see `utils::enable_ansi_support`).
+    pub fn build(self) -> Colorizer {
+        use std::io::IsTerminal;
+
+        let enabled = match self.color_mode {
+            ColorMode::On => true,
+            ColorMode::Off => false,
+            ColorMode::Auto => std::io::stdout().is_terminal() && utils::enable_ansi_support(),
+        };
+        Colorizer {
+            enabled,
+            ..self.colorizer
+        }
+    }
 }
 
 /// Helper function to format Style info with colors applied
@@ -661,6 +2297,8 @@ fn format_style_info(_style: &Style) -> String {
 /// * `reader` - Input source implementing Read
 /// * `writer` - Output destination implementing Write  
 /// * `rules` - Slice of colorization rules
+/// * `debug_level` - How much matching detail to print
+/// * `options` - Extra outputs (e.g. the plain-text log) forwarded to the colorizer
 ///
 /// # Returns
 ///
@@ -672,6 +2310,7 @@ pub fn colorize_regex_with_debug<R, W>(
     writer: &mut W,
     rules: &[GrcatConfigEntry],
     debug_level: crate::args::DebugLevel,
+    options: &mut colorizer::ColorizeOptions,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     R: std::io::Read,
@@ -701,10 +2340,12 @@ where
         let mut line_reader = Cursor::new(format!("{}\n", line).into_bytes());
         let mut temp_output = Vec::new();
 
-        colorizer::colorize_regex(&mut line_reader, &mut temp_output, rules)?;
+        colorizer::colorize_regex_with_options(&mut line_reader, &mut temp_output, rules, options)?;
 
-        // Write the colored output (no need to add newline, colorize_regex already did)
+        // Write the colored output (no need to add newline, colorize_regex already did),
+        // flushed to stay next to its report on stderr
         writer.write_all(&temp_output)?;
+        writer.flush()?;
 
         // Print debug info to stderr based on debug level
         match debug_level {
@@ -756,7 +2397,7 @@ where
                         eprintln!("  {}", Style::new().bold().apply_to(&rule_display));
 
                         // Display first matched text with styles applied
-                        if let Some(captures) = rule.regex.captures_from_pos(&line, 0) {
+                        if let Some(captures) = rule.regex.captures_at(&line, 0) {
                             // Get the full match (group 0) and rebuild it with individual groups styled
                             if let Some(_full_match) = captures.get(0) {
                                 let mut styled_groups = Vec::new();
@@ -818,32 +2459,19 @@ fn load_config_from_embedded(pseudo_command: &str) -> Vec<GrcatConfigEntry> {
         None => return Vec::new(), // Failed to create cache
     };
 
-    // Load from cached rgrc.conf
-    let grc_conf_path = cache_dir.join("rgrc.conf");
-    let conf_dir = cache_dir.join("conf");
-
-    // Use load_config to find matching config file
-    if let Ok(f) = File::open(&grc_conf_path) {
-        let bufreader = std::io::BufReader::new(f);
-        let configreader = GrcConfigReader::new(bufreader.lines());
-        for (re, config_file) in configreader {
-            if re.is_match(pseudo_command) {
-                if std::env::var_os("RGRC_DEBUG").is_some() {
-                    eprintln!(
-                        "rgrc: embedded matched pattern '{}' -> {}",
-                        re.as_str(),
-                        config_file
-                    );
-                }
-                let config_path = conf_dir.join(&config_file);
-                if let Some(config_str) = config_path.to_str() {
-                    return load_grcat_config(config_str);
-                }
-            }
+    // Find the matching config file in the cached rgrc.conf
+    match match_command(cache_dir.join("rgrc.conf"), pseudo_command) {
+        Some((_, config)) => {
+            let mut rules: Vec<GrcatConfigEntry> = config_names(&config)
+                .flat_map(|name| {
+                    load_grcat_config(cache_dir.join("conf").join(name).to_string_lossy())
+                })
+                .collect();
+            sort_by_priority(&mut rules);
+            rules
         }
+        None => Vec::new(),
     }
-
-    Vec::new()
 }
 
 #[cfg(test)]
@@ -1113,4 +2741,266 @@ mod lib_test {
             std::env::set_var("HOME", "/home/testuser");
         }
     }
+
+    /// Environment lookup backed by a fixed list of variables.
+    fn fake_env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name: &str| vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+    }
+
+    fn to_strings(paths: &[std::path::PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_resource_paths_default_to_resource_paths_constant() {
+        let env = fake_env(&[("HOME", "/home/u")]);
+        let expected: Vec<String> = RESOURCE_PATHS
+            .iter()
+            .map(|p| p.replacen('~', "/home/u", 1))
+            .collect();
+        assert_eq!(to_strings(&resource_paths_from(&env)), expected);
+    }
+
+    #[test]
+    fn test_resource_paths_follow_xdg_and_rgrc_config_dir() {
+        let env = fake_env(&[
+            ("HOME", "/home/u"),
+            ("RGRC_CONFIG_DIR", "~/rules"),
+            ("XDG_CONFIG_HOME", "/cfg"),
+            ("XDG_DATA_HOME", "relative/is/ignored"),
+            ("XDG_DATA_DIRS", "/nix/share:relative:/opt/share"),
+        ]);
+        assert_eq!(
+            to_strings(&resource_paths_from(&env)),
+            [
+                "/home/u/rules",
+                "share",
+                "/cfg/rgrc",
+                "/home/u/.local/share/rgrc",
+                "/nix/share/rgrc",
+                "/opt/share/rgrc",
+                "/home/u/.grc",
+                "/cfg/grc",
+                "/home/u/.local/share/grc",
+                "/nix/share/grc",
+                "/opt/share/grc",
+                "/usr/local/share/grc",
+                "/usr/share/grc",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_paths_order() {
+        let env = fake_env(&[("HOME", "/home/u")]);
+        let (paths, user_count) = config_paths_from(&env);
+        assert_eq!(user_count, 2);
+        assert_eq!(
+            to_strings(&paths),
+            [
+                "/home/u/.config/rgrc/rgrc.conf",
+                "/home/u/.config/rgrc/rgrc.local",
+                "etc/rgrc.conf",
+                "/home/u/.rgrc",
+                "/etc/xdg/rgrc/rgrc.conf",
+                "/usr/local/etc/rgrc.conf",
+                "/etc/rgrc.conf",
+                "/home/u/.grc",
+                "/home/u/.grc/grc.conf",
+                "/home/u/.config/grc/grc.conf",
+                "/etc/xdg/grc/grc.conf",
+                "/usr/local/etc/grc.conf",
+                "/etc/grc.conf",
+            ]
+        );
+
+        // RGRC_CONF, then RGRC_CONFIG_DIR come before the XDG user config
+        let env = fake_env(&[
+            ("HOME", "/home/u"),
+            ("RGRC_CONF", "~/work/rgrc.conf"),
+            ("RGRC_CONFIG_DIR", "/work/rgrc"),
+            ("XDG_CONFIG_HOME", "/cfg"),
+        ]);
+        let (paths, user_count) = config_paths_from(&env);
+        assert_eq!(user_count, 5);
+        assert_eq!(
+            to_strings(&paths[..5]),
+            [
+                "/home/u/work/rgrc.conf",
+                "/work/rgrc/rgrc.conf",
+                "/cfg/rgrc/rgrc.conf",
+                "/work/rgrc/rgrc.local",
+                "/cfg/rgrc/rgrc.local"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legacy_grc_locations_come_after_rgrc_ones() {
+        let dir = tempfile::TempDir::new().expect("create tempdir");
+        let root = dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        // conf.x exists in both layouts, conf.y only in grc's, conf.z only system-wide
+        write("home/.config/rgrc/conf.x", "rgrc");
+        write("home/.grc/conf.x", "grc");
+        write("home/.grc/conf.y", "grc");
+        write("usr/share/grc/conf.z", "grc system");
+        write("home/.grc/grc.conf", "grc");
+
+        let home = root.join("home").to_string_lossy().into_owned();
+        let data_dirs = root.join("usr/share").to_string_lossy().into_owned();
+        let vars = [
+            ("HOME", home.as_str()),
+            ("XDG_DATA_DIRS", data_dirs.as_str()),
+        ];
+        let env = fake_env(&vars);
+        let first = |paths: &[std::path::PathBuf], name: &str| {
+            paths
+                .iter()
+                .map(|p| p.join(name))
+                .find(|p| p.is_file())
+                .map(|p| std::fs::read_to_string(p).unwrap())
+        };
+
+        let dirs = resource_paths_from(&env);
+        assert_eq!(first(&dirs, "conf.x").as_deref(), Some("rgrc"));
+        assert_eq!(first(&dirs, "conf.y").as_deref(), Some("grc"));
+        assert_eq!(first(&dirs, "conf.z").as_deref(), Some("grc system"));
+
+        // ~/.grc/grc.conf is used until there is an rgrc.conf (ignoring the
+        // checkout's etc/rgrc.conf and the system's files)
+        let (files, _) = config_paths_from(&env);
+        let mapping = || {
+            files
+                .iter()
+                .find(|p| p.starts_with(root) && p.is_file())
+                .cloned()
+        };
+        assert_eq!(mapping(), Some(root.join("home/.grc/grc.conf")));
+        write("home/.config/rgrc/rgrc.conf", "rgrc");
+        assert_eq!(mapping(), Some(root.join("home/.config/rgrc/rgrc.conf")));
+    }
+
+    #[test]
+    #[cfg(feature = "embed-configs")]
+    fn test_cache_manifest_roundtrip() {
+        let mut manifest = CacheManifest {
+            fingerprint: 0x1234,
+            ..Default::default()
+        };
+        manifest.files.insert("conf.ping".to_string(), (1, 2));
+        manifest
+            .files
+            .insert("rgrc.conf".to_string(), (u64::MAX, 0));
+        assert_eq!(CacheManifest::parse(&manifest.render()), Some(manifest));
+
+        assert_eq!(CacheManifest::parse("fingerprint 0\n"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "embed-configs")]
+    fn test_populate_cache_rewrites_only_stale_files() {
+        use utils::fnv1a;
+
+        let dir = tempfile::TempDir::new().expect("create tempdir");
+        assert!(populate_cache(dir.path()));
+        let manifest_path = dir.path().join(CACHE_MANIFEST);
+        let mut manifest =
+            CacheManifest::parse(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+
+        // conf.ping: an old embedded version, untouched since it was written
+        let old = "regexp=old\n";
+        let ping = dir.path().join("conf").join("conf.ping");
+        std::fs::write(&ping, old).unwrap();
+        manifest
+            .files
+            .insert("conf.ping".to_string(), (0, fnv1a(old.as_bytes())));
+
+        // conf.df: edited locally after it was written
+        let df = dir.path().join("conf").join("conf.df");
+        std::fs::write(&df, "regexp=mine\n").unwrap();
+
+        manifest.fingerprint = 0;
+        std::fs::write(&manifest_path, manifest.render()).unwrap();
+        assert!(populate_cache(dir.path()));
+
+        assert_eq!(
+            std::fs::read_to_string(&ping).unwrap().as_str(),
+            embedded_config("conf.ping").unwrap().as_ref()
+        );
+        assert_eq!(std::fs::read_to_string(&df).unwrap(), "regexp=mine\n");
+    }
+
+    #[test]
+    #[cfg(feature = "embed-configs")]
+    fn test_cache_lock_recovers_stale_lock() {
+        let dir = tempfile::TempDir::new().expect("create tempdir");
+        let cache_dir = dir.path().join("rgrc");
+        let lock_path = dir.path().join("rgrc.lock");
+
+        // A lock left behind by a crashed process
+        let file = std::fs::File::create(&lock_path).unwrap();
+        let old = std::time::SystemTime::now() - CACHE_LOCK_STALE * 2;
+        file.set_modified(old).unwrap();
+
+        let lock = CacheLock::acquire(&cache_dir).expect("stale lock taken over");
+        assert!(lock_path.exists());
+        drop(lock);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    #[cfg(feature = "embed-configs")]
+    fn test_cache_lock_takes_over_a_stale_lock_once() {
+        let dir = tempfile::TempDir::new().expect("create tempdir");
+        let cache_dir = dir.path().join("rgrc");
+        let lock_path = dir.path().join("rgrc.lock");
+        let old = std::time::SystemTime::now() - CACHE_LOCK_STALE * 2;
+        let leave_stale_lock = || {
+            let file = std::fs::File::create(&lock_path).unwrap();
+            file.set_modified(old).unwrap();
+        };
+
+        // The second of two attempts that both found the lock stale comes too
+        // late: the first already replaced it with its own, which stays
+        leave_stale_lock();
+        let first = CacheLock::acquire(&cache_dir).expect("stale lock taken over");
+        CacheLock::remove_stale(&lock_path);
+        assert!(lock_path.exists());
+        assert!(!CacheLock::is_stale(&lock_path));
+        drop(first);
+
+        // Two attempts at once: only one gets the lock, the other times out
+        leave_stale_lock();
+        let barrier = std::sync::Barrier::new(2);
+        let locks: Vec<Option<CacheLock>> = std::thread::scope(|scope| {
+            let attempts: Vec<_> = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        CacheLock::acquire(&cache_dir)
+                    })
+                })
+                .collect();
+            attempts.into_iter().map(|a| a.join().unwrap()).collect()
+        });
+        assert_eq!(locks.iter().filter(|lock| lock.is_some()).count(), 1);
+        drop(locks);
+        assert!(!lock_path.exists());
+
+        // No renamed lock is left behind
+        let left: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert!(left.is_empty(), "{:?}", left);
+    }
 }
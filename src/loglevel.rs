@@ -0,0 +1,252 @@
+//! # loglevel.rs - Built-in log severity rules
+//!
+//! Severity words are the most common thing to highlight in a log, and every
+//! config that tries ends up with its own slightly different list. A config
+//! can instead reference the rules built here with a `loglevel=MODE` line,
+//! which is expanded in place like `include=` (see `expand_includes`).
+//!
+//! ## Recognition
+//!
+//! Each severity has a set of names (`WARN`, `WARNING`, `WRN`, ...). They are
+//! recognized:
+//!
+//! - in upper case anywhere, as a whole word (`2024-05-01 ERROR db: ...`)
+//! - in any case after a level key (`level=info`, `"lvl":"warn"`,
+//!   `severity: Error`) or in brackets (`[debug]`)
+//! - as the syslog priority that starts a line (`<11>`, i.e. facility 1,
+//!   severity 3), as written by `dmesg -r` and raw syslog streams
+//!
+//! ## Modes
+//!
+//! - `token`: only the level word (or priority) is styled
+//! - `line`: lines of warning severity and above are styled entirely, with the
+//!   level word in bold; lower severities style the word only
+
+/// How much of a line the severity styles, as given by `loglevel=MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The level word only
+    Token,
+    /// The whole line, for warnings and above
+    Line,
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "token" => Ok(Mode::Token),
+            "line" => Ok(Mode::Line),
+            _ => Err(format!(
+                "unknown loglevel mode '{}' (expected token or line)",
+                s
+            )),
+        }
+    }
+}
+
+/// A severity: its names, syslog severity number and theme style.
+struct Severity {
+    names: &'static [&'static str],
+    syslog: Option<u8>,
+    style: &'static str,
+    /// Whether `Mode::Line` styles the whole line
+    whole_line: bool,
+}
+
+/// Severities from the least to the most severe, so that the later rules win
+/// on a line naming several.
+const SEVERITIES: &[Severity] = &[
+    Severity {
+        names: &["TRACE", "TRC"],
+        syslog: None,
+        style: "dark",
+        whole_line: false,
+    },
+    Severity {
+        names: &["DEBUG", "DBG"],
+        syslog: Some(7),
+        style: "blue",
+        whole_line: false,
+    },
+    Severity {
+        names: &["INFO", "INF"],
+        syslog: Some(6),
+        style: "green",
+        whole_line: false,
+    },
+    Severity {
+        names: &["NOTICE"],
+        syslog: Some(5),
+        style: "cyan",
+        whole_line: false,
+    },
+    Severity {
+        names: &["WARNING", "WARN", "WRN"],
+        syslog: Some(4),
+        style: "yellow",
+        whole_line: true,
+    },
+    Severity {
+        names: &["ERROR", "ERR"],
+        syslog: Some(3),
+        style: "red",
+        whole_line: true,
+    },
+    Severity {
+        names: &[
+            "CRITICAL",
+            "CRIT",
+            "FATAL",
+            "FTL",
+            "PANIC",
+            "ALERT",
+            "EMERGENCY",
+            "EMERG",
+        ],
+        syslog: Some(2),
+        style: "bold red",
+        whole_line: true,
+    },
+];
+
+/// Highest syslog priority (facility 23, severity 7)
+const MAX_PRIORITY: u8 = 191;
+
+/// The grcat rules of `mode`, one entry per severity, in config syntax.
+pub fn rules(mode: Mode) -> String {
+    let mut text = String::new();
+    for severity in SEVERITIES {
+        let names = severity.names.join("|");
+        let mut alternatives = vec![
+            format!(r"\b({})\b", names),
+            format!(
+                r#"(?i:(?:\b(?:level|lvl|severity)["']?\s*[=:]\s*["']?|\[)({})\b)"#,
+                names
+            ),
+        ];
+        if let Some(number) = severity.syslog {
+            // The priority is facility * 8 + severity; emerg (0) and alert (1) count as critical
+            let priorities: Vec<String> = (0..=MAX_PRIORITY)
+                .filter(|priority| match number {
+                    2 => priority % 8 <= 2,
+                    _ => priority % 8 == number,
+                })
+                .map(|priority| priority.to_string())
+                .collect();
+            alternatives.push(format!("^(<(?:{})>)", priorities.join("|")));
+        }
+
+        let whole_line = mode == Mode::Line && severity.whole_line;
+        let (regexp, line_style, word_style) = if whole_line {
+            let bold = if severity.style.starts_with("bold") {
+                severity.style.to_string()
+            } else {
+                format!("bold {}", severity.style)
+            };
+            (
+                format!("^.*?(?:{}).*", alternatives.join("|")),
+                severity.style,
+                bold,
+            )
+        } else {
+            (
+                alternatives.join("|"),
+                "default",
+                severity.style.to_string(),
+            )
+        };
+        let word_styles = vec![word_style; alternatives.len()].join(",");
+
+        text.push_str(&format!(
+            "# {} (loglevel={})\nregexp={}\ncolours={},{}\ncount=once\n-\n",
+            severity.names[0].to_lowercase(),
+            if mode == Mode::Line { "line" } else { "token" },
+            regexp,
+            line_style,
+            word_styles
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grc::GrcatConfigReader;
+    use std::io::BufRead;
+
+    fn styled_words(mode: Mode, line: &str) -> Vec<(String, String)> {
+        let text = rules(mode);
+        let rules: Vec<_> = GrcatConfigReader::new(text.as_bytes().lines()).collect();
+        assert_eq!(rules.len(), SEVERITIES.len());
+        let mut words = Vec::new();
+        for rule in &rules {
+            if let Some(captures) = rule.regex.captures_at(line, 0) {
+                for (group, found) in captures.iter().into_iter().enumerate() {
+                    if let (Some(found), Some(style)) = (found, rule.colors.get(group))
+                        && *style != crate::Style::new()
+                    {
+                        words.push((
+                            line[found.start()..found.end()].to_string(),
+                            style.keywords().join(" "),
+                        ));
+                    }
+                }
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn test_level_words_in_common_formats() {
+        let token = |line| styled_words(Mode::Token, line);
+        let word = |text: &str, style: &str| vec![(text.to_string(), style.to_string())];
+        assert_eq!(
+            token("2024-05-01 12:00:00 ERROR db: timeout"),
+            word("ERROR", "red")
+        );
+        assert_eq!(token("[warn] disk almost full"), word("warn", "yellow"));
+        assert_eq!(
+            token(r#"{"level":"info","msg":"up"}"#),
+            word("info", "green")
+        );
+        assert_eq!(token("ts=1 level=Debug msg=x"), word("Debug", "blue"));
+        assert_eq!(token("severity: fatal"), word("fatal", "bold red"));
+        assert_eq!(token("TRC tick"), word("TRC", "dark"));
+        // Syslog priority 11 is facility 1 (user), severity 3 (err)
+        assert_eq!(token("<11>sshd: failed"), word("<11>", "red"));
+        assert_eq!(token("<13>cron: done"), word("<13>", "cyan"));
+        assert_eq!(token("<8>kernel: oops"), word("<8>", "bold red"));
+        // Lower-case words in prose are not levels
+        assert!(token("for more info see the error page").is_empty());
+    }
+
+    #[test]
+    fn test_line_mode_styles_warnings_and_above() {
+        let line = styled_words(Mode::Line, "W0501 WARN cache miss");
+        assert_eq!(
+            line,
+            [
+                ("W0501 WARN cache miss".to_string(), "yellow".to_string()),
+                ("WARN".to_string(), "bold yellow".to_string())
+            ]
+        );
+        assert_eq!(
+            styled_words(Mode::Line, "INFO started"),
+            [("INFO".to_string(), "green".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_mode_from_str() {
+        assert_eq!("line".parse(), Ok(Mode::Line));
+        assert!(
+            "all"
+                .parse::<Mode>()
+                .unwrap_err()
+                .contains("expected token or line")
+        );
+    }
+}
@@ -1,24 +1,30 @@
 // Import testable components from lib
 use rgrc::{
-    ColorMode,
-    args::{get_completion_script, parse_args},
+    ColorMode, Colorizer, InputFormat, OutputFormat, RgrcError, Style,
+    args::{Args, get_completion_script, parse_args},
     buffer::LineBufferedWriter,
-    colorizer::colorize_regex as colorize,
-    grc::GrcatConfigEntry,
+    colorizer::{ColorizeOptions, RuleProfile, RuleSelector, RuleStats},
+    fetch::ConfigCommand,
+    grc::{CompiledRegex, GrcatConfigEntry},
     load_rules_for_command,
+    notify::Notifier,
+    scaffold,
+    self_update::UpdateOutcome,
+    streams::StderrInput,
+    timestamps::{TimestampWriter, Timestamps},
     utils::{
-        SUPPORTED_COMMANDS, command_exists, set_process_title,
+        CommandIndex, NO_ALIAS_COMMANDS, SUPPORTED_COMMANDS, set_process_title,
         should_use_colorization_for_command_supported,
     },
 };
 
 #[cfg(feature = "debug")]
-use rgrc::args::DebugLevel;
-#[cfg(feature = "debug")]
-use rgrc::colorize_regex_with_debug;
+use rgrc::args::{DebugLevel, VerboseFormat};
 
-use std::io::{self, IsTerminal, Write};
-use std::process::{Command, Stdio};
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Child, ChildStderr, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 #[cfg(feature = "debug")]
 use std::time::Instant;
 
@@ -43,6 +49,333 @@ fn handle_io_error(e: std::io::Error) -> Result<(), Box<dyn std::error::Error>>
     Err(Box::new(e))
 }
 
+/// Report `e` on stderr and exit with its exit code (see `RgrcError::exit_code`).
+fn fail(e: RgrcError) -> ! {
+    eprintln!("Error: {}", e);
+    std::process::exit(e.exit_code());
+}
+
+/// `@VERSION` for an installed config with a version, nothing without.
+fn version_suffix(version: &str) -> String {
+    if version.is_empty() {
+        String::new()
+    } else {
+        format!("@{}", version)
+    }
+}
+
+/// Spawn `cmd`, exiting with 127 when `name` is not found and 126 when it cannot be run.
+///
+/// Ctrl-C is left to the command from then on (see `leave_interrupts_to_child`).
+/// With a `timeout` (`--timeout`), the command is stopped once it has run that long.
+fn spawn(cmd: &mut Command, name: &str, timeout: Option<Duration>) -> Child {
+    let child = cmd
+        .spawn()
+        .unwrap_or_else(|e| fail(RgrcError::spawn(name, e)));
+    rgrc::utils::leave_interrupts_to_child();
+    if let Some(timeout) = timeout {
+        watch_timeout(&child, timeout);
+    }
+    child
+}
+
+/// Set when `--timeout` stopped the command, which makes rgrc exit with `TIMEOUT_EXIT_CODE`
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+
+/// Exit status after `--timeout` stopped the command, as with timeout(1)
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Exit status of `--exit-on-match` when no rule matched, as with grep -q
+const NO_MATCH_EXIT_CODE: i32 = 1;
+
+/// Time a command stopped by `--timeout` gets to exit after SIGTERM, before SIGKILL
+const TIMEOUT_KILL_AFTER: Duration = Duration::from_secs(2);
+
+/// Stop `child` once it has run for `timeout`: SIGTERM, then SIGKILL if it is
+/// still running `TIMEOUT_KILL_AFTER` later.
+///
+/// Only the command itself is signalled. Processes it started may keep its
+/// output open, so rgrc stops waiting for that output when it sends SIGKILL;
+/// otherwise it exits as usual once the command has.
+fn watch_timeout(child: &Child, timeout: Duration) {
+    let pid = child.id();
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        TIMED_OUT.store(true, Ordering::SeqCst);
+        if !rgrc::utils::stop_process(pid, false) {
+            return;
+        }
+        std::thread::sleep(TIMEOUT_KILL_AFTER);
+        rgrc::utils::stop_process(pid, true);
+        std::process::exit(TIMEOUT_EXIT_CODE);
+    });
+}
+
+/// Wait for `child` and exit with its status (see `exit_code`).
+fn wait_and_exit(child: &mut Child) -> ! {
+    let status = child.wait().unwrap_or_else(|e| fail(RgrcError::Io(e)));
+    std::process::exit(exit_code(status));
+}
+
+/// Copy `reader` to stdout unchanged, returning the number of bytes copied.
+///
+/// `io::copy` moves the data inside the kernel on Linux when both ends are file
+/// descriptors (`splice` between pipes, `sendfile`/`copy_file_range` from
+/// files), and falls back to a buffered copy elsewhere, so uncoloured output
+/// costs next to nothing.
+fn passthrough(reader: &mut impl Read) -> io::Result<u64> {
+    io::copy(reader, &mut io::stdout().lock())
+}
+
+/// The exit code to propagate for a command that finished with `status`.
+///
+/// A command killed by a signal gives 128 plus the signal number, as in a shell,
+/// and one stopped by `--timeout` gives `TIMEOUT_EXIT_CODE`. A command ended by
+/// Ctrl-C or Ctrl-\ ends rgrc by the same signal instead, without returning, so
+/// the shell knows it was interrupted.
+fn exit_code(status: ExitStatus) -> i32 {
+    if TIMED_OUT.load(Ordering::SeqCst) {
+        return TIMEOUT_EXIT_CODE;
+    }
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        if rgrc::utils::INTERRUPT_SIGNALS.contains(&signal) {
+            rgrc::utils::exit_by_signal(signal);
+        }
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Whether the rules run even when the output is not coloured, to count the
+/// lines they match or pick those written.
+fn rules_pick_lines(args: &Args) -> bool {
+    args.count.is_some() || args.only_matching_lines || !args.drop_matching.is_empty()
+}
+
+/// The rule of `--bell-on`, which rings the bell on the lines its regex matches
+/// and leaves their colours to the command's own rules.
+fn bell_rule(args: &Args) -> Option<GrcatConfigEntry> {
+    let regex = CompiledRegex::new(args.bell_on.as_deref()?).expect("checked by parse_args");
+    Some(GrcatConfigEntry::new(regex, vec![Style::new().beep()]))
+}
+
+/// Build the colorizer for `rules` with the options requested on the command
+/// line; `enabled` is whether the output is to be coloured at all.
+fn build_colorizer(rules: Vec<GrcatConfigEntry>, args: &Args, enabled: bool) -> Colorizer {
+    let builder = Colorizer::builder()
+        .rules(rules)
+        .color_mode(if enabled {
+            ColorMode::On
+        } else {
+            ColorMode::Off
+        })
+        .renderer(args.format)
+        .input_format(args.input_format)
+        .shade_rows(args.shade_rows)
+        .pretty(args.pretty)
+        .hooks(args.allow_hooks)
+        .line_timeout(args.line_timeout)
+        .disable_slow_rules(args.disable_slow_rules)
+        .max_line_length(args.max_line_length)
+        .count_matches(args.count.is_some())
+        .only_matching_lines(args.only_matching_lines)
+        .drop_matching(args.drop_matching.clone());
+    #[cfg(feature = "debug")]
+    let builder = builder.debug_level(args.debug_level);
+    builder.build()
+}
+
+/// Run `colorizer` from `reader` to `writer`, with the plain-text log and the
+/// `--stats` / `--profile` counters.
+///
+/// With `--stderr`, the command's `stderr` is colorized alongside into rgrc's
+/// stderr by the colorizer that comes with it (see `rgrc::streams`), its lines
+/// tagged with `--tag-stderr`.
+///
+/// With the `debug` feature this also emits JSON match events to stderr
+/// (`--verbose-format=json`) and reports timing when `RGRCTIME` is set.
+fn run_colorizer<'a, R: Read, W: Write + Send>(
+    reader: &mut R,
+    writer: &mut W,
+    stderr: Option<(ChildStderr, &Colorizer)>,
+    colorizer: &Colorizer,
+    args: &Args,
+    log: Option<&'a mut dyn Write>,
+    reports: &'a mut Reports,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stderr_colored = stderr
+        .as_ref()
+        .is_some_and(|(_, colorizer)| colorizer.is_enabled() && args.format == OutputFormat::Ansi);
+    let tag = args.tag_stderr.then(|| {
+        if stderr_colored {
+            format!("{} ", Style::new().red().apply_to("[stderr]"))
+        } else {
+            "[stderr] ".to_string()
+        }
+    });
+    // --timestamps prefixes the lines of both streams, counting from the same start
+    let stamps = args.timestamps.map(|format| {
+        Timestamps::new(
+            format,
+            colorizer.is_enabled() && args.format == OutputFormat::Ansi,
+        )
+    });
+    let colorize = |reader: &mut R, writer: &mut W, options: ColorizeOptions| {
+        let mut writer = TimestampWriter::new(writer, stamps);
+        match stderr {
+            Some((reader_err, colorizer_err)) => rgrc::streams::colorize_streams(
+                colorizer,
+                reader,
+                StderrInput {
+                    reader: reader_err,
+                    colorizer: colorizer_err,
+                    tag: tag.as_deref(),
+                },
+                writer,
+                TimestampWriter::new(io::stderr(), stamps.map(|s| s.colored(stderr_colored))),
+                options,
+            ),
+            None => colorizer.colorize_with_options(reader, &mut writer, options),
+        }
+    };
+
+    // --notify-on watches every line, coloured or not
+    let mut notifier = args.notify_on.as_deref().map(|regex| {
+        let regex = CompiledRegex::new(regex).expect("checked by parse_args");
+        let title = match &args.shell_command {
+            Some(line) => line.clone(),
+            None => args
+                .command
+                .first()
+                .map_or("rgrc", String::as_str)
+                .to_string(),
+        };
+        Notifier::new(regex, &title)
+    });
+
+    #[cfg(feature = "debug")]
+    let mut stderr = io::stderr();
+    #[cfg_attr(not(feature = "debug"), allow(unused_mut))]
+    let mut options = ColorizeOptions {
+        // Reborrow so the options may also hold the shorter-lived stderr sink
+        log: log.map(|log| log as &mut dyn Write),
+        stats: reports.stats.as_mut(),
+        profile: reports.profile.as_mut(),
+        stop_on_match: args.exit_on_match,
+        notify: notifier.as_mut(),
+        ..Default::default()
+    };
+
+    #[cfg(feature = "debug")]
+    {
+        // JSON events replace the human-readable report
+        if args.verbose_format == VerboseFormat::Json {
+            options.events = Some(&mut stderr);
+        } else if args.debug_level == DebugLevel::Off && std::env::var_os("RGRCTIME").is_some() {
+            let t_before_colorize = Instant::now();
+            let result = colorize(reader, writer, options);
+            eprintln!("[rgrc:time] colorize: {:?}", t_before_colorize.elapsed());
+            return result;
+        }
+    }
+
+    colorize(reader, writer, options)
+}
+
+/// Counters requested with `--stats` / `--profile` / `--count`, printed to stderr at exit.
+///
+/// The hit counters are also kept for `--exit-on-match` and `--count`, which
+/// find matches through them, but printed in full only with `--stats`.
+struct Reports {
+    stats: Option<RuleStats>,
+    profile: Option<RuleProfile>,
+    print_stats: bool,
+    count: Option<RuleSelector>,
+    fail_above: Option<usize>,
+}
+
+impl Reports {
+    fn new(args: &Args, rule_count: usize) -> Self {
+        Reports {
+            stats: (args.stats || args.exit_on_match.is_some() || args.count.is_some())
+                .then(|| RuleStats::new(rule_count)),
+            profile: args.profile.then(|| RuleProfile::new(rule_count)),
+            print_stats: args.stats,
+            count: args.count,
+            fail_above: args.fail_above,
+        }
+    }
+
+    /// The number of lines `--count` counts, if asked for.
+    fn count(&self) -> Option<usize> {
+        let stats = self.stats.as_ref()?;
+        self.count.map(|selector| stats.matched_lines(selector))
+    }
+
+    /// Whether the `--count` went over the limit of `--fail-above`.
+    fn over_limit(&self) -> bool {
+        matches!((self.count(), self.fail_above), (Some(count), Some(limit)) if count > limit)
+    }
+
+    /// `code`, or `COUNT_OVER_LIMIT_EXIT_CODE` instead of success when the count
+    /// went over the limit.
+    fn exit_code(&self, code: i32) -> i32 {
+        if code == 0 && self.over_limit() {
+            COUNT_OVER_LIMIT_EXIT_CODE
+        } else {
+            code
+        }
+    }
+
+    fn write(&self, rules: &[GrcatConfigEntry]) -> io::Result<()> {
+        let mut stderr = io::stderr();
+        if let Some(stats) = self.stats.as_ref().filter(|_| self.print_stats) {
+            stats.write_report(&mut stderr, rules)?;
+        }
+        if let (Some(count), Some(selector)) = (self.count(), self.count) {
+            write!(stderr, "rgrc: {} line(s) matched", count)?;
+            if let RuleSelector::Rule(index) = selector {
+                let pattern = rules
+                    .get(index)
+                    .map_or("no such rule", |r| r.regex.as_str());
+                write!(stderr, " rule {} ({})", index, pattern)?;
+            }
+            match self.fail_above {
+                Some(limit) if count > limit => writeln!(stderr, ", over the limit of {}", limit)?,
+                _ => writeln!(stderr)?,
+            }
+        }
+        if let Some(profile) = &self.profile {
+            profile.write_report(&mut stderr, rules, PROFILE_REPORT_LIMIT)?;
+        }
+        Ok(())
+    }
+}
+
+/// Exit status when the `--count` went over the limit of `--fail-above`
+const COUNT_OVER_LIMIT_EXIT_CODE: i32 = 1;
+
+/// Number of rules listed by `--profile`
+const PROFILE_REPORT_LIMIT: usize = 10;
+
+/// Open the `--log FILE` destination; the error names the file.
+///
+/// The file is line-buffered like stdout so the transcript stays current while
+/// long-running commands (ping, tail -f) are still producing output.
+fn open_log_file(
+    path: &str,
+) -> Result<LineBufferedWriter<io::BufWriter<std::fs::File>>, RgrcError> {
+    std::fs::File::create(path)
+        .map(|file| LineBufferedWriter::new(io::BufWriter::new(file)))
+        .map_err(|e| {
+            RgrcError::Io(io::Error::new(
+                e.kind(),
+                format!("cannot open log file '{}': {}", path, e),
+            ))
+        })
+}
+
 // Use mimalloc for faster memory allocation (reduces startup overhead)
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
@@ -95,13 +428,7 @@ fn flush_and_rebuild_cache() {
 /// - --completions SHELL: Print completion script for SHELL (bash|zsh|fish|ash)
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command-line arguments
-    let args = match parse_args() {
-        Ok(args) => args,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
-        }
-    };
+    let args = parse_args().unwrap_or_else(|e| fail(e));
 
     // Handle --version flag first: print version and exit
     if args.show_version {
@@ -109,6 +436,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(0);
     }
 
+    // Relocate the cache before anything reads or rebuilds it
+    #[cfg(feature = "embed-configs")]
+    if let Some(dir) = &args.cache_dir {
+        rgrc::set_cache_dir(dir);
+    }
+
+    // Before any config is read, so every rule compiles with it
+    rgrc::grc::set_regex_engine(args.engine);
+    rgrc::grc::set_compat(args.compat);
+    if let Some(timeout) = args.line_timeout {
+        rgrc::grc::set_match_timeout(timeout);
+    }
+
+    // An explicit grc.conf is searched before every other mapping file
+    if let Some(conf) = &args.conf
+        && let Err(e) = rgrc::set_grc_conf(conf)
+    {
+        fail(e);
+    }
+
     // Handle --completions flag: print completions for the requested shell
     if let Some(shell) = args.show_completions.as_deref() {
         match get_completion_script(shell) {
@@ -116,11 +463,234 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 print!("{}", script);
                 std::process::exit(0);
             }
+            None => fail(RgrcError::Args(format!(
+                "Unsupported shell for completions: {}",
+                shell
+            ))),
+        }
+    }
+
+    // Handle --paths flag: print the config search order
+    if args.show_paths {
+        rgrc::write_search_paths(&mut io::stdout().lock()).or_else(handle_io_error)?;
+        std::process::exit(0);
+    }
+
+    // Handle --explain flag: show how the command resolves instead of running it
+    if args.explain {
+        rgrc::write_explanation(&mut io::stdout().lock(), &args.command)
+            .or_else(handle_io_error)?;
+        std::process::exit(0);
+    }
+
+    // Handle --show-config flag: print the effective rules in conf syntax
+    if args.show_config {
+        let target = match &args.config {
+            Some(name) => name.clone(),
+            None => args.command.join(" "),
+        };
+        let rules = load_rules_for_command(&target);
+        let mut stdout = io::stdout().lock();
+        writeln!(
+            stdout,
+            "# Effective rgrc rules for {:?} ({} rules)",
+            target,
+            rules.len()
+        )
+        .and_then(|_| rgrc::grc::write_grcat_config(&mut stdout, &rules))
+        .or_else(handle_io_error)?;
+        std::process::exit(0);
+    }
+
+    // Handle `rgrc repl`: try rules interactively on lines typed by the user
+    if args.repl {
+        let stdin = io::stdin();
+        let prompt = stdin.is_terminal();
+        rgrc::repl::run(
+            stdin.lock(),
+            &mut io::stdout().lock(),
+            args.config.as_deref(),
+            load_rules_for_command,
+            prompt,
+        )
+        .or_else(handle_box_error)?;
+        std::process::exit(0);
+    }
+
+    // Handle `rgrc fetch-configs [URL]` and `rgrc config ...`: install and
+    // manage the configs of config indexes
+    if args.fetch_configs || args.config_command.is_some() {
+        let Some(dir) = rgrc::user_config_dir() else {
+            fail(RgrcError::Args(
+                "installing configs needs HOME or RGRC_CONFIG_DIR set".to_string(),
+            ));
+        };
+        let index_url = args
+            .config_index
+            .clone()
+            .or_else(|| std::env::var(rgrc::fetch::INDEX_ENV).ok())
+            .filter(|url| !url.is_empty());
+        let report_installed = |configs: &[rgrc::fetch::Installed]| {
+            for config in configs {
+                let version = &config.entry.version;
+                println!(
+                    "installed {}{} for '{}'",
+                    dir.join(&config.entry.name).display(),
+                    if version.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", version)
+                    },
+                    config.entry.regexp
+                );
+            }
+        };
+        let result = match &args.config_command {
             None => {
-                eprintln!("Unsupported shell for completions: {}", shell);
-                std::process::exit(1);
+                let Some(index_url) = index_url else {
+                    fail(RgrcError::Args(format!(
+                        "fetch-configs needs the URL of a config index, or {} set",
+                        rgrc::fetch::INDEX_ENV
+                    )));
+                };
+                rgrc::fetch::fetch_configs(&index_url, &dir)
+                    .map(|configs| report_installed(&configs))
+            }
+            Some(ConfigCommand::List) => rgrc::fetch::ConfigStore::new(&dir)
+                .installed()
+                .map_err(RgrcError::from)
+                .map(|installed| {
+                    if installed.is_empty() {
+                        println!("no configs installed in {}", dir.display());
+                    }
+                    for config in installed {
+                        println!(
+                            "{}{}{}  '{}'  from {}",
+                            config.entry.name,
+                            version_suffix(&config.entry.version),
+                            if config.pinned { " pinned" } else { "" },
+                            config.entry.regexp,
+                            config.index
+                        );
+                    }
+                }),
+            Some(ConfigCommand::Install(names)) => {
+                rgrc::fetch::install_configs(&dir, index_url.as_deref(), names)
+                    .map(|configs| report_installed(&configs))
+            }
+            Some(ConfigCommand::Remove(names)) => {
+                let store = rgrc::fetch::ConfigStore::new(&dir);
+                names.iter().try_for_each(|name| {
+                    store.remove(name)?;
+                    println!("removed {}", name);
+                    Ok(())
+                })
+            }
+            Some(ConfigCommand::Update(names)) => {
+                rgrc::fetch::update_configs(&dir, names).map(|updates| {
+                    if updates.is_empty() {
+                        println!("all configs are up to date");
+                    }
+                    for (config, old_version) in updates {
+                        println!(
+                            "updated {0}{1} to {0}{2}",
+                            config.entry.name,
+                            version_suffix(&old_version),
+                            version_suffix(&config.entry.version)
+                        );
+                    }
+                })
+            }
+        };
+        match result {
+            Ok(()) => std::process::exit(0),
+            Err(e) => fail(e),
+        }
+    }
+
+    // Handle `rgrc suggest`: print rules for the sample output on stdin
+    if args.suggest {
+        let mut sample = Vec::new();
+        let read = match &args.sample {
+            Some(path) => {
+                std::fs::File::open(path).and_then(|mut file| file.read_to_end(&mut sample))
             }
+            None => io::stdin().read_to_end(&mut sample),
+        };
+        if let Err(e) = read {
+            fail(e.into());
         }
+        let sample = String::from_utf8_lossy(&sample);
+        let lines = sample.lines().count();
+        let suggestions: Vec<_> = scaffold::suggest(&sample)
+            .into_iter()
+            .filter(|suggestion| suggestion.lines > 0)
+            .collect();
+        if suggestions.is_empty() {
+            eprintln!("rgrc: no rules to suggest for this sample");
+        } else {
+            println!(
+                "# Rules suggested by `rgrc suggest` for {} sample lines, most lines first",
+                lines
+            );
+            print!("{}", scaffold::write_suggestions(&suggestions, lines));
+        }
+        std::process::exit(0);
+    }
+
+    // Handle `rgrc new-config CMD`: write a starter config from the command's output
+    if args.new_config {
+        let name = scaffold::command_name(&args.command[0]);
+        let sample = match &args.sample {
+            Some(path) => std::fs::read(path)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .map_err(RgrcError::from),
+            None => scaffold::sample_output(&args.command),
+        }
+        .unwrap_or_else(|e| fail(e));
+        let path = format!("conf.{}", name);
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| {
+                file.write_all(scaffold::config_template(name, &sample).as_bytes())
+            });
+        if let Err(e) = written {
+            fail(RgrcError::Io(io::Error::new(
+                e.kind(),
+                format!("{}: {}", path, e),
+            )));
+        }
+        for line in sample.lines().take(scaffold::SAMPLE_LINES) {
+            println!("  {}", line);
+        }
+        println!();
+        println!("Wrote {} with rule stubs for output like the above.", path);
+        println!("Map {} to it in rgrc.conf with:", name);
+        println!();
+        print!("{}", scaffold::mapping_entry(name));
+        std::process::exit(0);
+    }
+
+    // Handle `rgrc self-update`: replace the binary with the latest release
+    if args.self_update {
+        match rgrc::self_update::self_update() {
+            Ok(UpdateOutcome::UpToDate(version)) => println!("rgrc {} is up to date", version),
+            Ok(UpdateOutcome::Updated(version)) => println!("updated rgrc to {}", version),
+            Err(e) => fail(e),
+        }
+        std::process::exit(0);
+    }
+
+    // Handle `rgrc plugin SHELL`: print a plugin setting up aliases and completions
+    if let Some(shell) = args.plugin_shell {
+        let grc = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into()))
+            .unwrap_or_else(|| "rgrc".to_string());
+        print!("{}", rgrc::aliases::plugin_script(shell, &grc));
+        std::process::exit(0);
     }
 
     // Handle --aliases and --all-aliases flags: generate shell aliases for commands.
@@ -138,20 +708,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .flat_map(|s| s.split(',').map(|p| p.trim().to_string()))
             .collect();
 
-        // Curated list of commands known to work well with grc
-        for cmd in SUPPORTED_COMMANDS {
-            // Output a shell alias if:
-            // 1. The command is not in the exclude list, AND
-            // 2. Either we're generating all aliases (--all-aliases) OR the command exists in PATH (which::which)
-            if !except_set.contains(cmd as &str) && (args.show_all_aliases || command_exists(cmd)) {
-                // Print shell alias in the format: alias CMD='grc CMD';
-                if cmd == &"journalctl" {
-                    // Special alias: run rgrc as wrapper so rgrc can control paging and coloring
-                    println!("alias {}='{} journalctl --no-pager | less -R'", cmd, grc);
-                } else {
-                    println!("alias {}='{} {}'", cmd, grc, cmd);
-                }
-            }
+        // Curated list of commands known to work well with grc, limited to the ones in PATH
+        // unless generating all aliases (--all-aliases), minus the excluded ones (--except) and those
+        // that must not be aliased (git, cargo, npm).
+        // PATH is read once for all of them
+        let installed = (!args.show_all_aliases).then(CommandIndex::from_env);
+        let commands: Vec<&str> = SUPPORTED_COMMANDS
+            .iter()
+            .copied()
+            .filter(|cmd| !except_set.contains(*cmd) && !NO_ALIAS_COMMANDS.contains(cmd))
+            .filter(|cmd| installed.as_ref().is_none_or(|index| index.contains(cmd)))
+            .collect();
+        let script = rgrc::aliases::alias_script(args.alias_shell, &grc, &commands);
+
+        if args.install_aliases {
+            let path =
+                rgrc::aliases::install(args.alias_shell, &script).unwrap_or_else(|e| fail(e));
+            println!("Installed {} aliases in {}", commands.len(), path.display());
+            println!("{}", rgrc::aliases::install_hint(args.alias_shell, &path));
+        } else {
+            print!("{}", script);
         }
         std::process::exit(0);
     }
@@ -163,55 +739,89 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(0);
     }
 
+    // --strip without a command: remove the escape sequences of stdin
+    if args.strip && args.command.is_empty() && args.shell_command.is_none() {
+        match rgrc::utils::strip_stream(io::stdin().lock(), io::stdout().lock()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => fail(RgrcError::Io(e)),
+            _ => std::process::exit(0),
+        }
+    }
+
+    // Open the plain-text transcript up front so a bad path fails before the command runs
+    let mut log_writer = args
+        .log_file
+        .as_deref()
+        .map(open_log_file)
+        .transpose()
+        .unwrap_or_else(|e| fail(e));
+
+    // The transcript, JSONL/markup output and --stats/--profile counters all need every line to pass
+    // through the colorizer, so the direct passthrough shortcuts below are only taken without them
+    let must_pipe = log_writer.is_some()
+        || args.format != OutputFormat::Ansi
+        || args.stats
+        || args.profile
+        || args.tag_stderr
+        || args.exit_on_match.is_some()
+        || args.count.is_some()
+        || args.notify_on.is_some()
+        || args.only_matching_lines
+        || !args.drop_matching.is_empty()
+        || args.timestamps.is_some();
+
+    // JSONL and markup output describe rule matches rather than painting them, and
+    // --exit-on-match looks for them, so rules are loaded regardless of --color and
+    // TTY detection; --strip never colours. Otherwise stdout and stderr each follow
+    // their own --color-stdout / --color-stderr, or --color
+    let stream_color_mode = |mode: Option<ColorMode>| {
+        if args.strip {
+            ColorMode::Off
+        } else if args.format != OutputFormat::Ansi || args.exit_on_match.is_some() {
+            ColorMode::On
+        } else {
+            mode.unwrap_or(args.color)
+        }
+    };
+    let color_mode = stream_color_mode(args.color_stdout);
+
     // If --config is specified, read from stdin and colorize using the specified config
     if let Some(ref config_name) = args.config {
-        let color_mode = args.color;
-
-        // Detect if stdout is a terminal (TTY)
+        // Detect if stdout is a terminal (TTY) that shows colours
         let stdout_is_terminal = io::stdout().is_terminal();
+        let ansi_supported = rgrc::utils::enable_ansi_support();
 
         // Determine if we should colorize based on color mode and TTY status
         let should_colorize = match color_mode {
             ColorMode::Off => false,
             ColorMode::On => true,
-            ColorMode::Auto => stdout_is_terminal,
+            ColorMode::Auto => stdout_is_terminal && ansi_supported,
         };
 
-        if !should_colorize {
+        if !should_colorize && !must_pipe {
             // Just pass through stdin to stdout without coloring
-            let stdin = io::stdin();
-            let stdout = io::stdout();
-            let mut reader = io::BufReader::new(stdin.lock());
-            let mut writer = io::BufWriter::new(stdout.lock());
-            match io::copy(&mut reader, &mut writer) {
-                Ok(_) => {
-                    let _ = writer.flush();
-                    std::process::exit(0);
-                }
-                Err(e) => {
-                    if e.kind() != std::io::ErrorKind::BrokenPipe {
-                        eprintln!("Error copying stdin to stdout: {}", e);
-                    }
-                    let _ = writer.flush();
-                    std::process::exit(0);
-                }
+            if let Err(e) = passthrough(&mut io::stdin().lock())
+                && e.kind() != std::io::ErrorKind::BrokenPipe
+            {
+                eprintln!("Error copying stdin to stdout: {}", e);
             }
+            std::process::exit(0);
         }
 
-        // Load colorization rules for the specified config
-        let rules: Vec<GrcatConfigEntry> = load_rules_for_command(config_name);
+        // Load colorization rules for the specified config (--count,
+        // --only-matching-lines and --drop-matching run them uncoloured)
+        let rules: Vec<GrcatConfigEntry> = if should_colorize || rules_pick_lines(&args) {
+            let mut rules = load_rules_for_command(config_name);
+            rules.extend(bell_rule(&args));
+            rules
+        } else {
+            Vec::new()
+        };
+        let styled_input = should_colorize && args.input_format != InputFormat::Text;
 
-        if rules.is_empty() {
+        if rules.is_empty() && !styled_input && !must_pipe {
             // No rules found, just pass through
-            let stdin = io::stdin();
-            let stdout = io::stdout();
-            let mut reader = io::BufReader::new(stdin.lock());
-            let mut writer = io::BufWriter::new(stdout.lock());
-            match io::copy(&mut reader, &mut writer) {
-                Ok(_) => {
-                    let _ = writer.flush();
-                    std::process::exit(0);
-                }
+            match passthrough(&mut io::stdin().lock()) {
+                Ok(_) => std::process::exit(0),
                 Err(e) => {
                     if e.kind() != std::io::ErrorKind::BrokenPipe {
                         eprintln!(
@@ -219,95 +829,135 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             config_name
                         );
                     }
-                    let _ = writer.flush();
                     std::process::exit(1);
                 }
             }
         }
 
-        // Read from stdin and colorize
-        let stdin = io::stdin();
-        let mut buffered_stdin = io::BufReader::with_capacity(64 * 1024, stdin.lock());
+        // Read from stdin and colorize. The colorizer buffers its input and flushes the
+        // output whenever it has written all the lines read so far
+        let mut stdin = io::stdin().lock();
         let mut buffered_stdout = io::BufWriter::with_capacity(64 * 1024, io::stdout());
-        let mut line_buffered_writer = LineBufferedWriter::new(&mut buffered_stdout);
-
-        // Use debug colorizer if debug_level is not Off
-        #[cfg(feature = "debug")]
-        {
-            if args.debug_level != DebugLevel::Off {
-                if let Err(e) = colorize_regex_with_debug(
-                    &mut buffered_stdin,
-                    &mut line_buffered_writer,
-                    rules.as_slice(),
-                    args.debug_level,
-                ) {
-                    handle_box_error(e)?;
-                }
-            } else if let Err(e) = colorize(
-                &mut buffered_stdin,
-                &mut line_buffered_writer,
-                rules.as_slice(),
-            ) {
-                handle_box_error(e)?;
-            }
-        }
+        let mut reports = Reports::new(&args, rules.len());
+        let colorizer = build_colorizer(rules, &args, should_colorize);
 
-        #[cfg(not(feature = "debug"))]
-        {
-            if let Err(e) = colorize(
-                &mut buffered_stdin,
-                &mut line_buffered_writer,
-                rules.as_slice(),
-            ) {
-                handle_box_error(e)?;
-            }
+        if let Err(e) = run_colorizer(
+            &mut stdin,
+            &mut buffered_stdout,
+            None,
+            &colorizer,
+            &args,
+            log_writer.as_mut().map(|w| w as &mut dyn Write),
+            &mut reports,
+        ) {
+            handle_box_error(e)?;
         }
 
         // Flush buffered output
         if let Err(e) = buffered_stdout.flush() {
             handle_io_error(e)?;
         }
+        if let Some(mut log) = log_writer {
+            log.flush()?;
+        }
+        reports.write(colorizer.rules())?;
 
-        std::process::exit(0);
+        std::process::exit(reports.exit_code(0));
     }
 
-    if args.command.is_empty() {
-        eprintln!("No command specified.");
-        std::process::exit(1);
+    if args.command.is_empty() && args.shell_command.is_none() {
+        fail(RgrcError::Args("No command specified.".to_string()));
     }
 
-    // Apply color mode setting
-    let color_mode = args.color;
-    let command_name = args.command.first().unwrap();
+    // With --shell, what shows is the output of the line's last pipeline stage. The
+    // stages that run a supported command are tried from the last one on, and the
+    // first with rules colours the output: `... | ss -t` uses conf.ss, while
+    // `dmesg | tail -n 50` keeps the rules of dmesg, as tail leaves its lines as they are
+    fn first_word(stage: &str) -> &str {
+        stage.split_whitespace().next().unwrap_or_default()
+    }
+    let shell_line = args.shell_command.as_deref();
+    let stages: Vec<&str> = shell_line
+        .map(rgrc::utils::pipeline_stages)
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .filter(|stage| {
+            should_use_colorization_for_command_supported(first_word(stage))
+                || rgrc::locally_maps_command(stage)
+        })
+        .collect();
+    let command_name = match shell_line {
+        Some(line) => first_word(stages.first().unwrap_or(&line)),
+        None => args.command.first().unwrap(),
+    };
 
     // Update process title to show the wrapped command instead of "rgrc"
     // This makes tmux, ps, top etc. display the actual command being run
     set_process_title(command_name);
 
-    // Detect if stdout is a terminal (TTY)
+    // Detect if stdout is a terminal (TTY); on Windows its console (or ConPTY
+    // session) must also accept ANSI sequences for colours to be shown
     let stdout_is_terminal = io::stdout().is_terminal();
+    let ansi_supported = rgrc::utils::enable_ansi_support();
 
-    // Determine if we should colorize based on color mode and TTY status
-    let should_colorize = match color_mode {
-        ColorMode::Off => false,
-        ColorMode::On => should_use_colorization_for_command_supported(command_name),
-        ColorMode::Auto => {
-            stdout_is_terminal && should_use_colorization_for_command_supported(command_name)
-        }
+    let pseudo_command = match shell_line {
+        Some(line) => stages.first().unwrap_or(&line).to_string(),
+        None => args.command.join(" "),
     };
 
-    let pseudo_command = args.command.join(" ");
+    // Determine if we should colorize based on color mode and TTY status.
+    // CSV/TSV input is coloured whatever the command (psql --csv, sqlite3 -csv),
+    // and so are commands rgrc does not know that rgrc.local or a project's
+    // .rgrc.toml maps
+    let supported = should_use_colorization_for_command_supported(command_name)
+        || args.input_format != InputFormat::Text
+        || args.bell_on.is_some()
+        || rgrc::locally_maps_command(&pseudo_command);
+
+    // A `colour=` option of the command's mapping entry replaces auto, so commands
+    // that misbehave when coloured can be turned off in rgrc.conf
+    let mapped_color_mode = std::cell::LazyCell::new(|| {
+        let mode = if stages.len() > 1 {
+            stages
+                .iter()
+                .find_map(|stage| rgrc::color_mode_for_command(stage))
+        } else {
+            rgrc::color_mode_for_command(&pseudo_command)
+        };
+        mode.unwrap_or(ColorMode::Auto)
+    });
+    let with_mapping = |mode| match mode {
+        ColorMode::Auto => *mapped_color_mode,
+        mode => mode,
+    };
+    let stream_colored = |mode, is_terminal| match mode {
+        ColorMode::Off => false,
+        ColorMode::On => supported,
+        ColorMode::Auto => is_terminal && ansi_supported && supported,
+    };
+    let should_colorize = stream_colored(with_mapping(color_mode), stdout_is_terminal);
 
     // If we previously decided colorization should be attempted, allow an explicit
     // pseudo-command exclusion check here. This is done *before* loading rules so
     // plain `rgrc ls` (pseudo_command == "ls") can be treated as no-color while
     // `rgrc ls -l` will not match the exact exclusion and remains colorized.
-    let should_colorize = if should_colorize {
-        // exact match exclusions
-        !rgrc::utils::pseudo_command_excluded(&pseudo_command)
-    } else {
-        false
-    };
+    // RGRC_DISABLE and RGRC_EXCEPT exclude commands the same way, so aliases can be
+    // bypassed for a while (`RGRC_EXCEPT=ping ping host`) without editing them.
+    let excluded = rgrc::utils::pseudo_command_excluded(&pseudo_command)
+        || rgrc::utils::disabled_by_env(command_name);
+    let should_colorize = should_colorize && !excluded;
+
+    // --stderr: the command's stderr is coloured or not by a decision of its own, so
+    // `rgrc --stderr make 2>build.log` keeps the log free of escape sequences while
+    // the terminal still gets colours (on Windows ANSI support was enabled for the
+    // console both streams share)
+    let colorize_stderr = args.stderr
+        && stream_colored(
+            with_mapping(stream_color_mode(args.color_stderr)),
+            io::stderr().is_terminal(),
+        )
+        && !excluded;
 
     // OPTIMIZATION: Load colorization rules concurrently with command preparation
     // This allows rule loading (I/O + regex compilation) to happen in parallel
@@ -329,11 +979,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         None
     };
 
-    let rules: Vec<GrcatConfigEntry> = if should_colorize {
-        load_rules_for_command(&pseudo_command)
-    } else {
+    // --count, --only-matching-lines and --drop-matching run the rules of a
+    // supported command whether or not it is coloured
+    let run_rules =
+        should_colorize || colorize_stderr || (rules_pick_lines(&args) && supported && !excluded);
+    let mut rules: Vec<GrcatConfigEntry> = if !run_rules {
         Vec::new()
+    } else if stages.len() > 1 {
+        stages
+            .iter()
+            .map(|stage| load_rules_for_command(stage))
+            .find(|rules| !rules.is_empty())
+            .unwrap_or_default()
+    } else {
+        load_rules_for_command(&pseudo_command)
     };
+    if run_rules {
+        rules.extend(bell_rule(&args));
+    }
+    let styled_input = should_colorize && args.input_format != InputFormat::Text;
 
     #[cfg(feature = "debug")]
     if let Some(start) = t_load_start.filter(|_| record_time) {
@@ -344,72 +1008,92 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
-    // Spawn the command with appropriate stdout handling
-    let mut cmd = Command::new(command_name);
-    cmd.args(args.command.iter().skip(1));
+    // Spawn the command with appropriate stdout handling. On Windows std only
+    // tries `.exe`, so wrappers such as npm.cmd are found through PATHEXT first
+    let (program, mut cmd) = match shell_line {
+        Some(line) => rgrc::utils::shell_command(line),
+        None => {
+            #[cfg(windows)]
+            let mut cmd = Command::new(
+                rgrc::utils::find_command(command_name)
+                    .map_or_else(|| command_name.into(), std::path::PathBuf::into_os_string),
+            );
+            #[cfg(not(windows))]
+            let mut cmd = Command::new(command_name);
+            cmd.args(args.command.iter().skip(1));
+            (command_name.to_string(), cmd)
+        }
+    };
+
+    // --strip: the command's output with its escape sequences removed
+    if args.strip {
+        cmd.stdout(Stdio::piped());
+        let mut child = spawn(&mut cmd, &program, args.timeout);
+        let stdout = child
+            .stdout
+            .take()
+            .expect("child did not have a handle to stdout");
+        if let Err(e) = rgrc::utils::strip_stream(stdout, io::stdout().lock())
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            eprintln!("Error stripping the output: {}", e);
+        }
+        wait_and_exit(&mut child);
+    }
 
     // Optimization: When colorization is not needed AND output goes directly to terminal,
     // let the child process output directly to stdout. This completely avoids any piping overhead.
     // However, when output is piped (e.g., rgrc cmd | other_cmd), we must still use pipes
     // to maintain data flow integrity.
-    if !should_colorize && stdout_is_terminal {
+    if !should_colorize && !colorize_stderr && stdout_is_terminal && !must_pipe {
         cmd.stdout(Stdio::inherit()); // Inherit parent's stdout directly
         cmd.stderr(Stdio::inherit()); // Also inherit stderr for consistency
 
         // Spawn and wait for the command
-        let mut child = match cmd.spawn() {
-            Ok(c) => c,
-            Err(e) => {
-                // Friendly error for missing executable
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    eprintln!("Error: command not found: '{}'", command_name);
-                    std::process::exit(127);
-                } else {
-                    eprintln!("Failed to spawn '{}': {}", command_name, e);
-                    std::process::exit(1);
-                }
-            }
-        };
-
-        let ecode = match child.wait() {
-            Ok(status) => status,
-            Err(e) => {
-                eprintln!("Failed while waiting for '{}': {}", command_name, e);
-                std::process::exit(1);
-            }
-        };
-        std::process::exit(ecode.code().unwrap_or(1));
+        let mut child = spawn(&mut cmd, &program, args.timeout);
+        wait_and_exit(&mut child);
     }
 
     // Final check: we need both the decision to colorize AND actual rules
     // If no rules were loaded, skip colorization even if it was requested
-    if should_colorize && rules.is_empty() {
+    if (should_colorize || colorize_stderr) && rules.is_empty() && !styled_input && !must_pipe {
         // No rules found, but we're piping - just pass through without coloring
         // This handles the edge case where rule loading failed or returned empty
         cmd.stdout(Stdio::inherit());
         cmd.stderr(Stdio::inherit());
-        let mut child = cmd.spawn().expect("failed to spawn command");
-        let ecode = child.wait().expect("failed to wait on child");
-        std::process::exit(ecode.code().unwrap_or(1));
+        let mut child = spawn(&mut cmd, &program, args.timeout);
+        wait_and_exit(&mut child);
     }
 
     // Only pipe stdout when colorization is actually needed
     // This avoids unnecessary piping overhead when colors are disabled or not beneficial
     cmd.stdout(Stdio::piped());
+    // --stderr: colorize the command's stderr as well, on a thread of its own
+    if colorize_stderr || (args.stderr && args.tag_stderr) {
+        cmd.stderr(Stdio::piped());
+    }
 
-    // Spawn the command subprocess.
-    let mut child = match cmd.spawn() {
-        Ok(c) => c,
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                eprintln!("Error: command not found: '{}'", command_name);
-                std::process::exit(127);
-            } else {
-                eprintln!("Failed to spawn '{}': {}", command_name, e);
-                std::process::exit(1);
-            }
+    // Not colouring into a pipe: hand the output on as it is, without splitting it into
+    // lines, which on Linux moves it from pipe to pipe inside the kernel
+    if !should_colorize && !colorize_stderr && !must_pipe {
+        let mut child = spawn(&mut cmd, &program, args.timeout);
+        let mut stdout = child
+            .stdout
+            .take()
+            .expect("child did not have a handle to stdout");
+        if let Err(e) = passthrough(&mut stdout)
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            fail(RgrcError::Io(e));
         }
-    };
+        // When the reader went away (e.g. `| head`), the command gets SIGPIPE as it
+        // would writing to the pipe itself
+        drop(stdout);
+        wait_and_exit(&mut child);
+    }
+
+    // Spawn the command subprocess.
+    let mut child = spawn(&mut cmd, &program, args.timeout);
 
     #[cfg(feature = "debug")]
     if let Some(start) = t0.filter(|_| record_time) {
@@ -423,112 +1107,77 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .take()
         .expect("child did not have a handle to stdout");
 
-    // Optimization: Use a larger buffer to reduce system call overhead
-    // This can significantly improve performance for commands with lots of output
-    let mut buffered_stdout = std::io::BufReader::with_capacity(64 * 1024, &mut stdout); // 64KB buffer
-
-    // OPTIMIZATION: Increased write buffer from 4KB to 64KB to match read buffer
-    // This reduces system call overhead for large outputs while LineBufferedWriter
-    // still ensures real-time line-by-line flushing for interactive commands
-    let mut buffered_writer = std::io::BufWriter::with_capacity(64 * 1024, std::io::stdout()); // 64KB buffer
-
-    // Create a line-buffered writer that flushes after each line
-    let mut line_buffered_writer = LineBufferedWriter::new(&mut buffered_writer);
-
-    // Use debug colorizer if debug_level is not Off
-    #[cfg(feature = "debug")]
-    {
-        if args.debug_level != DebugLevel::Off {
-            if let Err(e) = colorize_regex_with_debug(
-                &mut buffered_stdout,
-                &mut line_buffered_writer,
-                rules.as_slice(),
-                args.debug_level,
-            ) {
-                handle_box_error(e)?;
-            }
-        } else {
-            // Measure colorize performance when requested (feature guarded)
-            #[cfg(feature = "debug")]
-            {
-                if record_time {
-                    let t_before_colorize = Instant::now();
-                    if let Err(e) = colorize(
-                        &mut buffered_stdout,
-                        &mut line_buffered_writer,
-                        rules.as_slice(),
-                    ) {
-                        handle_box_error(e)?;
-                    }
-                    eprintln!("[rgrc:time] colorize: {:?}", t_before_colorize.elapsed());
-                } else {
-                    colorize(
-                        &mut buffered_stdout,
-                        &mut line_buffered_writer,
-                        rules.as_slice(),
-                    )?;
-                }
-            }
-
-            #[cfg(not(feature = "debug"))]
-            {
-                // Normal path (no instrumentation): just colorize
-                if let Err(e) = colorize(
-                    &mut buffered_stdout,
-                    &mut line_buffered_writer,
-                    rules.as_slice(),
-                ) {
-                    handle_box_error(e)?;
-                }
-            }
+    // -q / --exit-on-match: nothing is written, and the first matching line stops the
+    // command, which may otherwise run on (tail -f); the pipe closes for it as for grep -q
+    if let Some(selector) = args.exit_on_match {
+        let mut reports = Reports::new(&args, rules.len());
+        let stderr_colorizer = build_colorizer(rules.clone(), &args, colorize_stderr);
+        let colorizer = build_colorizer(rules, &args, should_colorize);
+        let result = run_colorizer(
+            &mut stdout,
+            &mut io::sink(),
+            child.stderr.take().map(|e| (e, &stderr_colorizer)),
+            &colorizer,
+            &args,
+            log_writer.as_mut().map(|w| w as &mut dyn Write),
+            &mut reports,
+        );
+        let matched = reports
+            .stats
+            .as_ref()
+            .is_some_and(|stats| stats.matched_lines(selector) > 0);
+        drop(stdout);
+        if matched {
+            rgrc::utils::stop_process(child.id(), false);
+        }
+        if let Err(e) = child.wait() {
+            fail(RgrcError::Io(e));
+        }
+        result.or_else(handle_box_error)?;
+        if let Some(mut log) = log_writer {
+            log.flush()?;
         }
+        reports.write(colorizer.rules())?;
+        std::process::exit(if matched {
+            0
+        } else if TIMED_OUT.load(Ordering::SeqCst) {
+            TIMEOUT_EXIT_CODE
+        } else {
+            NO_MATCH_EXIT_CODE
+        });
     }
 
-    #[cfg(not(feature = "debug"))]
-    {
-        // Measure colorize performance when requested (feature guarded)
-        #[cfg(feature = "debug")]
-        {
-            if record_time {
-                let t_before_colorize = Instant::now();
-                if let Err(e) = colorize(
-                    &mut buffered_stdout,
-                    &mut line_buffered_writer,
-                    rules.as_slice(),
-                ) {
-                    handle_box_error(e)?;
-                }
-                eprintln!("[rgrc:time] colorize: {:?}", t_before_colorize.elapsed());
-            } else {
-                if let Err(e) = colorize(
-                    &mut buffered_stdout,
-                    &mut line_buffered_writer,
-                    rules.as_slice(),
-                ) {
-                    handle_box_error(e)?;
-                }
-            }
-        }
+    // The colorizer reads the command's output 64KB at a time and flushes the writer
+    // once it has written every line read so far: lines that arrive together go out in
+    // one write, while interactive commands (ping) still show each line as it comes
+    let mut buffered_writer = std::io::BufWriter::with_capacity(64 * 1024, std::io::stdout()); // 64KB buffer
 
-        #[cfg(not(feature = "debug"))]
-        {
-            // Normal path (no instrumentation): just colorize
-            if let Err(e) = colorize(
-                &mut buffered_stdout,
-                &mut line_buffered_writer,
-                rules.as_slice(),
-            ) {
-                handle_box_error(e)?;
-            };
-        }
+    let mut reports = Reports::new(&args, rules.len());
+    let stderr_colorizer = build_colorizer(rules.clone(), &args, colorize_stderr);
+    let colorizer = build_colorizer(rules, &args, should_colorize);
+
+    if let Err(e) = run_colorizer(
+        &mut stdout,
+        &mut buffered_writer,
+        child.stderr.take().map(|e| (e, &stderr_colorizer)),
+        &colorizer,
+        &args,
+        log_writer.as_mut().map(|w| w as &mut dyn Write),
+        &mut reports,
+    ) {
+        handle_box_error(e)?;
     }
 
     // Ensure all buffered output is written
     if let Err(e) = buffered_writer.flush() {
         handle_io_error(e)?;
     }
+    if let Some(mut log) = log_writer {
+        log.flush()?;
+    }
 
     // Wait for the spawned command to complete and propagate its exit code.
-    let ecode = child.wait().expect("failed to wait on child");
-    std::process::exit(ecode.code().expect("need an exit code"));
+    let status = child.wait().unwrap_or_else(|e| fail(RgrcError::Io(e)));
+    reports.write(colorizer.rules())?;
+    std::process::exit(reports.exit_code(exit_code(status)));
 }
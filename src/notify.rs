@@ -0,0 +1,116 @@
+//! # notify.rs - Desktop notifications on matching lines
+//!
+//! `rgrc --notify-on REGEX` sends a desktop notification when a line of the
+//! command's output matches, so a long-running command (`kubectl get pods -w`
+//! showing CrashLoopBackOff) can be left in the background. Notifications go
+//! through `notify-send` on Linux and the BSDs and `osascript` on macOS, and are
+//! rate-limited to one per `NOTIFY_INTERVAL`.
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::grc::CompiledRegex;
+
+/// Shortest time between two notifications, so that a burst of matching lines
+/// (every pod of a deployment failing at once) gives one.
+pub const NOTIFY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Sends a notification for the lines matching a regex (`--notify-on`).
+pub struct Notifier {
+    regex: CompiledRegex,
+    title: String,
+    last: Option<Instant>,
+}
+
+impl Notifier {
+    /// A notifier for the lines `regex` matches, with `title` as the title of
+    /// its notifications (the name of the command).
+    pub fn new(regex: CompiledRegex, title: &str) -> Self {
+        Notifier {
+            regex,
+            title: title.to_string(),
+            last: None,
+        }
+    }
+
+    /// Send a notification with `line` as its text if the regex matches it,
+    /// unless one was sent less than `NOTIFY_INTERVAL` ago.
+    pub fn check(&mut self, line: &str) {
+        if self.due(line, Instant::now()) {
+            send(&self.title, line);
+        }
+    }
+
+    /// Whether `line`, read at `now`, gets a notification; if so it counts as sent.
+    fn due(&mut self, line: &str, now: Instant) -> bool {
+        if !self.regex.is_match(line)
+            || self
+                .last
+                .is_some_and(|last| now.duration_since(last) < NOTIFY_INTERVAL)
+        {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+/// The command showing a notification on this platform, if there is one.
+fn notification_command(title: &str, text: &str) -> Option<Command> {
+    if cfg!(target_os = "macos") {
+        // Passed as arguments rather than spliced into the script, so no quoting is needed
+        let mut cmd = Command::new("osascript");
+        cmd.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            title,
+            text,
+        ]);
+        Some(cmd)
+    } else if cfg!(unix) {
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name=rgrc", "--", title, text]);
+        Some(cmd)
+    } else {
+        None
+    }
+}
+
+/// Show a notification without waiting for it; failures are reported on stderr.
+fn send(title: &str, text: &str) {
+    let Some(mut cmd) = notification_command(title, text) else {
+        eprintln!("rgrc: desktop notifications are not supported on this platform");
+        return;
+    };
+    let spawned = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        // Reap it in the background, like the command= hooks
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("rgrc: failed to send a notification: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifications_are_rate_limited() {
+        let mut notifier = Notifier::new(CompiledRegex::new("CrashLoop").unwrap(), "kubectl");
+        let start = Instant::now();
+        assert!(!notifier.due("web-1   Running", start));
+        assert!(notifier.due("web-1   CrashLoopBackOff", start));
+        assert!(!notifier.due("web-2   CrashLoopBackOff", start + Duration::from_secs(1)));
+        assert!(notifier.due("web-1   CrashLoopBackOff", start + NOTIFY_INTERVAL));
+    }
+}
@@ -0,0 +1,730 @@
+//! # prefilter.rs - Literal prefilters for colorization rules
+//!
+//! Many rules in the bundled configs contain a plain literal that every match
+//! must include (`ERROR`, `docker`, `/dev/`, ...). Checking for that literal
+//! with a substring search is far cheaper than running the regex, so the
+//! colorizer uses it to skip rules that cannot match a line.
+//!
+//! ## Extraction
+//!
+//! `required_literal()` is deliberately conservative: it only returns a
+//! literal when the pattern has no top-level alternation and sets no inline
+//! flags, and it only considers text outside groups and classes.
+//! When in doubt it returns `None`, which simply means "always run the regex".
+//!
+//! ## Literal screening
+//!
+//! `LiteralPrefilter` feeds the required literals of all rules into one
+//! Aho-Corasick automaton (`LiteralMatcher`), so a single scan of each line
+//! rules out every rule whose literal does not occur in it.
+//!
+//! ## Adaptive demotion
+//!
+//! The scan is not free either, and it is wasted on rules that match most
+//! lines. `AdaptivePrefilter` therefore only consults it for rules that have
+//! gone `DEMOTE_AFTER_LINES` lines without a match, re-enabling a rule as soon
+//! as its literal shows up; lines are not scanned while no rule is demoted.
+//!
+//! ## Rule sets
+//!
+//! With the `regex-set` feature, `RuleSet` compiles every Fast rule into one
+//! `regex::RegexSet`, so a single pass over the line tells which rules can
+//! match at all; only those are then run individually to extract captures.
+
+/// Return the longest literal that must appear in every match of `pattern`.
+///
+/// Returns `None` when no such literal can be determined safely (top-level
+/// alternation, inline flags such as `(?i)` or `(?x)`, or no literal run at all).
+///
+/// # Examples
+///
+/// ```
+/// use rgrc::prefilter::required_literal;
+///
+/// assert_eq!(required_literal(r"^ERROR:\s+(.*)"), Some("ERROR:".to_string()));
+/// assert_eq!(required_literal(r"warn(ing)?"), Some("warn".to_string()));
+/// assert_eq!(required_literal(r"foo|bar"), None);
+/// ```
+pub fn required_literal(pattern: &str) -> Option<String> {
+    if has_inline_flags(pattern) {
+        return None;
+    }
+
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut best = String::new();
+    let mut run = String::new();
+    let mut i = 0;
+
+    // Finish the current literal run, keeping it if it is the longest so far
+    fn flush(run: &mut String, best: &mut String) {
+        if run.chars().count() > best.chars().count() {
+            *best = std::mem::take(run);
+        } else {
+            run.clear();
+        }
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '|' => return None,
+            '(' => {
+                // Skip the whole group; its contents may be optional or alternated
+                flush(&mut run, &mut best);
+                i = skip_group(&chars, i)?;
+                continue;
+            }
+            '[' => {
+                flush(&mut run, &mut best);
+                i = skip_class(&chars, i)?;
+                continue;
+            }
+            '?' | '*' => {
+                // The previous character is optional
+                run.pop();
+                flush(&mut run, &mut best);
+            }
+            '{' => {
+                // Counted repetition: treat the previous character as optional
+                run.pop();
+                flush(&mut run, &mut best);
+                while i < chars.len() && chars[i] != '}' {
+                    i += 1;
+                }
+            }
+            '+' => {
+                // The previous character is required but may repeat
+                flush(&mut run, &mut best);
+            }
+            '.' | '^' | '$' | ')' => flush(&mut run, &mut best),
+            '\\' => {
+                let next = *chars.get(i + 1)?;
+                if next.is_ascii_alphanumeric() {
+                    // Codes (\x1b, \u{e9}) are literal characters; classes (\d, \w,
+                    // \p{L}) and assertions (\b) end the run
+                    let (code, end) = escape(&chars, i)?;
+                    match code {
+                        Some(c) => run.push(c),
+                        None => flush(&mut run, &mut best),
+                    }
+                    i = end;
+                } else {
+                    run.push(next);
+                    i += 2;
+                }
+                continue;
+            }
+            _ => run.push(c),
+        }
+        i += 1;
+    }
+    flush(&mut run, &mut best);
+
+    if best.is_empty() { None } else { Some(best) }
+}
+
+/// Read the escape sequence starting with the backslash at `start`, whose next
+/// character is a letter or digit.
+///
+/// Returns the character a code (`\xNN`, `\x{..}`, `\uNNNN`, `\u{..}`,
+/// `\UNNNNNNNN`) stands for, or `None` for any other escape, along with the
+/// index just past the sequence. Returns `None` when a braced escape is not closed.
+fn escape(chars: &[char], start: usize) -> Option<(Option<char>, usize)> {
+    let kind = chars[start + 1];
+    let rest = start + 2;
+    // Index of the closing brace of a `{..}` argument at `rest`
+    let closing = || {
+        chars[rest..]
+            .iter()
+            .position(|&c| c == '}')
+            .map(|offset| rest + offset)
+    };
+    match kind {
+        'x' | 'u' | 'U' => {
+            let (digits, end) = if chars.get(rest) == Some(&'{') {
+                let close = closing()?;
+                (&chars[rest + 1..close], close + 1)
+            } else {
+                let width = match kind {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let count = chars[rest..]
+                    .iter()
+                    .take(width)
+                    .take_while(|c| c.is_ascii_hexdigit())
+                    .count();
+                (&chars[rest..rest + count], rest + count)
+            };
+            let code = u32::from_str_radix(&digits.iter().collect::<String>(), 16)
+                .ok()
+                .and_then(char::from_u32);
+            Some((code, end))
+        }
+        // Unicode classes and named characters: \p{Greek}, \pL, \N{..}
+        'p' | 'P' | 'N' => match chars.get(rest) {
+            Some('{') => Some((None, closing()? + 1)),
+            Some(_) => Some((None, rest + 1)),
+            None => Some((None, rest)),
+        },
+        _ => Some((None, rest)),
+    }
+}
+
+/// Whether `pattern` sets inline flags (`(?i)`, `(?x:...)`, `(?-s)`, ...), which
+/// change how literal text matches. Named groups (`(?P<name>`) are not flags.
+fn has_inline_flags(pattern: &str) -> bool {
+    pattern.match_indices("(?").any(|(pos, _)| {
+        matches!(
+            pattern[pos + 2..].chars().next(),
+            Some(c) if (c.is_ascii_alphabetic() && c != 'P') || c == '-'
+        )
+    })
+}
+
+/// Return the index just past the group opening at `start`, or `None` when
+/// the group is unbalanced.
+fn skip_group(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => {
+                i = skip_class(chars, i)?;
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Return the index just past the character class opening at `start`.
+fn skip_class(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    // A leading `^` and a leading `]` are part of the class
+    if chars.get(i) == Some(&'^') {
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' if chars.get(i + 1) == Some(&':') => {
+                // POSIX class such as [:alpha:]
+                while i < chars.len() && !(chars[i] == ']' && chars[i - 1] == ':') {
+                    i += 1;
+                }
+            }
+            ']' => return Some(i + 1),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Multi-literal substring matcher (Aho-Corasick automaton over bytes).
+///
+/// Built once from the required literals of a rule list; `scan()` then finds
+/// which of them occur in a line with a single pass, however many there are.
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    nodes: Vec<AcNode>,
+    literal_count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct AcNode {
+    /// Outgoing edges, sorted by byte
+    next: Vec<(u8, u32)>,
+    /// Longest proper suffix that is also a trie path
+    fail: u32,
+    /// Literals ending at this node, including those inherited via `fail`
+    outputs: Vec<u32>,
+}
+
+impl AcNode {
+    fn child(&self, byte: u8) -> Option<u32> {
+        self.next
+            .binary_search_by_key(&byte, |(b, _)| *b)
+            .ok()
+            .map(|i| self.next[i].1)
+    }
+}
+
+impl LiteralMatcher {
+    /// Build the automaton for `literals`; ids are their positions in the slice.
+    pub fn new<S: AsRef<str>>(literals: &[S]) -> Self {
+        let mut nodes = vec![AcNode::default()];
+
+        // Phase 1: trie of all literals
+        for (id, literal) in literals.iter().enumerate() {
+            let mut state = 0usize;
+            for &byte in literal.as_ref().as_bytes() {
+                state = match nodes[state].child(byte) {
+                    Some(next) => next as usize,
+                    None => {
+                        nodes.push(AcNode::default());
+                        let next = nodes.len() - 1;
+                        let edges = &mut nodes[state].next;
+                        let pos = edges.partition_point(|(b, _)| *b < byte);
+                        edges.insert(pos, (byte, next as u32));
+                        next
+                    }
+                };
+            }
+            nodes[state].outputs.push(id as u32);
+        }
+
+        // Phase 2: failure links in breadth-first order
+        let mut queue = std::collections::VecDeque::new();
+        for &(_, child) in &nodes[0].next {
+            queue.push_back(child as usize);
+        }
+        while let Some(state) = queue.pop_front() {
+            for (byte, child) in nodes[state].next.clone() {
+                let mut fallback = nodes[state].fail as usize;
+                let fail = loop {
+                    if let Some(target) = nodes[fallback].child(byte) {
+                        break target;
+                    }
+                    if fallback == 0 {
+                        break 0;
+                    }
+                    fallback = nodes[fallback].fail as usize;
+                };
+                let child = child as usize;
+                nodes[child].fail = fail;
+                let inherited = nodes[fail as usize].outputs.clone();
+                nodes[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        LiteralMatcher {
+            nodes,
+            literal_count: literals.len(),
+        }
+    }
+
+    /// Number of literals the matcher was built from.
+    pub fn len(&self) -> usize {
+        self.literal_count
+    }
+
+    /// Whether the matcher was built from no literals.
+    pub fn is_empty(&self) -> bool {
+        self.literal_count == 0
+    }
+
+    /// Mark in `present` every literal that occurs in `haystack`.
+    ///
+    /// `present` must have `len()` entries; it is not cleared first.
+    pub fn scan(&self, haystack: &str, present: &mut [bool]) {
+        let mut state = 0usize;
+        for &byte in haystack.as_bytes() {
+            loop {
+                if let Some(next) = self.nodes[state].child(byte) {
+                    state = next as usize;
+                    break;
+                }
+                if state == 0 {
+                    break;
+                }
+                state = self.nodes[state].fail as usize;
+            }
+            for &id in &self.nodes[state].outputs {
+                present[id as usize] = true;
+            }
+        }
+    }
+}
+
+/// Per-line screen of a rule list by required literals.
+///
+/// Each rule with a `required_literal()` is mapped to a literal in a shared
+/// `LiteralMatcher`; `candidates()` reports the rules whose literal is absent
+/// from a line as unable to match. Rules without a literal always pass.
+#[derive(Debug, Clone)]
+pub struct LiteralPrefilter {
+    matcher: LiteralMatcher,
+    /// Literal id of each rule, indexed like the rule list
+    rule_literals: Vec<Option<usize>>,
+}
+
+impl LiteralPrefilter {
+    /// Build a prefilter for rules with the given regex sources.
+    ///
+    /// Returns `None` when no rule has a usable literal.
+    pub fn new<'a, I: IntoIterator<Item = &'a str>>(patterns: I) -> Option<Self> {
+        let mut literals: Vec<String> = Vec::new();
+        let mut rule_literals = Vec::new();
+        for pattern in patterns {
+            let id = required_literal(pattern).map(|literal| {
+                match literals.iter().position(|known| *known == literal) {
+                    Some(id) => id,
+                    None => {
+                        literals.push(literal);
+                        literals.len() - 1
+                    }
+                }
+            });
+            rule_literals.push(id);
+        }
+
+        if literals.is_empty() {
+            return None;
+        }
+        Some(LiteralPrefilter {
+            matcher: LiteralMatcher::new(&literals),
+            rule_literals,
+        })
+    }
+
+    /// Return, per rule, whether it may match `line`.
+    pub fn candidates(&self, line: &str) -> Vec<bool> {
+        let mut present = vec![false; self.matcher.len()];
+        self.matcher.scan(line, &mut present);
+        self.rule_literals
+            .iter()
+            .map(|id| id.is_none_or(|id| present[id]))
+            .collect()
+    }
+}
+
+/// Number of consecutive lines without a match after which a rule is demoted
+/// to its literal check.
+pub const DEMOTE_AFTER_LINES: usize = 2000;
+
+/// `LiteralPrefilter` consulted for the rules that rarely match.
+///
+/// Rules start active and run on every line. A rule with a required literal
+/// that goes `DEMOTE_AFTER_LINES` lines without a match is demoted: from then
+/// on it runs only on lines containing its literal, and such a line re-enables
+/// it. While some rule is demoted every line is scanned, and the scan also
+/// screens the active rules, since it is paid for anyway.
+#[derive(Debug, Clone)]
+pub struct AdaptivePrefilter {
+    literals: LiteralPrefilter,
+    /// Consecutive lines each rule went without a match, indexed like the rule list
+    idle_lines: Vec<usize>,
+    /// Number of rules currently demoted
+    demoted: usize,
+}
+
+impl AdaptivePrefilter {
+    /// Build a prefilter for rules with the given regex sources, all active.
+    ///
+    /// Returns `None` when no rule has a usable literal.
+    pub fn new<'a, I: IntoIterator<Item = &'a str>>(patterns: I) -> Option<Self> {
+        let literals = LiteralPrefilter::new(patterns)?;
+        let idle_lines = vec![0; literals.rule_literals.len()];
+        Some(AdaptivePrefilter {
+            literals,
+            idle_lines,
+            demoted: 0,
+        })
+    }
+
+    /// Whether `rule` is demoted to its literal check.
+    pub fn is_demoted(&self, rule: usize) -> bool {
+        self.literals.rule_literals[rule].is_some() && self.idle_lines[rule] >= DEMOTE_AFTER_LINES
+    }
+
+    /// Return, per rule, whether it may match `line`, or `None` when no rule is
+    /// demoted and all of them run. Demoted rules whose literal occurs are re-enabled.
+    pub fn candidates(&mut self, line: &str) -> Option<Vec<bool>> {
+        if self.demoted == 0 {
+            return None;
+        }
+        let candidates = self.literals.candidates(line);
+        for (rule, &present) in candidates.iter().enumerate() {
+            if present && self.is_demoted(rule) {
+                self.idle_lines[rule] = 0;
+                self.demoted -= 1;
+            }
+        }
+        Some(candidates)
+    }
+
+    /// Record whether `rule` matched the line just processed; a rule the
+    /// candidates ruled out did not.
+    pub fn record(&mut self, rule: usize, matched: bool) {
+        if matched {
+            if self.is_demoted(rule) {
+                self.demoted -= 1;
+            }
+            self.idle_lines[rule] = 0;
+        } else if self.literals.rule_literals[rule].is_some() {
+            self.idle_lines[rule] = self.idle_lines[rule].saturating_add(1);
+            if self.idle_lines[rule] == DEMOTE_AFTER_LINES {
+                self.demoted += 1;
+            }
+        }
+    }
+}
+
+/// Minimum number of set-compatible rules before building a `RuleSet` pays off.
+#[cfg(feature = "regex-set")]
+pub const RULE_SET_MIN_RULES: usize = 4;
+
+/// Whether the full `regex` crate matches at least everything regex-lite does
+/// for `pattern`, so a set miss proves the rule cannot match.
+///
+/// regex-lite's Perl classes are ASCII-only while the `regex` crate's are
+/// Unicode-aware. Positive classes only grow under Unicode, but negated ones
+/// (`\D`, `\W`, `\S`, `[^\w]`) and word boundaries can shrink, so such
+/// patterns are left out of the set.
+#[cfg(any(feature = "regex-set", feature = "dfa-cache"))]
+pub(crate) fn set_compatible(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut in_negated_class = false;
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some('b' | 'B' | 'D' | 'W' | 'S') => return false,
+                    Some('d' | 'w' | 's') if in_negated_class => return false,
+                    _ => {}
+                }
+                i += 2;
+                continue;
+            }
+            '[' if !in_class => {
+                in_class = true;
+                in_negated_class = chars.get(i + 1) == Some(&'^');
+            }
+            ']' if in_class => {
+                in_class = false;
+                in_negated_class = false;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    true
+}
+
+/// One-pass screen of all Fast rules using a `regex::RegexSet` (`regex-set` feature).
+///
+/// `candidates()` runs the set once per line and reports, for every rule,
+/// whether it can possibly match. Rules that are not in the set (Enhanced
+/// lookaround rules, or patterns whose semantics differ between the two
+/// engines) are always reported as candidates.
+#[cfg(feature = "regex-set")]
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    set: regex::RegexSet,
+    /// Position of each rule in the set, indexed like the rule list
+    slots: Vec<Option<usize>>,
+}
+
+#[cfg(feature = "regex-set")]
+impl RuleSet {
+    /// Build a set from the Fast rules of `rules`.
+    ///
+    /// Returns `None` when fewer than `RULE_SET_MIN_RULES` rules qualify,
+    /// since the set then costs more than it saves. Lazily compiled rules are
+    /// compiled here, as only the compiled form tells which engine they use.
+    pub fn new(rules: &[crate::grc::GrcatConfigEntry]) -> Option<Self> {
+        use crate::grc::CompiledRegex;
+
+        let mut patterns = Vec::new();
+        let mut slots = vec![None; rules.len()];
+        for (index, rule) in rules.iter().enumerate() {
+            if let Some(CompiledRegex::Fast(re)) = rule.regex.resolve()
+                && set_compatible(re.as_str())
+                && regex::Regex::new(re.as_str()).is_ok()
+            {
+                slots[index] = Some(patterns.len());
+                patterns.push(re.as_str());
+            }
+        }
+
+        if patterns.len() < RULE_SET_MIN_RULES {
+            return None;
+        }
+
+        let set = regex::RegexSet::new(&patterns).ok()?;
+        Some(RuleSet { set, slots })
+    }
+
+    /// Return, per rule, whether it may match `line`.
+    pub fn candidates(&self, line: &str) -> Vec<bool> {
+        let matched = self.set.matches(line);
+        self.slots
+            .iter()
+            .map(|slot| slot.is_none_or(|i| matched.matched(i)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_literal() {
+        assert_eq!(required_literal("ERROR"), Some("ERROR".to_string()));
+        assert_eq!(
+            required_literal(r"^(\S+)\s+docker-proxy"),
+            Some("docker-proxy".to_string())
+        );
+        assert_eq!(required_literal(r"\d+\.\d+ ms"), Some(" ms".to_string()));
+        assert_eq!(required_literal(r"/dev/\w+"), Some("/dev/".to_string()));
+        assert_eq!(required_literal(r"colou?r"), Some("colo".to_string()));
+        assert_eq!(required_literal(r"ab{2,3}c"), Some("a".to_string()));
+        assert_eq!(required_literal(r"[a-z]+ok[^x]"), Some("ok".to_string()));
+
+        // Alternation, case-insensitivity and pure classes give no literal
+        assert_eq!(required_literal(r"foo|bar"), None);
+        assert_eq!(required_literal(r"(?i)error"), None);
+        assert_eq!(required_literal(r"(?x) a b c"), None);
+        assert_eq!(
+            required_literal(r"(?P<user>\w+)@host"),
+            Some("@host".to_string())
+        );
+        assert_eq!(required_literal(r"(?<=\s)ms"), Some("ms".to_string()));
+        assert_eq!(required_literal(r"\d+\s\w+"), None);
+        assert_eq!(required_literal(r"(foo|bar)"), None);
+
+        // Character codes are decoded into the literal
+        assert_eq!(required_literal(r"\x1b\["), Some("\x1b[".to_string()));
+        assert_eq!(required_literal(r"\x41BC"), Some("ABC".to_string()));
+        assert_eq!(required_literal(r"\u{1b}\[0m"), Some("\x1b[0m".to_string()));
+        assert_eq!(required_literal(r"\u00e9t\u00e9"), Some("été".to_string()));
+        assert_eq!(required_literal(r"\x{1F600}!"), Some("😀!".to_string()));
+        // Unicode classes end the run without leaving their names in it
+        assert_eq!(required_literal(r"\p{Greek}+ok"), Some("ok".to_string()));
+        assert_eq!(required_literal(r"ab\pLcd"), Some("ab".to_string()));
+        assert_eq!(required_literal(r"x\P{L}"), Some("x".to_string()));
+
+        // Malformed patterns never panic
+        assert_eq!(required_literal(r"abc\"), None);
+        assert_eq!(required_literal(r"(abc"), None);
+        assert_eq!(required_literal(r"[abc"), None);
+        assert_eq!(required_literal(r"a\x{41"), None);
+        assert_eq!(required_literal(r"\x"), None);
+    }
+
+    #[test]
+    fn test_adaptive_demotion() {
+        let mut prefilter =
+            AdaptivePrefilter::new([r"ERROR: (.*)", r"\d+"]).expect("literal present");
+        assert_eq!(prefilter.candidates("all good"), None);
+
+        for _ in 0..DEMOTE_AFTER_LINES {
+            assert_eq!(prefilter.candidates("all good 1"), None);
+            prefilter.record(0, false);
+            prefilter.record(1, false);
+        }
+        // Only the rule with a literal is demoted
+        assert!(prefilter.is_demoted(0));
+        assert!(!prefilter.is_demoted(1));
+        assert_eq!(prefilter.candidates("all good 2"), Some(vec![false, true]));
+        prefilter.record(0, false);
+        assert!(prefilter.is_demoted(0));
+
+        // The literal re-enables the rule, and lines are no longer scanned
+        assert_eq!(
+            prefilter.candidates("ERROR: disk full"),
+            Some(vec![true, true])
+        );
+        assert!(!prefilter.is_demoted(0));
+        prefilter.record(0, true);
+        assert_eq!(prefilter.candidates("all good 3"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "regex-set")]
+    fn test_set_compatible() {
+        assert!(set_compatible(r"^ERROR (\d+)"));
+        assert!(set_compatible(r"[a-z\d]+ \w+ \s"));
+        assert!(!set_compatible(r"\bword\b"));
+        assert!(!set_compatible(r"\W+"));
+        assert!(!set_compatible(r"[^\w.]+"));
+        assert!(set_compatible(r"[^abc]+ \d"));
+    }
+
+    #[test]
+    #[cfg(feature = "regex-set")]
+    fn test_rule_set_candidates() {
+        use crate::Style;
+        use crate::grc::{CompiledRegex, GrcatConfigEntry};
+
+        let entry =
+            |p: &str| GrcatConfigEntry::new(CompiledRegex::new(p).unwrap(), vec![Style::new()]);
+        let rules = vec![
+            entry(r"ERROR"),
+            entry(r"\d+ ms"),
+            entry(r"\bword\b"),
+            entry(r"^warn"),
+            entry(r"(\w+)@(\w+)"),
+        ];
+
+        let set = RuleSet::new(&rules).expect("four compatible rules");
+        assert_eq!(
+            set.candidates("12 ms"),
+            vec![false, true, true, false, false]
+        );
+        assert_eq!(
+            set.candidates("ERROR user@host"),
+            vec![true, false, true, false, true]
+        );
+
+        // Too few rules for a set
+        assert!(RuleSet::new(&rules[..2]).is_none());
+    }
+
+    #[test]
+    fn test_literal_matcher() {
+        let matcher = LiteralMatcher::new(&["he", "she", "his", "hers", "bytes from"]);
+        assert_eq!(matcher.len(), 5);
+
+        let mut present = vec![false; matcher.len()];
+        matcher.scan("ushers", &mut present);
+        assert_eq!(present, vec![true, true, false, true, false]);
+
+        let mut present = vec![false; matcher.len()];
+        matcher.scan("64 bytes from 10.0.0.1", &mut present);
+        assert_eq!(present, vec![false, false, false, false, true]);
+
+        let mut present = vec![false; matcher.len()];
+        matcher.scan("", &mut present);
+        assert!(present.iter().all(|p| !p));
+
+        assert!(LiteralMatcher::new::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_literal_prefilter_candidates() {
+        let prefilter =
+            LiteralPrefilter::new([r"bytes from (\S+)", r"\d+", r"ESTAB", r"^ESTAB\w*"])
+                .expect("literals present");
+
+        assert_eq!(
+            prefilter.candidates("64 bytes from host"),
+            vec![true, true, false, false]
+        );
+        assert_eq!(
+            prefilter.candidates("tcp ESTAB 0 0"),
+            vec![false, true, true, true]
+        );
+        assert!(LiteralPrefilter::new([r"\d+", r"\w+"]).is_none());
+    }
+}
@@ -0,0 +1,379 @@
+//! # preview.rs - Interactive config previewer (`tui` feature)
+//!
+//! `rgrv preview CONF SAMPLE` shows the rules of a grcat config next to a
+//! sample of command output colorized with them. Rules can be toggled on and
+//! off and their regexes edited; the sample is recolorized on every change,
+//! which makes writing a new config a matter of seconds per rule.
+//!
+//! The screen is drawn with plain ANSI sequences over `crossterm`, which only
+//! provides raw mode, the alternate screen and key events. `Preview` itself
+//! does not touch the terminal, so its rendering and key handling are tested
+//! without one.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::{cursor, execute, queue, terminal};
+
+use crate::colorizer::colorize_regex;
+use crate::grc::{CompiledRegex, GrcatConfigEntry, GrcatConfigReader, write_grcat_config};
+use crate::style::Style;
+
+const KEYS: &str = "↑↓ select  space toggle  e edit  w save  PgUp/PgDn scroll  q quit";
+
+/// State of the previewer: the rules being edited and the sample they color.
+pub struct Preview {
+    conf: PathBuf,
+    rules: Vec<GrcatConfigEntry>,
+    enabled: Vec<bool>,
+    sample: Vec<String>,
+    /// Selected rule
+    selected: usize,
+    /// First sample line shown
+    scroll: usize,
+    /// Regex being typed while editing the selected rule
+    editing: Option<String>,
+    /// Message shown in the bottom line until the next key
+    status: Option<String>,
+}
+
+impl Preview {
+    /// Create a previewer for the rules of the config `conf`, read from `source`,
+    /// and the sample output `sample`.
+    pub fn new(conf: &Path, source: &str, sample: &str) -> Self {
+        let rules: Vec<GrcatConfigEntry> =
+            GrcatConfigReader::new(source.as_bytes().lines()).collect();
+        Preview {
+            conf: conf.to_path_buf(),
+            enabled: vec![true; rules.len()],
+            rules,
+            sample: sample.lines().map(str::to_string).collect(),
+            selected: 0,
+            scroll: 0,
+            editing: None,
+            status: None,
+        }
+    }
+
+    /// Apply a key press; returns false when the previewer should exit.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        self.status = None;
+        if let Some(buffer) = self.editing.as_mut() {
+            match key {
+                KeyCode::Char(c) => buffer.push(c),
+                KeyCode::Backspace => {
+                    buffer.pop();
+                }
+                KeyCode::Esc => self.editing = None,
+                KeyCode::Enter => self.finish_edit(),
+                _ => {}
+            }
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rules.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => {
+                if let Some(enabled) = self.enabled.get_mut(self.selected) {
+                    *enabled = !*enabled;
+                }
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                self.editing = self
+                    .rules
+                    .get(self.selected)
+                    .map(|rule| rule.regex.as_str().to_string());
+            }
+            KeyCode::Char('w') => self.status = Some(self.save()),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => {
+                self.scroll = (self.scroll + 10).min(self.sample.len().saturating_sub(1));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Replace the regex of the selected rule with the edited one, if it compiles.
+    fn finish_edit(&mut self) {
+        let Some(pattern) = self.editing.take() else {
+            return;
+        };
+        match CompiledRegex::new(&pattern) {
+            Ok(regex) => self.rules[self.selected].regex = regex,
+            Err(e) => {
+                // Keep editing so the typo can be fixed
+                self.status = Some(e.to_string());
+                self.editing = Some(pattern);
+            }
+        }
+    }
+
+    /// Write the enabled rules next to the config as `CONF.new`.
+    fn save(&self) -> String {
+        let mut path = self.conf.clone().into_os_string();
+        path.push(".new");
+        let path = PathBuf::from(path);
+
+        let mut out = Vec::new();
+        let written = write_grcat_config(&mut out, &self.active_rules())
+            .and_then(|_| std::fs::write(&path, out));
+        match written {
+            Ok(()) => format!("Saved enabled rules to {}", path.display()),
+            Err(e) => format!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+
+    fn active_rules(&self) -> Vec<GrcatConfigEntry> {
+        self.rules
+            .iter()
+            .zip(&self.enabled)
+            .filter(|(_, enabled)| **enabled)
+            .map(|(rule, _)| rule.clone())
+            .collect()
+    }
+
+    /// Render a `width` x `height` frame, one string (with ANSI styles) per row.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let left = (width * 2 / 5).max(12).min(width);
+        let right = width.saturating_sub(left + 1);
+        let body = height.saturating_sub(2);
+
+        let active = self.rules_summary();
+        let header = format!(
+            "{} ({}) | sample: {} lines",
+            self.conf.display(),
+            active,
+            self.sample.len()
+        );
+        let mut frame = vec![
+            Style::new()
+                .bold()
+                .apply_to(&clip(&header, width))
+                .to_string(),
+        ];
+
+        // Keep the selected rule on screen
+        let first_rule = self.selected.saturating_sub(body.saturating_sub(1));
+        let visible = &self.sample[self.scroll.min(self.sample.len())..];
+        let colored = self.colorize(&visible[..visible.len().min(body)]);
+
+        for row in 0..body {
+            let index = first_rule + row;
+            let rule = match self.rules.get(index) {
+                Some(rule) => {
+                    let mark = if self.enabled[index] { "x" } else { " " };
+                    let text = pad(
+                        &clip(
+                            &format!("[{}] {:>2} {}", mark, index, rule.regex.as_str()),
+                            left,
+                        ),
+                        left,
+                    );
+                    if index == self.selected {
+                        Style::new().reverse().apply_to(&text).to_string()
+                    } else {
+                        text
+                    }
+                }
+                None => " ".repeat(left),
+            };
+            let sample = colored
+                .get(row)
+                .map_or_else(String::new, |line| clip(line, right));
+            frame.push(format!("{}|{}", rule, sample));
+        }
+
+        let footer = match (&self.editing, &self.status) {
+            (Some(buffer), Some(status)) => format!("regexp={}_  ({})", buffer, status),
+            (Some(buffer), None) => format!("regexp={}_  (Enter apply, Esc cancel)", buffer),
+            (None, Some(status)) => status.clone(),
+            (None, None) => KEYS.to_string(),
+        };
+        frame.push(
+            Style::new()
+                .reverse()
+                .apply_to(&pad(&clip(&footer, width), width))
+                .to_string(),
+        );
+        frame
+    }
+
+    fn rules_summary(&self) -> String {
+        let enabled = self.enabled.iter().filter(|e| **e).count();
+        format!("{}/{} rules enabled", enabled, self.rules.len())
+    }
+
+    /// Colorize `lines` with the enabled rules, one output string per line.
+    fn colorize(&self, lines: &[String]) -> Vec<String> {
+        let mut input = lines.join("\n");
+        input.push('\n');
+        let mut output = Vec::new();
+        if colorize_regex(&mut input.as_bytes(), &mut output, &self.active_rules()).is_err() {
+            return lines.to_vec();
+        }
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Run the previewer for the config `conf` and the sample output `sample` until `q`.
+pub fn run(conf: &Path, sample: &Path) -> io::Result<()> {
+    let source = std::fs::read_to_string(conf)?;
+    let sample = std::fs::read_to_string(sample)?;
+    let mut preview = Preview::new(conf, &source, &sample);
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = event_loop(&mut preview, &mut stdout);
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn event_loop<W: Write>(preview: &mut Preview, out: &mut W) -> io::Result<()> {
+    loop {
+        let (width, height) = terminal::size()?;
+        for (row, line) in preview
+            .render(width as usize, height as usize)
+            .iter()
+            .enumerate()
+        {
+            queue!(
+                out,
+                cursor::MoveTo(0, row as u16),
+                terminal::Clear(terminal::ClearType::CurrentLine)
+            )?;
+            write!(out, "{}", line)?;
+        }
+        out.flush()?;
+
+        // Resizes and other events simply redraw
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !preview.handle_key(key.code)
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Cut `text` to `width` visible characters, keeping (and closing) ANSI sequences.
+fn clip(text: &str, width: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut visible = 0;
+    let mut styled = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            styled = true;
+            out.push(c);
+            // Copy the whole CSI sequence up to its final byte
+            for c in chars.by_ref() {
+                out.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else if visible < width {
+            out.push(c);
+            visible += 1;
+        }
+    }
+    if styled {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+/// Pad plain `text` with spaces to `width` characters.
+fn pad(text: &str, width: usize) -> String {
+    format!("{:<width$}", text, width = width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::strip_ansi;
+
+    const CONF: &str = "regexp=(ERROR)\ncolours=default,red\n-\nregexp=\\d+ms\ncolours=green\n";
+    const SAMPLE: &str = "ERROR after 12ms\nok\n";
+
+    fn plain(frame: &[String]) -> Vec<String> {
+        frame
+            .iter()
+            .map(|line| strip_ansi(line).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_shows_rules_and_colored_sample() {
+        let preview = Preview::new(Path::new("conf.demo"), CONF, SAMPLE);
+        let frame = preview.render(60, 6);
+        assert_eq!(frame.len(), 6);
+        assert!(frame[1].contains("\x1b[31mERROR"));
+
+        let text = plain(&frame);
+        assert_eq!(text[0], "conf.demo (2/2 rules enabled) | sample: 2 lines");
+        assert_eq!(text[1], "[x]  0 (ERROR)          |ERROR after 12ms");
+        assert_eq!(text[2], "[x]  1 \\d+ms            |ok");
+        assert!(text[5].starts_with("↑↓ select"));
+    }
+
+    #[test]
+    fn test_toggle_and_edit_recolor_the_sample() {
+        let mut preview = Preview::new(Path::new("conf.demo"), CONF, SAMPLE);
+        assert!(preview.handle_key(KeyCode::Char(' ')));
+        let frame = preview.render(60, 6);
+        assert!(!frame[1].contains("\x1b[31m"));
+        assert!(plain(&frame)[0].contains("1/2 rules enabled"));
+
+        // Edit rule 1 so it matches "ok" instead of the duration
+        preview.handle_key(KeyCode::Down);
+        preview.handle_key(KeyCode::Char('e'));
+        for _ in "\\d+ms".chars() {
+            preview.handle_key(KeyCode::Backspace);
+        }
+        for c in "ok(".chars() {
+            preview.handle_key(KeyCode::Char(c));
+        }
+        preview.handle_key(KeyCode::Enter);
+        // Invalid regexes keep the editor open with the error
+        assert!(plain(&preview.render(60, 6))[5].starts_with("regexp=ok(_  (Regex syntax error"));
+        preview.handle_key(KeyCode::Backspace);
+        preview.handle_key(KeyCode::Enter);
+
+        let frame = preview.render(60, 6);
+        assert!(frame[2].contains("\x1b[32mok"));
+        assert!(!preview.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn test_save_writes_enabled_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let conf = dir.path().join("conf.demo");
+        let mut preview = Preview::new(&conf, CONF, SAMPLE);
+        preview.handle_key(KeyCode::Char(' '));
+        preview.handle_key(KeyCode::Char('w'));
+
+        let saved = std::fs::read_to_string(dir.path().join("conf.demo.new")).unwrap();
+        assert_eq!(saved, "regexp=\\d+ms\ncolours=green\ncount=more\n");
+    }
+
+    #[test]
+    fn test_clip_keeps_escapes() {
+        assert_eq!(clip("abcdef", 3), "abc");
+        assert_eq!(
+            clip("\x1b[31mabc\x1b[0mdef", 4),
+            "\x1b[31mabc\x1b[0md\x1b[0m"
+        );
+    }
+}
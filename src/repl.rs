@@ -0,0 +1,332 @@
+//! # repl.rs - Interactive rule tester (`rgrc repl`)
+//!
+//! Reads lines typed or pasted by the user and echoes them colorized with the
+//! current rules, so a config can be tried out without a command producing the
+//! output. Lines starting with `:` are commands: they load another config,
+//! add or edit ad-hoc rules, and list which rules styled which spans of the
+//! last line.
+//!
+//! Matches are collected by the colorizer itself (see `ColorizeOptions::matches`),
+//! so `count`, `replace` and `skip` behave exactly as they do on real output.
+
+use std::io::{BufRead, Write};
+
+use crate::colorizer::{ColorizeOptions, RuleMatch, colorize_regex_with_options};
+use crate::grc::{CompiledRegex, GrcatConfigEntry, styles_from_str, write_grcat_config};
+use crate::style::Style;
+
+const HELP: &str = "\
+Type or paste lines to see them colorized. Commands:
+  :load NAME            Use the rules for NAME (a command or config name, e.g. df)
+  :add REGEX            Append a rule styling the whole match in bold
+  :colours N STYLES     Set the colours of rule N (e.g. :colours 3 bold red,green)
+  :drop N               Remove rule N
+  :rules                List the current rules
+  :show                 Print the current rules in conf syntax
+  :why                  List the rules and spans that matched the last line
+  :auto on|off          List the matches after every line
+  :help                 Show this help
+  :quit                 Exit (as does end of input)";
+
+/// State of an interactive session.
+struct Repl<L> {
+    /// Name the rules were loaded for, if any
+    name: Option<String>,
+    rules: Vec<GrcatConfigEntry>,
+    /// Last input line and the matches found on it
+    last: Option<(String, Vec<RuleMatch>)>,
+    /// Whether to list the matches after every line
+    auto: bool,
+    load: L,
+}
+
+/// Run an interactive session reading from `input` until `:quit` or end of input.
+///
+/// `load` returns the rules for a command or config name; it is used for the
+/// initial `name` and for `:load`. A prompt is written before each line when
+/// `prompt` is true (i.e. when a user is typing).
+pub fn run<R, W, L>(
+    input: R,
+    output: &mut W,
+    name: Option<&str>,
+    load: L,
+    prompt: bool,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    R: BufRead,
+    W: Write,
+    L: Fn(&str) -> Vec<GrcatConfigEntry>,
+{
+    let mut repl = Repl {
+        name: None,
+        rules: Vec::new(),
+        last: None,
+        auto: false,
+        load,
+    };
+
+    match name {
+        Some(name) => repl.load(output, name)?,
+        None => writeln!(output, "No rules loaded; use :load NAME or :add REGEX.")?,
+    }
+    writeln!(output, "Type :help for commands.")?;
+
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            write!(output, "rgrc> ")?;
+            output.flush()?;
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+
+        match line.strip_prefix(':') {
+            Some(command) => {
+                if !repl.command(output, command.trim())? {
+                    break;
+                }
+            }
+            None => repl.colorize(output, line)?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+impl<L: Fn(&str) -> Vec<GrcatConfigEntry>> Repl<L> {
+    /// Execute `command` (without the leading `:`); returns false on `:quit`.
+    fn command<W: Write>(
+        &mut self,
+        output: &mut W,
+        command: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let (verb, rest) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(verb, rest)| (verb, rest.trim()));
+
+        match verb {
+            "q" | "quit" | "exit" => return Ok(false),
+            "h" | "help" => writeln!(output, "{}", HELP)?,
+            "load" if !rest.is_empty() => self.load(output, rest)?,
+            "add" if !rest.is_empty() => match CompiledRegex::new(rest) {
+                Ok(regex) => {
+                    self.rules
+                        .push(GrcatConfigEntry::new(regex, vec![Style::new().bold()]));
+                    writeln!(output, "Added rule #{}.", self.rules.len() - 1)?;
+                    self.rerun(output)?;
+                }
+                Err(e) => writeln!(output, "{}", e)?,
+            },
+            "colours" | "colors" => {
+                let (index, styles) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                match (self.rule_index(index), styles_from_str(styles.trim())) {
+                    (Err(e), _) | (_, Err(e)) => writeln!(output, "{}", e)?,
+                    (Ok(index), Ok(styles)) => {
+                        self.rules[index].colors = styles;
+                        self.rerun(output)?;
+                    }
+                }
+            }
+            "drop" => match self.rule_index(rest) {
+                Ok(index) => {
+                    let rule = self.rules.remove(index);
+                    writeln!(output, "Removed rule #{}: {}", index, rule.regex.as_str())?;
+                    self.rerun(output)?;
+                }
+                Err(e) => writeln!(output, "{}", e)?,
+            },
+            "rules" => self.write_rules(output)?,
+            "show" => write_grcat_config(output, &self.rules)?,
+            "why" => match &self.last {
+                Some((_, matches)) => write_matches(output, &self.rules, matches)?,
+                None => writeln!(output, "No line entered yet.")?,
+            },
+            "auto" if matches!(rest, "on" | "off") => self.auto = rest == "on",
+            _ => writeln!(
+                output,
+                "Unknown command :{}; type :help for commands.",
+                command
+            )?,
+        }
+        Ok(true)
+    }
+
+    /// Replace the rules with those loaded for `name`.
+    fn load<W: Write>(&mut self, output: &mut W, name: &str) -> std::io::Result<()> {
+        self.rules = (self.load)(name);
+        self.name = Some(name.to_string());
+        if self.rules.is_empty() {
+            writeln!(output, "No rules found for '{}'.", name)
+        } else {
+            writeln!(output, "Loaded {} rules for '{}'.", self.rules.len(), name)
+        }
+    }
+
+    /// Parse a rule number typed by the user.
+    fn rule_index(&self, text: &str) -> Result<usize, String> {
+        match text.parse::<usize>() {
+            Ok(index) if index < self.rules.len() => Ok(index),
+            _ => Err(format!(
+                "No rule #{}; there are {} rules (see :rules).",
+                text,
+                self.rules.len()
+            )),
+        }
+    }
+
+    /// Echo `line` colorized and remember its matches.
+    fn colorize<W: Write>(
+        &mut self,
+        output: &mut W,
+        line: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut matches = Vec::new();
+        let mut options = ColorizeOptions {
+            matches: Some(&mut matches),
+            ..Default::default()
+        };
+        let input = format!("{}\n", line);
+        colorize_regex_with_options(&mut input.as_bytes(), output, &self.rules, &mut options)?;
+
+        if self.auto {
+            write_matches(output, &self.rules, &matches)?;
+        }
+        self.last = Some((line, matches));
+        Ok(())
+    }
+
+    /// Colorize the last line again after the rules changed.
+    fn rerun<W: Write>(&mut self, output: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((line, _)) = self.last.take() {
+            self.colorize(output, line)?;
+        }
+        Ok(())
+    }
+
+    fn write_rules<W: Write>(&self, output: &mut W) -> std::io::Result<()> {
+        match &self.name {
+            Some(name) => writeln!(
+                output,
+                "{} rules (loaded for '{}'):",
+                self.rules.len(),
+                name
+            )?,
+            None => writeln!(output, "{} rules:", self.rules.len())?,
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            writeln!(
+                output,
+                "  #{:<3} {}  [{}]{}",
+                index,
+                rule.regex.as_str(),
+                colours(&rule.colors),
+                if rule.skip { "  (skip)" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// List `matches` grouped by rule, in the order the rules were applied.
+fn write_matches<W: Write>(
+    output: &mut W,
+    rules: &[GrcatConfigEntry],
+    matches: &[RuleMatch],
+) -> std::io::Result<()> {
+    if matches.is_empty() {
+        return writeln!(output, "  no rule matched");
+    }
+
+    let mut current = None;
+    for found in matches {
+        if current != Some(found.rule) {
+            current = Some(found.rule);
+            let pattern = rules.get(found.rule).map_or("", |rule| rule.regex.as_str());
+            writeln!(output, "  rule #{} {}", found.rule, pattern)?;
+        }
+        let style = rules
+            .get(found.rule)
+            .and_then(|rule| rule.colors.get(found.group))
+            .map_or_else(String::new, |style| colours(std::slice::from_ref(style)));
+        writeln!(
+            output,
+            "    group {} {:>3}..{:<3} {:?} -> {}",
+            found.group, found.range.start, found.range.end, found.text, style
+        )?;
+    }
+    Ok(())
+}
+
+/// Render styles as the comma-separated keywords of a `colours=` line.
+fn colours(styles: &[Style]) -> String {
+    styles
+        .iter()
+        .map(|style| match style.keywords() {
+            words if words.is_empty() => "default".to_string(),
+            words => words.join(" "),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grc::GrcatConfigReader;
+
+    fn load(name: &str) -> Vec<GrcatConfigEntry> {
+        let conf = match name {
+            "demo" => {
+                "regexp=(ERROR|WARN)\ncolours=default,bold red\n-\nregexp=\\d+ms\ncolours=green\n"
+            }
+            _ => "",
+        };
+        GrcatConfigReader::new(conf.as_bytes().lines()).collect()
+    }
+
+    fn session(name: Option<&str>, input: &str) -> String {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, name, load, false).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_lines_are_colorized_and_matches_listed() {
+        let output = session(Some("demo"), "WARN took 12ms\n:why\n");
+        assert!(output.contains("Loaded 2 rules for 'demo'."));
+        assert!(output.contains("\x1b["));
+        assert!(output.contains("  rule #0 (ERROR|WARN)\n"));
+        assert!(output.contains("group 1   0..4   \"WARN\" -> bold red"));
+        assert!(output.contains("  rule #1 \\d+ms\n"));
+        assert!(output.contains("\"12ms\" -> green"));
+    }
+
+    #[test]
+    fn test_ad_hoc_rules() {
+        let output = session(
+            None,
+            "id=42\n:add id=(\\d+)\n:colours 0 default,yellow\n:why\n:drop 0\n:rules\n:add (\n",
+        );
+        assert!(output.contains("No rules loaded"));
+        assert!(output.contains("Added rule #0."));
+        assert!(output.contains("group 1   3..5   \"42\" -> yellow"));
+        assert!(output.contains("Removed rule #0: id=(\\d+)"));
+        assert!(output.contains("0 rules:"));
+        assert!(output.contains("Regex syntax error"));
+    }
+
+    #[test]
+    fn test_auto_quit_and_unknown_commands() {
+        let output = session(
+            Some("demo"),
+            ":auto on\nno match here\n:bogus\n:quit\nERROR\n",
+        );
+        assert!(output.contains("  no rule matched"));
+        assert!(output.contains("Unknown command :bogus"));
+        // Nothing after :quit is processed
+        assert!(!output.contains("ERROR"));
+        assert!(session(Some("none"), ":why\n").contains("No rules found for 'none'."));
+    }
+}
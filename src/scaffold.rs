@@ -0,0 +1,344 @@
+//! # scaffold.rs - Starter configs for new commands (`rgrc new-config`, `rgrc suggest`)
+//!
+//! `rgrc new-config CMD [ARGS...]` runs the command (or reads a sample of its
+//! output with `--sample FILE`), shows the output and writes a commented
+//! `conf.CMD` to start from, with the `rgrc.conf` entry that maps the command
+//! to it.
+//!
+//! Its rules come from `suggest`, which `rgrc suggest < SAMPLE` prints on its
+//! own: rules for the token classes of command output (addresses, sizes,
+//! durations, percentages, paths, error and success words, numbers) and for
+//! the UPPERCASE status words the sample has, ranked by how many sample lines
+//! each matches. Later rules override earlier ones, so the narrower rules
+//! ranked after the broad ones win where they overlap.
+
+use std::process::{Command, Stdio};
+
+use crate::RgrcError;
+use crate::grc::CompiledRegex;
+
+/// Sample lines quoted in the template and shown by `rgrc new-config`.
+pub const SAMPLE_LINES: usize = 20;
+
+/// A rule most command output can use.
+#[derive(Debug, Clone, Copy)]
+pub struct Stub {
+    pub description: &'static str,
+    pub regexp: &'static str,
+    pub colours: &'static str,
+}
+
+/// The token classes `suggest` proposes rules for.
+pub const STUBS: &[Stub] = &[
+    // First, so that the classes of numbers below override it
+    Stub {
+        description: "numbers",
+        regexp: r"\b\d+(\.\d+)?",
+        colours: "yellow",
+    },
+    Stub {
+        description: "error keywords",
+        regexp: r"(?i)\b(error|errors|fail|failed|failure|fatal|denied)\b",
+        colours: "bold red",
+    },
+    Stub {
+        description: "warning keywords",
+        regexp: r"(?i)\b(warn|warning|warnings|deprecated)\b",
+        colours: "yellow",
+    },
+    Stub {
+        description: "success keywords",
+        regexp: r"(?i)\b(ok|success|successful|done|passed)\b",
+        colours: "green",
+    },
+    Stub {
+        description: "IPv4 addresses",
+        regexp: r"\b\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}\b",
+        colours: "bright_blue",
+    },
+    Stub {
+        description: "IPv6 addresses",
+        regexp: r"\b(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\b|\b(?:[0-9a-fA-F]{1,4}:){1,6}:(?:[0-9a-fA-F]{1,4}(?::[0-9a-fA-F]{1,4})*)?",
+        colours: "magenta",
+    },
+    Stub {
+        description: "sizes",
+        regexp: r"\b\d+(?:\.\d+)?(?:\s?(?:[KMGTPE]i?B|B|bytes)|[KMGTPE])\b",
+        colours: "cyan",
+    },
+    Stub {
+        description: "durations",
+        regexp: r"\b\d+(?:\.\d+)?(?:ns|us|ms|s|m|h|d)\b|\b\d+(?:\.\d+)?\s(?:sec|secs|seconds?|min|mins|minutes?|hours?|days?)\b",
+        colours: "green",
+    },
+    Stub {
+        description: "percentages",
+        regexp: r"\b\d+(?:\.\d+)?%",
+        colours: "bold yellow",
+    },
+    Stub {
+        description: "paths",
+        regexp: r#"(?:^|[\s=:'"(])((?:~|\.{1,2})?/[\w.-]+(?:/[\w.-]*)*)"#,
+        colours: "default,blue",
+    },
+];
+
+/// The name of the config for the command `command` (its file name, without
+/// directories).
+pub fn command_name(command: &str) -> &str {
+    command.rsplit(['/', '\\']).next().unwrap_or(command)
+}
+
+/// The `rgrc.conf` entry mapping `name` to its config, in the form of the
+/// bundled `rgrc.conf`.
+pub fn mapping_entry(name: &str) -> String {
+    // \b only ends a name that ends with a word character
+    let end = if name.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+        "\\b"
+    } else {
+        "(\\s|$)"
+    };
+    format!(
+        "# {0} command\n^([/\\w\\.]+\\/)?{1}{2}\nconf.{0}\n",
+        name,
+        regex_lite::escape(name),
+        end
+    )
+}
+
+/// Run `command` and return its output, stderr after stdout.
+pub fn sample_output(command: &[String]) -> Result<String, RgrcError> {
+    let output = Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| RgrcError::spawn(&command[0], e))?;
+    let mut sample = String::from_utf8_lossy(&output.stdout).into_owned();
+    sample.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(sample)
+}
+
+/// A rule proposed for sample output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub description: String,
+    pub regexp: String,
+    pub colours: String,
+    /// Number of sample lines the rule matches
+    pub lines: usize,
+}
+
+/// UPPERCASE words that mean something went wrong, to be coloured red.
+const ERROR_WORDS: &[&str] = &[
+    "ABORTED", "CRASHED", "CRITICAL", "DEAD", "DENIED", "DOWN", "ERR", "ERROR", "FAIL", "FAILED",
+    "FAILURE", "FATAL", "KILLED", "PANIC", "REJECTED",
+];
+
+/// UPPERCASE words that mean something needs a look, to be coloured yellow.
+const WARNING_WORDS: &[&str] = &[
+    "DEGRADED",
+    "DEPRECATED",
+    "PENDING",
+    "RETRY",
+    "SKIPPED",
+    "TIMEOUT",
+    "UNKNOWN",
+    "WAITING",
+    "WARN",
+    "WARNING",
+];
+
+/// UPPERCASE words that mean all is well, to be coloured green.
+const SUCCESS_WORDS: &[&str] = &[
+    "ACTIVE",
+    "DONE",
+    "ENABLED",
+    "ESTABLISHED",
+    "HEALTHY",
+    "LISTEN",
+    "OK",
+    "PASS",
+    "PASSED",
+    "READY",
+    "RUNNING",
+    "SUCCESS",
+    "UP",
+];
+
+/// Rules for the UPPERCASE words of `lines` (status words such as `RUNNING` or
+/// `FAILED`, or column headers), one per meaning.
+fn uppercase_word_rules(lines: &[&str]) -> Vec<Suggestion> {
+    let word = regex_lite::Regex::new(r"\b[A-Z][A-Z_]*[A-Z]\b").expect("valid regex");
+    let known = |w: &str| {
+        [ERROR_WORDS, WARNING_WORDS, SUCCESS_WORDS]
+            .concat()
+            .contains(&w)
+    };
+    // Two letters are more often a unit (KB) than a word, unless a known one (OK)
+    let mut words: Vec<&str> = lines
+        .iter()
+        .flat_map(|line| word.find_iter(line).map(|m| m.as_str()))
+        .filter(|w| w.len() > 2 || known(w))
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+
+    // The words of no list are "other" words
+    let classes: [(&str, &str, Option<&[&str]>); 4] = [
+        ("UPPERCASE error words", "bold red", Some(ERROR_WORDS)),
+        ("UPPERCASE warning words", "yellow", Some(WARNING_WORDS)),
+        ("UPPERCASE success words", "green", Some(SUCCESS_WORDS)),
+        ("other UPPERCASE words", "bold", None),
+    ];
+    classes
+        .iter()
+        .filter_map(|(description, colours, list)| {
+            let class: Vec<&str> = words
+                .iter()
+                .copied()
+                .filter(|w| list.map_or(!known(w), |list| list.contains(w)))
+                .collect();
+            (!class.is_empty()).then(|| Suggestion {
+                description: format!("{} ({})", description, class.join(", ")),
+                regexp: format!(r"\b({})\b", class.join("|")),
+                colours: colours.to_string(),
+                lines: 0,
+            })
+        })
+        .collect()
+}
+
+/// Rules for output like `sample`: one per token class of `STUBS` and per
+/// meaning of the UPPERCASE words in it, those matching the most lines first.
+/// Rules matching no line are kept, last, as stubs for output the sample lacks.
+pub fn suggest(sample: &str) -> Vec<Suggestion> {
+    let lines: Vec<&str> = sample.lines().collect();
+    let stubs = STUBS.iter().map(|stub| Suggestion {
+        description: stub.description.to_string(),
+        regexp: stub.regexp.to_string(),
+        colours: stub.colours.to_string(),
+        lines: 0,
+    });
+    let mut suggestions: Vec<Suggestion> = stubs.chain(uppercase_word_rules(&lines)).collect();
+    for suggestion in &mut suggestions {
+        suggestion.lines = CompiledRegex::new(&suggestion.regexp)
+            .map(|re| lines.iter().filter(|line| re.is_match(line)).count())
+            .unwrap_or(0);
+    }
+    // Stable, so rules matching as many lines keep their order
+    suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.lines));
+    suggestions
+}
+
+/// `suggestions` in conf syntax, each with a comment counting the lines it
+/// matches of the `total` sample lines.
+pub fn write_suggestions(suggestions: &[Suggestion], total: usize) -> String {
+    let mut text = String::new();
+    for (i, suggestion) in suggestions.iter().enumerate() {
+        if i > 0 {
+            text.push_str("=======\n");
+        }
+        text.push_str(&format!(
+            "# {} (matches {} of {} sample lines)\nregexp={}\ncolours={}\n",
+            suggestion.description, suggestion.lines, total, suggestion.regexp, suggestion.colours
+        ));
+    }
+    text
+}
+
+/// A starter `conf.NAME` for output like `sample`.
+pub fn config_template(name: &str, sample: &str) -> String {
+    let lines: Vec<&str> = sample.lines().collect();
+    let mut template = format!(
+        "# conf.{0} - rules for the output of {0}\n\
+         #\n\
+         # Written by `rgrc new-config`. Adjust the stubs below to the output of {0}\n\
+         # and remove those that do not apply. Put this file in ~/.config/rgrc (or\n\
+         # share/ of rgrc to contribute it), try it with `rgrc repl -c {0}`, and map\n\
+         # the command to it in rgrc.conf with:\n\
+         #\n",
+        name
+    );
+    for line in mapping_entry(name).lines().skip(1) {
+        template.push_str(&format!("#   {}\n", line));
+    }
+    if !lines.is_empty() {
+        template.push_str("#\n# Sample output:\n#\n");
+        for line in lines.iter().take(SAMPLE_LINES) {
+            template.push_str(&format!("#   {}\n", line));
+        }
+        if lines.len() > SAMPLE_LINES {
+            template.push_str(&format!(
+                "#   ({} more lines)\n",
+                lines.len() - SAMPLE_LINES
+            ));
+        }
+    }
+    template.push('\n');
+    template.push_str(&write_suggestions(&suggest(sample), lines.len()));
+    template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grc::GrcatConfigReader;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_template_is_a_config() {
+        let sample = "Connecting to 10.0.0.1\nERROR: timeout after 30s\nok\n";
+        let template = config_template("mytool", sample);
+        assert!(template.starts_with("# conf.mytool - rules for the output of mytool\n"));
+        assert!(template.contains("#   ^([/\\w\\.]+\\/)?mytool\\b\n#   conf.mytool\n"));
+        assert!(template.contains("#   ERROR: timeout after 30s\n"));
+        assert!(template.contains("# error keywords (matches 1 of 3 sample lines)\n"));
+        assert!(template.contains("# numbers (matches 2 of 3 sample lines)\n"));
+
+        let rules: Vec<_> = GrcatConfigReader::new(template.as_bytes().lines()).collect();
+        assert_eq!(rules.len(), suggest(sample).len());
+    }
+
+    #[test]
+    fn test_suggestions_are_ranked_by_coverage() {
+        let sample = "\
+web-1   RUNNING   512MiB  12.5%   3s    /var/log/web.log
+web-2   FAILED    1.2GB   99%     250ms /var/log/web.log
+db-1    RUNNING   4 KB    0.1%    2 min fe80::1
+gw      PENDING   0B      -       -     192.168.1.1
+";
+        let suggestions = suggest(sample);
+        let matching = |description: &str| {
+            suggestions
+                .iter()
+                .find(|s| s.description.starts_with(description))
+                .map(|s| s.lines)
+        };
+        assert_eq!(matching("sizes"), Some(4));
+        assert_eq!(matching("percentages"), Some(3));
+        assert_eq!(matching("durations"), Some(3));
+        assert_eq!(matching("paths"), Some(2));
+        assert_eq!(matching("IPv6 addresses"), Some(1));
+        assert_eq!(matching("IPv4 addresses"), Some(1));
+        assert_eq!(matching("UPPERCASE success words (RUNNING)"), Some(2));
+        assert_eq!(matching("UPPERCASE error words (FAILED)"), Some(1));
+        assert_eq!(matching("UPPERCASE warning words (PENDING)"), Some(1));
+        assert_eq!(matching("other UPPERCASE words"), None);
+        assert!(suggestions.windows(2).all(|w| w[0].lines >= w[1].lines));
+        assert_eq!(suggestions.last().unwrap().lines, 0);
+
+        let text = write_suggestions(&suggestions[..2], 4);
+        assert!(text.starts_with("# numbers (matches 4 of 4 sample lines)\nregexp="));
+        assert_eq!(text.matches("=======\n").count(), 1);
+    }
+
+    #[test]
+    fn test_names_and_mappings() {
+        assert_eq!(command_name("/usr/local/bin/tofu"), "tofu");
+        assert_eq!(command_name("tofu"), "tofu");
+        assert_eq!(
+            mapping_entry("g++"),
+            "# g++ command\n^([/\\w\\.]+\\/)?g\\+\\+(\\s|$)\nconf.g++\n"
+        );
+    }
+}
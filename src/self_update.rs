@@ -0,0 +1,335 @@
+//! # self_update.rs - Replacing rgrc with its latest release (`rgrc self-update`)
+//!
+//! `rgrc self-update` asks GitHub for the latest release of rgrc and, when it is
+//! newer than the running binary, downloads the archive built for the target
+//! rgrc was built for (`rgrc-TARGET.tar.gz`, `.zip` on Windows, as
+//! `script/install.sh` does). The archive is checked against the release's
+//! `SHA256SUMS` before the binary it holds replaces the running one. That only
+//! catches a corrupt download: the sums come from the same release, and no
+//! signature is checked, so whoever can publish a release (or tamper with one)
+//! can replace rgrc. Signing the sums with minisign, or verifying a GitHub
+//! artifact attestation, would close that gap.
+//!
+//! The binary is replaced by renaming the new one over it, so rgrc running
+//! meanwhile keeps its old file. Windows does not allow that for a running
+//! executable, but does allow renaming it away first: the old binary is left as
+//! `rgrc.old.exe` and removed by the next update.
+//!
+//! Binaries installed by a package manager (cargo, Homebrew, Nix, Scoop, a deb
+//! in `/usr/bin` or elsewhere in the system directories) are left for it to
+//! update.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::RgrcError;
+use crate::fetch::{compare_versions, download, sha256_hex};
+
+/// The releases page of rgrc, which release assets are downloaded from.
+pub const RELEASES_URL: &str = concat!(env!("CARGO_PKG_REPOSITORY"), "/releases");
+
+/// The target triple rgrc was built for, naming its release archive.
+pub const TARGET: &str = env!("RGRC_TARGET");
+
+/// The file listing the SHA-256 of each archive of a release.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// What `self_update` did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The running version is the latest one
+    UpToDate(String),
+    /// The binary was replaced with the given version
+    Updated(String),
+}
+
+/// The URL of the GitHub API describing the latest release of rgrc.
+fn latest_release_url() -> String {
+    let repository = env!("CARGO_PKG_REPOSITORY");
+    format!(
+        "{}/releases/latest",
+        repository.replacen("https://github.com/", "https://api.github.com/repos/", 1)
+    )
+}
+
+/// The `tag_name` of a release as described by the GitHub API.
+fn tag_name(release_json: &str) -> Option<&str> {
+    let rest = &release_json[release_json.find("\"tag_name\"")? + "\"tag_name\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    Some(&rest[..rest.find('"')?])
+}
+
+/// The name of the release archive for `target`.
+pub fn archive_name(target: &str) -> String {
+    let extension = if target.contains("windows") {
+        "zip"
+    } else {
+        "tar.gz"
+    };
+    format!("rgrc-{}.{}", target, extension)
+}
+
+/// The checksum `sums` (in `sha256sum` format) lists for the file `name`.
+fn checksum_for<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (sum, file) = line.split_once(char::is_whitespace)?;
+        // sha256sum marks files read in binary mode with a '*'
+        let file = file.trim_start().trim_start_matches('*');
+        (file == name).then_some(sum)
+    })
+}
+
+/// The package manager that installed the binary at `exe` and the command
+/// updating it, judging by where it is.
+pub fn package_manager(exe: &Path) -> Option<(&'static str, &'static str)> {
+    let path = exe.to_string_lossy().replace('\\', "/");
+    if path.contains("/.cargo/bin/") {
+        Some(("cargo", "cargo install rgrc"))
+    } else if path.contains("/Cellar/") || path.contains("/homebrew/") {
+        Some(("Homebrew", "brew upgrade rgrc"))
+    } else if path.starts_with("/nix/store/") {
+        Some(("Nix", "your Nix configuration"))
+    } else if path.contains("/scoop/apps/") {
+        Some(("Scoop", "scoop update rgrc"))
+    } else if ["/bin/", "/usr/bin/", "/usr/sbin/", "/usr/lib"]
+        .iter()
+        .any(|dir| path.starts_with(dir))
+    {
+        Some(("the system package manager", "the system package manager"))
+    } else {
+        None
+    }
+}
+
+/// Replace the running binary with the latest release, if it is newer.
+pub fn self_update() -> Result<UpdateOutcome, RgrcError> {
+    let exe = std::env::current_exe()?.canonicalize()?;
+    if let Some((manager, command)) = package_manager(&exe) {
+        return Err(RgrcError::Fetch(format!(
+            "{} was installed by {}; update it with {}",
+            exe.display(),
+            manager,
+            command
+        )));
+    }
+    remove_old_binary(&exe);
+
+    let url = latest_release_url();
+    let release = String::from_utf8_lossy(&download(&url)?).into_owned();
+    let Some(tag) = tag_name(&release) else {
+        return Err(RgrcError::Fetch(format!(
+            "{}: no tag_name in the release",
+            url
+        )));
+    };
+    let version = tag.trim_start_matches('v');
+    if compare_versions(version, env!("CARGO_PKG_VERSION")).is_le() {
+        return Ok(UpdateOutcome::UpToDate(
+            env!("CARGO_PKG_VERSION").to_string(),
+        ));
+    }
+    install_release(&format!("{}/download/{}", RELEASES_URL, tag), TARGET, &exe)?;
+    Ok(UpdateOutcome::Updated(version.to_string()))
+}
+
+/// Download the archive for `target` from the release assets at `assets_url`,
+/// check it against the release's checksums and replace `exe` with the binary
+/// it holds.
+pub fn install_release(assets_url: &str, target: &str, exe: &Path) -> Result<(), RgrcError> {
+    let name = archive_name(target);
+    let sums_url = format!("{}/{}", assets_url, CHECKSUMS_FILE);
+    let sums = String::from_utf8_lossy(&download(&sums_url)?).into_owned();
+    let Some(expected) = checksum_for(&sums, &name) else {
+        return Err(RgrcError::Fetch(format!(
+            "{}: no checksum for {}",
+            sums_url, name
+        )));
+    };
+    let url = format!("{}/{}", assets_url, name);
+    let archive = download(&url)?;
+    let sha256 = sha256_hex(&archive);
+    if !sha256.eq_ignore_ascii_case(expected) {
+        return Err(RgrcError::Fetch(format!(
+            "{}: checksum mismatch (expected {}, got {})",
+            url, expected, sha256
+        )));
+    }
+
+    // Unpacked next to the binary, so that it is renamed within one filesystem
+    let Some(dir) = exe.parent() else {
+        return Err(RgrcError::Fetch(format!("{}: no directory", exe.display())));
+    };
+    let work = dir.join(format!(".rgrc-update-{}", std::process::id()));
+    std::fs::create_dir_all(&work)?;
+    let result = unpack_and_replace(&work, &name, &archive, exe);
+    let _ = std::fs::remove_dir_all(&work);
+    result
+}
+
+fn unpack_and_replace(
+    work: &Path,
+    name: &str,
+    archive: &[u8],
+    exe: &Path,
+) -> Result<(), RgrcError> {
+    let archive_path = work.join(name);
+    std::fs::write(&archive_path, archive)?;
+    // tar reads both formats; Windows 10 and later ship a bsdtar
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(work)
+        .stdin(Stdio::null())
+        .status()
+        .map_err(|e| RgrcError::spawn("tar", e))?;
+    let binary = work.join(format!("rgrc{}", std::env::consts::EXE_SUFFIX));
+    if !status.success() || !binary.is_file() {
+        return Err(RgrcError::Fetch(format!("{}: no rgrc binary in it", name)));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+    }
+    replace_binary(&binary, exe)
+}
+
+/// Where a replaced binary is left until the next update, on Windows.
+fn old_binary(exe: &Path) -> PathBuf {
+    exe.with_extension("old.exe")
+}
+
+/// Remove the binary a previous update left behind, if any.
+fn remove_old_binary(exe: &Path) {
+    if cfg!(windows) {
+        let _ = std::fs::remove_file(old_binary(exe));
+    }
+}
+
+/// Move `new` to `exe`, in place of the binary there.
+fn replace_binary(new: &Path, exe: &Path) -> Result<(), RgrcError> {
+    if cfg!(windows) {
+        // A running executable cannot be replaced, but it can be renamed
+        let old = old_binary(exe);
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old)?;
+        if let Err(e) = std::fs::rename(new, exe) {
+            let _ = std::fs::rename(&old, exe);
+            return Err(e.into());
+        }
+        Ok(())
+    } else {
+        Ok(std::fs::rename(new, exe)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_metadata() {
+        let release = r#"{"url": "x", "tag_name" : "v0.7.0", "name": "0.7.0"}"#;
+        assert_eq!(tag_name(release), Some("v0.7.0"));
+        assert_eq!(tag_name(r#"{"name": "0.7.0"}"#), None);
+        assert_eq!(
+            latest_release_url(),
+            "https://api.github.com/repos/lazywalker/rgrc/releases/latest"
+        );
+
+        assert_eq!(
+            archive_name("x86_64-unknown-linux-musl"),
+            "rgrc-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            archive_name("x86_64-pc-windows-msvc"),
+            "rgrc-x86_64-pc-windows-msvc.zip"
+        );
+        let sums = "aa11  rgrc-a.tar.gz\nbb22 *rgrc-b.zip\n";
+        assert_eq!(checksum_for(sums, "rgrc-a.tar.gz"), Some("aa11"));
+        assert_eq!(checksum_for(sums, "rgrc-b.zip"), Some("bb22"));
+        assert_eq!(checksum_for(sums, "rgrc-c.zip"), None);
+    }
+
+    #[test]
+    fn test_package_managers_are_left_alone() {
+        let manager = |path: &str| package_manager(Path::new(path)).map(|(name, _)| name);
+        assert_eq!(manager("/home/me/.cargo/bin/rgrc"), Some("cargo"));
+        assert_eq!(
+            manager("/opt/homebrew/Cellar/rgrc/0.6.13/bin/rgrc"),
+            Some("Homebrew")
+        );
+        assert_eq!(manager("/usr/bin/rgrc"), Some("the system package manager"));
+        for path in [
+            "/bin/rgrc",
+            "/usr/sbin/rgrc",
+            "/usr/lib/rgrc/rgrc",
+            "/usr/lib64/rgrc",
+            "/usr/libexec/rgrc",
+        ] {
+            assert_eq!(
+                manager(path),
+                Some("the system package manager"),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(
+            manager("C:/Users/me/scoop/apps/rgrc/current/rgrc.exe"),
+            Some("Scoop")
+        );
+        assert_eq!(
+            manager(r"C:\Users\me\scoop\apps\rgrc\0.6.13\rgrc.exe"),
+            Some("Scoop")
+        );
+        assert_eq!(manager("/usr/local/bin/rgrc"), None);
+        assert_eq!(manager("/home/me/.local/bin/rgrc"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_install_release_checks_the_archive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let assets = dir.path().join("assets");
+        let bin = dir.path().join("bin");
+        std::fs::create_dir(&assets).unwrap();
+        std::fs::create_dir(&bin).unwrap();
+        std::fs::write(assets.join("rgrc"), "new").unwrap();
+        let name = archive_name("x86_64-unknown-linux-musl");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(assets.join(&name))
+            .arg("-C")
+            .arg(&assets)
+            .arg("rgrc")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let exe = bin.join("rgrc");
+        std::fs::write(&exe, "old").unwrap();
+        let url = format!("file://{}", assets.display());
+        let install = || install_release(&url, "x86_64-unknown-linux-musl", &exe);
+
+        std::fs::write(
+            assets.join(CHECKSUMS_FILE),
+            format!("{}  {}\n", "0".repeat(64), name),
+        )
+        .unwrap();
+        let error = install().unwrap_err().to_string();
+        assert!(error.contains("checksum mismatch"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "old");
+
+        let sha256 = sha256_hex(&std::fs::read(assets.join(&name)).unwrap());
+        std::fs::write(
+            assets.join(CHECKSUMS_FILE),
+            format!("{}  {}\n", sha256, name),
+        )
+        .unwrap();
+        install().unwrap();
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "new");
+        // Nothing is left behind next to the binary
+        assert_eq!(std::fs::read_dir(&bin).unwrap().count(), 1);
+    }
+}
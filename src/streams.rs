@@ -0,0 +1,250 @@
+//! # streams.rs - Colorizing a command's stdout and stderr together
+//!
+//! With `--stderr` both output streams of the command are piped. Each is
+//! colorized on its own thread, and a writer thread receives their output as
+//! whole lines over a channel, so a line of one stream never lands in the
+//! middle of a line of the other (when both end up on the same terminal), and
+//! lines keep roughly the order in which the command wrote them.
+
+use std::io::{Read, Write};
+use std::sync::mpsc::{Sender, channel};
+
+use crate::Colorizer;
+use crate::colorizer::ColorizeOptions;
+
+/// One of the command's output streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// The command's stderr, with the colorizer for it and the tag of its lines.
+///
+/// Stderr has a colorizer of its own, as whether it is coloured is decided
+/// apart from stdout (`--color-stderr`, or whether stderr is a terminal).
+pub struct StderrInput<'a, E> {
+    pub reader: E,
+    pub colorizer: &'a Colorizer,
+    /// Text each line starts with (`--tag-stderr`)
+    pub tag: Option<&'a str>,
+}
+
+/// Colorize `stdout` into `out` and `stderr` into `err`, concurrently.
+///
+/// `options` (the log, stats and profile of `--log`, `--stats` and `--profile`)
+/// apply to stdout only. Returns once both streams have ended and their output
+/// is written.
+pub fn colorize_streams<O, E, WO, WE>(
+    colorizer: &Colorizer,
+    stdout: &mut O,
+    stderr: StderrInput<'_, E>,
+    out: WO,
+    err: WE,
+    options: ColorizeOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    O: Read,
+    E: Read + Send,
+    WO: Write + Send,
+    WE: Write + Send,
+{
+    let (sender, receiver) = channel();
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(move || write_lines(receiver.iter(), out, err));
+
+        let stderr_sender = sender.clone();
+        let stderr_thread = scope.spawn(move || {
+            let StderrInput {
+                mut reader,
+                colorizer,
+                tag,
+            } = stderr;
+            let mut lines = LineSender::new(Stream::Stderr, stderr_sender, tag);
+            colorizer
+                .colorize(&mut reader, &mut lines)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            lines.flush()
+        });
+
+        let mut lines = LineSender::new(Stream::Stdout, sender, None);
+        let result = colorizer
+            .colorize_with_options(stdout, &mut lines, options)
+            .and_then(|()| Ok(lines.flush()?));
+        // Hang up, so the writer stops once stderr is done too
+        drop(lines);
+
+        let stderr_result = stderr_thread.join().expect("stderr thread panicked");
+        let write_result = writer.join().expect("writer thread panicked");
+        result?;
+        stderr_result?;
+        Ok(write_result?)
+    })
+}
+
+/// Write each batch of lines to the writer of its stream, as it arrives.
+fn write_lines<WO: Write, WE: Write>(
+    batches: impl Iterator<Item = (Stream, Vec<u8>)>,
+    mut out: WO,
+    mut err: WE,
+) -> std::io::Result<()> {
+    for (stream, lines) in batches {
+        let writer: &mut dyn Write = match stream {
+            Stream::Stdout => &mut out,
+            Stream::Stderr => &mut err,
+        };
+        writer.write_all(&lines)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Writer handing the complete lines written to it to the writer thread, each
+/// time it is flushed (after every batch of lines, see `Colorizer::colorize`).
+struct LineSender<'t> {
+    stream: Stream,
+    sender: Sender<(Stream, Vec<u8>)>,
+    tag: Option<&'t str>,
+    pending: Vec<u8>,
+}
+
+impl<'t> LineSender<'t> {
+    fn new(stream: Stream, sender: Sender<(Stream, Vec<u8>)>, tag: Option<&'t str>) -> Self {
+        LineSender {
+            stream,
+            sender,
+            tag,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Send the lines of `pending`, up to its last `\n` (or all of it when
+    /// `all`), tagging each one.
+    fn send(&mut self, all: bool) -> std::io::Result<()> {
+        let end = match self.pending.iter().rposition(|&b| b == b'\n') {
+            _ if all => self.pending.len(),
+            Some(newline) => newline + 1,
+            None => return Ok(()),
+        };
+        if end == 0 {
+            return Ok(());
+        }
+        let rest = self.pending.split_off(end);
+        let lines = std::mem::replace(&mut self.pending, rest);
+        let lines = match self.tag {
+            Some(tag) => tag_lines(&lines, tag),
+            None => lines,
+        };
+        self.sender
+            .send((self.stream, lines))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl Write for LineSender<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send(false)
+    }
+}
+
+impl Drop for LineSender<'_> {
+    fn drop(&mut self) {
+        // A last line without a line ending; the writer may already be gone
+        let _ = self.send(true);
+    }
+}
+
+/// `lines` with `tag` at the start of each line.
+fn tag_lines(lines: &[u8], tag: &str) -> Vec<u8> {
+    let mut tagged = Vec::with_capacity(lines.len() + tag.len());
+    for line in lines.split_inclusive(|&b| b == b'\n') {
+        tagged.extend_from_slice(tag.as_bytes());
+        tagged.extend_from_slice(line);
+    }
+    tagged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_sender_sends_whole_lines() {
+        let (sender, receiver) = channel();
+        let mut lines = LineSender::new(Stream::Stderr, sender, Some("! "));
+        lines.write_all(b"one\ntw").unwrap();
+        lines.flush().unwrap();
+        lines.write_all(b"o\nthree").unwrap();
+        lines.flush().unwrap();
+        drop(lines);
+
+        let batches: Vec<_> = receiver.iter().collect();
+        assert_eq!(
+            batches,
+            [
+                (Stream::Stderr, b"! one\n".to_vec()),
+                (Stream::Stderr, b"! two\n".to_vec()),
+                (Stream::Stderr, b"! three".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_colorize_streams() {
+        use crate::grc::GrcatConfigReader;
+        use std::io::BufRead;
+
+        let conf = "regexp=fail\ncolours=red\n";
+        let rules = GrcatConfigReader::new(conf.as_bytes().lines()).collect();
+        let colorizer = Colorizer::builder()
+            .rules(rules)
+            .color_mode(crate::ColorMode::On)
+            .build();
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let stderr = StderrInput {
+            reader: &b"it will fail\n"[..],
+            colorizer: &colorizer,
+            tag: Some("[stderr] "),
+        };
+        colorize_streams(
+            &colorizer,
+            &mut &b"ok\nfail\n"[..],
+            stderr,
+            &mut out,
+            &mut err,
+            ColorizeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(out, b"ok\n\x1b[31mfail\x1b[0m\n");
+        assert_eq!(err, b"[stderr] it will \x1b[31mfail\x1b[0m\n");
+
+        // Each stream is coloured by its own colorizer
+        let plain = Colorizer::builder()
+            .rules(colorizer.rules().to_vec())
+            .color_mode(crate::ColorMode::Off)
+            .build();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let stderr = StderrInput {
+            reader: &b"it will fail\n"[..],
+            colorizer: &plain,
+            tag: None,
+        };
+        colorize_streams(
+            &colorizer,
+            &mut &b"fail\n"[..],
+            stderr,
+            &mut out,
+            &mut err,
+            ColorizeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(out, b"\x1b[31mfail\x1b[0m\n");
+        assert_eq!(err, b"it will fail\n");
+    }
+}
@@ -6,7 +6,7 @@
 //!
 //! ## Features
 //!
-//! - 🎨 Full ANSI color support (8 colors + bright variants)
+//! - 🎨 Full ANSI color support (8 colors + bright variants, 256-color palette)
 //! - ✨ Text attributes (bold, italic, underline, blink, reverse)
 //! - 📦 Zero external dependencies
 //! - 🚀 362 lines of code (vs console crate's much larger footprint)
@@ -20,9 +20,14 @@
 //! println!("{}", style.apply_to("Error!"));
 //! ```
 //!
+//! Styles are usually built from grcat keywords with `grc::style_from_str`;
+//! `Style::keywords` goes the other way and `Style::to_ansi_codes` gives the
+//! raw escape sequence.
+//!
 //! This module was created to eliminate the `console` crate dependency,
 //! reducing binary size and compile times.
 
+use std::borrow::Cow;
 use std::fmt;
 
 /// ANSI style builder for terminal colors and text attributes
@@ -37,6 +42,8 @@ pub struct Style {
     blink: bool,
     reverse: bool,
     bright: bool,
+    /// Ring the terminal bell when the style is used (grc's `beep`); no SGR code
+    beep: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -49,9 +56,14 @@ enum Color {
     Magenta,
     Cyan,
     White,
+    /// Entry of the 256-color palette
+    Fixed(u8),
 }
 
 impl Style {
+    /// The SGR sequence that clears all colors and attributes.
+    pub const RESET: &'static str = "\x1b[0m";
+
     /// Create a new empty style with no formatting
     #[inline]
     pub const fn new() -> Self {
@@ -65,6 +77,7 @@ impl Style {
             blink: false,
             reverse: false,
             bright: false,
+            beep: false,
         }
     }
 
@@ -117,6 +130,13 @@ impl Style {
         self
     }
 
+    /// Foreground color `index` of the 256-color palette
+    #[inline]
+    pub const fn color256(mut self, index: u8) -> Self {
+        self.fg_color = Some(Color::Fixed(index));
+        self
+    }
+
     // Background colors
     #[inline]
     pub const fn on_black(mut self) -> Self {
@@ -166,6 +186,13 @@ impl Style {
         self
     }
 
+    /// Background color `index` of the 256-color palette
+    #[inline]
+    pub const fn on_color256(mut self, index: u8) -> Self {
+        self.bg_color = Some(Color::Fixed(index));
+        self
+    }
+
     // Text attributes
     #[inline]
     pub const fn bold(mut self) -> Self {
@@ -211,72 +238,160 @@ impl Style {
         self
     }
 
+    /// Ring the terminal bell when text in this style is written (see `rings_bell`).
+    #[inline]
+    pub const fn beep(mut self) -> Self {
+        self.beep = true;
+        self
+    }
+
+    /// Whether the colorizer rings the bell for text in this style. The bell is
+    /// not part of the SGR sequence, so `apply_to` and `to_ansi_codes` ignore it.
+    #[inline]
+    #[allow(dead_code)]
+    pub const fn rings_bell(&self) -> bool {
+        self.beep
+    }
+
     /// Apply this style to a string, returning a formatted wrapper
     pub fn apply_to<'a>(&self, text: &'a str) -> StyledText<'a> {
         StyledText { text, style: *self }
     }
 
-    /// Generate ANSI escape codes for this style
-    fn to_ansi_codes(self) -> String {
+    /// Describe this style as grcat `colours=` keywords (e.g. `["bold", "red"]`).
+    ///
+    /// The keywords round-trip through `grc::style_from_str`; an unstyled
+    /// value returns an empty list. Palette colors are `colorN` / `on_colorN`.
+    pub fn keywords(&self) -> Vec<Cow<'static, str>> {
+        let mut words = Vec::new();
+
+        if self.bold {
+            words.push("bold".into());
+        }
+        if self.dim {
+            words.push("dark".into());
+        }
+        if self.italic {
+            words.push("italic".into());
+        }
+        if self.underlined {
+            words.push("underline".into());
+        }
+        if self.blink {
+            words.push("blink".into());
+        }
+        if self.reverse {
+            words.push("reverse".into());
+        }
+        if self.beep {
+            words.push("beep".into());
+        }
+
+        if let Some(fg) = self.fg_color {
+            words.push(match (fg, self.bright) {
+                (Color::Fixed(index), _) => format!("color{}", index).into(),
+                (Color::Black, false) => "black".into(),
+                (Color::Red, false) => "red".into(),
+                (Color::Green, false) => "green".into(),
+                (Color::Yellow, false) => "yellow".into(),
+                (Color::Blue, false) => "blue".into(),
+                (Color::Magenta, false) => "magenta".into(),
+                (Color::Cyan, false) => "cyan".into(),
+                (Color::White, false) => "white".into(),
+                (Color::Black, true) => "bright_black".into(),
+                (Color::Red, true) => "bright_red".into(),
+                (Color::Green, true) => "bright_green".into(),
+                (Color::Yellow, true) => "bright_yellow".into(),
+                (Color::Blue, true) => "bright_blue".into(),
+                (Color::Magenta, true) => "bright_magenta".into(),
+                (Color::Cyan, true) => "bright_cyan".into(),
+                (Color::White, true) => "bright_white".into(),
+            });
+        }
+
+        if let Some(bg) = self.bg_color {
+            words.push(match bg {
+                Color::Fixed(index) => format!("on_color{}", index).into(),
+                Color::Black => "on_black".into(),
+                Color::Red => "on_red".into(),
+                Color::Green => "on_green".into(),
+                Color::Yellow => "on_yellow".into(),
+                Color::Blue => "on_blue".into(),
+                Color::Magenta => "on_magenta".into(),
+                Color::Cyan => "on_cyan".into(),
+                Color::White => "on_white".into(),
+            });
+        }
+
+        words
+    }
+
+    /// The SGR sequence that sets this style, e.g. `"\x1b[1;31m"` for bold red.
+    ///
+    /// Returns an empty string for an unstyled value. The sequence does not reset
+    /// anything first, so write [`Style::RESET`] after the styled text.
+    pub fn to_ansi_codes(self) -> String {
         if self.is_empty() {
             return String::new();
         }
 
-        let mut codes = Vec::new();
+        let mut codes: Vec<Cow<'static, str>> = Vec::new();
 
         // Text attributes
         if self.bold {
-            codes.push("1");
+            codes.push("1".into());
         }
         if self.dim {
-            codes.push("2");
+            codes.push("2".into());
         }
         if self.italic {
-            codes.push("3");
+            codes.push("3".into());
         }
         if self.underlined {
-            codes.push("4");
+            codes.push("4".into());
         }
         if self.blink {
-            codes.push("5");
+            codes.push("5".into());
         }
         if self.reverse {
-            codes.push("7");
+            codes.push("7".into());
         }
 
         // Foreground color
         if let Some(fg) = self.fg_color {
             codes.push(match fg {
-                Color::Black if self.bright => "90",
-                Color::Black => "30",
-                Color::Red if self.bright => "91",
-                Color::Green if self.bright => "92",
-                Color::Yellow if self.bright => "93",
-                Color::Blue if self.bright => "94",
-                Color::Magenta if self.bright => "95",
-                Color::Cyan if self.bright => "96",
-                Color::White if self.bright => "97",
-                Color::Red => "31",
-                Color::Green => "32",
-                Color::Yellow => "33",
-                Color::Blue => "34",
-                Color::Magenta => "35",
-                Color::Cyan => "36",
-                Color::White => "37",
+                Color::Fixed(index) => format!("38;5;{}", index).into(),
+                Color::Black if self.bright => "90".into(),
+                Color::Black => "30".into(),
+                Color::Red if self.bright => "91".into(),
+                Color::Green if self.bright => "92".into(),
+                Color::Yellow if self.bright => "93".into(),
+                Color::Blue if self.bright => "94".into(),
+                Color::Magenta if self.bright => "95".into(),
+                Color::Cyan if self.bright => "96".into(),
+                Color::White if self.bright => "97".into(),
+                Color::Red => "31".into(),
+                Color::Green => "32".into(),
+                Color::Yellow => "33".into(),
+                Color::Blue => "34".into(),
+                Color::Magenta => "35".into(),
+                Color::Cyan => "36".into(),
+                Color::White => "37".into(),
             });
         }
 
         // Background color
         if let Some(bg) = self.bg_color {
             codes.push(match bg {
-                Color::Black => "40",
-                Color::Red => "41",
-                Color::Green => "42",
-                Color::Yellow => "43",
-                Color::Blue => "44",
-                Color::Magenta => "45",
-                Color::Cyan => "46",
-                Color::White => "47",
+                Color::Fixed(index) => format!("48;5;{}", index).into(),
+                Color::Black => "40".into(),
+                Color::Red => "41".into(),
+                Color::Green => "42".into(),
+                Color::Yellow => "43".into(),
+                Color::Blue => "44".into(),
+                Color::Magenta => "45".into(),
+                Color::Cyan => "46".into(),
+                Color::White => "47".into(),
             });
         }
 
@@ -313,7 +428,13 @@ impl<'a> fmt::Display for StyledText<'a> {
             write!(f, "{}", self.text)
         } else {
             // Write: ANSI codes + text + reset
-            write!(f, "{}{}\x1b[0m", self.style.to_ansi_codes(), self.text)
+            write!(
+                f,
+                "{}{}{}",
+                self.style.to_ansi_codes(),
+                self.text,
+                Style::RESET
+            )
         }
     }
 }
@@ -386,6 +507,30 @@ mod tests {
         assert_eq!(format!("{}", styled), "\x1b[31mhello\x1b[0m");
     }
 
+    #[test]
+    fn test_keywords() {
+        assert!(Style::new().keywords().is_empty());
+        assert_eq!(Style::new().bold().red().keywords(), vec!["bold", "red"]);
+        assert_eq!(
+            Style::new().bright().cyan().on_blue().keywords(),
+            vec!["bright_cyan", "on_blue"]
+        );
+        assert_eq!(Style::new().dim().keywords(), vec!["dark"]);
+        assert_eq!(
+            Style::new().color256(208).on_color256(16).keywords(),
+            vec!["color208", "on_color16"]
+        );
+    }
+
+    #[test]
+    fn test_palette_colors() {
+        let style = Style::new().bold().color256(208);
+        assert_eq!(style.to_ansi_codes(), "\x1b[1;38;5;208m");
+
+        let style = Style::new().bright().color256(46).on_color256(0);
+        assert_eq!(style.to_ansi_codes(), "\x1b[38;5;46;48;5;0m");
+    }
+
     #[test]
     fn test_empty_style() {
         let style = Style::new();
@@ -0,0 +1,246 @@
+//! # timestamps.rs - Time prefixes for output lines
+//!
+//! `rgrc --timestamps` starts each line of the output with the time it was
+//! written, like `ts` from moreutils: the local time of day, or with
+//! `--timestamps=relative` the time elapsed since the command started. The
+//! prefix is dimmed when the output is coloured, so it stays out of the way of
+//! the command's own colours.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::style::Style;
+
+/// What the time prefix shows (`--timestamps[=absolute|relative]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// The local time of day, `14:03:27.512`
+    #[default]
+    Absolute,
+    /// The time since the command started, `00:01:05.208`
+    Relative,
+}
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(TimestampFormat::Absolute),
+            "relative" => Ok(TimestampFormat::Relative),
+            _ => Err(format!(
+                "invalid timestamps '{}' (expected absolute or relative)",
+                s
+            )),
+        }
+    }
+}
+
+/// The time prefixes of one run, shared by the writers of its output streams.
+#[derive(Debug, Clone, Copy)]
+pub struct Timestamps {
+    format: TimestampFormat,
+    start: Instant,
+    style: Option<Style>,
+}
+
+impl Timestamps {
+    /// Prefixes in `format`, relative ones counted from now, written dimmed
+    /// when `colored`.
+    pub fn new(format: TimestampFormat, colored: bool) -> Self {
+        Timestamps {
+            format,
+            start: Instant::now(),
+            style: colored.then(|| Style::new().dim()),
+        }
+    }
+
+    /// The same prefixes, dimmed or not for a stream coloured or not.
+    pub fn colored(self, colored: bool) -> Self {
+        Timestamps {
+            style: colored.then(|| Style::new().dim()),
+            ..self
+        }
+    }
+
+    /// The prefix of a line written now, with its trailing space.
+    fn prefix(&self) -> String {
+        let time = match self.format {
+            TimestampFormat::Absolute => local_time_of_day(),
+            TimestampFormat::Relative => self.start.elapsed(),
+        };
+        let text = format_time(time);
+        match self.style {
+            Some(style) => format!("{} ", style.apply_to(&text)),
+            None => format!("{} ", text),
+        }
+    }
+}
+
+/// `time` as `HH:MM:SS.mmm`; hours go past 24 for long runs.
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_millis()
+    )
+}
+
+/// The time since local midnight.
+fn local_time_of_day() -> Duration {
+    #[cfg(unix)]
+    {
+        // struct tm, with room for the fields some platforms add after tm_isdst
+        #[repr(C)]
+        struct Tm {
+            sec: i32,
+            min: i32,
+            hour: i32,
+            _rest: [i32; 6],
+            _extra: [u64; 4],
+        }
+        unsafe extern "C" {
+            fn localtime_r(time: *const std::os::raw::c_long, tm: *mut Tm) -> *mut Tm;
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        // time_t is a C long on the platforms rgrc runs on
+        let secs = now.as_secs() as std::os::raw::c_long;
+        let mut tm = Tm {
+            sec: 0,
+            min: 0,
+            hour: 0,
+            _rest: [0; 6],
+            _extra: [0; 4],
+        };
+        // SAFETY: both pointers are valid for the call, and tm has room for a struct tm
+        if unsafe { localtime_r(&secs, &mut tm) }.is_null() {
+            return Duration::from_secs(secs as u64 % 86400) + subsec(now);
+        }
+        let day_secs = tm.hour as u64 * 3600 + tm.min as u64 * 60 + tm.sec as u64;
+        Duration::from_secs(day_secs) + subsec(now)
+    }
+    #[cfg(windows)]
+    {
+        #[repr(C)]
+        struct SystemTime {
+            year: u16,
+            month: u16,
+            day_of_week: u16,
+            day: u16,
+            hour: u16,
+            minute: u16,
+            second: u16,
+            milliseconds: u16,
+        }
+        unsafe extern "system" {
+            fn GetLocalTime(time: *mut SystemTime);
+        }
+
+        let mut time = SystemTime {
+            year: 0,
+            month: 0,
+            day_of_week: 0,
+            day: 0,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            milliseconds: 0,
+        };
+        // SAFETY: time is a valid SYSTEMTIME to fill in
+        unsafe { GetLocalTime(&mut time) };
+        let day_secs = time.hour as u64 * 3600 + time.minute as u64 * 60 + time.second as u64;
+        Duration::from_secs(day_secs) + Duration::from_millis(time.milliseconds as u64)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Duration::from_secs(now.as_secs() % 86400) + subsec(now)
+    }
+}
+
+#[cfg(not(windows))]
+fn subsec(time: Duration) -> Duration {
+    Duration::from_nanos(time.subsec_nanos() as u64)
+}
+
+/// Writer starting each line written to it with a time prefix, when given
+/// `Timestamps`; without, it passes what is written through.
+pub struct TimestampWriter<W: Write> {
+    inner: W,
+    stamps: Option<Timestamps>,
+    at_line_start: bool,
+}
+
+impl<W: Write> TimestampWriter<W> {
+    /// Wrap `inner`, prefixing its lines with `stamps` when given.
+    pub fn new(inner: W, stamps: Option<Timestamps>) -> Self {
+        TimestampWriter {
+            inner,
+            stamps,
+            at_line_start: true,
+        }
+    }
+}
+
+impl<W: Write> Write for TimestampWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let Some(stamps) = self.stamps else {
+            return self.inner.write(buf);
+        };
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if self.at_line_start {
+                self.inner.write_all(stamps.prefix().as_bytes())?;
+            }
+            self.inner.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(Duration::from_millis(65_208)), "00:01:05.208");
+        assert_eq!(
+            format_time(Duration::from_secs(25 * 3600 + 3)),
+            "25:00:03.000"
+        );
+        assert!(local_time_of_day() < Duration::from_secs(86400 + 1));
+    }
+
+    #[test]
+    fn test_each_line_gets_a_prefix() {
+        let stamps = Timestamps::new(TimestampFormat::Relative, false);
+        let mut writer = TimestampWriter::new(Vec::new(), Some(stamps));
+        writer.write_all(b"one\ntw").unwrap();
+        writer.write_all(b"o\n\nthree").unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        let lines: Vec<_> = output.split('\n').collect();
+        assert_eq!(lines.len(), 4);
+        for (line, text) in lines.iter().zip(["one", "two", "", "three"]) {
+            // 00:00:00.000 and a space
+            assert_eq!(&line[13..], text);
+            assert!(line.starts_with("00:00:"));
+        }
+
+        let mut writer = TimestampWriter::new(Vec::new(), None);
+        writer.write_all(b"one\n").unwrap();
+        assert_eq!(writer.inner, b"one\n");
+    }
+}
@@ -0,0 +1,669 @@
+//! # toml_config.rs - Structured TOML configs (`toml-configs` feature)
+//!
+//! A TOML form of the grcat `key=value` format, with one `[[rule]]` table per
+//! entry and typed fields instead of free text:
+//!
+//! ```toml
+//! # Packet loss
+//! [[rule]]
+//! regexp = '(\d+)% packet loss'
+//! colours = ["default", "bold red"]
+//! count = "once"
+//! ```
+//!
+//! rgrc loads `conf.NAME.toml` in place of `conf.NAME` when both sit in the
+//! same directory (see `load_toml_file`). `grc_to_toml` and `toml_to_grc`
+//! convert between the two formats (behind `rgrv convert`). Comment lines are carried over and stay in front of the
+//! rule they precede; keys and values rgrc does not know (such as a misspelt
+//! `count=onse`) are kept as comments.
+//!
+//! A project can also map its own commands to configs in a `.rgrc.toml` at the
+//! root of its tree (see `ProjectConfig`), found from any directory below it.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ColorMode;
+
+use crate::grc::{
+    CompiledRegex, GrcatConfigEntry, GrcatConfigEntryCount, RegexEngine, apply_regex_flags,
+    colour_specs_from_str, fields_from_str, gradient_from_str, resolve_colours, sizes_from_str,
+    thresholds_from_str,
+};
+
+/// A parsed TOML config: the `[[rule]]` tables in order.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TomlConfig {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<TomlRule>,
+}
+
+/// One `[[rule]]` table; the fields mirror the grcat keys.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TomlRule {
+    pub regexp: String,
+    /// Inline regex flags for `regexp` (e.g. `"i"`; see `apply_regex_flags`)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub flags: String,
+    /// Style of the whole match, then of each capture group (e.g. `"bold red"`)
+    #[serde(default, alias = "colors", skip_serializing_if = "Vec::is_empty")]
+    pub colours: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<GrcatConfigEntryCount>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub replace: String,
+    /// Shell command run when the rule matches (with `--allow-hooks`)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub command: String,
+    /// Rules with a higher priority run first; ties keep their source order
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// Styles by severity for the captured number (e.g. `"90:red"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thresholds: Vec<String>,
+    /// Colour ramp for the captured number (e.g. `"0:200"`)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub gradient: String,
+    /// Logarithmic colour ramp for the captured byte size (e.g. `"1M:1T"`)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sizes: String,
+    /// Styles by field number, applied instead of `colours` (e.g. `"-1:green"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// Field separator; runs of whitespace when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub delimiter: String,
+    /// Regex engine for this rule; `--engine` (auto by default) when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<RegexEngine>,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+/// The file a project keeps its command mappings in, looked for in the working
+/// directory and then each of its parents.
+pub const PROJECT_CONFIG_FILE: &str = ".rgrc.toml";
+
+/// A project's `.rgrc.toml`: rgrc.conf entries for the tools of a repository,
+/// one `[[command]]` table each, searched before every other mapping.
+///
+/// ```toml
+/// [[command]]
+/// regexp = '^\./deploy\b'
+/// config = "conf.deploy"
+///
+/// [[command]]
+/// regexp = '^make\b'
+/// config = "conf.gcc"
+/// colour = "on"
+/// ```
+///
+/// The configs named are looked up in the directory holding `.rgrc.toml`
+/// first, then where rgrc always looks.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    #[serde(default, rename = "command")]
+    pub commands: Vec<ProjectCommand>,
+}
+
+/// One `[[command]]` table; the fields make up an entry of rgrc.conf.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectCommand {
+    /// Regex matched against the command line
+    pub regexp: String,
+    /// The configs of the command, as in rgrc.conf (`"conf.tool, conf.common"`)
+    #[serde(default)]
+    pub config: String,
+    /// Colour mode of the command (`on`, `off` or `auto`; see `config_color_mode`)
+    #[serde(default, alias = "color")]
+    pub colour: Option<String>,
+}
+
+impl ProjectConfig {
+    /// The `[[command]]` tables as rgrc.conf text. Fails on a table setting
+    /// neither a config nor a colour, naming it.
+    pub fn to_mapping(&self) -> Result<String, String> {
+        let mut mapping = String::new();
+        for (index, command) in self.commands.iter().enumerate() {
+            let problem = if command.regexp.trim().is_empty() {
+                Some("empty regexp")
+            } else if command.regexp.contains('\n') || command.config.contains('\n') {
+                Some("line break in regexp or config")
+            } else if command.config.trim().is_empty() && command.colour.is_none() {
+                Some("needs a config or a colour")
+            } else if let Some(mode) = &command.colour
+                && mode.parse::<ColorMode>().is_err()
+            {
+                Some("colour must be on, off or auto")
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                return Err(format!("command {}: {}", index + 1, problem));
+            }
+            let mut config = command.config.trim().to_string();
+            if let Some(mode) = &command.colour {
+                if !config.is_empty() {
+                    config.push(' ');
+                }
+                config.push_str(&format!("colour={}", mode));
+            }
+            mapping.push_str(&format!("{}\n{}\n", command.regexp.trim(), config));
+        }
+        Ok(mapping)
+    }
+}
+
+/// The `.rgrc.toml` of the project `dir` belongs to: the one in `dir` or in the
+/// nearest of its parents holding one.
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Read the project config at `path` as rgrc.conf text (see
+/// `ProjectConfig::to_mapping`). A file that cannot be read or parsed is
+/// reported on stderr and yields `None`.
+pub fn load_project_mapping(path: &Path) -> Option<String> {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::from_str::<ProjectConfig>(&text).map_err(|e| e.to_string()))
+        .and_then(|config| config.to_mapping());
+    match parsed {
+        Ok(mapping) => Some(mapping),
+        Err(e) => {
+            eprintln!("rgrc: {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Parse the TOML config `text` into rules.
+///
+/// Regexes are compiled lazily, as `GrcatConfigReader::lazy()` does. Fails on
+/// TOML that does not fit the schema, an unknown flag or style, naming the rule.
+pub fn parse_toml_config(text: &str) -> Result<Vec<GrcatConfigEntry>, String> {
+    let config: TomlConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+    config
+        .rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            let pattern = apply_regex_flags(&rule.regexp, &rule.flags)
+                .map_err(|e| format!("rule {}: {}", index + 1, e))?;
+            let colors = colour_specs_from_str(&rule.colours.join(","))
+                .and_then(|specs| resolve_colours(specs, &rule.regexp))
+                .map_err(|e| format!("rule {}: {}", index + 1, e))?;
+            let thresholds = if rule.thresholds.is_empty() {
+                Vec::new()
+            } else {
+                thresholds_from_str(&rule.thresholds.join(","))
+                    .map_err(|e| format!("rule {}: {}", index + 1, e))?
+            };
+            let gradient = if rule.gradient.is_empty() {
+                None
+            } else {
+                Some(
+                    gradient_from_str(&rule.gradient)
+                        .map_err(|e| format!("rule {}: {}", index + 1, e))?,
+                )
+            };
+            let sizes = if rule.sizes.is_empty() {
+                None
+            } else {
+                Some(
+                    sizes_from_str(&rule.sizes)
+                        .map_err(|e| format!("rule {}: {}", index + 1, e))?,
+                )
+            };
+            let fields = if rule.fields.is_empty() {
+                Vec::new()
+            } else {
+                fields_from_str(&rule.fields.join(","))
+                    .map_err(|e| format!("rule {}: {}", index + 1, e))?
+            };
+            Ok(GrcatConfigEntry {
+                regex: CompiledRegex::lazy_with_engine(&pattern, rule.engine.unwrap_or_default()),
+                colors,
+                skip: rule.skip,
+                count: rule.count.unwrap_or(GrcatConfigEntryCount::More),
+                replace: rule.replace,
+                command: rule.command,
+                priority: rule.priority,
+                thresholds,
+                gradient,
+                sizes,
+                fields,
+                delimiter: rule.delimiter,
+                engine: rule.engine.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// The TOML file standing in for the config at `path`: `path` itself if it
+/// ends in `.toml`, else `path.toml`.
+pub fn toml_path(path: &Path) -> PathBuf {
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        path.to_path_buf()
+    } else {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".toml");
+        PathBuf::from(name)
+    }
+}
+
+/// Load the TOML config standing in for `path` (see `toml_path`).
+///
+/// `None` when there is no such file. A file that fails to parse is reported
+/// on stderr and yields no rules, rather than falling back to `path`.
+pub fn load_toml_file(path: &Path) -> Option<Vec<GrcatConfigEntry>> {
+    let path = toml_path(path);
+    let text = std::fs::read_to_string(&path).ok()?;
+    match parse_toml_config(&text) {
+        Ok(rules) => Some(rules),
+        Err(e) => {
+            eprintln!("rgrc: {}: {}", path.display(), e);
+            Some(Vec::new())
+        }
+    }
+}
+
+/// Convert the grcat config `text` to TOML.
+pub fn grc_to_toml(text: &str) -> Result<String, String> {
+    let mut output = String::new();
+    // Comments waiting for the next rule
+    let mut comments: Vec<String> = Vec::new();
+    // The entry being read, with its lines
+    let mut rule: Option<(TomlRule, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        if !line.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            // Any other line ends the entry, as in GrcatConfigReader
+            flush_rule(&mut output, &mut comments, rule.take())?;
+            if line.trim_start().starts_with('#') {
+                comments.push(line.trim_start().to_string());
+            }
+            continue;
+        }
+
+        let (current, lines) = rule.get_or_insert_with(Default::default);
+        lines.push(line);
+        let Some((key, value)) = line.split_once('=') else {
+            comments.push(format!("# {}", line));
+            continue;
+        };
+        let value = value.trim_start();
+        match key.trim_end() {
+            "regexp" => {
+                current.regexp = value.to_string();
+            }
+            "colours" | "colors" | "colour" => {
+                current.colours = value.split(',').map(|s| s.trim().to_string()).collect();
+            }
+            "count" if value == "once" => current.count = Some(GrcatConfigEntryCount::Once),
+            "count" if value == "more" => current.count = Some(GrcatConfigEntryCount::More),
+            "count" if value == "stop" => current.count = Some(GrcatConfigEntryCount::Stop),
+            "count" if value == "block" => current.count = Some(GrcatConfigEntryCount::Block),
+            "count" if value == "unblock" => current.count = Some(GrcatConfigEntryCount::Unblock),
+            "skip" => match value.to_lowercase().as_str() {
+                "true" | "1" | "yes" => current.skip = true,
+                "false" | "0" | "no" => current.skip = false,
+                // rgrc reads other values as false
+                _ => comments.push(format!("# {}", line)),
+            },
+            "replace" => current.replace = value.to_string(),
+            "flags" => current.flags = value.to_string(),
+            "command" => current.command = value.to_string(),
+            "thresholds" => {
+                current.thresholds = value.split(',').map(|s| s.trim().to_string()).collect();
+            }
+            "gradient" => current.gradient = value.to_string(),
+            "sizes" => current.sizes = value.to_string(),
+            "fields" => {
+                current.fields = value.split(',').map(|s| s.trim().to_string()).collect();
+            }
+            "delimiter" => current.delimiter = value.replace("\\t", "\t"),
+            "engine" => match value.trim().parse() {
+                Ok(engine) => current.engine = Some(engine),
+                // rgrc reports other values and uses the default engine
+                Err(_) => comments.push(format!("# {}", line)),
+            },
+            "priority" => match value.parse() {
+                Ok(priority) => current.priority = priority,
+                // rgrc reads other values as 0
+                Err(_) => comments.push(format!("# {}", line)),
+            },
+            _ => comments.push(format!("# {}", line)),
+        }
+    }
+    flush_rule(&mut output, &mut comments, rule.take())?;
+    for comment in comments {
+        output.push_str(&comment);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Append `rule` (with the comments before it) to `output`.
+///
+/// An entry without `regexp` is ignored by rgrc, so its keys become comments
+/// (unknown keys and lines without `=` already are).
+fn flush_rule(
+    output: &mut String,
+    comments: &mut Vec<String>,
+    rule: Option<(TomlRule, Vec<&str>)>,
+) -> Result<(), String> {
+    let Some((rule, lines)) = rule else {
+        return Ok(());
+    };
+    let key = |line: &str| {
+        line.split_once('=')
+            .map(|(key, _)| key.trim_end().to_string())
+    };
+    if !lines
+        .iter()
+        .any(|line| key(line).as_deref() == Some("regexp"))
+    {
+        comments.extend(
+            lines
+                .iter()
+                .filter(|line| {
+                    matches!(
+                        key(line).as_deref(),
+                        Some(
+                            "colours"
+                                | "colors"
+                                | "colour"
+                                | "replace"
+                                | "flags"
+                                | "command"
+                                | "priority"
+                                | "thresholds"
+                                | "gradient"
+                                | "sizes"
+                                | "fields"
+                                | "delimiter"
+                                | "engine"
+                        )
+                    )
+                })
+                .map(|line| format!("# {}", line)),
+        );
+        return Ok(());
+    }
+
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    for comment in comments.drain(..) {
+        output.push_str(&comment);
+        output.push('\n');
+    }
+    let table = TomlConfig { rules: vec![rule] };
+    output.push_str(&toml::to_string(&table).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+/// Convert the TOML config `text` to grcat syntax.
+///
+/// Comment lines are written in front of the rule whose `[[rule]]` header
+/// follows them; comments after the last rule end the file.
+pub fn toml_to_grc(text: &str) -> Result<String, String> {
+    let config: TomlConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+
+    // Comments before each [[rule]] header, plus those after the last one
+    let mut comments: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            comments.last_mut().unwrap().push(line);
+        } else if line == "[[rule]]" {
+            comments.push(Vec::new());
+        }
+    }
+
+    let mut output = String::new();
+    for (index, rule) in config.rules.iter().enumerate() {
+        let before = comments.get(index).map_or(&[][..], Vec::as_slice);
+        if before.is_empty() && index > 0 {
+            // Comment lines separate entries by themselves
+            output.push_str("-\n");
+        }
+        for comment in before {
+            output.push_str(comment);
+            output.push('\n');
+        }
+        output.push_str(&format!("regexp={}\n", rule.regexp));
+        if !rule.flags.is_empty() {
+            output.push_str(&format!("flags={}\n", rule.flags));
+        }
+        if !rule.colours.is_empty() {
+            output.push_str(&format!("colours={}\n", rule.colours.join(",")));
+        }
+        if let Some(count) = &rule.count {
+            let count = match count {
+                GrcatConfigEntryCount::Once => "once",
+                GrcatConfigEntryCount::More => "more",
+                GrcatConfigEntryCount::Stop => "stop",
+                GrcatConfigEntryCount::Block => "block",
+                GrcatConfigEntryCount::Unblock => "unblock",
+            };
+            output.push_str(&format!("count={}\n", count));
+        }
+        if !rule.replace.is_empty() {
+            output.push_str(&format!("replace={}\n", rule.replace));
+        }
+        if rule.skip {
+            output.push_str("skip=true\n");
+        }
+        if !rule.command.is_empty() {
+            output.push_str(&format!("command={}\n", rule.command));
+        }
+        if rule.priority != 0 {
+            output.push_str(&format!("priority={}\n", rule.priority));
+        }
+        if !rule.thresholds.is_empty() {
+            output.push_str(&format!("thresholds={}\n", rule.thresholds.join(",")));
+        }
+        if !rule.gradient.is_empty() {
+            output.push_str(&format!("gradient={}\n", rule.gradient));
+        }
+        if !rule.sizes.is_empty() {
+            output.push_str(&format!("sizes={}\n", rule.sizes));
+        }
+        if !rule.fields.is_empty() {
+            output.push_str(&format!("fields={}\n", rule.fields.join(",")));
+        }
+        if !rule.delimiter.is_empty() {
+            output.push_str(&format!(
+                "delimiter={}\n",
+                rule.delimiter.replace('\t', "\\t")
+            ));
+        }
+        if let Some(engine) = rule.engine {
+            output.push_str(&format!("engine={}\n", engine.as_str()));
+        }
+    }
+    for comment in comments.iter().skip(config.rules.len()).flatten() {
+        output.push_str(comment);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRC: &str = "\
+# ping statistics
+regexp=(\\d+)% packet loss
+colours=default,bold red
+count=once
+-
+regexp=^PING
+colors=bold, cyan
+skip=yes
+replace=\\1
+# trailing note
+";
+
+    #[test]
+    fn test_grc_to_toml() {
+        let toml = grc_to_toml(GRC).unwrap();
+        assert_eq!(
+            toml,
+            "# ping statistics\n\
+             [[rule]]\n\
+             regexp = '(\\d+)% packet loss'\n\
+             colours = [\"default\", \"bold red\"]\n\
+             count = \"once\"\n\
+             \n\
+             [[rule]]\n\
+             regexp = \"^PING\"\n\
+             colours = [\"bold\", \"cyan\"]\n\
+             skip = true\n\
+             replace = '\\1'\n\
+             # trailing note\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_keeps_rules_and_comments() {
+        let grc = toml_to_grc(&grc_to_toml(GRC).unwrap()).unwrap();
+        assert_eq!(
+            grc,
+            "# ping statistics\n\
+             regexp=(\\d+)% packet loss\n\
+             colours=default,bold red\n\
+             count=once\n\
+             -\n\
+             regexp=^PING\n\
+             colours=bold,cyan\n\
+             replace=\\1\n\
+             skip=true\n\
+             # trailing note\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_and_bad_values() {
+        let toml = grc_to_toml("regexp=x\nmode=fast\n").unwrap();
+        assert!(toml.starts_with("# mode=fast\n[[rule]]\n"));
+        assert_eq!(grc_to_toml("colours=red\n").unwrap(), "# colours=red\n");
+        assert_eq!(
+            grc_to_toml("regexp=x\ncount=onse\n").unwrap(),
+            "# count=onse\n[[rule]]\nregexp = \"x\"\n"
+        );
+        assert_eq!(
+            grc_to_toml("regexp=x\ncount=block\n").unwrap(),
+            "[[rule]]\nregexp = \"x\"\ncount = \"block\"\n"
+        );
+        assert!(toml_to_grc("[[rule]]\nregexp = 'x'\ncount = 'twice'\n").is_err());
+        assert!(toml_to_grc("[[rule]]\nregex = 'x'\n").is_err());
+    }
+
+    /// Every bundled config converts to TOML and back to the same rules
+    #[test]
+    fn test_bundled_configs_round_trip() {
+        use crate::grc::{GrcatConfigReader, write_grcat_config};
+        use std::io::BufRead;
+
+        let rules = |text: &str| {
+            let entries: Vec<_> = GrcatConfigReader::new(text.as_bytes().lines())
+                .lazy()
+                .collect();
+            let mut output = Vec::new();
+            write_grcat_config(&mut output, &entries).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let share = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("share");
+        for entry in std::fs::read_dir(share).unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let converted = toml_to_grc(&grc_to_toml(&text).unwrap()).unwrap();
+            assert_eq!(rules(&text), rules(&converted), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_parse_toml_config() {
+        let rules = parse_toml_config(
+            "[[rule]]\nregexp = '(\\d+)ms'\ncolours = ['default', 'bold green']\ncount = 'stop'\n\n\
+             [[rule]]\nregexp = 'x'\nflags = 'i'\nskip = true\nreplace = 'y'\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].regex.as_str(), "(\\d+)ms");
+        assert_eq!(rules[0].colors.len(), 2);
+        assert_eq!(rules[0].count, GrcatConfigEntryCount::Stop);
+        assert_eq!(rules[1].regex.as_str(), "(?i)x");
+        assert!(rules[1].skip);
+        assert_eq!(rules[1].count, GrcatConfigEntryCount::More);
+        assert_eq!(rules[1].replace, "y");
+
+        let error = parse_toml_config("[[rule]]\nregexp = 'x'\ncolours = ['purple']\n");
+        assert!(error.unwrap_err().starts_with("rule 1: "));
+        let error = parse_toml_config("[[rule]]\nregexp = 'x'\nflags = 'q'\n");
+        assert!(
+            error
+                .unwrap_err()
+                .starts_with("rule 1: unknown regex flag 'q'")
+        );
+    }
+
+    #[test]
+    fn test_project_config_mapping() {
+        let config: ProjectConfig = toml::from_str(
+            "[[command]]\nregexp = '^tool\\b'\nconfig = 'conf.tool'\n\n\
+             [[command]]\nregexp = ' ^top '\ncolour = 'off'\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.to_mapping().unwrap(),
+            "^tool\\b\nconf.tool\n^top\ncolour=off\n"
+        );
+        let config: ProjectConfig =
+            toml::from_str("[[command]]\nregexp = '^x'\ncolour = 'always'\n").unwrap();
+        assert_eq!(
+            config.to_mapping().unwrap_err(),
+            "command 1: colour must be on, off or auto"
+        );
+
+        let config: ProjectConfig = toml::from_str("[[command]]\nregexp = '^x'\n").unwrap();
+        assert_eq!(
+            config.to_mapping().unwrap_err(),
+            "command 1: needs a config or a colour"
+        );
+        assert!(toml::from_str::<ProjectConfig>("[[command]]\nregex = '^x'\n").is_err());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let nested = dir.path().join("src/bin");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "").unwrap();
+        assert_eq!(
+            find_project_config(&nested),
+            Some(dir.path().join(PROJECT_CONFIG_FILE))
+        );
+    }
+
+    #[test]
+    fn test_toml_path() {
+        assert_eq!(toml_path(Path::new("a/conf.x")), Path::new("a/conf.x.toml"));
+        assert_eq!(
+            toml_path(Path::new("conf.x.toml")),
+            Path::new("conf.x.toml")
+        );
+    }
+}
@@ -103,8 +103,8 @@ pub fn set_process_title(title: &str) {
 /// Check whether an executable named `cmd` exists on the user's `PATH`.
 ///
 /// This performs a lightweight search of directories in the `PATH` environment
-/// variable and returns `true` if a file with the given name exists in any
-/// directory. On Windows, common executable extensions are also considered.
+/// variable (see `find_command`) and returns `true` if the command is found.
+/// To check many commands, scan `PATH` once with `CommandIndex` instead.
 ///
 /// # Examples
 ///
@@ -113,30 +113,336 @@ pub fn set_process_title(title: &str) {
 /// assert!(!rgrc::utils::command_exists("this-command-doesnt-exist-xyz"));
 /// ```
 pub fn command_exists(cmd: &str) -> bool {
+    find_command(cmd).is_some()
+}
+
+/// The file that runs for the command `cmd`: the first executable match in the
+/// `PATH` directories, or `None`.
+///
+/// On Windows a name without an extension is looked up with each extension of
+/// `PATHEXT` in turn (default `.COM;.EXE;.BAT;.CMD`), as `cmd.exe` does, so
+/// `npm` finds `npm.cmd`; a name that already has one of them is looked up as
+/// it is.
+pub fn find_command(cmd: &str) -> Option<std::path::PathBuf> {
     // Empty command is not valid
     if cmd.is_empty() {
+        return None;
+    }
+    let path = std::env::var_os("PATH")?;
+    find_command_in(cmd, &path, &path_extensions())
+}
+
+/// A command running `line` with the user's shell (`--shell`), and that shell's name.
+///
+/// The shell is `$SHELL -c`, or without `SHELL` `/bin/sh -c` (on Windows
+/// `%ComSpec% /C`, with `line` passed on unquoted, as cmd.exe expects).
+pub fn shell_command(line: &str) -> (String, std::process::Command) {
+    if let Some(shell) = std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+    {
+        let mut cmd = std::process::Command::new(&shell);
+        cmd.arg("-c").arg(line);
+        return (shell, cmd);
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let shell = std::env::var("ComSpec").unwrap_or_else(|_| "cmd.exe".to_string());
+        let mut cmd = std::process::Command::new(&shell);
+        cmd.arg("/C").raw_arg(line);
+        (shell, cmd)
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = std::process::Command::new("/bin/sh");
+        cmd.arg("-c").arg(line);
+        ("/bin/sh".to_string(), cmd)
+    }
+}
+
+/// The extensions that make a file a command on this platform: `PATHEXT` on
+/// Windows (default `.COM;.EXE;.BAT;.CMD`), none elsewhere.
+fn path_extensions() -> Vec<String> {
+    #[cfg(windows)]
+    {
+        std::env::var("PATHEXT")
+            .ok()
+            .filter(|exts| !exts.is_empty())
+            .unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+    #[cfg(not(windows))]
+    {
+        Vec::new()
+    }
+}
+
+/// The extension of `extensions` that `name` ends with, ignoring case.
+fn matching_extension<'a>(name: &str, extensions: &'a [String]) -> Option<&'a str> {
+    extensions
+        .iter()
+        .find(|ext| {
+            name.len() > ext.len()
+                && name.is_char_boundary(name.len() - ext.len())
+                && name[name.len() - ext.len()..].eq_ignore_ascii_case(ext)
+        })
+        .map(String::as_str)
+}
+
+/// Whether `path` is a file that can be run: a regular file (or a link to one)
+/// with an execute permission bit on Unix, any regular file elsewhere (where the
+/// extension decides).
+fn is_executable(path: &std::path::Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
         return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
     }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
 
-    if let Ok(path) = std::env::var("PATH") {
-        for dir in std::env::split_paths(&path) {
-            let full_path = dir.join(cmd);
-            if full_path.exists() {
-                return true;
-            }
-            // Also check with common extensions on Windows
-            #[cfg(target_os = "windows")]
-            {
-                for ext in &[".exe", ".cmd", ".bat", ".com"] {
-                    let full_path_with_ext = dir.join(format!("{}{}", cmd, ext));
-                    if full_path_with_ext.exists() {
-                        return true;
+/// `find_command` over the directories of `path`, trying `extensions` on a
+/// name that has none of them (and nothing else when there are extensions).
+fn find_command_in(
+    cmd: &str,
+    path: &std::ffi::OsStr,
+    extensions: &[String],
+) -> Option<std::path::PathBuf> {
+    let has_extension = extensions.is_empty() || matching_extension(cmd, extensions).is_some();
+    std::env::split_paths(path).find_map(|dir| {
+        if has_extension {
+            Some(dir.join(cmd)).filter(|candidate| is_executable(candidate))
+        } else {
+            extensions
+                .iter()
+                .map(|ext| dir.join(format!("{}{}", cmd, ext)))
+                .find(|candidate| is_executable(candidate))
+        }
+    })
+}
+
+/// The commands on `PATH`, read from its directories once.
+///
+/// Answers `command_exists` for many commands (such as the ~70 checked when
+/// generating aliases) without searching `PATH` for each of them. On Windows a
+/// command is known both with and without its `PATHEXT` extension, and names
+/// are compared ignoring case.
+///
+/// ```ignore
+/// let commands = rgrc::utils::CommandIndex::from_env();
+/// assert!(commands.contains("ls"));
+/// ```
+#[derive(Debug, Default)]
+pub struct CommandIndex {
+    names: std::collections::HashSet<String>,
+    /// Names are lowercase (with `PATHEXT`)
+    ignore_case: bool,
+}
+
+impl CommandIndex {
+    /// Index the commands of the `PATH` directories.
+    pub fn from_env() -> Self {
+        match std::env::var_os("PATH") {
+            Some(path) => Self::scan(&path, &path_extensions()),
+            None => Self::default(),
+        }
+    }
+
+    /// Index the commands of the directories of `path`, where files need one of
+    /// `extensions` (if any) to be commands.
+    fn scan(path: &std::ffi::OsStr, extensions: &[String]) -> Self {
+        let mut names = std::collections::HashSet::new();
+        for dir in std::env::split_paths(path) {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                if extensions.is_empty() {
+                    if !names.contains(&name) && is_executable(&entry.path()) {
+                        names.insert(name);
                     }
+                } else if let Some(ext) = matching_extension(&name, extensions)
+                    && is_executable(&entry.path())
+                {
+                    let name = name.to_ascii_lowercase();
+                    names.insert(name[..name.len() - ext.len()].to_string());
+                    names.insert(name);
                 }
             }
         }
+        CommandIndex {
+            names,
+            ignore_case: !extensions.is_empty(),
+        }
+    }
+
+    /// Whether `cmd` is a command on `PATH`.
+    pub fn contains(&self, cmd: &str) -> bool {
+        if self.ignore_case {
+            self.names.contains(&cmd.to_ascii_lowercase())
+        } else {
+            self.names.contains(cmd)
+        }
+    }
+}
+
+/// Let the console interpret the ANSI escape sequences written to stdout, and
+/// return whether it does.
+///
+/// On Windows this turns on virtual terminal processing for a console stdout,
+/// which Windows 10 and later consoles and every ConPTY session (Windows
+/// Terminal, VS Code) support; it is `false` only for older consoles, which
+/// would print the sequences as text. Output that is not a console (pipes,
+/// files, mintty) and other platforms are always `true`.
+pub fn enable_ansi_support() -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+
+        const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn GetConsoleMode(handle: *mut std::ffi::c_void, mode: *mut u32) -> i32;
+            fn SetConsoleMode(handle: *mut std::ffi::c_void, mode: u32) -> i32;
+        }
+
+        let handle = std::io::stdout().as_raw_handle();
+        let mut mode = 0;
+        // SAFETY: the handle is stdout's, and mode a valid place for the result
+        unsafe {
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                return true;
+            }
+            mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        true
     }
-    false
+}
+
+/// Ask the process `pid` to stop (SIGTERM), or when `force` make it (SIGKILL),
+/// and return whether the signal was delivered.
+///
+/// On Windows, which has no signals, both terminate the process.
+pub fn stop_process(pid: u32, force: bool) -> bool {
+    #[cfg(unix)]
+    {
+        const SIGKILL: i32 = 9;
+        const SIGTERM: i32 = 15;
+
+        unsafe extern "C" {
+            fn kill(pid: i32, sig: i32) -> i32;
+        }
+
+        let Ok(pid) = i32::try_from(pid) else {
+            return false;
+        };
+        // SAFETY: kill only reads its arguments
+        unsafe { kill(pid, if force { SIGKILL } else { SIGTERM }) == 0 }
+    }
+    #[cfg(windows)]
+    {
+        const PROCESS_TERMINATE: u32 = 0x0001;
+
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn OpenProcess(access: u32, inherit: i32, pid: u32) -> *mut std::ffi::c_void;
+            fn TerminateProcess(handle: *mut std::ffi::c_void, exit_code: u32) -> i32;
+            fn CloseHandle(handle: *mut std::ffi::c_void) -> i32;
+        }
+
+        let _ = force;
+        // SAFETY: the handle is checked before use and closed once
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+            if handle.is_null() {
+                return false;
+            }
+            let stopped = TerminateProcess(handle, 1) != 0;
+            CloseHandle(handle);
+            stopped
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (pid, force);
+        false
+    }
+}
+
+/// Signals sent by the terminal (Ctrl-C and Ctrl-\) that `leave_interrupts_to_child`
+/// leaves to the command: SIGINT and SIGQUIT.
+#[cfg(unix)]
+pub const INTERRUPT_SIGNALS: [i32; 2] = [2, 3];
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn signal(signum: i32, handler: usize) -> usize;
+    fn raise(sig: i32) -> i32;
+}
+
+/// Let the command rgrc runs handle Ctrl-C (and Ctrl-\) on its own, from now on.
+///
+/// The terminal interrupts its whole foreground process group, rgrc and the
+/// command alike. Ignoring the interrupt keeps rgrc colorizing what the command
+/// writes while it handles it (ping prints its statistics), until the command
+/// exits; a command that ignores Ctrl-C keeps running, as it would without rgrc.
+/// Ctrl-Z stops both, and `fg` resumes both, as they share the group.
+///
+/// Call it once the command has started, as it would otherwise inherit the
+/// ignored interrupts.
+pub fn leave_interrupts_to_child() {
+    #[cfg(unix)]
+    {
+        const SIG_IGN: usize = 1;
+        for sig in INTERRUPT_SIGNALS {
+            // SAFETY: installs no handler, only the "ignore" disposition
+            unsafe { signal(sig, SIG_IGN) };
+        }
+    }
+    #[cfg(windows)]
+    {
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn SetConsoleCtrlHandler(handler: *const std::ffi::c_void, add: i32) -> i32;
+        }
+
+        // SAFETY: a null handler with `add` makes the process ignore Ctrl-C
+        unsafe { SetConsoleCtrlHandler(std::ptr::null(), 1) };
+    }
+}
+
+/// End rgrc by the signal `sig` that ended the command, so the shell sees an
+/// interrupted command (and stops a script or loop, as it would for the command).
+///
+/// Exits with 128 plus the signal number should rgrc survive it.
+#[cfg(unix)]
+pub fn exit_by_signal(sig: i32) -> ! {
+    const SIG_DFL: usize = 0;
+    // SAFETY: restores the default disposition, then signals this process
+    unsafe {
+        signal(sig, SIG_DFL);
+        raise(sig);
+    }
+    std::process::exit(128 + sig);
 }
 
 /// Curated list of commands that ship with colorization rules.
@@ -154,7 +460,11 @@ pub fn command_exists(cmd: &str) -> bool {
 /// ```
 pub const SUPPORTED_COMMANDS: &[&str] = &[
     "ant",
+    "apt",
+    "apt-get",
     "blkid",
+    "brew",
+    "cargo",
     "common",
     "curl",
     "cvs",
@@ -162,8 +472,11 @@ pub const SUPPORTED_COMMANDS: &[&str] = &[
     "diff",
     "dig",
     "diskutil",
+    "dmesg",
     "dnf",
     "docker",
+    "dpkg",
+    "dpkg-query",
     "du",
     "kdig",
     "dummy",
@@ -175,6 +488,7 @@ pub const SUPPORTED_COMMANDS: &[&str] = &[
     "gcc",
     "getfacl",
     "getsebool",
+    "git",
     "id",
     "ifconfig",
     "ip",
@@ -197,11 +511,15 @@ pub const SUPPORTED_COMMANDS: &[&str] = &[
     "mount",
     "mvn",
     "netstat",
+    "npm",
     "nmap",
     "ntpdate",
+    "pacman",
+    "paru",
     "php",
     "ping",
     "ping2",
+    "pnpm",
     "podman",
     "proftpd",
     "ps",
@@ -218,6 +536,7 @@ pub const SUPPORTED_COMMANDS: &[&str] = &[
     "journalctl",
     "tail",
     "tcpdump",
+    "terraform",
     "traceroute",
     "tune2fs",
     "ulimit",
@@ -226,10 +545,17 @@ pub const SUPPORTED_COMMANDS: &[&str] = &[
     "wdiff",
     "whois",
     "yaml",
+    "yay",
+    "yarn",
     "go",
     "iostat",
 ];
 
+/// Supported commands that get no shell alias: piped through rgrc, git turns
+/// off its own colours and its pager, and the programs `cargo run` and `npm run`
+/// start lose their terminal.
+pub const NO_ALIAS_COMMANDS: &[&str] = &["cargo", "git", "npm", "pnpm", "yarn"];
+
 /// Check if a command has colorization rules available (used for Always strategy)
 /// Return `true` when a command has shipped colorization rules (present in
 /// `SUPPORTED_COMMANDS`). This is a simple membership check used by the
@@ -295,10 +621,330 @@ pub fn pseudo_command_excluded(pseudo_command: &str) -> bool {
     !parts[1].starts_with('-')
 }
 
+/// Environment variable listing commands not to colour, comma-separated
+/// (`RGRC_EXCEPT=ls,ps`): `--except` for aliases already in place.
+pub const EXCEPT_ENV: &str = "RGRC_EXCEPT";
+
+/// Environment variable turning colours off for every command (`RGRC_DISABLE=1`).
+pub const DISABLE_ENV: &str = "RGRC_DISABLE";
+
+/// Check whether the environment turns colours off for `command`: either
+/// `RGRC_DISABLE` is set to anything but empty or `0`, or `RGRC_EXCEPT` lists
+/// its name (a path such as `/usr/bin/ping` counts as `ping`).
+pub fn disabled_by_env(command: &str) -> bool {
+    disabled_by(command, |name| std::env::var(name).ok())
+}
+
+fn disabled_by(command: &str, var: impl Fn(&str) -> Option<String>) -> bool {
+    if var(DISABLE_ENV).is_some_and(|value| !value.is_empty() && value != "0") {
+        return true;
+    }
+    let name = command.rsplit(['/', '\\']).next().unwrap_or(command);
+    var(EXCEPT_ENV).is_some_and(|except| except.split(',').any(|cmd| cmd.trim() == name))
+}
+
+/// The stages of the shell pipeline `line`, in order and trimmed, split at each
+/// `|` or `|&` that is not quoted, escaped or part of `||`.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert_eq!(pipeline_stages("ss -t | grep ':443'"), ["ss -t", "grep ':443'"]);
+/// assert_eq!(pipeline_stages("ping -c1 a || echo 'x|y'"), ["ping -c1 a || echo 'x|y'"]);
+/// ```
+pub fn pipeline_stages(line: &str) -> Vec<&str> {
+    let bytes = line.as_bytes();
+    let mut stages = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    let mut quote = None;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (Some(q), b) if b == q => quote = None,
+            (Some(b'"'), b'\\') | (None, b'\\') => i += 1,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"') => quote = Some(bytes[i]),
+            (None, b'|') if bytes.get(i + 1) == Some(&b'|') => i += 1,
+            (None, b'|') => {
+                stages.push(line[start..i].trim());
+                if bytes.get(i + 1) == Some(&b'&') {
+                    i += 1;
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    stages.push(line[start.min(line.len())..].trim());
+    stages
+}
+
+/// Remove terminal escape sequences from `text`.
+///
+/// Handles the sequences commands realistically emit:
+/// - CSI sequences such as SGR colors (`ESC [ 1;31 m`) and cursor movement
+/// - OSC sequences such as hyperlinks and window titles, terminated by BEL or `ESC \`
+/// - DCS, SOS, PM and APC strings (`ESC P` ... `ESC \`), such as tmux passthrough
+/// - Charset selection (`ESC ( B`) and other short escapes (`ESC =`, ...)
+///
+/// Text without any `ESC` byte is returned borrowed, so the common case
+/// costs a single scan.
+///
+/// # Examples
+///
+/// ```
+/// use rgrc::utils::strip_ansi;
+///
+/// assert_eq!(strip_ansi("\x1b[1;31mError\x1b[0m: disk full"), "Error: disk full");
+/// assert_eq!(strip_ansi("plain"), "plain");
+/// ```
+pub fn strip_ansi(text: &str) -> std::borrow::Cow<'_, str> {
+    match strip_ansi_bytes(text.as_bytes()) {
+        std::borrow::Cow::Borrowed(_) => std::borrow::Cow::Borrowed(text),
+        // Only whole sequences and characters are removed, so the rest is still UTF-8
+        std::borrow::Cow::Owned(bytes) => std::borrow::Cow::Owned(
+            String::from_utf8(bytes)
+                .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        ),
+    }
+}
+
+/// Remove terminal escape sequences from `bytes`, as `strip_ansi` does for
+/// text; the bytes need not be UTF-8 (`--strip`).
+pub fn strip_ansi_bytes(bytes: &[u8]) -> std::borrow::Cow<'_, [u8]> {
+    if !bytes.contains(&0x1b) {
+        return std::borrow::Cow::Borrowed(bytes);
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut plain_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != 0x1b {
+            i += 1;
+            continue;
+        }
+
+        out.extend_from_slice(&bytes[plain_start..i]);
+        i += 1;
+
+        match bytes.get(i) {
+            // CSI: ESC [ parameters/intermediates, terminated by a byte in 0x40..=0x7e
+            Some(b'[') => {
+                i += 1;
+                while i < bytes.len() && !(0x40..=0x7e).contains(&bytes[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            // OSC: ESC ] payload, terminated by BEL or ST (ESC \); DCS (ESC P),
+            // SOS (ESC X), PM (ESC ^) and APC (ESC _) strings end with ST too
+            Some(&introducer @ (b']' | b'P' | b'X' | b'^' | b'_')) => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == 0x07 && introducer == b']' {
+                        i += 1;
+                        break;
+                    }
+                    if bytes[i] == 0x1b && bytes.get(i + 1) == Some(&b'\\') {
+                        i += 2;
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            // nF escapes such as charset selection (ESC ( B): intermediates then a final byte
+            Some(0x20..=0x2f) => {
+                while i < bytes.len() && (0x20..=0x2f).contains(&bytes[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            // Any other escape is a two-byte sequence
+            Some(_) => i += 1,
+            None => {}
+        }
+
+        // Never resume in the middle of a multi-byte character
+        i = i.min(bytes.len());
+        while i < bytes.len() && (0x80..0xc0).contains(&bytes[i]) {
+            i += 1;
+        }
+        plain_start = i;
+    }
+
+    out.extend_from_slice(&bytes[plain_start..]);
+    std::borrow::Cow::Owned(out)
+}
+
+/// Copy `reader` to `writer` line by line without its escape sequences
+/// (`--strip`), flushing whenever the input read so far is written, so that
+/// the output of a long-running command shows as it comes.
+pub fn strip_stream<R: std::io::Read, W: std::io::Write>(
+    reader: R,
+    mut writer: W,
+) -> std::io::Result<()> {
+    use std::io::BufRead;
+    let mut reader = std::io::BufReader::with_capacity(64 * 1024, reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        writer.write_all(&strip_ansi_bytes(&line))?;
+        if reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+    }
+    writer.flush()
+}
+
+/// Quote `text` as a JSON string literal, escaping quotes, backslashes and
+/// control characters.
+///
+/// # Examples
+///
+/// ```
+/// use rgrc::utils::json_quote;
+///
+/// assert_eq!(json_quote("say \"hi\""), r#""say \"hi\"""#);
+/// assert_eq!(json_quote("tab\there"), r#""tab\there""#);
+/// ```
+pub fn json_quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\x7f' => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Write `contents` to `path` through a temporary file and a rename, so
+/// concurrent readers see either the old or the new file, never a partial one.
+pub fn write_atomic(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut name = path
+        .file_name()
+        .ok_or(std::io::ErrorKind::InvalidInput)?
+        .to_os_string();
+    name.push(format!(".tmp{}", std::process::id()));
+    let tmp = path.with_file_name(name);
+
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+///
+/// Unlike `std`'s default hasher the result is stable across builds and
+/// platforms, so it can be used to name files in the cache directory.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_disabled_by_env() {
+        let env = |disable: Option<&'static str>, except: Option<&'static str>| {
+            move |name: &str| match name {
+                DISABLE_ENV => disable.map(String::from),
+                EXCEPT_ENV => except.map(String::from),
+                _ => None,
+            }
+        };
+        assert!(!disabled_by("ping", env(None, None)));
+        assert!(disabled_by("ping", env(Some("1"), None)));
+        assert!(!disabled_by("ping", env(Some("0"), None)));
+        assert!(!disabled_by("ping", env(Some(""), None)));
+        assert!(disabled_by("ping", env(None, Some("ls, ping"))));
+        assert!(disabled_by("/usr/bin/ping", env(None, Some("ping"))));
+        assert!(!disabled_by("ping6", env(None, Some("ping,ls"))));
+        assert!(!disabled_by("ping", env(Some("0"), Some("ls"))));
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conf.ping");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(write_atomic(&dir.path().join("missing").join("x"), b"").is_err());
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values of the 64-bit FNV-1a test suite
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        // SGR sequences
+        assert_eq!(strip_ansi("\x1b[31mred\x1b[0m"), "red");
+        assert_eq!(strip_ansi("a\x1b[1;4;32mb\x1b[0mc"), "abc");
+
+        // Cursor movement and erase sequences
+        assert_eq!(strip_ansi("\x1b[2K\x1b[1Gprogress"), "progress");
+
+        // OSC hyperlink terminated by ST and window title terminated by BEL
+        assert_eq!(
+            strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            "link"
+        );
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+
+        // Two-byte escape and truncated sequences
+        assert_eq!(strip_ansi("\x1b(Bplain"), "plain");
+        assert_eq!(strip_ansi("cut\x1b[31"), "cut");
+        assert_eq!(strip_ansi("end\x1b"), "end");
+
+        // Multi-byte text is preserved
+        assert_eq!(strip_ansi("\x1b[32m✓ ok\x1b[0m"), "✓ ok");
+
+        // DCS strings, and bytes that are not UTF-8
+        assert_eq!(strip_ansi("\x1bPtmux;data\x1b\\shown"), "shown");
+        assert_eq!(
+            strip_ansi_bytes(b"\x1b[1m\xff\xfe\x1b[0m\n"),
+            &b"\xff\xfe\n"[..]
+        );
+
+        // Plain text is borrowed unchanged
+        assert!(matches!(strip_ansi("plain"), std::borrow::Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_json_quote() {
+        assert_eq!(json_quote(""), "\"\"");
+        assert_eq!(json_quote("a\\b"), r#""a\\b""#);
+        assert_eq!(json_quote("line\n"), r#""line\n""#);
+        assert_eq!(json_quote("\x1b[0m"), r#""\u001b[0m""#);
+        assert_eq!(json_quote("✓ ok"), "\"✓ ok\"");
+    }
+
     #[test]
     fn test_command_exists() {
         // The available system commands vary between platforms and CI images.
@@ -347,6 +993,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_command_in_tries_path_extensions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (first, second) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::create_dir(&first).unwrap();
+        std::fs::create_dir(&second).unwrap();
+        // npm ships a POSIX script next to its Windows wrapper
+        write_executable(&first.join("npm"));
+        write_executable(&second.join("npm.cmd"));
+        let path = std::env::join_paths([&first, &second]).unwrap();
+
+        // Without extensions (Unix), names are taken as they are
+        assert_eq!(find_command_in("npm", &path, &[]), Some(first.join("npm")));
+        assert_eq!(
+            find_command_in("npm.cmd", &path, &[]),
+            Some(second.join("npm.cmd"))
+        );
+
+        // With PATHEXT, a bare name only matches with an extension
+        let exts = [".com", ".exe", ".bat", ".cmd"].map(String::from);
+        assert_eq!(
+            find_command_in("npm", &path, &exts),
+            Some(second.join("npm.cmd"))
+        );
+        assert_eq!(
+            find_command_in("npm.cmd", &path, &exts),
+            Some(second.join("npm.cmd"))
+        );
+        assert_eq!(find_command_in("missing", &path, &exts), None);
+    }
+
+    /// Create an empty file that can be run.
+    fn write_executable(path: &std::path::Path) {
+        std::fs::write(path, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_only_executable_files_are_commands() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_executable(&dir.path().join("ping"));
+        std::fs::write(dir.path().join("README"), "").unwrap();
+        std::fs::create_dir(dir.path().join("docker")).unwrap();
+        let path = dir.path().as_os_str();
+
+        assert!(find_command_in("ping", path, &[]).is_some());
+        assert_eq!(find_command_in("README", path, &[]), None);
+        assert_eq!(find_command_in("docker", path, &[]), None);
+
+        let index = CommandIndex::scan(path, &[]);
+        assert!(index.contains("ping"));
+        assert!(!index.contains("README"));
+        assert!(!index.contains("docker"));
+    }
+
+    #[test]
+    fn test_command_index_scans_path_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (first, second) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::create_dir(&first).unwrap();
+        std::fs::create_dir(&second).unwrap();
+        write_executable(&first.join("ping"));
+        write_executable(&second.join("npm.CMD"));
+        write_executable(&second.join("notes.txt"));
+        let path = std::env::join_paths([&first, &second, &dir.path().join("missing")]).unwrap();
+
+        let index = CommandIndex::scan(&path, &[]);
+        assert!(index.contains("ping"));
+        assert!(index.contains("npm.CMD"));
+        assert!(!index.contains("npm"));
+
+        // With PATHEXT, commands are known with and without the extension, in any case
+        let exts = [".com", ".exe", ".bat", ".cmd"].map(String::from);
+        let index = CommandIndex::scan(&path, &exts);
+        assert!(index.contains("npm"));
+        assert!(index.contains("NPM.cmd"));
+        assert!(!index.contains("ping"));
+        assert!(!index.contains("notes"));
+        assert!(!index.contains("notes.txt"));
+    }
+
     #[test]
     fn test_should_use_colorization_for_command_supported() {
         // Test supported commands
@@ -355,6 +1087,14 @@ mod tests {
         assert!(should_use_colorization_for_command_supported("df"));
         // Journalctl support added
         assert!(should_use_colorization_for_command_supported("journalctl"));
+        assert!(should_use_colorization_for_command_supported("dmesg"));
+        assert!(should_use_colorization_for_command_supported("apt-get"));
+        assert!(should_use_colorization_for_command_supported("paru"));
+        assert!(should_use_colorization_for_command_supported("brew"));
+        assert!(should_use_colorization_for_command_supported("git"));
+        assert!(should_use_colorization_for_command_supported("cargo"));
+        assert!(should_use_colorization_for_command_supported("pnpm"));
+        assert!(should_use_colorization_for_command_supported("terraform"));
 
         // Test unsupported commands
         assert!(!should_use_colorization_for_command_supported(
@@ -363,6 +1103,21 @@ mod tests {
         assert!(!should_use_colorization_for_command_supported(""));
     }
 
+    #[test]
+    fn test_pipeline_stages() {
+        assert_eq!(pipeline_stages("ping -c 1 host"), ["ping -c 1 host"]);
+        assert_eq!(
+            pipeline_stages("cat /proc/net/tcp | ss -t |& grep ESTAB"),
+            ["cat /proc/net/tcp", "ss -t", "grep ESTAB"]
+        );
+        // Quoted, escaped and `||` bars do not split
+        assert_eq!(
+            pipeline_stages(r#"grep 'a|b' x || echo "c|d" \| e"#),
+            [r#"grep 'a|b' x || echo "c|d" \| e"#]
+        );
+        assert_eq!(pipeline_stages("dmesg |"), ["dmesg", ""]);
+    }
+
     #[test]
     fn test_pseudo_command_excluded() {
         // Command alone should be excluded
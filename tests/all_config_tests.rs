@@ -68,6 +68,20 @@ fn test_conf_dig() {
     assert!(regex.is_match("8.8.8.8"));
 }
 
+#[test]
+fn test_conf_dmesg() {
+    // Kernel ring buffer timestamps, in the formats of -T, -e and -d too
+    let pattern = r"^(?:<\d+>)?(?:[a-z]+\d?\s*:\s*[a-z]+\s*:\s*)?(\[[^\]]*\d[^\]]*\]|\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}[,.]\d+[+-]\d{2}:?\d{2})";
+    let regex = CompiledRegex::new(pattern).unwrap();
+    assert!(regex.is_match("[    1.834567] usb 1-1: new device"));
+    assert!(regex.is_match("[Mon Jan 15 10:23:45 2024] usb 1-1: USB disconnect"));
+    assert!(regex.is_match("[  +0.000123] wlp2s0: associated"));
+    assert!(regex.is_match("<3>[   10.123456] mmcblk0: error -110"));
+    assert!(regex.is_match("kern  :warn  : [   11.234567] thermal"));
+    assert!(regex.is_match("2024-01-15T10:23:45,123456+01:00 r8169"));
+    assert!(!regex.is_match("usb 1-1: new device"));
+}
+
 #[test]
 fn test_conf_dnf() {
     // DNF package manager
@@ -28,6 +28,14 @@ fn timetrace_env_var_handling() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("test line\ntest", rules);
@@ -48,31 +56,114 @@ fn replace_with_backrefs_modifies_line() {
         count: GrcatConfigEntryCount::More,
         replace: "\\1=\\2".to_string(), // Replace with = separator
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("server:8080 test", rules);
-    // Replace should transform text and break outer loop (line 273)
-    // The replace logic is executed (lines 251-273)
-    let stripped = strip_ansi(&result);
-    // Just verify the output contains something (replace logic executed)
-    assert!(!stripped.is_empty());
+    // The replacement text takes the rule's first style
+    assert_eq!(
+        result,
+        format!("{} test\n", Style::new().red().apply_to("server=8080"))
+    );
 }
 
-/// Lines 248-274: Replace with multiple capture groups and break outer loop
+/// Replace rewrites every match of a `count=more` rule, never the text it inserted
 #[test]
-fn replace_breaks_outer_loop_and_restarts() {
+fn replace_rewrites_each_match_once() {
     let rules = vec![GrcatConfigEntry {
         regex: CompiledRegex::new(r"(\d+)\.(\d+)").unwrap(),
         colors: vec![Style::new().cyan()],
         count: GrcatConfigEntryCount::More,
         replace: "\\1_\\2".to_string(), // Replace dot with underscore
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("version 1.2.3 test", rules);
-    let stripped = strip_ansi(&result);
-    // Just verify replace logic runs (lines 251-273) and breaks outer loop
-    assert!(!stripped.is_empty());
+    assert_eq!(strip_ansi(&result), "version 1_2.3 test\n");
+
+    let result = run_colorize(
+        "1.2 and 3.4 or 5.6",
+        rules_with_count(GrcatConfigEntryCount::Once),
+    );
+    assert_eq!(strip_ansi(&result), "1_2 and 3.4 or 5.6\n");
+    let result = run_colorize(
+        "1.2 and 3.4 or 5.6",
+        rules_with_count(GrcatConfigEntryCount::More),
+    );
+    assert_eq!(strip_ansi(&result), "1_2 and 3_4 or 5_6\n");
+}
+
+fn rules_with_count(count: GrcatConfigEntryCount) -> Vec<GrcatConfigEntry> {
+    vec![GrcatConfigEntry {
+        regex: CompiledRegex::new(r"(\d+)\.(\d+)").unwrap(),
+        colors: vec![],
+        count,
+        replace: "\\1_\\2".to_string(),
+        skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
+    }]
+}
+
+/// Styles given before a replacement follow their text; later rules see the new line
+#[test]
+fn replace_keeps_earlier_styles_in_place() {
+    let rules = vec![
+        GrcatConfigEntry::new(
+            CompiledRegex::new(r"ok$").unwrap(),
+            vec![Style::new().green()],
+        ),
+        GrcatConfigEntry {
+            regex: CompiledRegex::new(r"(\d{4})-(\d\d)-(\d\d)").unwrap(),
+            colors: vec![],
+            count: GrcatConfigEntryCount::More,
+            replace: "\\3/\\2".to_string(),
+            skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
+        },
+        GrcatConfigEntry::new(
+            CompiledRegex::new(r"\d\d/").unwrap(),
+            vec![Style::new().blue()],
+        ),
+    ];
+
+    let result = run_colorize("2024-05-06 ok", rules);
+    assert_eq!(
+        result,
+        format!(
+            "{}05 {}\n",
+            Style::new().blue().apply_to("06/"),
+            Style::new().green().apply_to("ok")
+        )
+    );
 }
 
 /// Lines 303-316: Zero-width match offset advancement
@@ -86,6 +177,14 @@ fn zero_width_lookahead_prevents_infinite_loop() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // This should complete without infinite loop (offset+=1 on zero-width)
@@ -104,6 +203,14 @@ fn word_boundary_zero_width_advances_correctly() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("one two three", rules);
@@ -121,6 +228,14 @@ fn style_range_bounds_check_prevents_panic() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Short line to test bounds checking
@@ -137,6 +252,14 @@ fn run_length_encoding_merges_consecutive_same_style() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Multiple digit sequences should each be styled as one segment
@@ -154,6 +277,14 @@ fn final_segment_output_for_partial_styling() {
         count: GrcatConfigEntryCount::Once,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Only "hello" is styled, " world" should still be output
@@ -171,6 +302,14 @@ fn cache_optimization_skips_overlapping_regions() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Multiple matches should use cache optimization to skip redundant checks
@@ -190,6 +329,14 @@ fn capture_group_index_out_of_colors_bounds() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("time 12:34:56 test", rules);
@@ -206,6 +353,14 @@ fn last_end_tracking_updates_correctly() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Multiple word matches should update last_end progressively
@@ -223,6 +378,14 @@ fn count_once_matches_only_first_occurrence() {
         count: GrcatConfigEntryCount::Once,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("test test test", rules);
@@ -240,6 +403,14 @@ fn count_stop_prevents_subsequent_rules() {
             count: GrcatConfigEntryCount::Stop,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
         GrcatConfigEntry {
             regex: CompiledRegex::new(r"here").unwrap(),
@@ -247,6 +418,14 @@ fn count_stop_prevents_subsequent_rules() {
             count: GrcatConfigEntryCount::More,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
     ];
 
@@ -264,6 +443,14 @@ fn no_match_breaks_while_loop() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // No match should cause break and output unchanged line
@@ -280,6 +467,14 @@ fn empty_style_ranges_outputs_line_unchanged() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // No match -> empty style_ranges -> fast path
@@ -296,6 +491,14 @@ fn style_application_respects_line_length_bounds() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("x", rules); // Very short line
@@ -312,6 +515,14 @@ fn style_boundary_at_position_zero_handled() {
             count: GrcatConfigEntryCount::Once,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
         GrcatConfigEntry {
             regex: CompiledRegex::new(r"\d+$").unwrap(),
@@ -319,6 +530,14 @@ fn style_boundary_at_position_zero_handled() {
             count: GrcatConfigEntryCount::Once,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
     ];
 
@@ -336,6 +555,14 @@ fn multiple_style_boundaries_tracked_correctly() {
             count: GrcatConfigEntryCount::More,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
         GrcatConfigEntry {
             regex: CompiledRegex::new(r"b").unwrap(),
@@ -343,6 +570,14 @@ fn multiple_style_boundaries_tracked_correctly() {
             count: GrcatConfigEntryCount::More,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
     ];
 
@@ -353,14 +588,22 @@ fn multiple_style_boundaries_tracked_correctly() {
 
 /// Lines 196, 200, 203, 206: Skip and stop_line_processing checks
 #[test]
-fn skip_rule_is_ignored_in_processing() {
+fn skip_rule_drops_matched_lines() {
     let rules = vec![
         GrcatConfigEntry {
             regex: CompiledRegex::new(r"skip").unwrap(),
             colors: vec![Style::new().red()],
             count: GrcatConfigEntryCount::More,
             replace: String::new(),
-            skip: true, // This rule should be skipped
+            skip: true, // Lines matching this rule are dropped
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
         GrcatConfigEntry {
             regex: CompiledRegex::new(r"process").unwrap(),
@@ -368,12 +611,20 @@ fn skip_rule_is_ignored_in_processing() {
             count: GrcatConfigEntryCount::More,
             replace: String::new(),
             skip: false,
+            command: String::new(),
+            priority: 0,
+            thresholds: Vec::new(),
+            gradient: None,
+            sizes: None,
+            fields: Vec::new(),
+            delimiter: String::new(),
+            engine: rgrc::grc::RegexEngine::Auto,
         },
     ];
 
-    let result = run_colorize("skip process", rules);
-    // "skip" rule is ignored, only "process" rule runs
-    assert!(strip_ansi(&result).contains("skip process"));
+    let result = run_colorize("skip process\nprocess only", rules);
+    // The "skip" line is dropped before the "process" rule runs on it
+    assert_eq!(strip_ansi(&result), "process only\n");
 }
 
 /// Lines 178, 181, 184, 186-187: Offset advancement logic
@@ -385,6 +636,14 @@ fn offset_advances_past_match_end() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Each digit should match separately (offset advances by 1)
@@ -401,6 +660,14 @@ fn rule_matched_once_flag_stops_matching() {
         count: GrcatConfigEntryCount::Once,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("abc", rules);
@@ -421,6 +688,14 @@ fn multiple_capture_groups_iterate_correctly() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("host:8080", rules);
@@ -447,6 +722,14 @@ fn empty_line_fast_path_writes_newline_only() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Input with multiple empty lines between content
@@ -479,6 +762,14 @@ fn cache_skip_when_offset_behind_last_end() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     let result = run_colorize("test word", rules);
@@ -501,6 +792,14 @@ fn timetrace_feature_increments_counter_and_reports() {
         count: GrcatConfigEntryCount::More,
         replace: String::new(),
         skip: false,
+        command: String::new(),
+        priority: 0,
+        thresholds: Vec::new(),
+        gradient: None,
+        sizes: None,
+        fields: Vec::new(),
+        delimiter: String::new(),
+        engine: rgrc::grc::RegexEngine::Auto,
     }];
 
     // Process multiple lines to increment lines_processed (line 159)
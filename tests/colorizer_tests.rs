@@ -138,6 +138,79 @@ mod multiline_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_crlf_line_endings_are_kept() -> Result<(), Box<dyn std::error::Error>> {
+        // The \r is not part of the line the rules see, so $ still matches
+        let rules = vec![rule(r"\d+$", Style::new().red())?];
+        let output = colorize_test("took 12\r\n\r\nmixed 3\nlast 4\r", &rules)?;
+        assert_eq!(
+            output,
+            "took \x1b[31m12\x1b[0m\r\n\r\nmixed \x1b[31m3\x1b[0m\nlast \x1b[31m4\x1b[0m\r\n"
+        );
+
+        // Also without rules
+        assert_eq!(colorize_test("a\r\nb\n", &[])?, "a\r\nb\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_is_flushed_once_per_read() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::{Read, Write};
+
+        /// Hands out one chunk per read, as a pipe does with what was written to it
+        struct Chunks(Vec<&'static [u8]>);
+        impl Read for Chunks {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.is_empty() {
+                    return Ok(0);
+                }
+                let chunk = self.0.remove(0);
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            }
+        }
+
+        /// Records what was flushed, one entry per flush
+        #[derive(Default)]
+        struct Flushes {
+            pending: Vec<u8>,
+            flushed: Vec<String>,
+        }
+        impl Write for Flushes {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.pending.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                let pending = std::mem::take(&mut self.pending);
+                self.flushed.push(String::from_utf8(pending).unwrap());
+                Ok(())
+            }
+        }
+
+        let rules = [rule("b", Style::new().red())?];
+        for rules in [&rules[..], &[]] {
+            // The second read ends in the middle of a line, which waits for the third
+            let mut input = Chunks(vec![b"a\nb\nc\n", b"d\ne", b"\n"]);
+            let mut output = Flushes::default();
+            colorize_regex(&mut input, &mut output, rules)?;
+            let b = if rules.is_empty() {
+                "b"
+            } else {
+                "\x1b[31mb\x1b[0m"
+            };
+            assert_eq!(
+                output.flushed,
+                [
+                    format!("a\n{}\nc\n", b),
+                    "d\n".to_string(),
+                    "e\n".to_string()
+                ]
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_multiple_lines_with_matches() -> Result<(), Box<dyn std::error::Error>> {
         let rules = vec![rule("foo", Style::new().green())?];
@@ -624,28 +697,43 @@ mod edge_case_tests {
 
     #[test]
     fn test_skip_rule_functionality() -> Result<(), Box<dyn std::error::Error>> {
-        // Create rules: one normal rule and one skipped rule
+        // Create rules: one normal rule and one rule dropping the lines it matches
         let normal_rule = rule("ERROR", Style::new().red())?;
-        let mut skip_rule = rule("WARNING", Style::new().yellow())?;
-        skip_rule.skip = true; // Mark this rule as skipped
+        let mut skip_rule = rule("^-+$", Style::new().yellow())?;
+        skip_rule.skip = true;
 
         let rules = vec![normal_rule, skip_rule];
 
-        let input = "This is an ERROR message\nThis is a WARNING message\n";
+        let input = "This is an ERROR message\n-----\nThis is a WARNING message\n";
         let output = colorize_test(input, &rules)?;
 
-        println!("Output: {:?}", output);
+        // The separator line is gone, the others are kept
+        assert_eq!(
+            output,
+            format!(
+                "This is an {} message\nThis is a WARNING message\n",
+                Style::new().red().apply_to("ERROR")
+            )
+        );
 
-        // Both ERROR and WARNING text should be present
-        assert!(output.contains("ERROR"));
-        assert!(output.contains("WARNING"));
+        Ok(())
+    }
 
-        // Check that ERROR has color codes (red) but WARNING does not have yellow color codes
-        // We can't easily check exact ANSI codes, so we'll check that the output contains ANSI codes for ERROR
-        // and that WARNING appears without the yellow color code pattern
-        assert!(output.contains("\x1b[")); // Should have some ANSI codes
-        assert!(output.contains("ERROR")); // ERROR should be present
+    #[test]
+    fn test_skip_rule_after_count_rules() -> Result<(), Box<dyn std::error::Error>> {
+        let mut stop = rule("^KEEP", Style::new().green())?;
+        stop.count = rgrc::grc::GrcatConfigEntryCount::Stop;
+        let mut once = rule("noise", Style::new().red())?;
+        once.count = rgrc::grc::GrcatConfigEntryCount::Once;
+        let mut skip_rule = rule("noise", Style::new())?;
+        skip_rule.skip = true;
 
+        // A count=stop rule that matched first keeps the line; count=once does not
+        let output = colorize_test("KEEP noise\nnoise noise\nplain\n", &[stop, once, skip_rule])?;
+        assert_eq!(
+            output,
+            format!("{} noise\nplain\n", Style::new().green().apply_to("KEEP"))
+        );
         Ok(())
     }
 }
@@ -692,19 +780,178 @@ mod advanced_features_tests {
         Ok(())
     }
 
-    /// Lines 248-274: Replace functionality breaks outer loop
-    /// Tests that when a rule performs text replacement, it breaks the outer rule
-    /// processing loop and follow-up rules are not applied.
+    /// Count::Block styles whole lines until a Count::Unblock rule matches
+    /// Tests that the region spans lines, overrides other rules inside it, and
+    /// that the unblock line keeps its own styles.
+    #[test]
+    fn test_count_block_styles_lines_until_unblock() -> Result<(), Box<dyn std::error::Error>> {
+        let mut begin =
+            GrcatConfigEntry::new(CompiledRegex::new(r"^BEGIN")?, vec![Style::new().yellow()]);
+        begin.count = GrcatConfigEntryCount::Block;
+        let mut end =
+            GrcatConfigEntry::new(CompiledRegex::new(r"^END")?, vec![Style::new().green()]);
+        end.count = GrcatConfigEntryCount::Unblock;
+        let err = GrcatConfigEntry::new(CompiledRegex::new(r"err")?, vec![Style::new().red()]);
+
+        let output = colorize_test(
+            "a err\nBEGIN x\nin err\nEND y\nout err\n",
+            &[begin, end, err],
+        )?;
+        let lines: Vec<&str> = output.lines().collect();
+        let (yellow, green, red) = (
+            Style::new().yellow(),
+            Style::new().green(),
+            Style::new().red(),
+        );
+        assert_eq!(lines[0], format!("a {}", red.apply_to("err")));
+        assert_eq!(lines[1], yellow.apply_to("BEGIN x").to_string());
+        assert_eq!(lines[2], yellow.apply_to("in err").to_string());
+        assert_eq!(lines[3], format!("{} y", green.apply_to("END")));
+        assert_eq!(lines[4], format!("out {}", red.apply_to("err")));
+        Ok(())
+    }
+
+    /// thresholds= styles the captured number by the highest threshold it reaches
+    #[test]
+    fn test_thresholds_style_captured_number_by_severity() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut usage = GrcatConfigEntry::new(
+            CompiledRegex::new(r"(\d+)% (/\S*)")?,
+            vec![Style::new(), Style::new().green(), Style::new().blue()],
+        );
+        usage.thresholds = rgrc::grc::thresholds_from_str("90:red,70:yellow")?;
+
+        let output = colorize_test(
+            "12% /boot
+75% /
+90% /home
+n/a% /tmp
+",
+            &[usage],
+        )?;
+        let lines: Vec<&str> = output.lines().collect();
+        let path = |text| Style::new().blue().apply_to(text).to_string();
+        assert_eq!(
+            lines[0],
+            format!("{}% {}", Style::new().green().apply_to("12"), path("/boot"))
+        );
+        assert_eq!(
+            lines[1],
+            format!("{}% {}", Style::new().yellow().apply_to("75"), path("/"))
+        );
+        assert_eq!(
+            lines[2],
+            format!("{}% {}", Style::new().red().apply_to("90"), path("/home"))
+        );
+        assert_eq!(lines[3], "n/a% /tmp");
+        Ok(())
+    }
+
+    /// gradient= places the captured number on a green to red ramp between its bounds
+    #[test]
+    fn test_gradient_styles_number_between_bounds() -> Result<(), Box<dyn std::error::Error>> {
+        let mut latency = GrcatConfigEntry::new(
+            CompiledRegex::new(r"time=(\d+(\.\d+)?) ms")?,
+            vec![Style::new(), Style::new().cyan()],
+        );
+        latency.gradient = Some(rgrc::grc::gradient_from_str("10:110")?);
+        let mut loss = GrcatConfigEntry::new(CompiledRegex::new(r"\d+% loss")?, vec![]);
+        loss.gradient = Some(rgrc::grc::gradient_from_str("0:100:ansi")?);
+
+        let output = colorize_test(
+            "time=3 ms\ntime=60.0 ms\ntime=900 ms\n55% loss\n",
+            &[latency, loss],
+        )?;
+        let lines: Vec<&str> = output.lines().collect();
+        let ms = |style: Style, value| format!("time={} ms", style.apply_to(value));
+        assert_eq!(lines[0], ms(Style::new().color256(46), "3"));
+        assert_eq!(lines[1], ms(Style::new().color256(226), "60.0"));
+        assert_eq!(lines[2], ms(Style::new().color256(196), "900"));
+        assert_eq!(
+            lines[3],
+            Style::new().yellow().apply_to("55% loss").to_string()
+        );
+        Ok(())
+    }
+
+    /// sizes= places the captured size on the ramp by the logarithm of its bytes
+    #[test]
+    fn test_sizes_style_sizes_on_log_scale() -> Result<(), Box<dyn std::error::Error>> {
+        let mut du = GrcatConfigEntry::new(
+            CompiledRegex::new(r"^(\d+(\.\d+)?[KMGT]?)\s")?,
+            vec![Style::new(), Style::new().cyan()],
+        );
+        du.sizes = Some(rgrc::grc::sizes_from_str("1K:1T")?);
+
+        let output = colorize_test("512\tsrc\n4.0K\tdocs\n1.0G\ttarget\n2T\tbackup\n", &[du])?;
+        let lines: Vec<&str> = output.lines().collect();
+        let size = |style: Style, value, path| format!("{}\t{}", style.apply_to(value), path);
+        // 1K is the first step and 1T the last, with one step per 8 times the size
+        assert_eq!(lines[0], size(Style::new().color256(46), "512", "src"));
+        assert_eq!(lines[1], size(Style::new().color256(82), "4.0K", "docs"));
+        assert_eq!(lines[2], size(Style::new().color256(214), "1.0G", "target"));
+        assert_eq!(lines[3], size(Style::new().color256(196), "2T", "backup"));
+        Ok(())
+    }
+
+    /// fields= styles columns by number on the lines the regexp selects
+    #[test]
+    fn test_fields_style_columns_by_number() -> Result<(), Box<dyn std::error::Error>> {
+        let mut table =
+            GrcatConfigEntry::new(CompiledRegex::new(r"^\s*\d")?, vec![Style::new().red()]);
+        table.fields = rgrc::grc::fields_from_str("1:cyan,3:bold,-1:green")?;
+        let mut csv = GrcatConfigEntry::new(CompiledRegex::new(r"^csv")?, vec![]);
+        csv.fields = rgrc::grc::fields_from_str("3:yellow")?;
+        csv.delimiter = ",".to_string();
+
+        let output = colorize_test(
+            "PID TTY CMD\n  42 pts/0   bash -l\n7 ?\ncsv,,b c\n",
+            &[table, csv],
+        )?;
+        let lines: Vec<&str> = output.lines().collect();
+        let (cyan, bold, green) = (
+            Style::new().cyan(),
+            Style::new().bold(),
+            Style::new().green(),
+        );
+        // The header is not selected by the regexp
+        assert_eq!(lines[0], "PID TTY CMD");
+        assert_eq!(
+            lines[1],
+            format!(
+                "  {} pts/0   {} {}",
+                cyan.apply_to("42"),
+                bold.apply_to("bash"),
+                green.apply_to("-l")
+            )
+        );
+        // Missing fields are left alone; the last field may also be the first
+        assert_eq!(
+            lines[2],
+            format!("{} {}", cyan.apply_to("7"), green.apply_to("?"))
+        );
+        // An empty field between delimiters still counts
+        assert_eq!(
+            lines[3],
+            format!("csv,,{}", Style::new().yellow().apply_to("b c"))
+        );
+        Ok(())
+    }
+
+    /// Replace functionality feeds the rewritten line to follow-up rules
+    /// Tests that text inserted by a replacement can be styled by later rules.
     #[test]
-    fn test_replace_prevents_followup_rules() -> Result<(), Box<dyn std::error::Error>> {
+    fn test_replace_followup_rules_see_new_text() -> Result<(), Box<dyn std::error::Error>> {
         let mut r1 = GrcatConfigEntry::new(CompiledRegex::new(r"Hello (\w+)")?, vec![Style::new()]);
         r1.replace = "\\1-XYZ".to_string();
 
         let r2 = GrcatConfigEntry::new(CompiledRegex::new(r"XYZ")?, vec![Style::new().red()]);
 
         let output = colorize_test("Hello world\n", &[r1, r2])?;
-        // Replacement should be applied
-        assert!(output.contains("world") || output.contains("XYZ"));
+        assert_eq!(
+            output,
+            format!("world-{}\n", Style::new().red().apply_to("XYZ"))
+        );
         Ok(())
     }
 
@@ -718,7 +965,12 @@ mod advanced_features_tests {
 
         let output = colorize_test("foo-123 bar\n", &[r])?;
         // Backreferences should swap the parts
-        assert!(output.contains("123") || output.contains("foo"));
+        assert_eq!(output, "123-foo bar\n");
+
+        // Captured text is inserted as is, even when it looks like a backreference
+        let mut r = GrcatConfigEntry::new(CompiledRegex::new(r"<(.*)>")?, vec![]);
+        r.replace = "[\\1]".to_string();
+        assert_eq!(colorize_test("<a\\2b>\n", &[r])?, "[a\\2b]\n");
         Ok(())
     }
 
@@ -1170,3 +1422,681 @@ mod colorize_regex_tests {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod output_options_tests {
+    use rgrc::Style;
+    use rgrc::colorizer::{
+        ColorizeOptions, DelimitedColumns, RuleProfile, RuleStats, colorize_regex_with_options,
+    };
+    use rgrc::json::JsonHighlighter;
+    use rgrc::{InputFormat, OutputFormat};
+
+    use super::*;
+
+    #[test]
+    fn test_log_sink_receives_plain_text() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![rule(r"ERROR", Style::new().red())?];
+        let mut output = Vec::new();
+        let mut log = Vec::new();
+        let mut options = ColorizeOptions {
+            log: Some(&mut log),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "ERROR: disk\n\n\x1b[1mok\x1b[0m\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+
+        assert!(String::from_utf8(output)?.contains("\x1b[31mERROR\x1b[0m"));
+        assert_eq!(String::from_utf8(log)?, "ERROR: disk\n\nok\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_timeout_leaves_lines_uncoloured() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mask = GrcatConfigEntry::new(CompiledRegex::new(r"pass=\S+")?, vec![]);
+        mask.replace = "pass=***".to_string();
+        let rules = vec![mask, rule(r"ok", Style::new().green())?];
+        let input = "pass=x ok\npass=y ok\nok\n";
+
+        // No time at all: each rule runs out the budget once, then is disabled
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            line_timeout: Some(std::time::Duration::ZERO),
+            disable_slow_rules: true,
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+        assert_eq!(String::from_utf8(output)?, input);
+        assert_eq!(options.disabled_rules, [0, 1]);
+
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            line_timeout: Some(std::time::Duration::from_secs(60)),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "pass=x ok\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            format!("pass=*** {}\n", Style::new().green().apply_to("ok"))
+        );
+        assert!(options.disabled_rules.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_timeout_bounds_a_single_match() -> Result<(), Box<dyn std::error::Error>> {
+        use rgrc::grc::{RegexEngine, set_match_timeout};
+        use std::time::{Duration, Instant};
+
+        let timeout = Duration::from_millis(50);
+        set_match_timeout(timeout);
+
+        // Each pattern fails on every position of the line; tried naively, the
+        // nested repeats (or the candidates a lookaround rejects) take
+        // exponential or quadratic time
+        let line = format!("{}b\n", "a".repeat(50_000));
+        for pattern in [r"(a+)+$", r"(a+)+(?=c)", r"(a+)+(?!b)$"] {
+            let rules = vec![GrcatConfigEntry::new(
+                CompiledRegex::with_engine(pattern, RegexEngine::Enhanced)?,
+                vec![Style::new().red()],
+            )];
+            let mut output = Vec::new();
+            let mut options = ColorizeOptions {
+                line_timeout: Some(timeout),
+                ..Default::default()
+            };
+            let started = Instant::now();
+            colorize_regex_with_options(&mut line.as_bytes(), &mut output, &rules, &mut options)?;
+            let elapsed = started.elapsed();
+            assert!(
+                elapsed < Duration::from_secs(5),
+                "{}: {:?}",
+                pattern,
+                elapsed
+            );
+            assert_eq!(String::from_utf8(output)?, line);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_on_match_ends_the_input() -> Result<(), Box<dyn std::error::Error>> {
+        use rgrc::colorizer::{RuleSelector, RuleStats};
+        let rules = vec![
+            rule(r"ok", Style::new().green())?,
+            rule(r"fail", Style::new().red())?,
+        ];
+
+        let mut output = Vec::new();
+        let mut stats = RuleStats::new(rules.len());
+        let mut options = ColorizeOptions {
+            stats: Some(&mut stats),
+            stop_on_match: Some(RuleSelector::Rule(1)),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "ok\nfail\nnever read\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.matched_lines(RuleSelector::Rule(1)), 1);
+        assert_eq!(stats.matched_lines(RuleSelector::Any), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_beep_rings_the_bell_once_a_second() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![rule(r"timeout", Style::new().red().beep())?];
+
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions::default();
+        colorize_regex_with_options(
+            &mut "ok\ntimeout 1\ntimeout 2\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        // The burst rings once, and the bell is not part of the line's colours
+        assert_eq!(
+            String::from_utf8(output)?,
+            "ok\n\x07\x1b[31mtimeout\x1b[0m 1\n\x1b[31mtimeout\x1b[0m 2\n"
+        );
+
+        // Once the interval is over it rings again
+        options.last_bell = options
+            .last_bell
+            .map(|last| last - rgrc::colorizer::BELL_INTERVAL);
+        let mut output = Vec::new();
+        colorize_regex_with_options(
+            &mut "timeout 3\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        assert!(output.starts_with(b"\x07"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_lines_pass_through_raw() -> Result<(), Box<dyn std::error::Error>> {
+        let mut begin =
+            GrcatConfigEntry::new(CompiledRegex::new(r"^BEGIN")?, vec![Style::new().yellow()]);
+        begin.count = rgrc::grc::GrcatConfigEntryCount::Block;
+        let mut end = GrcatConfigEntry::new(CompiledRegex::new(r"^END")?, vec![]);
+        end.count = rgrc::grc::GrcatConfigEntryCount::Unblock;
+        let rules = vec![begin, end, rule(r"err", Style::new().red())?];
+
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            max_line_length: Some(8),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "an err\nBEGIN\nEND of a long line\nin\nEND\nlong err line\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+
+        // The long END line neither takes the block style nor closes the region
+        let yellow = Style::new().yellow();
+        assert_eq!(
+            String::from_utf8(output)?,
+            format!(
+                "an {}\n{}\nEND of a long line\n{}\nEND\nlong err line\n",
+                Style::new().red().apply_to("err"),
+                yellow.apply_to("BEGIN"),
+                yellow.apply_to("in")
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_matching_keeps_matched_lines_and_blocks() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut begin =
+            GrcatConfigEntry::new(CompiledRegex::new(r"^BEGIN")?, vec![Style::new().yellow()]);
+        begin.count = rgrc::grc::GrcatConfigEntryCount::Block;
+        let mut end = GrcatConfigEntry::new(CompiledRegex::new(r"^END")?, vec![]);
+        end.count = rgrc::grc::GrcatConfigEntryCount::Unblock;
+        let rules = vec![begin, end, rule(r"err", Style::new().red())?];
+        let input = "ok\nan err\n\nBEGIN\nin\n\nEND\nafter\n";
+
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            only_matching: true,
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+        // Lines inside the block region are kept, matched or not
+        let yellow = Style::new().yellow();
+        assert_eq!(
+            String::from_utf8(output)?,
+            format!(
+                "an {}\n{}\n{}\n\nEND\n",
+                Style::new().red().apply_to("err"),
+                yellow.apply_to("BEGIN"),
+                yellow.apply_to("in")
+            )
+        );
+
+        // Uncoloured, the same lines are kept as they were read
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            only_matching: true,
+            count_only: true,
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+        assert_eq!(String::from_utf8(output)?, "an err\nBEGIN\nin\n\nEND\n");
+
+        // Without rules no line matches
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            only_matching: true,
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &[], &mut options)?;
+        assert!(output.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_matching_leaves_out_the_rules_lines() -> Result<(), Box<dyn std::error::Error>> {
+        use rgrc::colorizer::{RuleSelector, RuleStats};
+        let rules = vec![
+            rule(r"^DEBUG", Style::new().blue())?,
+            rule(r"err", Style::new().red())?,
+        ];
+        let input = "DEBUG open\nan err\nDEBUG err\nok\n";
+
+        let mut output = Vec::new();
+        let mut stats = RuleStats::new(rules.len());
+        let mut options = ColorizeOptions {
+            stats: Some(&mut stats),
+            drop_matching: &[RuleSelector::Rule(0)],
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+        // Dropped lines are still counted
+        assert_eq!(
+            String::from_utf8(output)?,
+            format!("an {}\nok\n", Style::new().red().apply_to("err"))
+        );
+        assert_eq!(stats.rule_hits, [2, 2]);
+
+        // Uncoloured, with any rule
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            count_only: true,
+            drop_matching: &[RuleSelector::Any],
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+        assert_eq!(String::from_utf8(output)?, "ok\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsonl_reports_spans() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![
+            GrcatConfigEntry::new(
+                CompiledRegex::new(r"(\d+)%")?,
+                vec![Style::new(), Style::new().bold().red()],
+            ),
+            rule(r"ok", Style::new().green())?,
+        ];
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            format: OutputFormat::Jsonl,
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "use 95% \"x\"\n\nok\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+
+        let output = String::from_utf8(output)?;
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"{"text":"use 95% \"x\"","matches":[{"rule":0,"group":0,"start":4,"end":7,"style":[]},{"rule":0,"group":1,"start":4,"end":6,"style":["bold","red"]}]}"#
+        );
+        assert_eq!(lines[1], r#"{"text":"","matches":[]}"#);
+        assert_eq!(
+            lines[2],
+            r#"{"text":"ok","matches":[{"rule":1,"group":0,"start":0,"end":2,"style":["green"]}]}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsonl_without_rules() -> Result<(), Box<dyn std::error::Error>> {
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            format: OutputFormat::Jsonl,
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut "a\tb\n".as_bytes(), &mut output, &[], &mut options)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "{\"text\":\"a\\tb\",\"matches\":[]}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_markup_tags_styled_spans() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![
+            GrcatConfigEntry::new(
+                CompiledRegex::new(r"(\d+)%")?,
+                vec![Style::new(), Style::new().bold().red()],
+            ),
+            rule(r"ok", Style::new().green())?,
+        ];
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            format: OutputFormat::Markup,
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "[disk] 75% ok\n\nplain [x]\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "[[disk] [bold red]75[/]% [green]ok[/]\n\nplain [[x]\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_delimited_columns_under_rule_matches() -> Result<(), Box<dyn std::error::Error>> {
+        let columns = DelimitedColumns::new(InputFormat::Csv, true).unwrap();
+        let rules = vec![rule(r"\bERR\b", Style::new().bold().red())?];
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            format: OutputFormat::Markup,
+            columns: Some(&columns),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "id,msg,state\n1,\"a, b\",ERR\n2,,ok\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "[cyan]id[/],[green]msg[/],[yellow]state[/]\n\
+             [cyan on_color236]1[/][on_color236],[/][green on_color236]\"a, b\"[/]\
+             [on_color236],[/][bold red]ERR[/]\n\
+             [cyan]2[/],,[yellow]ok[/]\n"
+        );
+
+        // Text input has no columns; TSV splits on tabs
+        assert!(DelimitedColumns::new(InputFormat::Text, true).is_none());
+        let columns = DelimitedColumns::new(InputFormat::Tsv, false).unwrap();
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            format: OutputFormat::Markup,
+            columns: Some(&columns),
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut "a,b\tc\n".as_bytes(), &mut output, &[], &mut options)?;
+        assert_eq!(String::from_utf8(output)?, "[cyan]a,b[/]\t[green]c[/]\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_tokens_under_rule_matches() -> Result<(), Box<dyn std::error::Error>> {
+        let mut highlighter = JsonHighlighter::new();
+        let mut skip = rule(r"^#", Style::new())?;
+        skip.skip = true;
+        let rules = vec![rule(r"\berror\b", Style::new().bold().red())?, skip];
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            format: OutputFormat::Markup,
+            json: Some(&mut highlighter),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "{\"msg\": \"an error\",\n# \"dropped\": [\n\"ok\": 1}\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+        // The dropped line still opens an array, so "ok" is read as a value
+        assert_eq!(
+            String::from_utf8(output)?,
+            "{[bold blue]\"msg\"[/]: [green]\"an [/][bold red]error[/][green]\"[/],\n\
+             [green]\"ok\"[/]: [cyan]1[/]}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_count_lines_per_rule() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![
+            rule(r"\d+", Style::new().red())?,
+            rule(r"ERROR", Style::new().bold())?,
+            rule(r"never", Style::new().green())?,
+        ];
+        let mut stats = RuleStats::new(rules.len());
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            stats: Some(&mut stats),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "1 2 3\nERROR 4\nplain\n\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+
+        // A rule counts once per line even with several matches on it
+        assert_eq!(stats.lines, 4);
+        assert_eq!(stats.unmatched_lines, 2);
+        assert_eq!(stats.rule_hits, vec![2, 1, 0]);
+
+        let mut report = Vec::new();
+        stats.write_report(&mut report, &rules)?;
+        let report = String::from_utf8(report)?;
+        assert!(report.contains("4 line(s), 2 matched no rule"));
+        assert!(report.contains("never  (never matched)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_sink_receives_numbered_json() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![rule(r"ERROR", Style::new().bold().red())?];
+        let mut events = Vec::new();
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            events: Some(&mut events),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "ERROR: x\n\nok\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+
+        // Events go to their own sink; the colored output is unchanged
+        assert!(String::from_utf8(output)?.contains("\x1b["));
+        assert_eq!(
+            String::from_utf8(events)?,
+            "{\"line\":1,\"text\":\"ERROR: x\",\"matches\":[{\"rule\":0,\"group\":0,\"start\":0,\"end\":5,\"style\":[\"bold\",\"red\"]}]}\n\
+             {\"line\":2,\"text\":\"\",\"matches\":[]}\n\
+             {\"line\":3,\"text\":\"ok\",\"matches\":[]}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_records_regex_calls() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = vec![
+            // Classes keep the literal prefilter, and word boundaries the DFA
+            // screen (dfa-cache), from skipping any regex calls
+            rule(r"\b[a]", Style::new().red())?,
+            rule(r"[z]{3}\b", Style::new().bold())?,
+        ];
+        let mut profile = RuleProfile::new(rules.len());
+        let mut output = Vec::new();
+        let mut options = ColorizeOptions {
+            profile: Some(&mut profile),
+            ..Default::default()
+        };
+        colorize_regex_with_options(
+            &mut "a a\nb\n".as_bytes(),
+            &mut output,
+            &rules,
+            &mut options,
+        )?;
+
+        // "a a": two hits (the second ends the line); "b": one failing search
+        assert_eq!(profile.calls, vec![3, 2]);
+
+        let mut report = Vec::new();
+        profile.write_report(&mut report, &rules, 1)?;
+        let report = String::from_utf8(report)?;
+        assert!(report.contains("spent in rule regexes"));
+        // Only `limit` rules are listed
+        assert_eq!(report.lines().count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_rules_are_demoted_to_the_literal_prefilter()
+    -> Result<(), Box<dyn std::error::Error>> {
+        use rgrc::prefilter::DEMOTE_AFTER_LINES;
+
+        // The word boundary keeps the regex-set and DFA screens out of the way
+        let rules = vec![
+            rule(r"\bERROR", Style::new().red())?,
+            rule(r"\d+", Style::new().bold())?,
+        ];
+        let mut input = String::new();
+        for n in 0..DEMOTE_AFTER_LINES + 500 {
+            input.push_str(&format!("line {}\n", n));
+        }
+        input.push_str("ERROR 7\nline 8\n");
+
+        let run = |disable_prefilter: bool| -> Result<_, Box<dyn std::error::Error>> {
+            let mut profile = RuleProfile::new(rules.len());
+            let mut output = Vec::new();
+            let mut options = ColorizeOptions {
+                profile: Some(&mut profile),
+                disable_prefilter,
+                ..Default::default()
+            };
+            colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+            Ok((output, profile.calls))
+        };
+        let (screened, screened_calls) = run(false)?;
+        let (unscreened, unscreened_calls) = run(true)?;
+
+        // The demoted rule still colours the line its literal is on
+        assert_eq!(screened, unscreened);
+        assert!(String::from_utf8(screened)?.contains("\x1b[31mERROR\x1b[0m"));
+        // but its regex stopped running on the lines without it, until the
+        // match re-enabled it ("ERROR 7" takes two calls, "line 8" one)
+        assert_eq!(unscreened_calls[0], DEMOTE_AFTER_LINES + 503);
+        assert_eq!(screened_calls[0], DEMOTE_AFTER_LINES + 3);
+        assert_eq!(screened_calls[1], unscreened_calls[1]);
+        Ok(())
+    }
+    #[test]
+    #[cfg(unix)]
+    fn test_hooks_run_only_when_allowed() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("hooks.log");
+        let mut timeout = rule(r"timeout \d+", Style::new().red())?;
+        timeout.command = format!("echo \"$RGRC_MATCH\" >> '{}'", log.display());
+        let rules = vec![timeout];
+        let input = "timeout 1 timeout 2\nok\ntimeout 3\n";
+
+        let mut output = Vec::new();
+        colorize_regex_with_options(
+            &mut input.as_bytes(),
+            &mut output,
+            &rules,
+            &mut ColorizeOptions::default(),
+        )?;
+
+        let mut options = ColorizeOptions {
+            hooks: true,
+            ..Default::default()
+        };
+        colorize_regex_with_options(&mut input.as_bytes(), &mut output, &rules, &mut options)?;
+
+        // Hooks run in the background: wait for both lines to arrive
+        let mut logged = String::new();
+        for _ in 0..100 {
+            logged = std::fs::read_to_string(&log).unwrap_or_default();
+            if logged.lines().count() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        // Once per matching line, with the first match of the line
+        let mut lines: Vec<&str> = logged.lines().collect();
+        lines.sort();
+        assert_eq!(lines, ["timeout 1", "timeout 3"]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod span_tests {
+    use rgrc::Style;
+    use rgrc::colorizer::resolve_spans;
+
+    #[test]
+    fn test_later_ranges_override_earlier() {
+        let red = Style::new().red();
+        let bold = Style::new().bold();
+        let spans = resolve_spans(10, &[(0, 8, &red, 0, 0), (2, 4, &bold, 1, 0)]);
+        assert_eq!(spans, vec![(0..2, &red), (2..4, &bold), (4..8, &red)]);
+    }
+
+    #[test]
+    fn test_adjacent_equal_styles_merge() {
+        let red = Style::new().red();
+        let also_red = Style::new().red();
+        let spans = resolve_spans(6, &[(0, 3, &red, 0, 0), (3, 6, &also_red, 1, 0)]);
+        assert_eq!(spans, vec![(0..6, &red)]);
+    }
+
+    #[test]
+    fn test_unstyled_ranges_clear_and_clip() {
+        let red = Style::new().red();
+        let plain = Style::new();
+        let spans = resolve_spans(5, &[(0, 9, &red, 0, 0), (1, 2, &plain, 1, 0)]);
+        assert_eq!(spans, vec![(0..1, &red), (2..5, &red)]);
+
+        // Empty and out-of-range matches produce no spans
+        assert!(resolve_spans(5, &[(3, 3, &red, 0, 0), (7, 9, &red, 0, 0)]).is_empty());
+    }
+
+    #[test]
+    fn test_many_overlapping_ranges_match_the_byte_by_byte_result() {
+        let styles = [
+            Style::new(),
+            Style::new().red(),
+            Style::new().green(),
+            Style::new().bold(),
+        ];
+        // Pseudo-random ranges, some nested, some disjoint, some past the end
+        let mut seed = 7u64;
+        let mut next = |bound: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as usize % bound
+        };
+        let ranges: Vec<_> = (0..500)
+            .map(|rule| {
+                let start = next(210);
+                (start, start + next(40), &styles[next(4)], rule, 0)
+            })
+            .collect();
+
+        // The latest range covering each byte wins
+        let len = 200;
+        let mut expected: Vec<(std::ops::Range<usize>, &Style)> = Vec::new();
+        for at in 0..len {
+            let Some(&(_, _, style, ..)) = ranges
+                .iter()
+                .rev()
+                .find(|(start, end, ..)| *start <= at && at < *end)
+            else {
+                continue;
+            };
+            if *style == Style::new() {
+                continue;
+            }
+            match expected.last_mut() {
+                Some((range, last)) if range.end == at && **last == *style => range.end += 1,
+                _ => expected.push((at..at + 1, style)),
+            }
+        }
+        assert_eq!(resolve_spans(len, &ranges), expected);
+    }
+}
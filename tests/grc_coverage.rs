@@ -238,6 +238,26 @@ fn test_grcat_reader_count_stop() {
     }
 }
 
+/// Count field parsing - Block and Unblock variants
+/// Tests that grcat's count=block and count=unblock are recognized.
+#[test]
+fn test_grcat_reader_count_block_unblock() {
+    use std::io::BufReader;
+    let config = "regexp=begin
+colours=red
+count=block
+-
+regexp=end
+count=unblock
+";
+    let reader = BufReader::new(config.as_bytes());
+    let entries: Vec<_> = rgrc::grc::GrcatConfigReader::new(reader.lines()).collect();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].count, GrcatConfigEntryCount::Block);
+    assert_eq!(entries[1].count, GrcatConfigEntryCount::Unblock);
+}
+
 /// Lines 373-377: Count field parsing - More variant (default)
 /// Tests that count=more is correctly parsed to GrcatConfigEntryCount::More.
 #[test]
@@ -403,6 +423,31 @@ fn test_style_dark_aliases_dim() {
     assert_eq!(format!("{}", style.apply_to("x")), "\x1b[2;32mx\x1b[0m");
 }
 
+/// Every listed keyword parses, and palette indexes outside 0-255 do not
+#[test]
+fn test_style_keywords_are_all_accepted() {
+    for keyword in rgrc::grc::STYLE_KEYWORDS {
+        assert!(
+            rgrc::grc::style_from_str(keyword).is_ok(),
+            "listed keyword '{}' should parse",
+            keyword
+        );
+    }
+    assert!(rgrc::grc::style_from_str("''").is_ok());
+    assert!(rgrc::grc::style_from_str("color255").is_ok());
+    assert_eq!(
+        rgrc::grc::style_from_str("bold color256").unwrap_err(),
+        "unhandled style: color256"
+    );
+    assert!(rgrc::grc::style_from_str("bright-red").is_err());
+    assert_eq!(
+        rgrc::grc::style_from_str("bold red")
+            .unwrap()
+            .to_ansi_codes(),
+        "\x1b[1;31m"
+    );
+}
+
 /// Lines 163-177: styles_from_str with comma-separated list
 /// Tests parsing of comma-separated style strings into a vector of Style objects.
 #[test]
@@ -544,3 +589,494 @@ fn grcatreader_missing_colours_is_empty_vector() {
         "missing colours should lead to empty colors vector"
     );
 }
+
+#[test]
+fn compiled_regex_lazy_compiles_on_first_use() {
+    let re = CompiledRegex::lazy(r"bytes from (\S+)");
+    assert!(!re.is_compiled());
+    // The pattern is available without compiling
+    assert_eq!(re.as_str(), r"bytes from (\S+)");
+    assert!(!re.is_compiled());
+
+    let caps = re
+        .captures_from_pos("64 bytes from host", 0)
+        .expect("should match");
+    assert_eq!(caps.get(1).unwrap().as_str(), "host");
+    assert!(re.is_compiled());
+    assert!(matches!(re.resolve(), Some(CompiledRegex::Fast(_))));
+}
+
+#[test]
+fn compiled_regex_lazy_invalid_never_matches() {
+    let re = CompiledRegex::lazy("invalid(regex");
+    assert!(!re.is_match("invalid(regex"));
+    assert!(re.captures_from_pos("anything", 0).is_none());
+    assert!(re.is_compiled());
+    assert!(re.resolve().is_none());
+}
+
+#[test]
+fn grcatreader_lazy_defers_compilation() {
+    let input = "regexp=^A (\\d+)\ncolours=red\n\nregexp=invalid(regex\ncolours=green\n\n";
+    let reader = std::io::Cursor::new(input);
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::BufReader::new(reader).lines())
+            .lazy()
+            .collect();
+
+    // Invalid patterns are kept until first use
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| !e.regex.is_compiled()));
+    assert!(entries[0].regex.is_match("A 42"));
+    assert!(entries[0].regex.is_compiled());
+    assert!(!entries[1].regex.is_compiled());
+}
+
+#[test]
+fn write_grcat_config_roundtrips_through_reader() {
+    let input = "regexp=^(ERROR|WARN) (\\d+)ms\ncolours=default,bold red,bright_yellow on_blue\ncount=once\n-\nregexp=token=(\\w+)\nreplace=token=***\nskip=yes\ncount=stop\n";
+    let read = |text: &str| -> Vec<GrcatConfigEntry> {
+        GrcatConfigReader::new(std::io::Cursor::new(text.to_string()).lines()).collect()
+    };
+    let entries = read(input);
+
+    let mut out = Vec::new();
+    rgrc::grc::write_grcat_config(&mut out, &entries).unwrap();
+    let written = String::from_utf8(out).unwrap();
+    assert!(written.contains("colours=default,bold red,bright_yellow on_blue\n"));
+
+    let reread = read(&written);
+    assert_eq!(reread.len(), entries.len());
+    for (a, b) in entries.iter().zip(&reread) {
+        assert_eq!(a.regex.as_str(), b.regex.as_str());
+        assert_eq!(a.colors, b.colors);
+        assert_eq!(a.count, b.count);
+        assert_eq!(a.skip, b.skip);
+        assert_eq!(a.replace, b.replace);
+    }
+}
+
+#[test]
+fn style_macros_resolve_in_colours() {
+    use rgrc::grc::style_from_str;
+
+    let input = "define ok=bold green\ndefine pair=red,blue\n\nregexp=(OK) (\\d+)\ncolours=@ok,@ok underline,yellow\n-\nregexp=(a)(b)\ndefine ok=cyan\ncolours=default,@pair\n-\nregexp=x\ncolours=@nope\n-\nregexp=y\ncolours=@ok\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+
+    assert_eq!(entries.len(), 4);
+    assert_eq!(
+        entries[0].colors,
+        [
+            style_from_str("bold green").unwrap(),
+            style_from_str("bold green underline").unwrap(),
+            style_from_str("yellow").unwrap(),
+        ]
+    );
+    assert_eq!(entries[1].colors.len(), 3);
+    // An undefined macro is treated like an unknown style
+    assert!(entries[2].colors.is_empty());
+    // A macro can be redefined, also among the keys of a rule
+    assert_eq!(entries[3].colors, [style_from_str("cyan").unwrap()]);
+}
+
+#[test]
+fn expand_style_macros_reports_undefined_names() {
+    use rgrc::grc::{expand_style_macros, parse_style_macro};
+    use std::collections::HashMap;
+
+    let macros = HashMap::from([("ok".to_string(), "bold green".to_string())]);
+    assert_eq!(
+        expand_style_macros("default,@ok", &macros).unwrap(),
+        "default,bold green"
+    );
+    assert_eq!(expand_style_macros("red", &macros).unwrap(), "red");
+    assert_eq!(
+        expand_style_macros("@ok,@err", &macros),
+        Err("undefined style macro: @err".to_string())
+    );
+
+    assert_eq!(
+        parse_style_macro("define ok = bold green"),
+        Some(("ok", "bold green"))
+    );
+    assert_eq!(parse_style_macro("define two words=red"), None);
+    assert_eq!(parse_style_macro("regexp=define"), None);
+}
+
+#[test]
+fn named_groups_take_their_style_by_name() {
+    use rgrc::grc::style_from_str;
+
+    let input = "regexp=(?P<proto>tcp|udp) (\\d+) (?<port>\\d+)\ncolours=default,name:port=yellow,name:proto=bold cyan\n-\nregexp=(?P<a>x)\ncolours=name:b=red\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        entries[0].colors,
+        [
+            style_from_str("default").unwrap(),
+            style_from_str("bold cyan").unwrap(),
+            rgrc::style::Style::new(),
+            style_from_str("yellow").unwrap(),
+        ]
+    );
+    // An unknown group name is treated like an unknown style
+    assert!(entries[1].colors.is_empty());
+}
+
+#[test]
+fn capture_group_names_follow_group_numbers() {
+    use rgrc::grc::capture_group_names;
+
+    assert_eq!(
+        capture_group_names(r"(?P<a>x)(?:y)(z)(?<b>\()[(]"),
+        [Some("a".to_string()), None, Some("b".to_string())]
+    );
+    // Lookarounds do not capture
+    assert_eq!(
+        capture_group_names(r"(?<=x)(?!y)(?<c>z)"),
+        [Some("c".to_string())]
+    );
+    assert!(capture_group_names(r"\(x\)").is_empty());
+}
+
+#[test]
+fn flags_apply_to_the_regexp() {
+    let input = "flags=i\nregexp=error\ncolours=red\n-\nregexp=^x(?= at$)\nflags=im\n-\nregexp=warn\nflags=q\n-\nregexp=ok\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+
+    // The rule with an unknown flag is skipped like one with an invalid regexp
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].regex.as_str(), "(?i)error");
+    assert!(entries[0].regex.is_match("ERROR: disk full"));
+    assert!(entries[1].regex.is_match("X AT"));
+    assert!(!entries[2].regex.is_match("OK"));
+}
+
+#[test]
+fn python_regex_syntax_is_translated() {
+    use rgrc::grc::{CompiledRegex, translate_python_regex};
+
+    let translated = |pattern| translate_python_regex(pattern).0;
+    assert_eq!(translated(r"^\s*(?P<n>\d+)$"), r"^\s*(?P<n>\d+)$");
+    assert_eq!(
+        translated(r#"(?P<q>['"]).*?(?P=q)\Z"#),
+        r#"(?P<q>['"]).*?\1\z"#
+    );
+    assert_eq!(translated(r"\011x\0[\1]\1"), r"\x{09}x\x{00}[\x{01}]\1");
+    assert_eq!(translated(r"^warn(?i)ing(?#level)(?u)"), r"(?i)^warning");
+    assert_eq!(translated(r"(?i)a(?m)b(?i:c)"), r"(?im)ab(?i:c)");
+
+    // Global flags apply to the whole pattern, as in Python
+    let regex = CompiledRegex::new(r"^warn(?i)ing").unwrap();
+    assert!(regex.is_match("WARNING: low memory"));
+    assert!(
+        CompiledRegex::new(r"(?P<word>\w+) (?P=word)")
+            .unwrap()
+            .is_match("the the")
+    );
+
+    let (_, notes) = translate_python_regex(r"(?L)(a)?(?(1)b|c)x*+(?P=none)");
+    assert_eq!(
+        notes,
+        [
+            "flag (?L) is not supported",
+            "conditional groups (?(...)...) are not supported",
+            "possessive quantifiers (*+, ++, ?+) are not supported",
+            "(?P=none) refers to no group before it",
+        ]
+    );
+}
+
+#[test]
+fn python_regex_literals_are_loosened() {
+    use rgrc::grc::{Compat, loosen_python_regex};
+
+    // Repetitions are kept, and {,n} gets its implicit minimum
+    assert_eq!(
+        loosen_python_regex(r"\d{2}-\d{1,}x{2,3}"),
+        r"\d{2}-\d{1,}x{2,3}"
+    );
+    assert_eq!(loosen_python_regex(r"a{,3}b{,}"), r"a{0,3}b{0,}");
+    // Other braces are literal text
+    assert_eq!(
+        loosen_python_regex(r"${HOME} {} {a,1} {2"),
+        r"$\{HOME} \{} \{a,1} \{2"
+    );
+    assert_eq!(loosen_python_regex(r"\{1}[{]"), r"\{1}[{]");
+    // So is [ inside a class; ] right after the opening bracket stays literal
+    assert_eq!(loosen_python_regex(r"[[(]x[]a[]"), r"[\[(]x[]a\[]");
+    assert_eq!(loosen_python_regex(r"[^][] [\[]"), r"[^]\[] [\[]");
+
+    assert_eq!("grc".parse(), Ok(Compat::Grc));
+    assert_eq!("rgrc".parse(), Ok(Compat::Rgrc));
+    assert!("python".parse::<Compat>().is_err());
+}
+
+#[test]
+fn apply_regex_flags_checks_letters() {
+    use rgrc::grc::apply_regex_flags;
+
+    assert_eq!(apply_regex_flags("a.b", "").unwrap(), "a.b");
+    assert_eq!(apply_regex_flags("a.b", " is ").unwrap(), "(?is)a.b");
+    assert_eq!(
+        apply_regex_flags("a", "iq"),
+        Err("unknown regex flag 'q' (expected one of imsx)".to_string())
+    );
+    assert_eq!(
+        apply_regex_flags("a", "ixi"),
+        Err("regex flag 'i' given twice".to_string())
+    );
+}
+
+#[test]
+fn priority_key_is_read_and_written() {
+    use rgrc::grc::write_grcat_config;
+
+    let input = "regexp=a\npriority=-3\n-\nregexp=b\npriority=soon\n-\nregexp=c\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    let priorities: Vec<i32> = entries.iter().map(|entry| entry.priority).collect();
+    assert_eq!(priorities, [-3, 0, 0]);
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    assert_eq!(written.matches("priority=").count(), 1);
+    assert!(written.starts_with("regexp=a\ncount=more\npriority=-3\n"));
+}
+
+#[test]
+fn thresholds_key_is_read_and_written() {
+    use rgrc::grc::write_grcat_config;
+    use rgrc::style::Style;
+
+    let input = "regexp=(\\d+)%\nthresholds=90:bold red, 70:yellow\n-\nregexp=b\nthresholds=high:red\n-\nregexp=c\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    // A rule with bad thresholds keeps its static colours
+    assert!(entries[1].thresholds.is_empty());
+    assert_eq!(
+        entries[0].thresholds,
+        [
+            (70.0, Style::new().yellow()),
+            (90.0, Style::new().bold().red())
+        ]
+    );
+    assert_eq!(entries[0].threshold_style("69.9"), None);
+    assert_eq!(entries[0].threshold_style("-95"), None);
+    assert_eq!(
+        entries[0].threshold_style("95.5"),
+        Some(&Style::new().bold().red())
+    );
+    assert_eq!(
+        entries[0].threshold_style("load 70"),
+        Some(&Style::new().yellow())
+    );
+    assert_eq!(entries[0].threshold_style("none"), None);
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    assert!(
+        String::from_utf8(written)
+            .unwrap()
+            .contains("thresholds=70:yellow,90:bold red\n")
+    );
+}
+
+#[test]
+fn gradient_key_is_read_and_written() {
+    use rgrc::grc::{Gradient, write_grcat_config};
+
+    let input = "regexp=(\\d+)MB free\ngradient=1000:0:ansi\n-\nregexp=b\ngradient=5:5\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    assert_eq!(
+        entries[0].gradient,
+        Some(Gradient {
+            min: 1000.0,
+            max: 0.0,
+            ansi: true
+        })
+    );
+    // Higher is better here: plenty free is green, little free is red
+    let style = |text| entries[0].gradient_style(text).copied();
+    assert_eq!(style("2048"), Some(rgrc::style::Style::new().green()));
+    assert_eq!(style("480"), Some(rgrc::style::Style::new().yellow()));
+    assert_eq!(style("12"), Some(rgrc::style::Style::new().red()));
+    assert_eq!(style("none"), None);
+    assert_eq!(entries[1].gradient, None);
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    assert!(
+        String::from_utf8(written)
+            .unwrap()
+            .contains("gradient=1000:0:ansi\n")
+    );
+}
+
+#[test]
+fn sizes_key_is_read_and_written() {
+    use rgrc::grc::{SizeScale, write_grcat_config};
+
+    let input = "regexp=\\S+$\nsizes=1M:1T:ansi\n-\nregexp=b\nsizes=1K:1024\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    assert_eq!(
+        entries[0].sizes,
+        Some(SizeScale {
+            min: 1048576.0,
+            max: 1099511627776.0,
+            ansi: true
+        })
+    );
+    // Units are powers of 1024 in any spelling, possibly after a space
+    let style = |text| entries[0].size_style(text).copied();
+    assert_eq!(style("12 MiB"), Some(rgrc::style::Style::new().green()));
+    assert_eq!(style("3GB"), Some(rgrc::style::Style::new().yellow()));
+    assert_eq!(style("900g"), Some(rgrc::style::Style::new().red()));
+    assert_eq!(style("none"), None);
+    assert_eq!(entries[1].sizes, None);
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    assert!(
+        String::from_utf8(written)
+            .unwrap()
+            .contains("sizes=1M:1T:ansi\n")
+    );
+}
+
+#[test]
+fn sizes_values_are_checked() {
+    use rgrc::grc::sizes_from_str;
+
+    assert!(sizes_from_str("1.5K : 2 GiB").is_ok_and(|sizes| sizes.min == 1536.0));
+    assert!(sizes_from_str("1T:1K").is_ok());
+    assert!(sizes_from_str("1K").is_err());
+    assert!(sizes_from_str("0:1G").is_err());
+    assert!(sizes_from_str("1K:1Q").is_err());
+    assert!(sizes_from_str("1K:1024").is_err());
+    assert!(sizes_from_str("1K:1G:rainbow").is_err());
+}
+
+#[test]
+fn engine_key_is_read_and_written() {
+    use rgrc::grc::{CompiledRegex, RegexEngine, write_grcat_config};
+
+    let input =
+        "regexp=\\d+\nengine=enhanced\n-\nregexp=a(?=b)\nengine=fast\n-\nregexp=c\nengine=pcre\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    // The lookahead is rejected by the fast engine, so that rule is skipped
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].engine, RegexEngine::Enhanced);
+    assert!(matches!(entries[0].regex, CompiledRegex::Enhanced(_)));
+    // An unknown engine is reported and the rule keeps the default
+    assert_eq!(entries[1].engine, RegexEngine::Auto);
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    assert!(written.contains("engine=enhanced\n"));
+    assert_eq!(written.matches("engine=").count(), 1);
+}
+
+#[test]
+fn gradient_values_are_checked() {
+    use rgrc::grc::gradient_from_str;
+
+    assert!(gradient_from_str("0:200").is_ok_and(|gradient| !gradient.ansi));
+    assert!(gradient_from_str("-20.5 : 40").is_ok());
+    assert!(gradient_from_str("0").is_err());
+    assert!(gradient_from_str("0:inf").is_err());
+    assert!(gradient_from_str("3:3").is_err());
+    assert!(gradient_from_str("0:9:rainbow").is_err());
+    assert!(gradient_from_str("0:9:ansi:x").is_err());
+}
+
+#[test]
+fn palette_colors_are_style_keywords() {
+    use rgrc::grc::style_from_str;
+    use rgrc::style::Style;
+
+    assert_eq!(
+        style_from_str("bold color208 on_color16"),
+        Ok(Style::new().bold().color256(208).on_color256(16))
+    );
+    assert!(style_from_str("color256").is_err());
+    assert!(style_from_str("on_color").is_err());
+    let style = Style::new().color256(99);
+    assert_eq!(style_from_str(&style.keywords().join(" ")), Ok(style));
+}
+
+#[test]
+fn fields_key_is_read_and_written() {
+    use rgrc::grc::write_grcat_config;
+    use rgrc::style::Style;
+
+    let input =
+        "regexp=^/dev\nfields=1:blue,-1:bold green\ndelimiter=\\t\n-\nregexp=b\nfields=0:red\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    assert_eq!(
+        entries[0].fields,
+        [(1, Style::new().blue()), (-1, Style::new().bold().green())]
+    );
+    assert_eq!(entries[0].delimiter, "\t");
+    // Fields count from 1, so 0 is rejected and the rule keeps its colours
+    assert!(entries[1].fields.is_empty());
+
+    let blue = Style::new().blue();
+    let green = Style::new().bold().green();
+    assert_eq!(
+        entries[0].field_styles("/dev/sda1\t98G\t/"),
+        [(1, 0..9, &blue), (3, 14..15, &green)]
+    );
+    assert_eq!(
+        entries[0].field_styles("/dev"),
+        [(1, 0..4, &blue), (1, 0..4, &green)]
+    );
+    assert!(entries[1].field_styles("a b").is_empty());
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    assert!(
+        String::from_utf8(written)
+            .unwrap()
+            .contains("fields=1:blue,-1:bold green\ndelimiter=\\t\n")
+    );
+}
+
+#[test]
+fn quoted_fields_keep_their_delimiter() {
+    use rgrc::grc::field_ranges;
+
+    let line = r#"1,"a, ""b""",,"open"#;
+    assert_eq!(field_ranges(line, ","), [0..1, 2..12, 13..13, 14..19]);
+    // Quotes only matter at the start of a field; whitespace fields ignore them
+    assert_eq!(field_ranges(r#"x"y,z"#, ","), [0..3, 4..5]);
+    assert_eq!(field_ranges(r#""a b" c"#, ""), [0..2, 3..5, 6..7]);
+}
+
+#[test]
+fn command_key_is_read_and_written() {
+    use rgrc::grc::write_grcat_config;
+
+    let input = "regexp=timeout\ncolours=red\ncommand=echo \"$RGRC_MATCH\"\n";
+    let entries: Vec<GrcatConfigEntry> =
+        GrcatConfigReader::new(std::io::Cursor::new(input.to_string()).lines()).collect();
+    assert_eq!(entries[0].command, "echo \"$RGRC_MATCH\"");
+
+    let mut written = Vec::new();
+    write_grcat_config(&mut written, &entries).unwrap();
+    assert!(
+        String::from_utf8(written)
+            .unwrap()
+            .ends_with("command=echo \"$RGRC_MATCH\"\n")
+    );
+}
@@ -358,13 +358,20 @@ mod grcat_config_reader_tests {
     fn test_specific_conf_files() {
         let test_files = vec![
             "conf.ant",
+            "conf.apt",
+            "conf.aptlist",
             "conf.blkid",
+            "conf.brew",
+            "conf.brewservices",
+            "conf.cargo",
             "conf.configure",
             "conf.curl",
             "conf.cvs",
             "conf.df",
             "conf.dig",
+            "conf.dmesg",
             "conf.dnf",
+            "conf.dpkg",
             "conf.docker-machinels",
             "conf.dockerimages",
             "conf.dockerinfo",
@@ -381,6 +388,9 @@ mod grcat_config_reader_tests {
             "conf.gcc",
             "conf.getfacl",
             "conf.getsebool",
+            "conf.gitblame",
+            "conf.gitlog",
+            "conf.gitstatus",
             "conf.go-test",
             "conf.id",
             "conf.ifconfig",
@@ -408,7 +418,11 @@ mod grcat_config_reader_tests {
             "conf.mtr",
             "conf.mvn",
             "conf.nmap",
+            "conf.npm",
+            "conf.npmaudit",
+            "conf.npmtest",
             "conf.ntpdate",
+            "conf.pacman",
             "conf.php",
             "conf.ping2",
             "conf.proftpd",
@@ -426,6 +440,8 @@ mod grcat_config_reader_tests {
             "conf.sysctl",
             "conf.systemctl",
             "conf.tcpdump",
+            "conf.terraform",
+            "conf.terraformstate",
             "conf.traceroute",
             "conf.tune2fs",
             "conf.ulimit",
@@ -492,6 +508,7 @@ mod grcat_config_reader_tests {
                 grc::GrcatConfigEntryCount::Once => found_once = true,
                 grc::GrcatConfigEntryCount::More => found_more = true,
                 grc::GrcatConfigEntryCount::Stop => found_stop = true,
+                grc::GrcatConfigEntryCount::Block | grc::GrcatConfigEntryCount::Unblock => {}
             }
         }
 
@@ -762,6 +779,69 @@ mod integration_tests {
         );
     }
 
+    /// Subcommands printing their own layout get a config of their own; as in
+    /// grc, the first matching entry of grc.conf wins
+    #[test]
+    fn test_grc_conf_routes_subcommands() {
+        let file = File::open(get_etc_dir().join("rgrc.conf")).expect("Failed to open grc.conf");
+        let entries: Vec<_> = GrcConfigReader::new(BufReader::new(file).lines()).collect();
+        let config_for = |command: &str| {
+            entries
+                .iter()
+                .find(|(regex, _)| regex.is_match(command))
+                .map(|(_, config)| config.as_str())
+        };
+
+        assert_eq!(config_for("apt list --upgradable"), Some("conf.aptlist"));
+        assert_eq!(config_for("apt search curl"), Some("conf.aptlist"));
+        assert_eq!(config_for("apt install curl"), Some("conf.apt"));
+        assert_eq!(config_for("apt-get upgrade"), Some("conf.apt"));
+        assert_eq!(config_for("/usr/bin/dpkg -l"), Some("conf.dpkg"));
+        assert_eq!(config_for("dpkg-query --list 'lib*'"), Some("conf.dpkg"));
+        assert_eq!(config_for("dpkg -i curl.deb"), Some("conf.apt"));
+        assert_eq!(config_for("pacman -Qi curl"), Some("conf.pacman"));
+        assert_eq!(config_for("yay -Syu"), Some("conf.pacman"));
+        assert_eq!(config_for("brew services"), Some("conf.brewservices"));
+        assert_eq!(config_for("brew services list"), Some("conf.brewservices"));
+        assert_eq!(config_for("brew services start redis"), Some("conf.brew"));
+        assert_eq!(
+            config_for("/opt/homebrew/bin/brew outdated"),
+            Some("conf.brew")
+        );
+        assert_eq!(config_for("git status --porcelain"), Some("conf.gitstatus"));
+        assert_eq!(config_for("git -C repo log --oneline"), Some("conf.gitlog"));
+        assert_eq!(config_for("git blame src/lib.rs"), Some("conf.gitblame"));
+        assert_eq!(config_for("git diff"), None);
+        assert_eq!(config_for("cargo build --release"), Some("conf.cargo"));
+        assert_eq!(config_for("cargo +nightly test"), Some("conf.cargo"));
+        assert_eq!(config_for("cargo clippy --all-targets"), Some("conf.cargo"));
+        assert_eq!(config_for("cargo run -- --help"), Some("conf.cargo"));
+        assert_eq!(config_for("cargo add serde"), None);
+        assert_eq!(config_for("npm audit --omit=dev"), Some("conf.npmaudit"));
+        assert_eq!(config_for("yarn npm audit"), Some("conf.npmaudit"));
+        assert_eq!(config_for("npm run test:unit"), Some("conf.npmtest"));
+        assert_eq!(config_for("pnpm test"), Some("conf.npmtest"));
+        assert_eq!(config_for("yarn add react"), Some("conf.npm"));
+        assert_eq!(config_for("npm ci"), Some("conf.npm"));
+        assert_eq!(config_for("npm run typecheck"), Some("conf.npm"));
+        assert_eq!(
+            config_for("terraform state list"),
+            Some("conf.terraformstate")
+        );
+        assert_eq!(
+            config_for("terraform -chdir=infra state list module.vpc"),
+            Some("conf.terraformstate")
+        );
+        assert_eq!(
+            config_for("terraform plan -no-color"),
+            Some("conf.terraform")
+        );
+        assert_eq!(
+            config_for("terraform state show aws_instance.web"),
+            Some("conf.terraform")
+        );
+    }
+
     #[test]
     fn test_complete_workflow_grc_conf_to_grcat() {
         let grc_conf_path = get_etc_dir().join("rgrc.conf");
@@ -18,6 +18,9 @@ fn test_simple_pattern_uses_fast_regex() {
         CompiledRegex::Enhanced(_) => {
             panic!("Simple pattern should use Fast regex, not Enhanced");
         }
+        CompiledRegex::Lazy(_) => {
+            panic!("new() should compile eagerly");
+        }
     }
 }
 
@@ -35,6 +38,9 @@ fn test_complex_pattern_uses_enhanced() {
             // Success! EnhancedRegex can handle lookahead
             println!("✓ Lookahead pattern uses Enhanced regex engine");
         }
+        CompiledRegex::Lazy(_) => {
+            panic!("new() should compile eagerly");
+        }
     }
 }
 
@@ -52,37 +58,23 @@ fn test_lookbehind_pattern_uses_enhanced() {
             // Success! EnhancedRegex can handle lookbehind
             println!("✓ Lookbehind pattern uses Enhanced regex engine");
         }
+        CompiledRegex::Lazy(_) => {
+            panic!("new() should compile eagerly");
+        }
     }
 }
 
 #[test]
-#[cfg(not(feature = "fancy-regex"))]
-fn test_backreference_fails() {
-    // Pattern with backreference is not supported by EnhancedRegex
+fn test_backreference_uses_enhanced() {
+    // Pattern with backreference is supported by both Enhanced engines
     let pattern = r"(\w+)\s+\1";
     let compiled = CompiledRegex::new(pattern);
 
-    // Backreferences are not supported, should fail to compile
     assert!(
-        compiled.is_err(),
-        "Backreference pattern should fail to compile"
+        matches!(compiled, Ok(CompiledRegex::Enhanced(_))),
+        "Backreference pattern should compile to Enhanced regex"
     );
-    println!("✓ Backreference pattern correctly fails to compile (not supported)");
-}
-
-#[test]
-#[cfg(feature = "fancy-regex")]
-fn test_backreference_works_with_fancy() {
-    // Pattern with backreference is supported by fancy-regex
-    let pattern = r"(\w+)\s+\1";
-    let compiled = CompiledRegex::new(pattern);
-
-    // With fancy-regex, backreferences should work
-    assert!(
-        compiled.is_ok(),
-        "Backreference pattern should compile with fancy-regex"
-    );
-    println!("✓ Backreference pattern works with fancy-regex");
+    println!("✓ Backreference pattern uses Enhanced regex engine");
 
     let regex = compiled.unwrap();
     // Test that it actually matches duplicated words
@@ -122,6 +114,9 @@ fn test_multiple_simple_patterns() {
                     pattern
                 );
             }
+            CompiledRegex::Lazy(_) => {
+                panic!("new() should compile eagerly");
+            }
         }
     }
 }
@@ -249,3 +244,78 @@ fn test_enhanced_ipv4_find_from_different_positions() {
     assert!(mat2.is_some(), "Should find second IPv4");
     assert_eq!(mat2.unwrap().as_str(), "10", "Second match should be '10'");
 }
+
+#[test]
+fn test_engine_can_be_forced() {
+    use rgrc::grc::RegexEngine;
+
+    // Enhanced skips the fast attempt even for a pattern it accepts
+    let compiled = CompiledRegex::with_engine(r"\d+ms", RegexEngine::Enhanced).unwrap();
+    assert!(matches!(compiled, CompiledRegex::Enhanced(_)));
+    assert!(compiled.is_match("took 12ms"));
+
+    // Fast never falls back, so lookaround fails to compile
+    assert!(matches!(
+        CompiledRegex::with_engine(r"\d+", RegexEngine::Fast),
+        Ok(CompiledRegex::Fast(_))
+    ));
+    assert!(CompiledRegex::with_engine(r"\d+(?=ms)", RegexEngine::Fast).is_err());
+}
+
+#[test]
+fn test_captures_api_is_the_same_for_every_engine() {
+    use rgrc::grc::RegexEngine;
+
+    let pattern = r"(?P<proto>tcp|udp)\s+(?P<port>\d+)";
+    for engine in [RegexEngine::Fast, RegexEngine::Enhanced] {
+        let regex = CompiledRegex::with_engine(pattern, engine).unwrap();
+        let lazy = CompiledRegex::lazy_with_engine(pattern, engine);
+        for regex in [&regex, &lazy] {
+            assert_eq!(regex.capture_names(), [None, Some("proto"), Some("port")]);
+
+            let text = "tcp 22 udp 53 icmp";
+            let found: Vec<_> = regex
+                .captures_iter(text)
+                .map(|caps| {
+                    let port = caps.name("port").unwrap();
+                    (caps.get(1).unwrap().as_str(), port.as_str(), port.start())
+                })
+                .collect();
+            assert_eq!(found, [("tcp", "22", 4), ("udp", "53", 11)]);
+
+            // Positions are in the whole text, which anchors still see
+            let caps = regex.captures_at(text, 5).unwrap();
+            assert_eq!(caps.name("proto").unwrap().start(), 7);
+            assert!(caps.name("missing").is_none());
+        }
+    }
+
+    // Unlike captures_from_pos, captures_at does not treat pos as a line start
+    let regex = CompiledRegex::new(r"^\w+").unwrap();
+    assert!(regex.captures_at("one two", 4).is_none());
+    assert_eq!(
+        regex
+            .captures_from_pos("one two", 4)
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .as_str(),
+        "two"
+    );
+}
+
+#[test]
+fn test_captures_iter_skips_empty_matches_like_regex() {
+    let text = "1;;2;";
+    let expected: Vec<_> = regex_lite::Regex::new(r"\d*")
+        .unwrap()
+        .find_iter(text)
+        .map(|m| m.range())
+        .collect();
+    let regex = CompiledRegex::new(r"\d*").unwrap();
+    let found: Vec<_> = regex
+        .captures_iter(text)
+        .map(|caps| caps.get(0).unwrap().start()..caps.get(0).unwrap().end())
+        .collect();
+    assert_eq!(found, expected);
+}
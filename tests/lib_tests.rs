@@ -211,6 +211,191 @@ fn test_color_mode_clone_semantics() {
     assert_eq!(mode1, mode2);
 }
 
+#[test]
+fn test_config_names_split_on_commas_and_whitespace() {
+    let names: Vec<&str> = rgrc::config_names("conf.ping, conf.common\tconf.df,,").collect();
+    assert_eq!(names, ["conf.ping", "conf.common", "conf.df"]);
+    assert_eq!(
+        rgrc::config_names("conf.ping").collect::<Vec<_>>(),
+        ["conf.ping"]
+    );
+    // Options are not config names
+    assert_eq!(
+        rgrc::config_names("conf.top, colour=off").collect::<Vec<_>>(),
+        ["conf.top"]
+    );
+}
+
+#[test]
+fn test_mapping_colour_options() {
+    use rgrc::{ColorMode, ConfigLoader, config_color_mode};
+
+    assert_eq!(config_color_mode("colour=off"), Some(ColorMode::Off));
+    assert_eq!(config_color_mode("conf.ps color=on"), Some(ColorMode::On));
+    assert_eq!(config_color_mode("conf.ps colour=sometimes"), None);
+
+    let loader = ConfigLoader::new()
+        .mapping_str("^top\ncolour=off\n^ps\nconf.ps\n")
+        .mapping_str("^ps\nconf.ps colour=on\n^top\nconf.top colour=on\n");
+    assert_eq!(loader.color_mode_for_command("top"), Some(ColorMode::Off));
+    // The first entry setting a colour counts
+    assert_eq!(loader.color_mode_for_command("ps aux"), Some(ColorMode::On));
+    assert_eq!(loader.color_mode_for_command("ping"), None);
+    // An entry with only options names no configs, so the next mapping's rules apply
+    let loader = loader.config_str("conf.top", "regexp=load\ncolours=red\n");
+    assert_eq!(loader.load_rules_for_command("top").len(), 1);
+}
+
+#[test]
+fn test_load_grcat_config_expands_includes() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("conf.sizes"),
+        "regexp=\\d+[KMG]\ncolours=cyan\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("conf.common"),
+        "regexp=\\d+:\\d+\ncolours=green\ninclude=sizes\n",
+    )
+    .unwrap();
+    // The include sits right below an entry but must not merge with it
+    let app = dir.path().join("conf.app");
+    std::fs::write(
+        &app,
+        "regexp=ERROR\ncolours=red\ninclude=common\nregexp=WARN\ncolours=yellow\n",
+    )
+    .unwrap();
+
+    let rules = rgrc::load_grcat_config(app.to_string_lossy());
+    let patterns: Vec<&str> = rules.iter().map(|rule| rule.regex.as_str()).collect();
+    assert_eq!(patterns, ["ERROR", "\\d+:\\d+", "\\d+[KMG]", "WARN"]);
+}
+
+#[test]
+fn test_expand_includes_reports_cycles_and_missing_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let a = dir.path().join("conf.a");
+    std::fs::write(dir.path().join("conf.b"), "include=conf.a\nregexp=b\n").unwrap();
+    let text = "regexp=a\ninclude=b\ninclude=nope\n";
+    std::fs::write(&a, text).unwrap();
+
+    let (expanded, problems) = rgrc::expand_includes(&a, text);
+    assert_eq!(expanded, "regexp=a\n-\nregexp=b\n-\n");
+    assert_eq!(
+        problems,
+        [
+            (
+                2,
+                "in 'b': include cycle: 'conf.a' is already being included".to_string()
+            ),
+            (3, "included config 'nope' not found".to_string()),
+        ]
+    );
+
+    // A file without includes is returned as is
+    let (expanded, problems) = rgrc::expand_includes(&a, "regexp=x\n");
+    assert!(matches!(expanded, std::borrow::Cow::Borrowed("regexp=x\n")));
+    assert!(problems.is_empty());
+}
+
+#[test]
+fn test_config_loader_searches_only_what_it_is_given() {
+    use rgrc::ConfigLoader;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("conf.app"),
+        "regexp=ERROR\ncolours=red\ninclude=common\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("conf.common"),
+        "regexp=\\d+ms\ncolours=yellow\n",
+    )
+    .unwrap();
+    let mapping = dir.path().join("rgrc.conf");
+    std::fs::write(&mapping, "^app\\b\nconf.app\n^tool\nconf.missing\n").unwrap();
+
+    // Nothing is searched by default, not even the bundled configs
+    assert!(
+        ConfigLoader::new()
+            .load_rules_for_command("ping")
+            .is_empty()
+    );
+
+    let loader = ConfigLoader::new()
+        .mapping_file(&mapping)
+        .search_dir(dir.path());
+    let patterns = |rules: Vec<rgrc::grc::GrcatConfigEntry>| -> Vec<String> {
+        rules
+            .iter()
+            .map(|rule| rule.regex.as_str().to_string())
+            .collect()
+    };
+    assert_eq!(
+        patterns(loader.load_rules_for_command("app --verbose")),
+        ["ERROR", "\\d+ms"]
+    );
+    // A mapping naming configs that cannot be found falls through to the next one
+    assert!(loader.load_rules_for_command("tool").is_empty());
+    let loader = loader.mapping_str("^tool\nconf.tool\n").config_str(
+        "conf.tool",
+        "regexp=done\ncolours=green\n-\ninclude=common\n",
+    );
+    assert_eq!(
+        patterns(loader.load_rules_for_command("tool")),
+        ["done", "\\d+ms"]
+    );
+
+    // Configs given as text shadow files of the same name, includes included
+    let loader = ConfigLoader::new()
+        .mapping_file(&mapping)
+        .search_dir(dir.path())
+        .config_str("conf.common", "regexp=ms\ncolours=cyan\n");
+    assert_eq!(
+        patterns(loader.load_rules_for_command("app")),
+        ["ERROR", "ms"]
+    );
+}
+
+#[test]
+fn test_config_loader_load_config_reports_missing_configs() {
+    use rgrc::{ConfigLoader, RgrcError};
+
+    let loader = ConfigLoader::new().config_str("conf.one", "regexp=1\ncolours=red\n");
+    assert_eq!(loader.load_config("conf.one").unwrap().len(), 1);
+    assert_eq!(loader.load_config("conf.one, conf.two").unwrap().len(), 1);
+
+    let err = loader.load_config("conf.two").unwrap_err();
+    assert!(matches!(err, RgrcError::ConfigNotFound(ref name) if name == "conf.two"));
+    assert_eq!(err.to_string(), "config not found: 'conf.two'");
+    assert_eq!(err.exit_code(), 1);
+}
+
+#[test]
+fn test_config_loader_orders_mappings_and_priorities() {
+    use rgrc::ConfigLoader;
+
+    let loader = ConfigLoader::new()
+        .mapping_str("^a\nconf.one, conf.two\n")
+        .mapping_str("^a\nconf.three\n")
+        .config_str("conf.one", "regexp=1\ncolours=red\n")
+        .config_str("conf.two", "regexp=2\ncolours=red\npriority=5\n")
+        .config_str("conf.three", "regexp=3\ncolours=red\n");
+    let rules = loader.load_rules_for_command("a");
+    let patterns: Vec<&str> = rules.iter().map(|rule| rule.regex.as_str()).collect();
+    assert_eq!(patterns, ["2", "1"]);
+
+    // An empty config does not stop the search
+    let loader = ConfigLoader::new()
+        .mapping_str("^a\nconf.empty\n")
+        .mapping_str("^a\nconf.three\n")
+        .config_str("conf.empty", "# nothing yet\n")
+        .config_str("conf.three", "regexp=3\ncolours=red\n");
+    assert_eq!(loader.load_rules_for_command("a").len(), 1);
+}
+
 #[cfg(feature = "embed-configs")]
 mod embed_configs_tests {
     use std::io::Write;
@@ -588,3 +773,223 @@ mod cache_error_handling_tests {
         }
     }
 }
+
+fn colorizer_rules(config: &str) -> Vec<rgrc::grc::GrcatConfigEntry> {
+    use std::io::BufRead;
+    rgrc::grc::GrcatConfigReader::new(config.as_bytes().lines()).collect()
+}
+
+#[test]
+fn test_colorizer_builder_applies_settings() {
+    use rgrc::{ColorMode, Colorizer, OutputFormat};
+
+    let rules = colorizer_rules("regexp=(\\d+)ms\ncolours=default,yellow\n");
+    let colorizer = Colorizer::builder()
+        .rules(rules.clone())
+        .color_mode(ColorMode::On)
+        .build();
+    assert!(colorizer.is_enabled());
+    assert_eq!(colorizer.rules().len(), 1);
+    assert_eq!(
+        colorizer.colorize_str("took 12ms\n").unwrap(),
+        "took \x1b[33m12\x1b[0mms\n"
+    );
+
+    let markup = Colorizer::builder()
+        .rules(rules.clone())
+        .color_mode(ColorMode::On)
+        .renderer(OutputFormat::Markup)
+        .build();
+    assert_eq!(
+        markup.colorize_str("took 12ms\n").unwrap(),
+        "took [yellow]12[/]ms\n"
+    );
+
+    // Off writes the input as it is, rules and all
+    let off = Colorizer::builder()
+        .rules(rules)
+        .color_mode(ColorMode::Off)
+        .build();
+    assert!(!off.is_enabled());
+    assert_eq!(off.colorize_str("took 12ms\n").unwrap(), "took 12ms\n");
+
+    // Without rules the input is copied
+    let empty = Colorizer::builder().color_mode(ColorMode::On).build();
+    assert_eq!(empty.colorize_str("a\nb\n").unwrap(), "a\nb\n");
+}
+
+#[test]
+fn test_colorizer_with_options_and_input_format() {
+    use rgrc::colorizer::{ColorizeOptions, RuleStats};
+    use rgrc::{ColorMode, Colorizer, InputFormat};
+
+    let colorizer = Colorizer::builder()
+        .rules(colorizer_rules("regexp=^ERROR\ncolours=red\n"))
+        .color_mode(ColorMode::On)
+        .max_line_length(Some(10))
+        .build();
+    let mut stats = RuleStats::new(1);
+    let mut log = Vec::new();
+    let mut output = Vec::new();
+    colorizer
+        .colorize_with_options(
+            &mut "ERROR a\nERROR and a long tail\nok\n".as_bytes(),
+            &mut output,
+            ColorizeOptions {
+                log: Some(&mut log),
+                stats: Some(&mut stats),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    // The long line is passed through uncoloured
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\x1b[31mERROR\x1b[0m a\nERROR and a long tail\nok\n"
+    );
+    assert_eq!(
+        String::from_utf8(log).unwrap(),
+        "ERROR a\nERROR and a long tail\nok\n"
+    );
+    assert_eq!(stats.lines, 3);
+    assert_eq!(stats.rule_hits, [1]);
+
+    // CSV columns are coloured without any rule
+    let csv = Colorizer::builder()
+        .color_mode(ColorMode::On)
+        .input_format(InputFormat::Csv)
+        .build();
+    let output = csv.colorize_str("a,b\n").unwrap();
+    assert!(output.contains('\x1b'));
+    assert_eq!(rgrc::utils::strip_ansi(&output), "a,b\n");
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_tests {
+    use super::colorizer_rules;
+    use rgrc::{ColorMode, Colorizer, InputFormat, OutputFormat};
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    /// The async colorizer writes exactly what the blocking one does, block
+    /// regions and dropped lines included
+    #[tokio::test]
+    async fn test_colorize_async_matches_colorize() {
+        let rules = "regexp=^BEGIN\ncolours=cyan\ncount=block\n-\n\
+                     regexp=^END\ncolours=default\ncount=unblock\n-\n\
+                     regexp=noise\nskip=yes\n-\n\
+                     regexp=(\\d+)ms\ncolours=default,yellow\n";
+        let input = "took 5ms\nBEGIN\ninside 7ms\nnoise\nEND\n\nlast 9ms\n";
+        for renderer in [
+            OutputFormat::Ansi,
+            OutputFormat::Jsonl,
+            OutputFormat::Markup,
+        ] {
+            let colorizer = Colorizer::builder()
+                .rules(colorizer_rules(rules))
+                .color_mode(ColorMode::On)
+                .renderer(renderer)
+                .build();
+            let mut output = Vec::new();
+            assert_send(colorizer.colorize_async(input.as_bytes(), &mut output))
+                .await
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                colorizer.colorize_str(input).unwrap()
+            );
+        }
+
+        let csv = Colorizer::builder()
+            .color_mode(ColorMode::On)
+            .input_format(InputFormat::Csv)
+            .build();
+        let mut output = Vec::new();
+        csv.colorize_async("a,b\n1,2\n".as_bytes(), &mut output)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            csv.colorize_str("a,b\n1,2\n").unwrap()
+        );
+    }
+
+    /// Lines are written as they are read, before the input ends
+    #[tokio::test]
+    async fn test_colorize_async_streams_lines() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let colorizer = Colorizer::builder()
+            .rules(colorizer_rules("regexp=ERROR\ncolours=red\n"))
+            .color_mode(ColorMode::On)
+            .build();
+        let (mut input, reader) = tokio::io::duplex(64);
+        let (mut writer, mut output) = tokio::io::duplex(64);
+
+        let feed = async {
+            input.write_all(b"ERROR one\n").await.unwrap();
+            let mut line = [0; 19];
+            output.read_exact(&mut line).await.unwrap();
+            assert_eq!(&line, b"\x1b[31mERROR\x1b[0m one\n");
+            drop(input);
+        };
+        let (result, ()) = tokio::join!(colorizer.colorize_async(reader, &mut writer), feed);
+        result.unwrap();
+    }
+}
+
+#[cfg(feature = "ffi")]
+mod ffi_tests {
+    use rgrc::ffi::{rgrc_colorize_line, rgrc_free, rgrc_free_rules, rgrc_load_rules};
+    use std::ffi::{CStr, c_char};
+
+    /// Colorize `line` through the C interface, releasing the result.
+    fn colorize(rules: *mut rgrc::ffi::RgrcRules, line: &CStr) -> Option<String> {
+        unsafe {
+            let colored = rgrc_colorize_line(rules, line.as_ptr());
+            if colored.is_null() {
+                return None;
+            }
+            let text = CStr::from_ptr(colored).to_str().unwrap().to_string();
+            rgrc_free(colored);
+            Some(text)
+        }
+    }
+
+    #[test]
+    fn test_ffi_colorizes_lines_of_a_command() {
+        let rules = unsafe { rgrc_load_rules(c"ping -c 1 localhost".as_ptr()) };
+        assert!(!rules.is_null());
+        let line = c"64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.045 ms";
+        let colored = colorize(rules, line).unwrap();
+        assert!(colored.contains("\x1b["));
+        assert!(!colored.ends_with('\n'));
+        assert_eq!(rgrc::utils::strip_ansi(&colored), line.to_str().unwrap());
+        unsafe { rgrc_free_rules(rules) };
+
+        // Without rules, lines come back unchanged
+        let rules = unsafe { rgrc_load_rules(c"no-such-command-xyz".as_ptr()) };
+        assert_eq!(colorize(rules, c"plain").as_deref(), Some("plain"));
+        assert_eq!(colorize(rules, c"").as_deref(), Some(""));
+        unsafe { rgrc_free_rules(rules) };
+    }
+
+    #[test]
+    fn test_ffi_rejects_null_and_invalid_input() {
+        unsafe {
+            assert!(rgrc_load_rules(std::ptr::null()).is_null());
+            assert!(rgrc_load_rules(c"\xff".as_ptr()).is_null());
+
+            let rules = rgrc_load_rules(c"ls".as_ptr());
+            assert!(rgrc_colorize_line(rules, std::ptr::null()).is_null());
+            assert!(rgrc_colorize_line(std::ptr::null_mut(), c"x".as_ptr()).is_null());
+            assert!(rgrc_colorize_line(rules, c"\xff".as_ptr() as *const c_char).is_null());
+            rgrc_free_rules(rules);
+
+            rgrc_free(std::ptr::null_mut());
+            rgrc_free_rules(std::ptr::null_mut());
+        }
+    }
+}
@@ -58,6 +58,54 @@ fn test_nonexistent_command_returns_127() {
     assert_eq!(output.status.code().unwrap_or(1), 127);
 }
 
+/// A file that exists but cannot be executed exits with 126, a usage error with
+/// 2 and a missing `--conf` file or an unwritable `--log` file with 1, each
+/// with an `Error:` line.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_error_exit_codes() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let not_executable = dir.path().join("script");
+    std::fs::write(&not_executable, "#!/bin/sh\n").unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .args(args)
+            .output()
+            .expect("failed to run rgrc")
+    };
+
+    let output = run(&["--color=off", not_executable.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(126));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error: cannot run '"));
+
+    let output = run(&["--color=bogus", "echo"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: Invalid color mode"));
+
+    let missing = dir.path().join("missing.conf");
+    let output = run(&["--conf", missing.to_str().unwrap(), "echo", "hi"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: config not found: '"));
+
+    let log = dir.path().join("missing").join("rgrc.log");
+    let output = run(&["--log", log.to_str().unwrap(), "echo", "hi"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error: cannot open log file '"));
+}
+
+/// A command killed by a signal exits with 128 plus the signal number
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_signalled_command_exit_code() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["--color=on", "sh", "-c", "kill -TERM $$"])
+        .output()
+        .expect("failed to run rgrc");
+
+    assert_eq!(output.status.code(), Some(128 + 15));
+}
+
 /// Lines 149-154: ColorMode::Off disables colorization
 /// Tests that --color=off prevents ANSI escape codes in output.
 /// This verifies the ColorMode::Off branch and should_colorize=false path.
@@ -77,6 +125,878 @@ fn test_color_off_mode_no_ansi() {
     assert!(stdout.contains("test output"));
 }
 
+/// Uncoloured output to a pipe is copied as raw bytes, not split into lines:
+/// invalid UTF-8 and a missing final newline come through unchanged, along with
+/// the command's exit status.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_color_off_to_pipe_copies_bytes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args([
+            "--color=off",
+            "sh",
+            "-c",
+            r"printf 'a\377\000b\r\nend'; exit 3",
+        ])
+        .output()
+        .expect("failed to run rgrc");
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"a\xff\0b\r\nend");
+}
+
+/// --stderr colorizes the command's stderr with the same rules, into rgrc's
+/// stderr; --tag-stderr marks its lines, with colours off too.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_stderr_is_colorized() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("conf.ping"), "regexp=fail\ncolours=red\n").unwrap();
+    std::fs::write(dir.path().join("rgrc.conf"), "^ping\nconf.ping\n").unwrap();
+    // A stand-in for ping, so the rules of a supported command apply
+    let ping = dir.path().join("ping");
+    std::fs::write(&ping, "#!/bin/sh\necho ok\necho fail >&2\nexit 4\n").unwrap();
+    std::fs::set_permissions(&ping, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", dir.path())
+            .env("PATH", &path)
+            .args(args)
+            .arg("ping")
+            .output()
+            .expect("failed to run rgrc")
+    };
+
+    let output = run(&["--color=on", "--stderr"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(output.stdout, b"ok\n");
+    assert_eq!(output.stderr, b"\x1b[31mfail\x1b[0m\n");
+
+    let output = run(&["--color=on"]);
+    assert_eq!(output.stderr, b"fail\n");
+
+    let output = run(&["--color=off", "--tag-stderr"]);
+    assert_eq!(output.stdout, b"ok\n");
+    assert_eq!(output.stderr, b"[stderr] fail\n");
+
+    // Each stream can be coloured on its own
+    let output = run(&["--color-stdout=off", "--color-stderr=on", "--stderr"]);
+    assert_eq!(output.stdout, b"ok\n");
+    assert_eq!(output.stderr, b"\x1b[31mfail\x1b[0m\n");
+    let output = run(&["--color=on", "--color-stderr=off", "--tag-stderr"]);
+    assert_eq!(output.stderr, b"[stderr] fail\n");
+}
+
+/// A `colour=` option in rgrc.conf replaces the auto colour mode of a command,
+/// while --color on the command line still wins.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_mapping_colour_option() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("conf.ping"), "regexp=fail\ncolours=red\n").unwrap();
+    let ping = dir.path().join("ping");
+    std::fs::write(&ping, "#!/bin/sh\necho fail\n").unwrap();
+    std::fs::set_permissions(&ping, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |mapping: &str, args: &[&str]| {
+        std::fs::write(dir.path().join("rgrc.conf"), mapping).unwrap();
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", dir.path())
+            .env("PATH", &path)
+            .args(args)
+            .arg("ping")
+            .output()
+            .expect("failed to run rgrc")
+            .stdout
+    };
+
+    // Piped, so auto would leave it uncoloured
+    assert_eq!(run("^ping\nconf.ping\n", &[]), b"fail\n");
+    assert_eq!(
+        run("^ping\nconf.ping colour=on\n", &[]),
+        b"\x1b[31mfail\x1b[0m\n"
+    );
+    assert_eq!(
+        run("^ping\nconf.ping colour=off\n", &["--color=on"]),
+        b"\x1b[31mfail\x1b[0m\n"
+    );
+    assert_eq!(run("^ping\ncolour=off\n", &["--color=auto"]), b"fail\n");
+}
+
+/// RGRC_DISABLE and RGRC_EXCEPT turn colours off when the command runs, even
+/// with --color=on as aliases may pass.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_disabled_by_environment() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("conf.ping"), "regexp=fail\ncolours=red\n").unwrap();
+    std::fs::write(dir.path().join("rgrc.conf"), "^ping\nconf.ping\n").unwrap();
+    let ping = dir.path().join("ping");
+    std::fs::write(&ping, "#!/bin/sh\necho fail\n").unwrap();
+    std::fs::set_permissions(&ping, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |env: &[(&str, &str)]| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", dir.path())
+            .env("PATH", &path)
+            .env_remove("RGRC_DISABLE")
+            .env_remove("RGRC_EXCEPT")
+            .envs(env.iter().copied())
+            .args(["--color=on", "ping"])
+            .output()
+            .expect("failed to run rgrc")
+            .stdout
+    };
+
+    assert_eq!(run(&[]), b"\x1b[31mfail\x1b[0m\n");
+    assert_eq!(run(&[("RGRC_DISABLE", "1")]), b"fail\n");
+    assert_eq!(run(&[("RGRC_DISABLE", "0")]), b"\x1b[31mfail\x1b[0m\n");
+    assert_eq!(run(&[("RGRC_EXCEPT", "ls,ping")]), b"fail\n");
+    assert_eq!(run(&[("RGRC_EXCEPT", "ls")]), b"\x1b[31mfail\x1b[0m\n");
+}
+
+/// A project's .rgrc.toml, found from a directory below it, maps its own tools to
+/// the configs next to it.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64", feature = "toml-configs"))]
+fn test_project_config() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join(".rgrc.toml"),
+        "[[command]]\nregexp = '^mytool\\b'\nconfig = 'conf.mytool'\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("conf.mytool"), "regexp=fail\ncolours=red\n").unwrap();
+    let bin = dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    std::fs::write(bin.join("mytool"), "#!/bin/sh\necho fail\n").unwrap();
+    std::fs::set_permissions(bin.join("mytool"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |env: &[(&str, &str)]| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .current_dir(&bin)
+            .env("PATH", &path)
+            .envs(env.iter().copied())
+            .args(["--color=on", "mytool"])
+            .output()
+            .expect("failed to run rgrc")
+            .stdout
+    };
+
+    assert_eq!(run(&[]), b"\x1b[31mfail\x1b[0m\n");
+    assert_eq!(run(&[("RGRC_NO_PROJECT_CONFIG", "1")]), b"fail\n");
+}
+
+/// `rgrc fetch-configs` installs the checked configs of an index and maps them in
+/// rgrc.local, which makes their commands coloured.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_fetch_configs() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    let bin = dir.path().join("bin");
+    let index = dir.path().join("index");
+    let config_dir = dir.path().join("config");
+    std::fs::create_dir(&bin).unwrap();
+    std::fs::create_dir(&index).unwrap();
+    // A stand-in for curl reading file:// URLs, and an unknown tool
+    for (name, script) in [
+        (
+            "curl",
+            "#!/bin/sh\nfor url; do :; done\nexec cat \"${url#file://}\"\n",
+        ),
+        ("mytool", "#!/bin/sh\necho fail\n"),
+    ] {
+        std::fs::write(bin.join(name), script).unwrap();
+        std::fs::set_permissions(bin.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let config = "regexp=fail\ncolours=red\n";
+    std::fs::write(index.join("conf.mytool"), config).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", &config_dir)
+            .env("PATH", &path)
+            .args(args)
+            .output()
+            .expect("failed to run rgrc")
+    };
+    let url = format!("file://{}/index.txt", index.display());
+
+    // A checksum that does not match installs nothing
+    std::fs::write(
+        index.join("index.txt"),
+        format!("{} conf.mytool ^mytool\\b\n", "0".repeat(64)),
+    )
+    .unwrap();
+    let output = run(&["fetch-configs", &url]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("checksum mismatch"));
+    assert!(!config_dir.join("conf.mytool").exists());
+
+    std::fs::write(
+        index.join("index.txt"),
+        format!(
+            "# sha256 name regex\n{} conf.mytool ^mytool\\b\n",
+            rgrc::fetch::sha256_hex(config.as_bytes())
+        ),
+    )
+    .unwrap();
+    let output = run(&["fetch-configs", &url]);
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(config_dir.join("conf.mytool")).unwrap(),
+        config
+    );
+    assert!(
+        std::fs::read_to_string(config_dir.join("rgrc.local"))
+            .unwrap()
+            .contains("\n^mytool\\b\nconf.mytool\n")
+    );
+    assert_eq!(
+        run(&["--color=on", "mytool"]).stdout,
+        b"\x1b[31mfail\x1b[0m\n"
+    );
+
+    // Without an index there is nothing to fetch
+    let output = run(&["fetch-configs"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// `rgrc config` installs configs at a version (pinning them) or at the newest,
+/// updates the unpinned ones, reinstalls what rgrc.lock records and removes them.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_config_package_management() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    let bin = dir.path().join("bin");
+    let index = dir.path().join("index");
+    let config_dir = dir.path().join("config");
+    std::fs::create_dir(&bin).unwrap();
+    std::fs::create_dir(&index).unwrap();
+    // A stand-in for curl reading file:// URLs
+    std::fs::write(
+        bin.join("curl"),
+        "#!/bin/sh\nfor url; do :; done\nexec cat \"${url#file://}\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(bin.join("curl"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let mut lines = String::new();
+    for (version, config) in [("1.9", "regexp=old\n"), ("1.10", "regexp=new\n")] {
+        let file = format!("conf.mytool@{}", version);
+        std::fs::write(index.join(&file), config).unwrap();
+        lines.push_str(&format!(
+            "{} {} ^mytool\\b\n",
+            rgrc::fetch::sha256_hex(config.as_bytes()),
+            file
+        ));
+    }
+    std::fs::write(index.join("index.txt"), lines).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", &config_dir)
+            .env(
+                "RGRC_CONFIG_INDEX",
+                format!("file://{}/index.txt", index.display()),
+            )
+            .env("PATH", &path)
+            .args(args)
+            .output()
+            .expect("failed to run rgrc");
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    let installed = || std::fs::read_to_string(config_dir.join("conf.mytool")).unwrap();
+
+    // An explicit version is installed and pinned, so update leaves it
+    let (code, stdout) = run(&["config", "install", "mytool@1.9"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("conf.mytool (1.9)"), "{}", stdout);
+    assert_eq!(installed(), "regexp=old\n");
+    assert!(
+        run(&["config", "list"])
+            .1
+            .starts_with("conf.mytool@1.9 pinned  '^mytool\\b'")
+    );
+    assert_eq!(run(&["config", "update"]).1, "all configs are up to date\n");
+
+    // Without a version the newest is installed, and later updated
+    run(&["config", "install", "conf.mytool"]);
+    assert_eq!(installed(), "regexp=new\n");
+    assert!(run(&["config", "list"]).1.starts_with("conf.mytool@1.10  "));
+
+    // Without names, what rgrc.lock records is installed again
+    std::fs::remove_file(config_dir.join("conf.mytool")).unwrap();
+    assert_eq!(run(&["config", "install"]).0, Some(0));
+    assert_eq!(installed(), "regexp=new\n");
+
+    assert_eq!(run(&["config", "remove", "mytool"]).1, "removed mytool\n");
+    assert!(!config_dir.join("conf.mytool").exists());
+    assert!(
+        !std::fs::read_to_string(config_dir.join("rgrc.local"))
+            .unwrap()
+            .contains("mytool")
+    );
+    assert_eq!(run(&["config", "remove", "mytool"]).0, Some(1));
+    assert_eq!(run(&["config", "install", "mytool@3.0"]).0, Some(1));
+}
+
+/// `rgrc new-config` writes a starter config from sample output, and does not
+/// overwrite one that exists.
+#[test]
+fn test_new_config_writes_a_starter_config() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("out.txt"), "deploy failed on 10.0.0.7\n").unwrap();
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .current_dir(dir.path())
+            .args(["new-config", "--sample", "out.txt", "/opt/bin/mytool"])
+            .output()
+            .expect("failed to run rgrc")
+    };
+
+    let output = run();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("  deploy failed on 10.0.0.7\n"));
+    assert!(stdout.ends_with("^([/\\w\\.]+\\/)?mytool\\b\nconf.mytool\n"));
+    let config = std::fs::read_to_string(dir.path().join("conf.mytool")).unwrap();
+    assert!(config.contains("# error keywords (matches 1 of 1 sample lines)\n"));
+
+    let output = run();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("conf.mytool")).unwrap(),
+        config
+    );
+}
+
+/// `rgrc suggest` prints rules for the sample on stdin, most lines matched first.
+#[test]
+fn test_suggest_prints_rules_for_a_sample() {
+    use std::io::Write;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .arg("suggest")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run rgrc");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"disk /dev/sda1 95% full\nWARNING: disk /dev/sdb1 at 80%\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# Rules suggested by `rgrc suggest` for 2 sample lines"));
+    assert!(stdout.contains("# percentages (matches 2 of 2 sample lines)\n"));
+    assert!(stdout.contains("# paths (matches 2 of 2 sample lines)\n"));
+    assert!(stdout.contains("# UPPERCASE warning words (WARNING) (matches 1 of 2 sample lines)\n"));
+    assert!(!stdout.contains("IPv4"));
+}
+
+/// The bash plugin sets up the aliases (unless RGRC_NO_ALIASES is set) and the
+/// completions in an interactive shell.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_bash_plugin() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["plugin", "bash"])
+        .output()
+        .expect("failed to run rgrc");
+    assert!(output.status.success());
+    let plugin = dir.path().join("rgrc.bash");
+    std::fs::write(&plugin, &output.stdout).unwrap();
+    let rgrc_dir = std::path::Path::new(env!("CARGO_BIN_EXE_rgrc"))
+        .parent()
+        .unwrap();
+    let run = |no_aliases: bool| {
+        let mut bash = Command::new("bash");
+        bash.env_clear()
+            .env("HOME", dir.path())
+            .env("XDG_CACHE_HOME", dir.path().join("cache"))
+            .env("PATH", format!("{}:/usr/bin:/bin", rgrc_dir.display()))
+            .args(["--norc", "-i", "-c"])
+            .arg(format!(
+                "source {}; alias diff; complete -p rgrc",
+                plugin.display()
+            ))
+            .stdin(Stdio::null());
+        if no_aliases {
+            bash.env("RGRC_NO_ALIASES", "1");
+        }
+        bash.output()
+    };
+    // Nothing to check without bash
+    let Ok(output) = run(false) else { return };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("complete -F _rgrc_completions rgrc"),
+        "{}",
+        stdout
+    );
+    if stdout.contains("alias diff=") {
+        assert!(dir.path().join("cache/rgrc/aliases.bash").exists());
+    }
+    let stdout = String::from_utf8_lossy(&run(true).unwrap().stdout).into_owned();
+    assert!(!stdout.contains("alias diff="));
+    assert!(stdout.contains("complete -F _rgrc_completions rgrc"));
+}
+
+/// --shell runs a command line with $SHELL -c, coloured by the rules of the last
+/// pipeline stage that has some, and exits with the shell's status.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_shell_runs_a_command_line() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("conf.ping"), "regexp=fail\ncolours=red\n").unwrap();
+    std::fs::write(dir.path().join("conf.ss"), "regexp=ok\ncolours=green\n").unwrap();
+    std::fs::write(
+        dir.path().join("rgrc.conf"),
+        "^ping\nconf.ping\n^ss\nconf.ss\n",
+    )
+    .unwrap();
+    // Stand-ins for ping, and for ss passing on what it reads
+    for (name, script) in [("ping", "echo ok\necho fail"), ("ss", "cat")] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |line: &str| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", dir.path())
+            .env("PATH", &path)
+            .env("SHELL", "/bin/sh")
+            .args(["--color=on", "--shell", line])
+            .output()
+            .expect("failed to run rgrc")
+    };
+
+    // tail has no rules here, so those of ping apply
+    let output = run("ping | tail -n 1; exit 3");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"\x1b[31mfail\x1b[0m\n");
+
+    let output = run("ping | ss");
+    assert_eq!(output.stdout, b"\x1b[32mok\x1b[0m\nfail\n");
+}
+
+/// -q stops the command at the first matching line and exits 0 without output;
+/// --exit-on-match=RULE waits for that rule, exiting 1 when it never matches.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_exit_on_match() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("conf.ping"),
+        "regexp=ok\ncolours=green\n-\nregexp=fail\ncolours=red\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("rgrc.conf"), "^ping\nconf.ping\n").unwrap();
+    let ping = dir.path().join("ping");
+    std::fs::write(
+        &ping,
+        // exec, so no process outlives the one rgrc stops while holding stderr open
+        "#!/bin/sh\necho ok\necho fail\nexec sleep \"${PING_SLEEP:-0}\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&ping, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |args: &[&str], sleep: &str| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", dir.path())
+            .env("PATH", &path)
+            .env("PING_SLEEP", sleep)
+            .args(args)
+            .arg("ping")
+            .output()
+            .expect("failed to run rgrc")
+    };
+
+    let started = std::time::Instant::now();
+    let output = run(&["-q"], "10");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    assert_eq!(run(&["--exit-on-match=1"], "0").status.code(), Some(0));
+    let output = run(&["--exit-on-match=2"], "0");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+/// --count reports the matching lines at exit, also when the output is not coloured
+/// (which leaves it as the command wrote it); --fail-above turns too many into status 1.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_bell_on_rings_for_any_command() {
+    // printf has no config of its own: the bell rule alone leaves the text as it is
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["--color=on", "--bell-on=down", "printf", "up\\ndown\\n"])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"up\n\x07down\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["--bell-on=(", "printf", "x"])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+#[cfg(all(unix, not(target_os = "macos"), target_arch = "x86_64"))]
+fn test_notify_on_sends_one_notification_per_burst() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    let sent = dir.path().join("sent");
+    let notify_send = dir.path().join("notify-send");
+    std::fs::write(
+        &notify_send,
+        format!("#!/bin/sh\nprintf '[%s]' \"$@\" >> '{}'\n", sent.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&notify_send, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    // Not coloured, yet every line is watched
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .env("PATH", &path)
+        .args(["--color=off", "--notify-on=CrashLoop", "printf"])
+        .arg("web-1 Running\\nweb-1 CrashLoopBackOff\\nweb-2 CrashLoopBackOff\\n")
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        output.stdout,
+        b"web-1 Running\nweb-1 CrashLoopBackOff\nweb-2 CrashLoopBackOff\n"
+    );
+
+    // The notification is sent in the background
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !sent.exists() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(
+        std::fs::read_to_string(&sent).unwrap(),
+        "[--app-name=rgrc][--][printf][web-1 CrashLoopBackOff]"
+    );
+}
+
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_only_matching_lines() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("conf.gcc"),
+        "regexp=warning\ncolours=yellow\n-\nregexp=error\ncolours=red\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("rgrc.conf"), "^gcc\nconf.gcc\n").unwrap();
+    let gcc = dir.path().join("gcc");
+    std::fs::write(
+        &gcc,
+        "#!/bin/sh\necho compiling\necho warning: a\necho linking\necho error: b\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&gcc, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    // Not coloured into a pipe, the rules still pick the lines
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .env("RGRC_CONFIG_DIR", dir.path())
+        .env("PATH", &path)
+        .args(["--only-matching-lines", "gcc"])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"warning: a\nerror: b\n");
+}
+
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_timestamps_prefix_each_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["--timestamps=relative", "printf", "a\\n\\nb\\n"])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    for (line, text) in lines.iter().zip(["a", "", "b"]) {
+        // 00:00:00.012 and a space
+        let (time, rest) = line.split_at(13);
+        assert!(
+            time.starts_with("00:00:") && time.ends_with(' '),
+            "{}",
+            line
+        );
+        assert_eq!(rest, text);
+    }
+}
+
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_strip_removes_escape_sequences() {
+    use std::io::Write;
+    let coloured = "\x1b[1;31merror\x1b[0m: \x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\\n";
+
+    // On stdin
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .arg("--strip")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run rgrc");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(coloured.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"error: docs\n");
+
+    // From a command, keeping its exit status
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args([
+            "--strip",
+            "sh",
+            "-c",
+            "printf '%s' \"$1\"; exit 3",
+            "sh",
+            coloured,
+        ])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"error: docs\n");
+}
+
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_count_matches() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("conf.gcc"),
+        "regexp=warning\ncolours=yellow\n-\nregexp=error\ncolours=red\nreplace=ERROR\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("rgrc.conf"), "^gcc\nconf.gcc\n").unwrap();
+    let gcc = dir.path().join("gcc");
+    std::fs::write(
+        &gcc,
+        "#!/bin/sh\necho warning: a\necho error: b\necho warning: c\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&gcc, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("RGRC_CONFIG_DIR", dir.path())
+            .env("PATH", &path)
+            .args(args)
+            .arg("gcc")
+            .output()
+            .expect("failed to run rgrc")
+    };
+
+    let output = run(&["--count"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"warning: a\nerror: b\nwarning: c\n");
+    assert_eq!(output.stderr, b"rgrc: 3 line(s) matched\n");
+
+    let output = run(&["--count=1", "--fail-above=1"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stderr, b"rgrc: 1 line(s) matched rule 1 (error)\n");
+
+    let output = run(&["--color=on", "--count=0", "--fail-above", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("ERROR"));
+    assert_eq!(
+        output.stderr,
+        b"rgrc: 2 line(s) matched rule 0 (warning), over the limit of 1\n"
+    );
+}
+
+/// --timeout stops a command that runs too long and exits with 124; one that
+/// finishes in time keeps its own status.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_timeout_stops_the_command() {
+    let started = std::time::Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["--color=on", "--timeout=0.2", "sleep", "10"])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(124));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .args(["--timeout", "10", "sh", "-c", "exit 3"])
+        .output()
+        .expect("failed to run rgrc");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+/// `journalctl -f` never ends, so each entry must be shown, coloured by the
+/// bundled conf.journalctl, as soon as journalctl writes it.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_journalctl_follow_is_shown_as_it_comes() {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::TempDir::new().unwrap();
+    let journalctl = dir.path().join("journalctl");
+    std::fs::write(
+        &journalctl,
+        "#!/bin/sh\necho 'Jan 15 10:23:45 myhost sshd[2214]: Accepted publickey'\nexec sleep 10\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&journalctl, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let share = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("share");
+
+    let mut rgrc = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+        .env("RGRC_CONFIG_DIR", &share)
+        .env("PATH", &path)
+        .args(["--color=on", "journalctl", "-f"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run rgrc");
+    let mut stdout = BufReader::new(rgrc.stdout.take().unwrap());
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = stdout.read_line(&mut line);
+        let _ = sender.send(line);
+    });
+    let line = receiver.recv_timeout(std::time::Duration::from_secs(5));
+    let _ = rgrc.kill();
+    let _ = rgrc.wait();
+
+    let line = line.expect("the entry was not shown while journalctl ran");
+    assert!(line.contains("\x1b["), "{:?}", line);
+    assert_eq!(
+        String::from_utf8_lossy(&rgrc::utils::strip_ansi_bytes(line.as_bytes())),
+        "Jan 15 10:23:45 myhost sshd[2214]: Accepted publickey\n"
+    );
+}
+
+/// Ctrl-C reaches the command as it would without rgrc: rgrc lives on to
+/// colorize what the command writes as it handles the interrupt, and ends by
+/// SIGINT itself when the command did.
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn test_ctrl_c_is_left_to_the_command() {
+    use std::io::{BufRead, BufReader, Read};
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+    // Interrupt rgrc's process group, as the terminal does, once the command runs
+    let interrupt = |script: &str| {
+        let mut rgrc = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .args(["--color=on", "sh", "-c", script])
+            .process_group(0)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to run rgrc");
+        let mut stdout = BufReader::new(rgrc.stdout.take().unwrap());
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(line, "ready\n");
+        let group = format!("-{}", rgrc.id());
+        let killed = Command::new("kill").args(["-INT", "--", &group]).status();
+        assert!(killed.unwrap().success());
+        let mut rest = String::new();
+        stdout.read_to_string(&mut rest).unwrap();
+        (rgrc.wait().unwrap(), rest)
+    };
+
+    let handled = "trap 'echo statistics; exit 0' INT; echo ready; while :; do sleep 0.05; done";
+    let (status, rest) = interrupt(handled);
+    assert!(status.success(), "{:?}", status);
+    assert_eq!(rest, "statistics\n");
+
+    let (status, _) = interrupt("echo ready; exec sleep 10");
+    assert_eq!(status.signal(), Some(2));
+}
+
 /// Lines 149-154: ColorMode::On forces colorization
 /// Tests that --color=on enables colorization even when stdout is not a terminal.
 /// This verifies the ColorMode::On branch sets should_colorize=true unconditionally.
@@ -617,6 +1537,468 @@ mod cli_integration_tests {
         assert!(!stdout.contains("alias grep='"));
     }
 
+    /// CLI Test: git, cargo and npm are colorized by rgrc but never aliased
+    ///
+    /// An alias would pipe every git command, turning off git's own colours and
+    /// pager, and take the terminal from the programs `cargo run` and `npm run` start.
+    #[test]
+    fn test_all_aliases_leaves_git_and_cargo_alone() {
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .arg("--all-aliases")
+            .output()
+            .expect("failed to run rgrc --all-aliases");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("alias brew='"));
+        assert!(!stdout.contains("alias git="));
+        assert!(!stdout.contains("alias cargo="));
+        assert!(!stdout.contains("alias npm="));
+    }
+
+    /// CLI Test: --shell powershell --install writes the aliases into the profile once
+    ///
+    /// Without pwsh in PATH the profile is PowerShell 7's default location, under
+    /// ~/.config on Unix. Installing again replaces the block instead of adding one.
+    #[test]
+    fn test_install_powershell_aliases() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let bin = td.path().join("bin");
+        std::fs::create_dir(&bin).unwrap();
+        let profile = td
+            .path()
+            .join(".config/powershell/Microsoft.PowerShell_profile.ps1");
+        std::fs::create_dir_all(profile.parent().unwrap()).unwrap();
+        std::fs::write(&profile, "Import-Module posh-git\n").unwrap();
+
+        for except in ["ls", "ls,ping"] {
+            let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+                .env("HOME", td.path())
+                .env("PATH", &bin)
+                .env_remove("XDG_CONFIG_HOME")
+                .env_remove("USERPROFILE")
+                .args(["--all-aliases", "--shell", "powershell", "--install"])
+                .args(["--except", except])
+                .output()
+                .expect("failed to run rgrc --install");
+            assert!(output.status.success(), "{:?}", output);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(stdout.contains(&profile.display().to_string()));
+        }
+
+        let text = std::fs::read_to_string(&profile).unwrap();
+        assert!(text.starts_with("Import-Module posh-git\n"));
+        assert_eq!(text.matches("# >>> rgrc aliases >>>").count(), 1);
+        assert!(text.contains("Remove-Item Alias:ps -Force -ErrorAction Ignore\n"));
+        assert!(text.contains(" ps @args }\n"));
+        assert!(!text.contains(" ping @args }"));
+        assert!(!text.contains("alias "));
+    }
+
+    /// CLI Test: --paths lists the search order without creating anything
+    #[test]
+    fn test_paths_lists_search_order() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let conf_dir = td.path().join("rules");
+        std::fs::create_dir(&conf_dir).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", &conf_dir)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("RGRC_CACHE_DIR")
+            .arg("--paths")
+            .output()
+            .expect("failed to run rgrc --paths");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mappings = stdout.find("Command mappings").unwrap();
+        let dirs = stdout.find("Config directories").unwrap();
+        assert!(mappings < dirs);
+        assert!(stdout.contains(&format!("[ok]         {}\n", conf_dir.display())));
+        assert!(stdout.contains(&format!(
+            "[missing]    {}\n",
+            td.path().join(".config/rgrc/rgrc.conf").display()
+        )));
+        // A diagnostic must not populate the cache
+        assert!(!td.path().join(".cache").exists());
+    }
+
+    /// CLI Test: --explain reports the matched mapping, config file and rule count
+    #[test]
+    fn test_explain_shows_resolution() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let conf_dir = td.path().join("rules");
+        std::fs::create_dir(&conf_dir).unwrap();
+        std::fs::write(conf_dir.join("rgrc.conf"), "^id\nconf.myid\n").unwrap();
+        std::fs::write(
+            conf_dir.join("conf.myid"),
+            "regexp=uid\ncolours=red\n-\nregexp=(unclosed\ncolours=blue\n",
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", &conf_dir)
+            .args(["--explain", "id", "-u"])
+            .output()
+            .expect("failed to run rgrc --explain");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Pseudo-command: \"id -u\""));
+        assert!(stdout.contains("'^id' -> conf.myid"));
+        assert!(stdout.contains(&format!("{}", conf_dir.join("conf.myid").display())));
+        assert!(stdout.contains("2 loaded, 1 compiled"));
+        assert!(stdout.contains("rule #1 failed to compile: '(unclosed'"));
+        // The command itself is not run
+        assert!(!stdout.contains("uid="));
+    }
+
+    /// CLI Test: a mapping listing several configs loads their rules in order
+    #[test]
+    fn test_mapping_concatenates_listed_configs() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let conf_dir = td.path().join("rules");
+        std::fs::create_dir(&conf_dir).unwrap();
+        std::fs::write(
+            conf_dir.join("rgrc.conf"),
+            "^id\nconf.myid, conf.missing conf.common\n",
+        )
+        .unwrap();
+        std::fs::write(conf_dir.join("conf.myid"), "regexp=uid\ncolours=red\n").unwrap();
+        std::fs::write(
+            conf_dir.join("conf.common"),
+            "regexp=\\d+\ncolours=blue\n-\nregexp=uid\ncolours=green\n",
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", &conf_dir)
+            .args(["--explain", "id"])
+            .output()
+            .expect("failed to run rgrc --explain");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("conf.missing not found"));
+        let myid = stdout
+            .find(&conf_dir.join("conf.myid").display().to_string())
+            .unwrap();
+        let common = stdout
+            .find(&conf_dir.join("conf.common").display().to_string())
+            .unwrap();
+        assert!(myid < common);
+        assert!(stdout.contains("3 loaded, 3 compiled"));
+
+        // The rules of conf.common come after those of conf.myid, so they win on "uid"
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", &conf_dir)
+            .args(["--color=on", "-c", "id"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to run rgrc");
+        use std::io::Write;
+        child.stdin.take().unwrap().write_all(b"uid 42\n").unwrap();
+        let output = child.wait_with_output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("\x1b[32muid\x1b[0m"), "{:?}", stdout);
+        assert!(stdout.contains("\x1b[34m42\x1b[0m"), "{:?}", stdout);
+    }
+
+    /// CLI Test: --format-in=csv colours the columns of any command's output
+    #[test]
+    fn test_format_in_csv_colours_columns() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let run = |color: &str| {
+            let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+                .env("HOME", td.path())
+                .env("RGRC_CONFIG_DIR", td.path())
+                .args([color, "--format-in=csv", "printf", "n,\"x, y\"\\n"])
+                .output()
+                .expect("failed to run rgrc");
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+
+        // printf has no config of its own
+        assert_eq!(
+            run("--color=on"),
+            "\x1b[36mn\x1b[0m,\x1b[32m\"x, y\"\x1b[0m\n"
+        );
+        assert_eq!(run("--color=off"), "n,\"x, y\"\n");
+    }
+
+    /// CLI Test: --format-in=json --pretty re-indents and colours JSON, only when colorizing
+    #[test]
+    fn test_format_in_json_pretty_prints() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let run = |color: &str| {
+            let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+                .env("HOME", td.path())
+                .env("RGRC_CONFIG_DIR", td.path())
+                .args([
+                    color,
+                    "--format-in=json",
+                    "--pretty",
+                    "printf",
+                    "{\"a\":[1]}",
+                ])
+                .output()
+                .expect("failed to run rgrc");
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+
+        assert_eq!(
+            run("--color=on"),
+            "{\n  \x1b[1;34m\"a\"\x1b[0m: [\n    \x1b[36m1\x1b[0m\n  ]\n}\n"
+        );
+        // Without colours the document is passed through as it is
+        assert_eq!(run("--color=off"), "{\"a\":[1]}");
+    }
+
+    /// CLI Test: loglevel= in a config pulls in the built-in severity rules
+    #[test]
+    fn test_loglevel_line_in_config() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        std::fs::write(td.path().join("rgrc.conf"), "^app\nconf.app\n").unwrap();
+        std::fs::write(
+            td.path().join("conf.app"),
+            "regexp=^\\d+\ncolours=blue\n-\nloglevel=line\n",
+        )
+        .unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", td.path())
+            .args(["--format=markup", "-c", "app"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to run rgrc");
+        use std::io::Write;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"12 INFO up\n13 level=error disk\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "[blue]12[/] [green]INFO[/] up\n[red]13 level=[/][bold red]error[/][red] disk[/]\n"
+        );
+    }
+
+    /// CLI Test: --compat=grc reads a config with Python grc's quirks
+    #[test]
+    fn test_compat_grc_reads_configs_as_grc_does() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        std::fs::write(td.path().join("rgrc.conf"), "^app\nconf.app\n").unwrap();
+        std::fs::write(
+            td.path().join("conf.app"),
+            "regexp=(\\w+)=\\${(\\w+)}\ncolours=bold,yellow,previous underline\n-\n\
+             regexp=err\ncolours=red beep\ncount=once\n-\n\
+             regexp=x\ncolours=blue\ncount=previous\n",
+        )
+        .unwrap();
+
+        let run = |compat: &str| {
+            let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+                .env("HOME", td.path())
+                .env("RGRC_CONFIG_DIR", td.path())
+                .args([compat, "--format=markup", "-c", "app"])
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .expect("failed to run rgrc");
+            use std::io::Write;
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(b"path=${HOME} err err x x\n")
+                .unwrap();
+            let output = child.wait_with_output().unwrap();
+            (
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )
+        };
+
+        let (stdout, stderr) = run("--compat=grc");
+        assert_eq!(
+            stdout,
+            "[yellow]path[/][bold]=${[/][underline yellow]HOME[/][bold]}[/] [beep red]err[/] err [blue]x[/] x\n"
+        );
+        assert_eq!(stderr, "");
+
+        // rgrc reports each quirk and colours what is left
+        let (stdout, stderr) = run("--compat=rgrc");
+        assert_eq!(
+            stdout,
+            "path=${HOME} [beep red]err[/] err [blue]x[/] [blue]x[/]\n"
+        );
+        assert!(stderr.contains("unhandled style: previous"), "{}", stderr);
+        assert!(
+            stderr.contains("Unknown count value: previous"),
+            "{}",
+            stderr
+        );
+    }
+
+    /// CLI Test: rules run by priority, then in the order of the listed configs
+    #[test]
+    fn test_priority_orders_rules_across_configs() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let conf_dir = td.path().join("rules");
+        std::fs::create_dir(&conf_dir).unwrap();
+        std::fs::write(conf_dir.join("rgrc.conf"), "^id\nconf.myid conf.local\n").unwrap();
+        std::fs::write(
+            conf_dir.join("conf.myid"),
+            "regexp=first\ncolours=red\n-\nregexp=last\ncolours=red\npriority=-1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            conf_dir.join("conf.local"),
+            "regexp=second\ncolours=blue\n-\nregexp=top\ncolours=blue\npriority=5\n",
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", &conf_dir)
+            .args(["--show-config", "id"])
+            .output()
+            .expect("failed to run rgrc --show-config");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let regexps: Vec<&str> = stdout
+            .lines()
+            .filter_map(|line| line.strip_prefix("regexp="))
+            .collect();
+        assert_eq!(regexps, ["top", "first", "second", "last"]);
+        assert!(stdout.contains("priority=5\n"));
+    }
+
+    /// CLI Test: --show-config prints the effective rules in conf syntax
+    #[test]
+    fn test_show_config_prints_conf_syntax() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let conf_dir = td.path().join("rules");
+        std::fs::create_dir(&conf_dir).unwrap();
+        std::fs::write(conf_dir.join("rgrc.conf"), "^id\nconf.myid\n").unwrap();
+        std::fs::write(
+            conf_dir.join("conf.myid"),
+            "regexp=(uid)=(\\d+)\ncolours=bold red,green\n\nregexp=secret\nreplace=***\ncount=stop\n",
+        )
+        .unwrap();
+
+        for args in [&["--show-config", "id"][..], &["--show-config", "-c", "id"]] {
+            let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+                .env("HOME", td.path())
+                .env("RGRC_CONFIG_DIR", &conf_dir)
+                .args(args)
+                .output()
+                .expect("failed to run rgrc --show-config");
+
+            assert!(output.status.success());
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert_eq!(
+                stdout,
+                "# Effective rgrc rules for \"id\" (2 rules)\n\
+                 regexp=(uid)=(\\d+)\ncolours=bold red,green\ncount=more\n-\n\
+                 regexp=secret\ncount=stop\nreplace=***\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_repl_colorizes_piped_lines() {
+        use std::io::Write;
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        std::fs::write(td.path().join("rgrc.conf"), "^myid\nconf.myid\n").unwrap();
+        std::fs::write(
+            td.path().join("conf.myid"),
+            "regexp=uid=(\\d+)\ncolours=default,green\n",
+        )
+        .unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", td.path())
+            .args(["repl", "-c", "myid"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to run rgrc repl");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"uid=1000\n:why\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Loaded 1 rules for 'myid'."));
+        // No prompt when the input is not a terminal
+        assert!(!stdout.contains("rgrc> "));
+        assert!(stdout.contains("uid=\x1b[32m1000\x1b[0m"));
+        assert!(stdout.contains("group 1   4..8   \"1000\" -> green"));
+    }
+
+    /// conf.NAME.toml is loaded in preference to conf.NAME next to it
+    #[cfg(feature = "toml-configs")]
+    #[test]
+    fn test_toml_config_preferred() {
+        use std::io::Write;
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        std::fs::write(td.path().join("rgrc.conf"), "^myid\nconf.myid\n").unwrap();
+        std::fs::write(td.path().join("conf.myid"), "regexp=uid\ncolours=red\n").unwrap();
+        std::fs::write(
+            td.path().join("conf.myid.toml"),
+            "[[rule]]\nregexp = 'uid=(\\d+)'\ncolours = ['default', 'green']\n",
+        )
+        .unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .env("HOME", td.path())
+            .env("RGRC_CONFIG_DIR", td.path())
+            .args(["--colour=on", "-c", "myid"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to run rgrc");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"uid=1000\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "uid=\x1b[32m1000\x1b[0m\n"
+        );
+    }
+
     /// CLI Test: --flush-cache rebuilds embedded config cache
     ///
     /// Tests the cache rebuild mechanism for embedded configs.
@@ -645,6 +2027,48 @@ mod cli_integration_tests {
         );
     }
 
+    /// CLI Test: --cache-dir and RGRC_CACHE_DIR relocate the cache
+    ///
+    /// The flag wins over RGRC_CACHE_DIR, which wins over XDG_CACHE_HOME;
+    /// nothing is written under HOME in either case.
+    #[cfg(feature = "embed-configs")]
+    #[test]
+    fn test_cache_dir_flag_and_env() {
+        use tempfile::TempDir;
+        let td = TempDir::new().unwrap();
+        let home = td.path().join("home");
+        let from_env = td.path().join("env-cache");
+        let from_flag = td.path().join("flag-cache");
+        let rgrc = || {
+            let mut cmd = Command::new(env!("CARGO_BIN_EXE_rgrc"));
+            cmd.env("HOME", &home)
+                .env("XDG_CACHE_HOME", td.path().join("xdg"))
+                .env("RGRC_CACHE_DIR", &from_env);
+            cmd
+        };
+
+        let output = rgrc().arg("--flush-cache").output().unwrap();
+        assert!(output.status.success());
+        assert!(from_env.join("rgrc.conf").exists());
+
+        let output = rgrc()
+            .args(["--cache-dir", from_flag.to_str().unwrap(), "--flush-cache"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(from_flag.join("rgrc.conf").exists());
+        assert!(String::from_utf8_lossy(&output.stdout).contains(from_flag.to_str().unwrap()));
+
+        let output = rgrc()
+            .env_remove("RGRC_CACHE_DIR")
+            .arg("--flush-cache")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(td.path().join("xdg/rgrc/rgrc.conf").exists());
+        assert!(!home.exists());
+    }
+
     /// CLI Test: Piped child command output is forwarded correctly
     ///
     /// Verifies that rgrc correctly pipes and forwards the child process's stdout.
@@ -683,4 +2107,29 @@ mod cli_integration_tests {
             "Output should not contain ANSI escape codes when piped to a non-TTY"
         );
     }
+
+    /// CLI Test: --log writes an uncolored transcript while stdout stays colored
+    #[test]
+    fn test_log_file_receives_plain_copy() {
+        let log_path = std::env::temp_dir().join(format!("rgrc-log-{}.txt", std::process::id()));
+
+        let output = Command::new(env!("CARGO_BIN_EXE_rgrc"))
+            .arg("--color=on")
+            .arg(format!("--log={}", log_path.display()))
+            .arg("id")
+            .output()
+            .expect("failed to run rgrc");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("\x1b["), "stdout should still be colorized");
+
+        let logged = std::fs::read_to_string(&log_path).expect("log file should exist");
+        let _ = std::fs::remove_file(&log_path);
+        assert!(logged.contains("uid="));
+        assert!(
+            !logged.contains('\x1b'),
+            "log should not contain ANSI codes"
+        );
+    }
 }
@@ -0,0 +1,70 @@
+// Tests for the grcat-compatible rgrcat binary
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+/// Run rgrcat with `args`, feeding `input` on stdin, with configs looked up in `config_dir`.
+fn rgrcat(args: &[&str], input: &str, config_dir: &std::path::Path) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rgrcat"))
+        .args(args)
+        .env("RGRC_CONFIG_DIR", config_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run rgrcat");
+    // rgrcat may exit without reading its input (usage errors, missing configs)
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_rgrcat_colorizes_with_named_config_and_path() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("conf.mine"),
+        "regexp=ERROR\ncolours=bold red\n",
+    )
+    .unwrap();
+
+    // Colours even though stdout is a pipe, as grcat does
+    let output = rgrcat(&["conf.mine"], "ERROR: disk\nok\n", dir.path());
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\x1b[1;31mERROR\x1b[0m: disk\nok\n"
+    );
+
+    let path = dir.path().join("conf.mine");
+    let output = rgrcat(&[path.to_str().unwrap()], "ERROR\n", dir.path());
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\x1b[1;31mERROR\x1b[0m\n"
+    );
+}
+
+#[test]
+fn test_rgrcat_reports_missing_configs_and_usage() {
+    let dir = TempDir::new().unwrap();
+
+    let output = rgrcat(&["conf.no-such-config"], "x\n", dir.path());
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "rgrcat: config not found: 'conf.no-such-config'\n"
+    );
+    assert!(output.stdout.is_empty());
+
+    let output = rgrcat(&["./missing/conf.x"], "x\n", dir.path());
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = rgrcat(&[], "", dir.path());
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage:"));
+
+    let output = rgrcat(&["--help"], "", dir.path());
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("CONFNAME"));
+}
@@ -404,7 +404,7 @@ mod rgrv {
 
         // Create conf file with bright colors
         let mut file = fs::File::create(&conf_file).unwrap();
-        writeln!(file, r"^\d+ bright-red bright-green bright-blue").unwrap();
+        writeln!(file, r"^\d+ bright_red bright_green bright_blue").unwrap();
         drop(file);
 
         let output = Command::new(get_rgrv_binary())
@@ -753,4 +753,669 @@ mod rgrv {
 
         assert!(!output.status.success());
     }
+
+    /// The golden samples shipped in tests/samples match the bundled configs
+    #[test]
+    fn test_sample_tests_pass_for_bundled_configs() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let output = Command::new(get_rgrv_binary())
+            .env("RGRC_CONFIG_DIR", root.join("share"))
+            .arg("test")
+            .arg(root.join("tests/samples"))
+            .output()
+            .expect("Failed to execute rgrv");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", stdout);
+        assert!(stdout.contains(", 0 failed"));
+    }
+
+    /// Missing and outdated expected files fail; --update rewrites them
+    #[test]
+    fn test_sample_tests_report_diffs_and_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.demo");
+        fs::write(&conf, "regexp=(\\d+)ms\ncolours=default,green\n").unwrap();
+        fs::write(
+            temp_dir.path().join("conf.demo.sample"),
+            "took 12ms\n[ok]\n",
+        )
+        .unwrap();
+
+        let run = |args: &[&str]| {
+            Command::new(get_rgrv_binary())
+                .arg("test")
+                .args(args)
+                .arg(&conf)
+                .output()
+                .expect("Failed to execute rgrv")
+        };
+
+        let output = run(&[]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("create it with --update"));
+
+        assert!(run(&["--update"]).status.success());
+        let expected = temp_dir.path().join("conf.demo.expected");
+        assert_eq!(
+            fs::read_to_string(&expected).unwrap(),
+            "took [green]12[/]ms\n[[ok]\n"
+        );
+        assert!(run(&[]).status.success());
+
+        fs::write(&expected, "took 12ms\n[[ok]\n").unwrap();
+        let output = run(&[]);
+        assert!(!output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("(1 lines differ)"));
+        assert!(stdout.contains("line 1:"));
+        assert!(stdout.contains("took [green]12[/]ms"));
+    }
+
+    /// Rules behind a stop/block catch-all, shadowed rules and duplicates are flagged
+    #[test]
+    fn test_unreachable_and_shadowed_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.test");
+        fs::write(
+            &conf,
+            "regexp=ERROR: \\S+\ncolours=red\ncount=stop\n-\n\
+             regexp=ERROR: disk\ncolours=yellow\n-\n\
+             regexp=^WARN\ncolours=yellow\n-\nregexp=^WARN\ncolours=green\n-\n\
+             regexp=^INFO\ncount=stop\n-\nregexp=INFO done\n-\n\
+             regexp=.*\ncount=block\n-\nregexp=never\ncolours=blue\n-\n\
+             regexp=hidden\nskip=true\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("ShadowedRule"));
+        assert!(stderr.contains("conf.test\u{1b}[0m:\u{1b}[1;33m5\u{1b}[0m"));
+        assert!(
+            stderr.contains(
+                "matched first by the rule at line 1 (ERROR: \\S+), which has count=stop"
+            )
+        );
+        assert!(stderr.contains("Duplicate pattern: same regexp as the rule at line 8"));
+        assert!(stderr.contains("the rule at line 19 matches every line and has count=block"));
+        // skip=true rules run like any other, so they can be unreachable too
+        assert!(stderr.contains(":\u{1b}[1;33m25\u{1b}[0m"));
+        // An anchored stop rule does not shadow rules matching elsewhere in the line
+        assert!(!stderr.contains(":\u{1b}[1;33m17\u{1b}[0m"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 4 errors"));
+    }
+
+    /// --fix repairs common mistakes in place and keeps a backup
+    #[test]
+    fn test_fix_rewrites_conf_with_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.test");
+        let original = "regexp=from (\\d{1,3}.\\d{1,3}.\\d{1,3}.\\d{1,3})\n\
+                        colors=default,bright-red \ncount=stop  \n-\n\
+                        regexp=trailing \nreplace=x \n";
+        fs::write(&conf, original).unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .args(["conf", "--fix"])
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("5 fixes (backup: "));
+        assert!(stdout.contains("line 2: colors= → colours="));
+        assert!(stdout.contains("line 2: bright-red → bright_red"));
+        assert_eq!(
+            fs::read_to_string(&conf).unwrap(),
+            "regexp=from (\\d{1,3}\\.\\d{1,3}\\.\\d{1,3}\\.\\d{1,3})\n\
+             colours=default,bright_red\ncount=stop\n-\n\
+             regexp=trailing \nreplace=x \n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("conf.test.bak")).unwrap(),
+            original
+        );
+    }
+
+    /// grc.conf references must resolve in the config search path
+    #[test]
+    fn test_grc_reports_unknown_conf_with_suggestion() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let temp_dir = TempDir::new().unwrap();
+        let grc_conf = temp_dir.path().join("rgrc.conf");
+        fs::write(&grc_conf, "^ping\\b\nconf.pnig\n^df\\b\nconf.df\n").unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .env("RGRC_CONFIG_DIR", root.join("share"))
+            .arg("grc")
+            .arg(&grc_conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Config file conf.pnig not found in any config directory"));
+        assert!(stderr.contains("Did you mean conf.ping?"));
+        assert!(!stderr.contains("conf.df"));
+    }
+
+    /// Every config listed in a grc.conf entry is checked
+    #[test]
+    fn test_grc_checks_each_listed_conf() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let temp_dir = TempDir::new().unwrap();
+        let grc_conf = temp_dir.path().join("rgrc.conf");
+        fs::write(&grc_conf, "^ping\\b\nconf.ping, conf.pnig conf.df\n").unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .env("RGRC_CONFIG_DIR", root.join("share"))
+            .arg("grc")
+            .arg(&grc_conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Config file conf.pnig not found in any config directory"));
+        assert!(!stderr.contains("conf.ping,"));
+        assert!(!stderr.contains("Config file conf.df"));
+    }
+
+    /// Drift between the embedded configs, share/ and the cache is reported
+    #[cfg(feature = "embed-configs")]
+    #[test]
+    fn test_embedded_reports_drift() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let temp_dir = TempDir::new().unwrap();
+        let share = temp_dir.path().join("share");
+        fs::create_dir(&share).unwrap();
+        for entry in fs::read_dir(root.join("share")).unwrap() {
+            let entry = entry.unwrap();
+            fs::copy(entry.path(), share.join(entry.file_name())).unwrap();
+        }
+        fs::create_dir(temp_dir.path().join("etc")).unwrap();
+        fs::copy(
+            root.join("etc/rgrc.conf"),
+            temp_dir.path().join("etc/rgrc.conf"),
+        )
+        .unwrap();
+        let cache = temp_dir.path().join("cache");
+        fs::create_dir_all(cache.join("conf")).unwrap();
+
+        let run = || {
+            Command::new(get_rgrv_binary())
+                .env("RGRC_CACHE_DIR", &cache)
+                .env("RGRC_CONFIG_DIR", temp_dir.path().join("none"))
+                .arg("embedded")
+                .arg(&share)
+                .output()
+                .expect("Failed to execute rgrv")
+        };
+
+        let output = run();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", stdout);
+        assert!(stdout.contains(", 0 drifted"));
+
+        fs::write(share.join("conf.ping"), "regexp=changed\ncolours=red\n").unwrap();
+        fs::write(share.join("conf.extra"), "regexp=new\ncolours=red\n").unwrap();
+        fs::remove_file(share.join("conf.df")).unwrap();
+        fs::write(cache.join("conf/conf.dig"), "regexp=edited\ncolours=red\n").unwrap();
+
+        let output = run();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!output.status.success());
+        assert!(stdout.contains("embedded copy differs from"));
+        assert!(stdout.contains("conf.extra is not embedded"));
+        assert!(stdout.contains("conf.df is missing"));
+        assert!(stdout.contains("cached copy"));
+        assert!(stdout.contains(", 4 drifted"));
+    }
+
+    /// convert writes TOML for a conf file and grcat syntax for a .toml file
+    #[cfg(feature = "toml-configs")]
+    #[test]
+    fn test_convert_to_toml_and_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.demo");
+        fs::write(
+            &conf,
+            "# errors\nregexp=ERROR (\\d+)\ncolours=red,bold\ncount=stop\n",
+        )
+        .unwrap();
+        let toml = temp_dir.path().join("conf.demo.toml");
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("convert")
+            .arg(&conf)
+            .arg(&toml)
+            .output()
+            .expect("Failed to execute rgrv");
+        assert!(output.status.success());
+        assert_eq!(
+            fs::read_to_string(&toml).unwrap(),
+            "# errors\n[[rule]]\nregexp = 'ERROR (\\d+)'\ncolours = [\"red\", \"bold\"]\ncount = \"stop\"\n"
+        );
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("convert")
+            .arg(&toml)
+            .output()
+            .expect("Failed to execute rgrv");
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            fs::read_to_string(&conf).unwrap()
+        );
+
+        fs::write(&toml, "[[rule]]\nregex = 'x'\n").unwrap();
+        let output = Command::new(get_rgrv_binary())
+            .arg("convert")
+            .arg(&toml)
+            .output()
+            .expect("Failed to execute rgrv");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("unknown field `regex`"));
+    }
+
+    /// conf.NAME.toml files are checked against the schema, regexes and styles
+    #[cfg(feature = "toml-configs")]
+    #[test]
+    fn test_conf_validates_toml() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.demo.toml");
+        let run = || {
+            Command::new(get_rgrv_binary())
+                .arg("conf")
+                .arg(&conf)
+                .output()
+                .expect("Failed to execute rgrv")
+        };
+
+        fs::write(
+            &conf,
+            "[[rule]]\nregexp = 'ok (\\d+)'\ncolours = ['default', 'green']\n",
+        )
+        .unwrap();
+        assert!(run().status.success());
+
+        fs::write(
+            &conf,
+            "[[rule]]\nregexp = 'ok'\n\n[[rule]]\nregexp = '(x'\ncolours = ['purple']\n",
+        )
+        .unwrap();
+        let output = run();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("RegexError"));
+        assert!(stderr.contains("Unknown style: 'purple'"));
+        assert!(stderr.contains(":\u{1b}[1;33m5\u{1b}[0m"));
+
+        fs::write(&conf, "[[rule]]\nregexp = 'ok'\ncount = 'twice'\n").unwrap();
+        let stderr = String::from_utf8_lossy(&run().stderr).to_string();
+        assert!(stderr.contains("unknown variant `twice`"));
+    }
+
+    /// include= lines are followed; missing files and cycles are errors
+    #[test]
+    fn test_conf_checks_includes() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("conf.common"),
+            "regexp=\\d+\ncolours=green\ninclude=app\n",
+        )
+        .unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=ERROR\ncolours=red\ninclude=common\n-\ninclude=missing\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("in 'common': include cycle: 'app' is already being included"));
+        assert!(stderr.contains("included config 'missing' not found"));
+        assert!(!stderr.contains("FormatError"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    /// Styles are checked by rgrc's own parser, so hyphenated names are errors
+    #[test]
+    fn test_conf_styles_match_the_colorizer() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=(a) (b) (c)\ncolours=bright-red,'' color208,on_color300\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Unknown style: 'bright-red'"));
+        assert!(stderr.contains("Use 'bright_red' (rgrv conf --fix rewrites it)"));
+        assert!(stderr.contains("Unknown style: 'on_color300'"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    /// Style macros must be defined, and defined before they are used
+    #[test]
+    fn test_conf_checks_style_macros() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("conf.styles"),
+            "define ok=bold green\n",
+        )
+        .unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "include=styles\ndefine err=bold red\n-\nregexp=OK\ncolours=@ok\n-\n\
+             regexp=ERR\ncolours=@err,@warn\n-\ndefine warn=yellow\ndefine bad=purple\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("undefined style macro: @warn (defined later, at line 10)"));
+        assert!(stderr.contains("Unknown style: 'purple'"));
+        assert!(!stderr.contains("@ok"));
+        assert!(!stderr.contains("@err"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    #[test]
+    fn test_conf_checks_group_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=(?P<proto>tcp|udp) (?P<port>\\d+)\ncolours=default,name:port=yellow,name:proto=bold\n-\n\
+             regexp=(?P<host>\\S+)\ncolours=name:hots=cyan\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("No capture group named 'hots' in the regexp"));
+        assert!(stderr.contains("Named groups: host"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 1 errors"));
+    }
+
+    #[test]
+    fn test_conf_checks_regex_flags() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "flags=i\nregexp=error\ncolours=red\n-\nregexp=warn\nflags=iq\ncolours=yellow\n-\n\
+             regexp=a b\nflags=x\ncolours=blue\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid flags: unknown regex flag 'q'"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 1 errors"));
+    }
+
+    /// Python regex syntax is translated; constructs without an equivalent are reported
+    #[test]
+    fn test_conf_checks_python_regex_syntax() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=(?P<q>['\"]).*?(?P=q)\\Z\ncolours=green\n-\n\
+             regexp=(?a)\\w+ done\ncolours=blue\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Unsupported Python regex syntax: flag (?a) is not supported"));
+        assert!(!stderr.contains("Invalid regex"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 1 errors"));
+    }
+
+    /// priority= must be an integer and reorders rules for the reachability check
+    #[test]
+    fn test_conf_checks_priority() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=.*\ncolours=red\ncount=stop\npriority=-1\n-\nregexp=error\ncolours=blue\n-\n\
+             regexp=warn\ncolours=yellow\npriority=high\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid priority: high"));
+        assert!(!stderr.contains("Rule can never fire"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 1 errors"));
+    }
+
+    /// thresholds= needs numeric thresholds and known styles
+    #[test]
+    fn test_conf_checks_thresholds() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=(\\d+)%\ncolours=default,green\nthresholds=70:yellow,90:bold red\n-\n\
+             regexp=loss (\\d+)\nthresholds=high:red\n-\nregexp=load (\\d+)\nthresholds=5:purple\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid thresholds: invalid threshold 'high'"));
+        assert!(stderr.contains("purple"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    /// sizes= needs two distinct sizes with known units
+    #[test]
+    fn test_conf_checks_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=^(\\S+)\\s\ncolours=default,cyan\nsizes=1K:1T\n-\n\
+             regexp=(\\d+) used\nsizes=1M:1Q\n-\nregexp=(\\d+) free\nsizes=1G:1G\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid sizes: invalid size bound '1Q'"));
+        assert!(stderr.contains("Invalid sizes: size bounds must differ, got 1G:1G"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    /// engine= names a known engine that can compile the rule's regexp
+    #[test]
+    fn test_conf_checks_engine() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=^(\\S+)\\s\ncolours=default,cyan\nengine=enhanced\n-\n\
+             regexp=\\d+(?= ms)\nengine=fast\n-\nregexp=up\nengine=pcre\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid regex with engine=fast"));
+        assert!(stderr.contains("Invalid engine: unknown regex engine 'pcre'"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    /// gradient= needs two distinct numeric bounds; palette colours are valid styles
+    #[test]
+    fn test_conf_checks_gradient() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=time=(\\S+) ms\ncolours=color244,on_color17 bold\ngradient=0:200\n-\n\
+             regexp=temp (\\d+)\ngradient=40:40\n-\nregexp=load (\\d+)\ngradient=0:4:rgb\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid gradient: gradient bounds must differ"));
+        assert!(stderr.contains("Invalid gradient: unknown gradient palette 'rgb'"));
+        assert!(!stderr.contains("Unknown style"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
+
+    /// loglevel= expands to the built-in rules; unknown modes are errors
+    #[test]
+    fn test_conf_checks_loglevel() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=^\\S+\ncolours=bold\nloglevel=token\n-\nloglevel=line\nloglevel=all\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("unknown loglevel mode 'all' (expected token or line)"));
+        assert!(stderr.contains("loglevel=line also whole warning and error lines"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 1 errors"));
+    }
+
+    /// fields= needs non-zero field numbers and known styles
+    #[test]
+    fn test_conf_checks_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let conf = temp_dir.path().join("conf.app");
+        fs::write(
+            &conf,
+            "regexp=^\\s*\\d\nfields=1:cyan,-1:green\ndelimiter=,\n-\n\
+             regexp=^x\nfields=0:red\n-\nregexp=^y\nfields=2 bold\n",
+        )
+        .unwrap();
+
+        let output = Command::new(get_rgrv_binary())
+            .arg("conf")
+            .arg(&conf)
+            .output()
+            .expect("Failed to execute rgrv");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid fields: invalid field number '0'"));
+        assert!(stderr.contains("Invalid fields: expected N:STYLE, got '2 bold'"));
+        assert!(!stderr.contains("Unexpected line"));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 files validated, 2 errors"));
+    }
 }